@@ -1,10 +1,24 @@
-use crate::models::user::User;
+use crate::identity::backup::{self, BackupError};
+use crate::identity::device::{self, LinkApproval, LinkRequest, TicketError};
+use crate::identity::keystore::{get_keystore, KeystoreError, UnlockedKey};
+use crate::identity::seed::{self, SeedError};
+use crate::identity::session::{Session, SessionError, SessionState};
+use crate::identity::{
+    is_legacy_user_id, is_valid_user_id, sign_record, user_id_from_public_key, KeyChain, KeyStatus,
+};
+use crate::models::follow::FollowEdge;
+use crate::models::user::{DeviceDelegation, KeyRevocation, KeyRotation, User, UserAlias};
+use crate::storage::authors::import_user_author;
+use crate::storage::post_docs;
+use crate::storage::repository::{
+    follow_repository, post_repository, settings_repository, user_repository,
+};
+use crate::validation::{ValidationError, BIO, DISPLAY_NAME};
 use base64::{engine::general_purpose, Engine as _};
 use ring::signature::{Ed25519KeyPair, KeyPair};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use tauri::command;
-use uuid::Uuid;
+use tauri::{command, State};
 
 /// 認証エラー
 ///
@@ -30,6 +44,34 @@ pub enum AuthError {
     #[error("Credentials not found")]
     CredentialsNotFound,
 
+    /// 保存された鍵が不正
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    /// パスフレーズが正しくない
+    #[error("Invalid passphrase")]
+    InvalidPassphrase,
+
+    /// パスフレーズが要件を満たさない
+    #[error("Weak passphrase: {0}")]
+    WeakPassphrase(String),
+
+    /// サインインしていない
+    #[error("Not signed in")]
+    NotSignedIn,
+
+    /// バックアップが不正
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
+
+    /// 端末リンクのチケットが不正
+    #[error("Invalid link ticket: {0}")]
+    InvalidTicket(String),
+
+    /// 入力検証エラー
+    #[error("Validation error: {0}")]
+    Validation(#[from] ValidationError),
+
     /// その他のエラー
     #[error("{0}")]
     Other(String),
@@ -44,13 +86,65 @@ impl From<crate::storage::StorageError> for AuthError {
     }
 }
 
+impl From<KeystoreError> for AuthError {
+    fn from(err: KeystoreError) -> Self {
+        match err {
+            KeystoreError::NotFound(_) => AuthError::CredentialsNotFound,
+            KeystoreError::InvalidUserId(_) => {
+                ValidationError::invalid("user_id", "not a valid user ID").into()
+            }
+            KeystoreError::InvalidPassphrase => AuthError::InvalidPassphrase,
+            KeystoreError::WeakPassphrase => AuthError::WeakPassphrase(err.to_string()),
+            KeystoreError::Format(_) => AuthError::InvalidKey(err.to_string()),
+            KeystoreError::Crypto(_) => AuthError::Other(err.to_string()),
+            KeystoreError::Io(_) => AuthError::FileSystem(err.to_string()),
+        }
+    }
+}
+
+impl From<SessionError> for AuthError {
+    fn from(err: SessionError) -> Self {
+        match err {
+            SessionError::NotSignedIn => AuthError::NotSignedIn,
+        }
+    }
+}
+
+impl From<BackupError> for AuthError {
+    fn from(err: BackupError) -> Self {
+        match err {
+            BackupError::Keystore(err) => err.into(),
+            _ => AuthError::InvalidBackup(err.to_string()),
+        }
+    }
+}
+
+impl From<SeedError> for AuthError {
+    fn from(err: SeedError) -> Self {
+        AuthError::InvalidKey(err.to_string())
+    }
+}
+
+impl From<TicketError> for AuthError {
+    fn from(err: TicketError) -> Self {
+        match err {
+            TicketError::Malformed(message) => AuthError::InvalidTicket(message),
+        }
+    }
+}
+
 /// エラーのシリアライズ実装
+///
+/// 入力検証エラーはフィールド名を含む構造化された形で、それ以外は文字列で返します。
 impl Serialize for AuthError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        match self {
+            AuthError::Validation(error) => error.serialize(serializer),
+            _ => serializer.serialize_str(self.to_string().as_ref()),
+        }
     }
 }
 
@@ -62,6 +156,24 @@ pub struct AuthResult {
     pub message: Option<String>,
 }
 
+/// アイデンティティのエクスポート結果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdentityExport {
+    /// 暗号化されたバックアップ（JSON）
+    pub backup: String,
+    /// Ed25519シードのリカバリーフレーズ（要求された場合のみ）
+    pub mnemonic: Option<String>,
+}
+
+/// 端末リンクのチケット
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceLinkTicket {
+    /// もう一方の端末に渡すチケット
+    pub ticket: String,
+    /// 両方の端末で一致することを確認する6桁のコード
+    pub code: String,
+}
+
 /// ユーザーリストアイテム
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserListItem {
@@ -69,14 +181,73 @@ pub struct UserListItem {
     pub display_name: String,
 }
 
+/// 旧形式の平文秘密鍵の保存ディレクトリを取得します。
+///
+/// 現在の鍵はキーストアに暗号化して保存され、このディレクトリは移行元としてのみ使用します。
+fn legacy_key_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join("kukuri-client").join("keys")
+}
+
+/// 旧形式の平文秘密鍵（PKCS#8）を読み込みます。
+fn read_legacy_pkcs8(user_id: &str) -> Result<Vec<u8>, AuthError> {
+    require_valid_user_id(user_id)?;
+    let key_path = legacy_key_dir().join(format!("{}.key", user_id));
+
+    if !key_path.exists() {
+        return Err(AuthError::CredentialsNotFound);
+    }
+
+    let private_key_b64 = fs::read_to_string(&key_path)
+        .map_err(|e| AuthError::FileSystem(format!("Failed to read private key: {}", e)))?;
+    general_purpose::STANDARD
+        .decode(private_key_b64.trim())
+        .map_err(|e| AuthError::InvalidKey(format!("Failed to decode private key: {}", e)))
+}
+
+/// ユーザーIDが公開鍵由来のIDまたは旧形式のUUIDであることを確認します。
+///
+/// ユーザーIDは鍵ファイルのパスに使用するため、ファイルシステムに触れる前に検証します。
+fn require_valid_user_id(user_id: &str) -> Result<(), AuthError> {
+    if !is_valid_user_id(user_id) {
+        return Err(ValidationError::invalid("user_id", "not a valid user ID").into());
+    }
+    Ok(())
+}
+
+/// 旧形式の平文秘密鍵からキーペアを読み込みます。
+fn load_legacy_key_pair(user_id: &str) -> Result<Ed25519KeyPair, AuthError> {
+    let pkcs8_bytes = read_legacy_pkcs8(user_id)?;
+    Ed25519KeyPair::from_pkcs8(&pkcs8_bytes)
+        .map_err(|e| AuthError::InvalidKey(format!("Failed to parse key pair: {:?}", e)))
+}
+
+/// 旧形式の平文秘密鍵をキーストアへ暗号化して移し、平文ファイルを削除します。
+fn import_legacy_key(user_id: &str, passphrase: &str) -> Result<(), AuthError> {
+    let pkcs8_bytes = read_legacy_pkcs8(user_id)?;
+    get_keystore().store(user_id, &pkcs8_bytes, passphrase)?;
+
+    fs::remove_file(legacy_key_dir().join(format!("{}.key", user_id)))
+        .map_err(|e| AuthError::FileSystem(format!("Failed to remove legacy key: {}", e)))
+}
+
 /// ユーザー作成コマンド
 ///
-/// 新しいユーザーを作成し、キーペアを生成して保存します。
+/// 新しいユーザーを作成し、キーペアを生成してパスフレーズで暗号化して保存します。
+/// 作成したユーザーでそのままセッションを開始します。
 #[command]
 pub async fn create_user(
+    session: State<'_, SessionState>,
     display_name: String,
     bio: Option<String>,
+    passphrase: String,
 ) -> Result<AuthResult, AuthError> {
+    // プロフィールとパスフレーズの検証（プロフィール保存前に失敗させる）
+    let display_name = DISPLAY_NAME.clean(&display_name)?;
+    let bio = bio.map(|bio| BIO.clean(&bio)).transpose()?;
+    if passphrase.chars().count() < crate::identity::keystore::MIN_PASSPHRASE_LENGTH {
+        return Err(KeystoreError::WeakPassphrase.into());
+    }
+
     // 1. 新しいキーペアを生成
     let rng = ring::rand::SystemRandom::new();
     let pkcs8_bytes = Ed25519KeyPair::generate_pkcs8(&rng)
@@ -88,34 +259,35 @@ pub async fn create_user(
     // 2. 公開鍵からユーザーIDを作成
     let public_key = key_pair.public_key().as_ref();
     let public_key_b64 = general_purpose::STANDARD.encode(public_key);
-    let user_id = Uuid::new_v4().to_string();
+    let user_id = user_id_from_public_key(public_key)
+        .ok_or_else(|| AuthError::KeyGeneration("Unexpected public key length".to_string()))?;
 
-    // 3. ユーザープロファイルを作成して保存
-    let user = User {
+    // 3. ユーザープロファイルを作成し、署名して保存
+    let mut user = User {
         id: user_id.clone(),
         display_name,
         bio: bio.unwrap_or_default(),
         public_key: public_key_b64,
         // その他のフィールドを初期化
         avatar: None,
+        avatar_thumbnail: None,
         following: vec![],
         followers: vec![],
+        devices: vec![],
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
 
-    // StorageManagerを使用してユーザーを保存
-    crate::storage::repository::user_repository::save_user(&user).await?; // Updated path and added .await
+    // 4. 秘密鍵をキーストアに暗号化して保存し、セッションを開始
+    let unlocked = register_key(&user_id, pkcs8_bytes.as_ref(), &passphrase)?;
+    let ticket = start_session(&session, &user_id, unlocked).await?;
 
-    // 4. 秘密鍵を安全に保存
-    let private_key_b64 = general_purpose::STANDARD.encode(pkcs8_bytes);
-    let app_dir = std::env::temp_dir().join("kukuri-client");
-    let key_dir = app_dir.join("keys");
-    std::fs::create_dir_all(&key_dir)
-        .map_err(|e| AuthError::FileSystem(format!("Failed to create key directory: {}", e)))?;
-
-    let key_path = key_dir.join(format!("{}.key", user_id));
-    std::fs::write(key_path, private_key_b64)
-        .map_err(|e| AuthError::FileSystem(format!("Failed to save private key: {}", e)))?;
+    // ユーザー自身の作成者として投稿ドキュメントのチケットを含むプロフィールを保存
+    user.post_doc_ticket = Some(ticket);
+    sign_record(&mut user, &key_pair);
+    user_repository::save_user(&user).await?;
 
     // ネットワークにユーザープロファイルを発信
     if let Err(e) = crate::network::iroh::publish_profile(&user).await {
@@ -133,20 +305,43 @@ pub async fn create_user(
 /// サインインコマンド
 ///
 /// 既存のユーザーでサインインします。
+/// パスフレーズで鍵のロックを解除し、セッションを開始します。
+/// 旧形式の平文鍵はこの時にキーストアへ移行されます。
 #[command]
-pub async fn sign_in(user_id: String) -> Result<AuthResult, AuthError> {
-    // ユーザーIDに基づいて秘密鍵を読み込み
-    let app_dir = std::env::temp_dir().join("kukuri-client");
-    let key_path = app_dir.join("keys").join(format!("{}.key", user_id));
+pub async fn sign_in(
+    session: State<'_, SessionState>,
+    user_id: String,
+    passphrase: String,
+) -> Result<AuthResult, AuthError> {
+    require_valid_user_id(&user_id)?;
+    let keystore = get_keystore();
 
-    if !key_path.exists() {
-        return Err(AuthError::CredentialsNotFound);
+    // 旧形式の平文鍵しかない場合はキーストアへ移行
+    if !keystore.contains(&user_id) {
+        import_legacy_key(&user_id, &passphrase)?;
     }
 
+    // ユーザーIDに基づいて秘密鍵のロックを解除
+    let unlocked = keystore.unlock(&user_id, &passphrase)?;
+
     // ユーザープロファイルを取得して検証
-    match crate::storage::repository::user_repository::get_user(&user_id).await {
-        // Updated path and added .await
-        Ok(Some(_user)) => {
+    match user_repository::get_user(&user_id).await {
+        Ok(Some(user)) => {
+            // 秘密鍵がプロフィールの公開鍵、または委任された端末の鍵と一致するか確認
+            let public_key_b64 =
+                general_purpose::STANDARD.encode(unlocked.key_pair().public_key().as_ref());
+            let is_delegated = user_repository::get_key_chain(&user_id)
+                .await?
+                .and_then(|chain| chain.status(&public_key_b64))
+                == Some(KeyStatus::Delegated);
+            if public_key_b64 != user.public_key && !is_delegated {
+                return Err(AuthError::InvalidKey(
+                    "Private key does not match profile public key".to_string(),
+                ));
+            }
+
+            start_session(&session, &user_id, unlocked).await?;
+
             // ネットワーク状態を取得
             let network_status = crate::network::iroh::get_network_status()
                 .map_err(|e| AuthError::Other(format!("Failed to get network status: {}", e)))?;
@@ -168,49 +363,781 @@ pub async fn sign_in(user_id: String) -> Result<AuthResult, AuthError> {
 /// ユーザーリスト取得コマンド
 ///
 /// 利用可能なすべてのユーザーのリストを取得します。
+/// キーストアの鍵と、まだ移行されていない旧形式の平文鍵の両方を対象とします。
 #[command]
 pub async fn list_users() -> Result<Vec<UserListItem>, AuthError> {
-    // アプリのデータディレクトリからキーファイルを検索
-    let app_dir = std::env::temp_dir().join("kukuri-client");
-    let key_dir = app_dir.join("keys");
+    let mut user_ids = get_keystore().list_user_ids()?;
 
-    // キーディレクトリが存在しない場合は空のリストを返す
-    if !key_dir.exists() {
-        return Ok(Vec::new());
+    // 旧形式の鍵ディレクトリも走査
+    let legacy_dir = legacy_key_dir();
+    if legacy_dir.exists() {
+        let entries = fs::read_dir(&legacy_dir)
+            .map_err(|e| AuthError::FileSystem(format!("Failed to read keys directory: {}", e)))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| {
+                AuthError::FileSystem(format!("Failed to read directory entry: {}", e))
+            })?;
+            let path = entry.path();
+
+            // .keyファイルのみを処理
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "key") {
+                if let Some(user_id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    if !user_ids.iter().any(|id| id == user_id) {
+                        user_ids.push(user_id.to_string());
+                    }
+                }
+            }
+        }
     }
 
     let mut users = Vec::new();
+    for user_id in user_ids {
+        // ユーザープロファイルを取得
+        if let Ok(Some(user)) = user_repository::get_user(&user_id).await {
+            users.push(UserListItem {
+                id: user.id,
+                display_name: user.display_name,
+            });
+        }
+    }
+
+    Ok(users)
+}
+
+/// サインアウトコマンド
+///
+/// 現在のセッションを終了し、復号済みの鍵をメモリから破棄します。
+/// ユーザーの投稿ドキュメントと鍵共有用の鍵も閉じます。
+#[command]
+pub async fn sign_out(session: State<'_, SessionState>) -> Result<(), AuthError> {
+    if let Some(previous) = session.end() {
+        post_docs::close_session(previous.user_id());
+        crate::network::unsubscribe_from_follow_edges(previous.user_id());
+    }
+    Ok(())
+}
+
+/// 現在のユーザー取得コマンド
+///
+/// サインイン中のユーザーのプロフィールを取得します。サインインしていない場合は `None` を返します。
+#[command]
+pub async fn current_user(session: State<'_, SessionState>) -> Result<Option<User>, AuthError> {
+    match session.current() {
+        Some(current) => match user_repository::get_user(current.user_id()).await? {
+            Some(user) => Ok(Some(follow_repository::with_follow_graph(user).await?)),
+            None => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+/// パスフレーズ変更コマンド
+///
+/// サインイン中のユーザーの現在のパスフレーズを確認し、新しいパスフレーズで鍵を再暗号化します。
+#[command]
+pub async fn change_passphrase(
+    session: State<'_, SessionState>,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), AuthError> {
+    let current = session.require()?;
+    get_keystore()
+        .change_passphrase(current.user_id(), &old_passphrase, &new_passphrase)
+        .map_err(Into::into)
+}
+
+/// アイデンティティのエクスポートコマンド
+///
+/// サインイン中のユーザーの秘密鍵・プロフィール・設定をパスフレーズで暗号化したバックアップを作成します。
+/// `include_mnemonic` が指定された場合は、シードのリカバリーフレーズも返します。
+#[command]
+pub async fn export_identity(
+    session: State<'_, SessionState>,
+    passphrase: String,
+    include_mnemonic: bool,
+) -> Result<IdentityExport, AuthError> {
+    let current = session.require()?;
+    let user_id = current.user_id();
+
+    // パスフレーズを確認して秘密鍵を復号
+    let pkcs8 = get_keystore().export_pkcs8(user_id, &passphrase)?;
+
+    let user = user_repository::get_user(user_id)
+        .await?
+        .ok_or(AuthError::UserNotFound)?;
+    let settings = settings_repository::get_settings(Some(user_id)).await?;
+
+    let backup = backup::create_backup(&pkcs8, &user, settings.as_ref(), &passphrase)?;
+    let mnemonic = if include_mnemonic {
+        Some(seed::mnemonic_from_pkcs8(&pkcs8)?.to_string())
+    } else {
+        None
+    };
+
+    Ok(IdentityExport { backup, mnemonic })
+}
+
+/// アイデンティティのインポートコマンド
+///
+/// `export_identity` で作成したバックアップを復号し、鍵をキーストアに登録します。
+/// ローカルにプロフィールや設定が無い場合はバックアップの内容で復元します。
+/// 発信するのは保存されているプロフィールです。
+/// インポートしたユーザーでそのままセッションを開始します。
+#[command]
+pub async fn import_identity(
+    session: State<'_, SessionState>,
+    backup: String,
+    passphrase: String,
+) -> Result<AuthResult, AuthError> {
+    let restored = backup::restore_backup(&backup, &passphrase)?;
+    let user_id = restored.user.id.clone();
+
+    let unlocked = register_key(&user_id, &restored.pkcs8, &passphrase)?;
+    start_session(&session, &user_id, unlocked).await?;
+
+    // プロフィールと設定は既存のものを優先
+    if user_repository::get_unverified_user(&user_id)
+        .await?
+        .is_none()
+    {
+        user_repository::save_user(&restored.user).await?;
+    }
+    if let Some(settings) = &restored.settings {
+        if settings_repository::get_settings(Some(&user_id))
+            .await?
+            .is_none()
+        {
+            settings_repository::save_settings(settings).await?;
+        }
+    }
+
+    // 既存のプロフィールを残した場合は、バックアップの古いプロフィールではなく保存されている方を発信
+    match user_repository::get_user(&user_id).await? {
+        Some(user) => {
+            if let Err(e) = crate::network::iroh::publish_profile(&user).await {
+                println!("Warning: Failed to publish profile: {}", e);
+            }
+        }
+        None => println!("Warning: Stored profile could not be verified"),
+    }
+
+    Ok(AuthResult {
+        user_id,
+        success: true,
+        message: None,
+    })
+}
+
+/// リカバリーフレーズからの復元コマンド
+///
+/// リカバリーフレーズから秘密鍵を復元し、新しいパスフレーズでキーストアに登録します。
+/// ローテーション後の鍵のフレーズは、ローテーション記録から元のユーザーIDに復元されます。
+/// ローカルにプロフィールが無い場合は `display_name` で新しいプロフィールを作成します。
+#[command]
+pub async fn recover_identity(
+    session: State<'_, SessionState>,
+    mnemonic: String,
+    passphrase: String,
+    display_name: Option<String>,
+) -> Result<AuthResult, AuthError> {
+    let display_name = display_name
+        .map(|display_name| DISPLAY_NAME.clean(&display_name))
+        .transpose()?;
+    let pkcs8 = seed::pkcs8_from_mnemonic(&mnemonic)?;
+    let key_pair = Ed25519KeyPair::from_pkcs8(&pkcs8)
+        .map_err(|e| AuthError::InvalidKey(format!("Failed to parse key pair: {:?}", e)))?;
+    let public_key = key_pair.public_key().as_ref();
+    let user_id = resolve_recovered_user(public_key).await?;
+
+    // ローカルにプロフィールが無ければ新しく作成する
+    let new_display_name = match user_repository::get_user(&user_id).await? {
+        Some(_) => None,
+        None => Some(display_name.ok_or(AuthError::UserNotFound)?),
+    };
+
+    let unlocked = register_key(&user_id, &pkcs8, &passphrase)?;
+    let ticket = start_session(&session, &user_id, unlocked).await?;
+
+    if let Some(display_name) = new_display_name {
+        let mut user = User {
+            id: user_id.clone(),
+            display_name,
+            bio: String::new(),
+            public_key: general_purpose::STANDARD.encode(public_key),
+            avatar: None,
+            avatar_thumbnail: None,
+            following: vec![],
+            followers: vec![],
+            devices: vec![],
+            post_doc_ticket: Some(ticket),
+            approval_required: false,
+            created_at: chrono::Utc::now().timestamp(),
+            signature: None,
+        };
+        sign_record(&mut user, &key_pair);
+        user_repository::save_user(&user).await?;
+
+        if let Err(e) = crate::network::iroh::publish_profile(&user).await {
+            println!("Warning: Failed to publish profile: {}", e);
+        }
+    }
+
+    Ok(AuthResult {
+        user_id,
+        success: true,
+        message: None,
+    })
+}
+
+/// 鍵のローテーションコマンド
+///
+/// 新しいキーペアを生成し、現在の鍵で署名したローテーション記録をユーザードキュメントに保存します。
+/// 新しい鍵はキーストアの同じユーザーIDに保存され、プロフィールは新しい鍵で署名し直されます。
+/// 以前の鍵の投稿ドキュメントのエントリは、新しい鍵の投稿ドキュメントに引き継がれます。
+/// `revoke_previous` が指定された場合は、漏洩した可能性のある以前の鍵を新しい鍵で失効させます。
+#[command]
+pub async fn rotate_key(
+    session: State<'_, SessionState>,
+    passphrase: String,
+    revoke_previous: bool,
+) -> Result<AuthResult, AuthError> {
+    let current = session.require()?;
+    let user_id = current.user_id().to_string();
+
+    // パスフレーズを確認（新しい鍵も同じパスフレーズで保存する）
+    get_keystore().export_pkcs8(&user_id, &passphrase)?;
+
+    let chain = user_repository::get_key_chain(&user_id)
+        .await?
+        .ok_or_else(|| AuthError::InvalidKey("Legacy user IDs cannot rotate keys".to_string()))?;
+    let previous_key = require_current_key(&chain, current.key_pair())?;
+
+    let mut user = user_repository::get_user(&user_id)
+        .await?
+        .ok_or(AuthError::UserNotFound)?;
+
+    // 1. 新しいキーペアを生成
+    let rng = ring::rand::SystemRandom::new();
+    let pkcs8_bytes = Ed25519KeyPair::generate_pkcs8(&rng)
+        .map_err(|e| AuthError::KeyGeneration(format!("Failed to generate key pair: {:?}", e)))?;
+    let new_key_pair = Ed25519KeyPair::from_pkcs8(pkcs8_bytes.as_ref())
+        .map_err(|e| AuthError::KeyGeneration(format!("Failed to parse key pair: {:?}", e)))?;
+    let new_key = general_purpose::STANDARD.encode(new_key_pair.public_key().as_ref());
+    let now = chrono::Utc::now().timestamp();
+
+    // 2. 現在の鍵でローテーション記録に署名して保存
+    let mut rotation = KeyRotation {
+        user_id: user_id.clone(),
+        sequence: chain.next_sequence(),
+        previous_key: previous_key.clone(),
+        new_key: new_key.clone(),
+        created_at: now,
+        signature: None,
+    };
+    sign_record(&mut rotation, current.key_pair());
+    user_repository::save_key_rotation(&rotation).await?;
+
+    // 3. 以前の鍵を新しい鍵で失効させる
+    if revoke_previous {
+        let mut revocation = KeyRevocation {
+            user_id: user_id.clone(),
+            revoked_key: previous_key,
+            revoked_at: now,
+            reason: Some("rotated".to_string()),
+            signature: None,
+        };
+        sign_record(&mut revocation, &new_key_pair);
+        user_repository::save_key_revocation(&revocation).await?;
+    }
+
+    // 4. 新しい鍵をキーストアに保存し、新しい鍵でセッションを開始し直す
+    let previous_post_doc = post_docs::own_post_doc(&user_id);
+    let previous_follower_doc = post_docs::own_follower_doc(&user_id);
+    let unlocked = register_key(&user_id, pkcs8_bytes.as_ref(), &passphrase)?;
+    let ticket = start_session(&session, &user_id, unlocked).await?;
+
+    // 公開するのは新しい鍵の投稿ドキュメントだけなので、以前の鍵のエントリを引き継ぐ
+    if let Err(e) =
+        post_docs::carry_over_own_docs(&user_id, previous_post_doc, previous_follower_doc).await
+    {
+        eprintln!("Failed to carry over post documents of {}: {}", user_id, e);
+    }
+
+    // 5. プロフィールを新しい鍵の投稿ドキュメントに切り替え、新しい鍵で署名し直して保存
+    user.public_key = new_key;
+    user.post_doc_ticket = Some(ticket);
+    sign_record(&mut user, &new_key_pair);
+    user_repository::save_user(&user).await?;
+
+    if let Err(e) = crate::network::iroh::publish_profile(&user).await {
+        println!("Warning: Failed to publish profile: {}", e);
+    }
+
+    Ok(AuthResult {
+        user_id,
+        success: true,
+        message: Some(format!("Rotated to key #{}", rotation.sequence)),
+    })
+}
+
+/// 鍵の失効コマンド
+///
+/// サインイン中のユーザーの以前の鍵、またはリンクした端末の鍵を現在の鍵で失効させます。
+/// 失効した鍵で署名された投稿は、読み込み時にフラグが付けられます。
+/// 現在の鍵を失効させる場合は `rotate_key` で `revoke_previous` を指定してください。
+#[command]
+pub async fn revoke_key(
+    session: State<'_, SessionState>,
+    public_key: String,
+    reason: Option<String>,
+) -> Result<(), AuthError> {
+    let current = session.require()?;
+    let user_id = current.user_id();
+
+    let chain = user_repository::get_key_chain(user_id)
+        .await?
+        .ok_or_else(|| AuthError::InvalidKey("Legacy user IDs cannot revoke keys".to_string()))?;
+    require_current_key(&chain, current.key_pair())?;
+    match chain.status(&public_key) {
+        Some(KeyStatus::Rotated) | Some(KeyStatus::Revoked) | Some(KeyStatus::Delegated) => {}
+        Some(KeyStatus::Current) => {
+            return Err(AuthError::InvalidKey(
+                "Rotate the current key before revoking it".to_string(),
+            ))
+        }
+        None => {
+            return Err(AuthError::InvalidKey(
+                "Key is not part of this user's key chain".to_string(),
+            ))
+        }
+    }
+
+    let mut revocation = KeyRevocation {
+        user_id: user_id.to_string(),
+        revoked_key: public_key,
+        revoked_at: chrono::Utc::now().timestamp(),
+        reason,
+        signature: None,
+    };
+    sign_record(&mut revocation, current.key_pair());
+    user_repository::save_key_revocation(&revocation)
+        .await
+        .map_err(Into::into)
+}
+
+/// 端末リンク要求コマンド
+///
+/// 新しい端末で実行します。端末の鍵を生成してパスフレーズで暗号化して保存し、
+/// 既存の端末で承認してもらうためのチケットと確認コードを返します。
+/// チケットには、委任に記録してもらう端末の鍵の投稿ドキュメントのチケットも含めます。
+#[command]
+pub async fn request_device_link(
+    device_name: String,
+    passphrase: String,
+) -> Result<DeviceLinkTicket, AuthError> {
+    let rng = ring::rand::SystemRandom::new();
+    let pkcs8_bytes = Ed25519KeyPair::generate_pkcs8(&rng)
+        .map_err(|e| AuthError::KeyGeneration(format!("Failed to generate key pair: {:?}", e)))?;
+    let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8_bytes.as_ref())
+        .map_err(|e| AuthError::KeyGeneration(format!("Failed to parse key pair: {:?}", e)))?;
+    let device_key = general_purpose::STANDARD.encode(key_pair.public_key().as_ref());
+
+    // 承認されるまでは端末の鍵から導出したIDで保存する
+    let pending_id = device::pending_key_id(&device_key)
+        .ok_or_else(|| AuthError::KeyGeneration("Unexpected public key length".to_string()))?;
+    get_keystore().store(&pending_id, pkcs8_bytes.as_ref(), &passphrase)?;
+    let unlocked = get_keystore().unlock(&pending_id, &passphrase)?;
+    let post_doc_ticket = post_docs::post_doc_ticket(unlocked.post_namespace()).await?;
+
+    let request = LinkRequest {
+        device_key: device_key.clone(),
+        device_name,
+        post_doc_ticket: Some(post_doc_ticket),
+    };
+    Ok(DeviceLinkTicket {
+        ticket: device::encode_request(&request),
+        code: device::confirmation_code(&device_key),
+    })
+}
+
+/// 端末リンク承認コマンド
+///
+/// 既存の端末で実行します。リンク要求の端末の鍵を現在の鍵で委任し、プロフィールに記録します。
+/// 端末の投稿ドキュメントのチケットも委任に記録され、フォローするユーザーに同期されます。
+/// 新しい端末に渡す承認チケットと、要求側と一致するはずの確認コードを返します。
+#[command]
+pub async fn approve_device_link(
+    session: State<'_, SessionState>,
+    ticket: String,
+) -> Result<DeviceLinkTicket, AuthError> {
+    let current = session.require()?;
+    let user_id = current.user_id().to_string();
+    let request = device::decode_request(&ticket)?;
+
+    let chain = user_repository::get_key_chain(&user_id)
+        .await?
+        .ok_or_else(|| AuthError::InvalidKey("Legacy user IDs cannot link devices".to_string()))?;
+    require_current_key(&chain, current.key_pair())?;
+
+    let mut user = user_repository::get_user(&user_id)
+        .await?
+        .ok_or(AuthError::UserNotFound)?;
+
+    let mut delegation = DeviceDelegation {
+        user_id: user_id.clone(),
+        device_key: request.device_key.clone(),
+        device_name: request.device_name,
+        created_at: chrono::Utc::now().timestamp(),
+        post_doc_ticket: request.post_doc_ticket,
+        signature: None,
+    };
+    sign_record(&mut delegation, current.key_pair());
+    if !chain.accepts_delegation(&user_id, &delegation) {
+        return Err(AuthError::InvalidTicket("Invalid device key".to_string()));
+    }
+
+    // 委任をプロフィールに記録し、署名し直して保存
+    user.devices
+        .retain(|device| device.device_key != delegation.device_key);
+    user.devices.push(delegation.clone());
+    sign_record(&mut user, current.key_pair());
+    user_repository::save_user(&user).await?;
+
+    if let Err(e) = crate::network::iroh::publish_profile(&user).await {
+        println!("Warning: Failed to publish profile: {}", e);
+    }
+
+    Ok(DeviceLinkTicket {
+        ticket: device::encode_approval(&LinkApproval { delegation }),
+        code: device::confirmation_code(&request.device_key),
+    })
+}
+
+/// 端末リンク完了コマンド
+///
+/// 新しい端末で実行します。承認チケットの委任を検証し、
+/// `request_device_link` で保存した端末の鍵をユーザーの鍵としてキーストアに登録してセッションを開始します。
+#[command]
+pub async fn complete_device_link(
+    session: State<'_, SessionState>,
+    ticket: String,
+    passphrase: String,
+) -> Result<AuthResult, AuthError> {
+    let LinkApproval { delegation } = device::decode_approval(&ticket)?;
+    let user_id = delegation.user_id.clone();
+
+    let keystore = get_keystore();
+    let pending_id = device::pending_key_id(&delegation.device_key)
+        .ok_or_else(|| AuthError::InvalidTicket("Invalid device key".to_string()))?;
+    let pkcs8 = keystore.export_pkcs8(&pending_id, &passphrase)?;
+
+    // 委任がユーザーの鍵の連鎖に含まれる鍵で署名されていることを確認
+    let chain = user_repository::get_key_chain(&user_id)
+        .await?
+        .ok_or_else(|| AuthError::InvalidTicket("Invalid user ID".to_string()))?;
+    if !chain.accepts_delegation(&user_id, &delegation) {
+        return Err(AuthError::InvalidTicket(
+            "Delegation is not signed by a valid key of this user".to_string(),
+        ));
+    }
+
+    let unlocked = register_key(&user_id, &pkcs8, &passphrase)?;
+    keystore.remove(&pending_id)?;
+    start_session(&session, &user_id, unlocked).await?;
+
+    Ok(AuthResult {
+        user_id,
+        success: true,
+        message: None,
+    })
+}
+
+/// セッションの鍵がユーザーの現在の鍵であることを確認し、その公開鍵 (Base64) を返します。
+///
+/// 鍵のローテーション・失効や端末の委任は、委任された端末の鍵や以前の鍵では行えません。
+fn require_current_key(chain: &KeyChain, key_pair: &Ed25519KeyPair) -> Result<String, AuthError> {
+    let public_key = general_purpose::STANDARD.encode(key_pair.public_key().as_ref());
+    if chain.status(&public_key) != Some(KeyStatus::Current) {
+        return Err(AuthError::InvalidKey(
+            "Signed-in key is not the current key of this user".to_string(),
+        ));
+    }
+    Ok(public_key)
+}
+
+/// リカバリーフレーズから復元した公開鍵のユーザーIDを求めます。
+///
+/// ローテーション後の鍵はユーザーIDの元になった鍵と異なるため、
+/// ユーザードキュメントのローテーション記録から元のユーザーIDを探します。
+/// 鍵の連鎖がある場合は、現在の鍵でなければエラーを返します。
+pub(crate) async fn resolve_recovered_user(public_key: &[u8]) -> Result<String, AuthError> {
+    let public_key_b64 = general_purpose::STANDARD.encode(public_key);
+    let user_id = match user_repository::find_user_by_rotated_key(&public_key_b64).await? {
+        Some(user_id) => user_id,
+        None => user_id_from_public_key(public_key)
+            .ok_or_else(|| AuthError::InvalidKey("Unexpected public key length".to_string()))?,
+    };
+
+    if let Some(chain) = user_repository::get_key_chain(&user_id).await? {
+        if chain.status(&public_key_b64) != Some(KeyStatus::Current) {
+            return Err(AuthError::InvalidKey(
+                "Recovery phrase is not for the current key of this user".to_string(),
+            ));
+        }
+    }
+
+    Ok(user_id)
+}
+
+/// 鍵をキーストアに暗号化して保存し、復号済みの鍵を返します。
+fn register_key(user_id: &str, pkcs8: &[u8], passphrase: &str) -> Result<UnlockedKey, AuthError> {
+    let keystore = get_keystore();
+    keystore.store(user_id, pkcs8, passphrase)?;
+    keystore.unlock(user_id, passphrase).map_err(Into::into)
+}
+
+/// セッションを開始し、ユーザーのiroh-docs作成者をノードに取り込みます。
+///
+/// 以降、このユーザーの投稿・プロフィール・設定はユーザー自身の作成者として保存されます。
+/// 鍵から導出した投稿ドキュメントを開き、その読み取り専用チケットを返します。
+/// ユーザーの現在の鍵の場合は、フォロワー限定の投稿ドキュメントも開きます。
+/// 以前のセッションのユーザーの投稿ドキュメントと鍵共有用の鍵は閉じ、フォロー関係の購読も終了します。
+async fn start_session(
+    session: &SessionState,
+    user_id: &str,
+    unlocked: UnlockedKey,
+) -> Result<String, AuthError> {
+    // 以前のセッションのドキュメントを閉じる（同じユーザーでも鍵が変わる場合がある）
+    if let Some(previous) = session.current() {
+        post_docs::close_session(previous.user_id());
+        crate::network::unsubscribe_from_follow_edges(previous.user_id());
+    }
+    import_user_author(user_id, unlocked.author()).await?;
+    let ticket = post_docs::open_own_post_doc(user_id, unlocked.post_namespace()).await?;
+    post_docs::register_agreement_secret(user_id, unlocked.agreement_secret());
+    // 鍵の連鎖がまだ無い新しいユーザーの鍵は、ユーザーIDの元になった現在の鍵
+    let is_current_key = match user_repository::get_key_chain(user_id).await? {
+        Some(chain) => require_current_key(&chain, unlocked.key_pair()).is_ok(),
+        None => true,
+    };
+    if is_current_key {
+        post_docs::open_own_follower_doc(user_id, unlocked.follower_namespace()).await?;
+    }
+    let current = session.start(user_id.to_string(), unlocked);
+
+    if let Err(e) = migrate_following(&current).await {
+        eprintln!("Failed to migrate follows of {}: {}", user_id, e);
+    }
+    if let Err(e) = publish_post_doc_ticket(&current, &ticket).await {
+        eprintln!("Failed to publish post document of {}: {}", user_id, e);
+    }
+    if let Err(e) = crate::network::subscribe_to_follow_edges(user_id).await {
+        println!("Warning: Failed to subscribe to follow edges: {}", e);
+    }
+
+    let user_id = user_id.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = follow_post_docs(&user_id).await {
+            eprintln!(
+                "Failed to sync post documents followed by {}: {}",
+                user_id, e
+            );
+        }
+    });
+    Ok(ticket)
+}
+
+/// 投稿ドキュメントのチケットの公開
+///
+/// プロフィールのチケットが異なる場合は更新して署名し直し、配信します。
+/// 投稿ドキュメントは鍵ごとに異なるため、プロフィールで公開するのはユーザーの現在の鍵の
+/// ドキュメントだけです。委任された端末のドキュメントのチケットは端末の委任に記録され、
+/// 以前の鍵のドキュメントのエントリは鍵のローテーション時に現在の鍵のドキュメントに引き継がれます。
+async fn publish_post_doc_ticket(current: &Session, ticket: &str) -> Result<(), AuthError> {
+    let Some(mut user) = user_repository::get_user(current.user_id()).await? else {
+        return Ok(());
+    };
+    if user.post_doc_ticket.as_deref() == Some(ticket) {
+        return Ok(());
+    }
+    let Some(chain) = user_repository::get_key_chain(current.user_id()).await? else {
+        return Ok(());
+    };
+    if require_current_key(&chain, current.key_pair()).is_err() {
+        return Ok(());
+    }
+
+    user.post_doc_ticket = Some(ticket.to_string());
+    sign_record(&mut user, current.key_pair());
+    user_repository::save_user(&user).await?;
+
+    if let Err(e) = crate::network::iroh::publish_profile(&user).await {
+        println!("Warning: Failed to publish profile: {}", e);
+    }
+    Ok(())
+}
+
+/// フォロー中のユーザーの投稿ドキュメントの同期開始
+///
+/// チケットを公開していないユーザーは読み飛ばし、同期を開始した件数を返します。
+/// フォローリクエストを承認したユーザーのフォロワー限定の投稿ドキュメントも同期します。
+async fn follow_post_docs(user_id: &str) -> Result<usize, AuthError> {
+    for approval in follow_repository::list_follow_approvals(user_id).await? {
+        let followee_id = &approval.edge.followee_id;
+        if !follow_repository::is_following(user_id, followee_id).await? {
+            continue;
+        }
+        if let Err(e) = post_docs::follow_follower_doc(&approval).await {
+            eprintln!("Failed to sync follower document of {}: {}", followee_id, e);
+        }
+    }
+
+    let mut followed = 0;
+    for followee_id in follow_repository::list_following(user_id).await? {
+        let Some(followee) = user_repository::get_user(&followee_id).await? else {
+            continue;
+        };
+        if followee.post_doc_tickets().is_empty() {
+            continue;
+        }
+        match post_docs::follow_post_docs_of(&followee).await {
+            Ok(()) => followed += 1,
+            Err(e) => eprintln!("Failed to sync post document of {}: {}", followee_id, e),
+        }
+    }
+    Ok(followed)
+}
+
+/// プロフィールの `following` に残るフォローの移行
+///
+/// 以前はフォロー関係をプロフィールの `following` に保存していました。
+/// まだフォロー関係のエントリが無いものを署名付きのエントリとして保存し、
+/// `following` を空にしたプロフィールを署名し直します。移行した件数を返します。
+async fn migrate_following(current: &Session) -> Result<usize, AuthError> {
+    let Some(mut user) = user_repository::get_user(current.user_id()).await? else {
+        return Ok(0);
+    };
+    if user.following.is_empty() {
+        return Ok(0);
+    }
+
+    let mut migrated = 0;
+    for followee_id in &user.following {
+        if follow_repository::get_follow_edge(&user.id, followee_id)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+        let mut edge = FollowEdge::new(&user.id, followee_id, true, None);
+        sign_record(&mut edge, current.key_pair());
+        follow_repository::save_follow_edge(&edge).await?;
+        migrated += 1;
+    }
+
+    user.following.clear();
+    sign_record(&mut user, current.key_pair());
+    user_repository::save_user(&user).await?;
+
+    Ok(migrated)
+}
+
+/// 旧形式ユーザーの移行
+///
+/// ランダムUUIDで保存された鍵ファイルとプロフィールを公開鍵由来のIDへ移行します。
+/// 起動時に呼び出され、移行したユーザー数を返します。
+pub async fn migrate_legacy_users() -> Result<usize, AuthError> {
+    let key_dir = legacy_key_dir();
+    if !key_dir.exists() {
+        return Ok(0);
+    }
 
-    // キーディレクトリ内のファイルを走査
     let entries = fs::read_dir(&key_dir)
         .map_err(|e| AuthError::FileSystem(format!("Failed to read keys directory: {}", e)))?;
 
+    let mut migrated = 0;
     for entry in entries {
         let entry = entry
             .map_err(|e| AuthError::FileSystem(format!("Failed to read directory entry: {}", e)))?;
-
         let path = entry.path();
 
-        // .keyファイルのみを処理
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "key") {
-            if let Some(file_stem) = path.file_stem() {
-                if let Some(user_id) = file_stem.to_str() {
-                    // ユーザープロファイルを取得
-                    if let Ok(Some(user)) =
-                        crate::storage::repository::user_repository::get_user(user_id).await
-                    {
-                        // Updated path and added .await
-                        users.push(UserListItem {
-                            id: user.id,
-                            display_name: user.display_name,
-                        });
-                    }
-                }
+        if !path.is_file() || !path.extension().is_some_and(|ext| ext == "key") {
+            continue;
+        }
+        let legacy_id = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(id) if is_legacy_user_id(id) => id.to_string(),
+            _ => continue,
+        };
+
+        match migrate_legacy_user(&legacy_id).await {
+            Ok(user_id) => {
+                println!("Migrated legacy user {} to {}", legacy_id, user_id);
+                migrated += 1;
             }
+            Err(e) => eprintln!("Failed to migrate legacy user {}: {}", legacy_id, e),
         }
     }
 
-    Ok(users)
+    Ok(migrated)
+}
+
+/// 旧形式ユーザー1件を移行し、新しいユーザーIDを返します。
+///
+/// 途中で失敗した場合も鍵ファイルは残るため、次回起動時に再実行されます。
+async fn migrate_legacy_user(legacy_id: &str) -> Result<String, AuthError> {
+    let key_pair = load_legacy_key_pair(legacy_id)?;
+    let public_key = key_pair.public_key().as_ref();
+    let user_id = user_id_from_public_key(public_key)
+        .ok_or_else(|| AuthError::InvalidKey("Unexpected public key length".to_string()))?;
+
+    // 1. プロフィールを新しいIDで署名し直して保存
+    if let Some(mut user) = user_repository::get_unverified_user(legacy_id).await? {
+        user.id = user_id.clone();
+        user.public_key = general_purpose::STANDARD.encode(public_key);
+        sign_record(&mut user, &key_pair);
+        user_repository::save_user(&user).await?;
+    }
+
+    // 2. 旧IDからの参照を解決できるよう対応付けを保存
+    let mut alias = UserAlias {
+        legacy_id: legacy_id.to_string(),
+        user_id: user_id.clone(),
+        signature: None,
+    };
+    sign_record(&mut alias, &key_pair);
+    user_repository::save_user_alias(&alias).await?;
+
+    // 3. 旧IDで作成された投稿を新しいIDで署名し直す
+    let legacy_posts = post_repository::list_unverified_posts()
+        .await?
+        .into_iter()
+        .filter(|post| post.author_id == legacy_id);
+    for mut post in legacy_posts {
+        // 投稿のキーには作成者IDが含まれるため、旧IDのキーを削除してから保存する
+        post_repository::delete_post(&post.id).await?;
+        post.author_id = user_id.clone();
+        sign_record(&mut post, &key_pair);
+        post_repository::save_post(&post).await?;
+    }
+
+    // 4. ユーザー設定を新しいIDへ移動
+    if let Some(mut settings) = settings_repository::get_settings(Some(legacy_id)).await? {
+        settings.user_id = Some(user_id.clone());
+        settings_repository::save_settings(&settings).await?;
+        settings_repository::delete_settings(Some(legacy_id)).await?;
+    }
+
+    // 5. 旧プロフィールを削除し、鍵ファイルを新しいIDへ移動
+    user_repository::delete_user(legacy_id).await?;
+    let key_dir = legacy_key_dir();
+    fs::rename(
+        key_dir.join(format!("{}.key", legacy_id)),
+        key_dir.join(format!("{}.key", user_id)),
+    )
+    .map_err(|e| AuthError::FileSystem(format!("Failed to rename key file: {}", e)))?;
+
+    Ok(user_id)
 }
 
 // テストコードは省略
@@ -1,9 +1,36 @@
-use crate::models::post::Post;
+use crate::identity::session::{Session, SessionError, SessionState};
+use crate::identity::{public_key_from_user_id, sign_record};
+use crate::media;
+use crate::media::pipeline;
+use crate::models::post::{
+    extract_hashtags, extract_mentions, normalize_hashtag, Attachment, AttachmentPreview, Post,
+    PostCursor, PostPage, PostRevision, Repost, Thread, TimelinePage, Visibility,
+};
+use crate::models::reaction::{Reaction, ReactionKind, ReactionSummary};
+use crate::storage::blob_store;
+use crate::storage::repository::{
+    follow_repository, moderation_repository, post_repository, reaction_repository, user_repository,
+};
+use crate::validation::{self, ValidationError, POST_CONTENT, SEARCH_QUERY};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use tauri::command;
+use std::collections::HashMap;
+use tauri::ipc::{InvokeBody, Request, Response};
+use tauri::{command, State};
 use uuid::Uuid;
 
+/// 1ページあたりの投稿数の既定値
+const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// 1ページあたりの投稿数の上限
+const MAX_PAGE_SIZE: usize = 100;
+
+/// 1件の投稿に添付できるファイル数の上限
+const MAX_ATTACHMENTS: usize = 4;
+
+/// 添付ファイル1件あたりのサイズの上限（10 MiB）
+const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
+
 /// 投稿エラー
 ///
 /// 投稿処理中に発生する可能性のあるエラーを定義します。
@@ -19,7 +46,23 @@ pub enum PostError {
 
     /// 入力検証エラー
     #[error("Validation error: {0}")]
-    Validation(String),
+    Validation(#[from] ValidationError),
+
+    /// 投稿が見つからない
+    #[error("Post not found")]
+    PostNotFound,
+
+    /// リポストが見つからない
+    #[error("Repost not found")]
+    RepostNotFound,
+
+    /// 投稿の作成者ではない
+    #[error("Only the author can modify this post")]
+    NotAuthor,
+
+    /// 認証エラー
+    #[error("Authentication error: {0}")]
+    Auth(String),
 
     /// その他のエラー
     #[error("{0}")]
@@ -33,16 +76,36 @@ impl From<crate::storage::StorageError> for PostError {
     }
 }
 
+impl From<SessionError> for PostError {
+    fn from(err: SessionError) -> Self {
+        PostError::Auth(err.to_string())
+    }
+}
+
 /// エラーのシリアライズ実装
+///
+/// 入力検証エラーはフィールド名を含む構造化された形で、それ以外は文字列で返します。
 impl Serialize for PostError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        match self {
+            PostError::Validation(error) => error.serialize(serializer),
+            _ => serializer.serialize_str(self.to_string().as_ref()),
+        }
     }
 }
 
+/// 投稿に添付するアップロード済みのファイル
+#[derive(Debug, Deserialize)]
+pub struct AttachmentUpload {
+    /// 元のファイル名
+    pub name: String,
+    /// `upload_attachment` が返した iroh-blobs のハッシュ
+    pub hash: String,
+}
+
 /// 投稿結果
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PostResult {
@@ -53,38 +116,286 @@ pub struct PostResult {
 
 /// 投稿作成コマンド
 ///
-/// 新しい投稿を作成し、ストレージに保存してネットワークに発信します。
+/// サインイン中のユーザーとして新しい投稿を作成し、ストレージに保存してネットワークに発信します。
+/// `visibility` に `followers` を指定すると、承認したフォロワーだけに共有する投稿になります。
 #[command]
-pub async fn create_post(author_id: String, content: String) -> Result<PostResult, PostError> {
+pub async fn create_post(
+    session: State<'_, SessionState>,
+    content: String,
+    visibility: Option<Visibility>,
+) -> Result<PostResult, PostError> {
+    let current = session.require()?;
+
     // 入力検証
-    if content.trim().is_empty() {
-        return Err(PostError::Validation("Content cannot be empty".to_string()));
+    let content = POST_CONTENT.clean(&content)?;
+
+    let visibility = visibility.unwrap_or_default();
+    publish_new_post(
+        current.as_ref(),
+        content,
+        Vec::new(),
+        None,
+        None,
+        visibility,
+    )
+    .await
+}
+
+/// 添付ファイルアップロードコマンド
+///
+/// リクエストの本文をそのまま添付ファイルの内容として受け取り、サイズを確認してから
+/// iroh-blobs に保存してハッシュを返します。JSON の配列として送らないため、
+/// 上限を超えるファイルは読み込む前に拒否されます。
+/// 画像の場合はプレビューの作成をキューに入れます。
+/// 保存したハッシュはセッションに記録され、`create_post_with_attachments` で添付できます。
+#[command]
+pub async fn upload_attachment(
+    session: State<'_, SessionState>,
+    request: Request<'_>,
+) -> Result<String, PostError> {
+    let current = session.require()?;
+
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err(ValidationError::invalid("data", "expected raw bytes").into());
+    };
+    if data.is_empty() {
+        return Err(ValidationError::Empty { field: "data" }.into());
+    }
+    if data.len() > MAX_ATTACHMENT_SIZE {
+        let reason = format!("exceeds maximum size of {} bytes", MAX_ATTACHMENT_SIZE);
+        return Err(ValidationError::invalid("data", reason).into());
+    }
+
+    let is_image = media::inspect(data, None).is_image();
+    let hash = blob_store::import_blob(data.clone()).await?;
+    if is_image {
+        pipeline::enqueue_preview(hash);
+    }
+    current.record_upload(hash.to_string());
+
+    Ok(hash.to_string())
+}
+
+/// 添付ファイル付き投稿作成コマンド
+///
+/// このセッションで `upload_attachment` により保存した添付ファイルのハッシュ・MIMEタイプ・
+/// サイズ・画像の大きさを添付ファイルのメタデータとして記録した投稿を作成します。
+/// メタデータは保存された内容から判定するため、呼び出し側の申告には依存しません。
+/// 添付ファイルがある場合、本文は空でも構いません。
+/// 公開範囲は `create_post` と同じです。添付ファイルの内容はハッシュを知っていれば取得できます。
+#[command]
+pub async fn create_post_with_attachments(
+    session: State<'_, SessionState>,
+    content: String,
+    attachments: Vec<AttachmentUpload>,
+    visibility: Option<Visibility>,
+) -> Result<PostResult, PostError> {
+    let current = session.require()?;
+
+    // 入力検証
+    let field = if attachments.is_empty() {
+        POST_CONTENT
+    } else {
+        POST_CONTENT.optional()
+    };
+    let content = field.clean(&content)?;
+    validation::check_count("attachments", attachments.len(), MAX_ATTACHMENTS)?;
+
+    // アップロード済みの内容からメタデータを作成
+    let mut stored = Vec::with_capacity(attachments.len());
+    for upload in attachments {
+        let hash = parse_hash(&upload.hash)?;
+        // 他の投稿の添付ファイルや編集履歴など、手元にある任意の内容は添付できない
+        if !current.is_uploaded(&upload.hash) {
+            return Err(ValidationError::invalid(
+                "attachments",
+                format!("{} has not been uploaded", upload.name),
+            )
+            .into());
+        }
+        let Some(data) = blob_store::read_blob(hash).await? else {
+            return Err(ValidationError::invalid(
+                "attachments",
+                format!("{} has not been uploaded", upload.name),
+            )
+            .into());
+        };
+        if data.len() > MAX_ATTACHMENT_SIZE {
+            return Err(ValidationError::invalid(
+                "attachments",
+                format!(
+                    "{} exceeds maximum size of {} bytes",
+                    upload.name, MAX_ATTACHMENT_SIZE
+                ),
+            )
+            .into());
+        }
+
+        let info = media::inspect(&data, Some(&upload.name));
+        stored.push(Attachment {
+            hash: hash.to_string(),
+            mime_type: info.mime_type,
+            size: data.len() as u64,
+            width: info.width,
+            height: info.height,
+            name: Some(upload.name).filter(|name| !name.is_empty()),
+        });
+    }
+
+    let visibility = visibility.unwrap_or_default();
+    publish_new_post(current.as_ref(), content, stored, None, None, visibility).await
+}
+
+/// 添付ファイル取得コマンド
+///
+/// 投稿 `post_id` の添付ファイル、またはその縮小画像の内容を返します。同期された投稿の
+/// 添付ファイルがまだ手元に無い場合は、ピアから取得します。
+/// サイズが上限を超える添付ファイルは、投稿の申告に関わらず取得の途中で中止します。
+/// サインイン中のユーザーが読めないフォロワー限定の投稿の添付ファイルは取得できません。
+#[command]
+pub async fn get_attachment(
+    session: State<'_, SessionState>,
+    post_id: String,
+    hash: String,
+) -> Result<Response, PostError> {
+    let blob_hash = parse_hash(&hash)?;
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
+    let post = post_repository::get_visible_post(&post_id, viewer_id)
+        .await?
+        .ok_or(PostError::PostNotFound)?;
+
+    let mut found = false;
+    for attachment in &post.attachments {
+        if attachment.hash == hash {
+            if attachment.size > MAX_ATTACHMENT_SIZE as u64 {
+                let reason = format!("exceeds maximum size of {} bytes", MAX_ATTACHMENT_SIZE);
+                return Err(ValidationError::invalid("hash", reason).into());
+            }
+            found = true;
+            break;
+        }
+        let original = parse_hash(&attachment.hash)?;
+        if let Some(preview) = blob_store::get_preview(original).await? {
+            if preview.thumbnails.iter().any(|image| image.hash == hash) {
+                found = true;
+                break;
+            }
+        }
+    }
+    if !found {
+        return Err(ValidationError::invalid("hash", "not an attachment of this post").into());
+    }
+
+    let bytes = blob_store::fetch_blob(blob_hash, MAX_ATTACHMENT_SIZE as u64).await?;
+
+    Ok(Response::new(bytes.to_vec()))
+}
+
+/// 添付画像プレビュー取得コマンド
+///
+/// 投稿 `post_id` の添付画像の縮小画像と blurhash を返します。縮小画像の内容は
+/// `get_attachment` で取得します。
+/// プレビューがまだ作成されていない場合は、元の画像を（必要ならピアから）取得して作成します。
+/// 画像以外の添付ファイルや、サイズが上限を超える添付ファイルでは `None` を返します。
+/// サイズは投稿の申告ではなく取得した内容で確認します。
+/// サインイン中のユーザーが読めないフォロワー限定の投稿の添付ファイルは対象外です。
+#[command]
+pub async fn get_attachment_preview(
+    session: State<'_, SessionState>,
+    post_id: String,
+    hash: String,
+) -> Result<Option<AttachmentPreview>, PostError> {
+    let blob_hash = parse_hash(&hash)?;
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
+
+    let post = post_repository::get_visible_post(&post_id, viewer_id)
+        .await?
+        .ok_or(PostError::PostNotFound)?;
+    let Some(attachment) = post.attachments.iter().find(|a| a.hash == hash) else {
+        return Err(ValidationError::invalid("hash", "not an attachment of this post").into());
+    };
+
+    if let Some(preview) = blob_store::get_preview(blob_hash).await? {
+        return Ok(Some(preview));
+    }
+    if !attachment.mime_type.starts_with("image/") {
+        return Ok(None);
+    }
+    match blob_store::fetch_blob(blob_hash, MAX_ATTACHMENT_SIZE as u64).await {
+        Ok(_) => {}
+        Err(crate::storage::StorageError::Validation(_)) => return Ok(None),
+        Err(e) => return Err(e.into()),
     }
 
-    if content.len() > 500 {
-        return Err(PostError::Validation(
-            "Content exceeds maximum length of 500 characters".to_string(),
-        ));
+    Ok(pipeline::ensure_preview(blob_hash).await?)
+}
+
+/// フォロワー限定の投稿ではないことを確認します。
+///
+/// リポストや引用は、フォロワー限定の投稿をフォロワー以外に広めるため許可しません。
+/// 公開の返信も投稿IDをスレッドのキーとして公開してしまうため、同様に許可しません。
+fn require_public(post: &Post, field: &'static str) -> Result<(), PostError> {
+    if post.visibility != Visibility::Public {
+        let reason = "followers-only posts cannot be shared or replied to";
+        return Err(ValidationError::invalid(field, reason).into());
+    }
+    Ok(())
+}
+
+/// 本文と添付ファイルから投稿を作成し、署名・保存・発信します。
+///
+/// `parent` を指定すると、その投稿への返信として同じスレッドに加えます。
+/// `quoted` を指定すると、その投稿を引用した投稿になります。
+/// `content` は検証・正規化済みの本文です。
+/// フォロワー限定の投稿は、フォローに承認が必要なユーザーだけが作成でき、発信されません。
+async fn publish_new_post(
+    current: &Session,
+    content: String,
+    attachments: Vec<Attachment>,
+    parent: Option<&Post>,
+    quoted: Option<&Post>,
+    visibility: Visibility,
+) -> Result<PostResult, PostError> {
+    if visibility == Visibility::Followers
+        && !follow_repository::requires_approval(current.user_id()).await?
+    {
+        let reason = "followers-only posts require follow approval to be enabled";
+        return Err(ValidationError::invalid("visibility", reason).into());
     }
 
     // 1. 投稿IDを生成
     let post_id = Uuid::new_v4().to_string();
 
-    // 2. 投稿を作成
-    let post = Post {
+    // 2. 本文からハッシュタグとメンションを抽出
+    let hashtags = extract_hashtags(&content);
+    let mentions = resolve_mentions(&content).await?;
+
+    // 3. 投稿を作成し、作成者の鍵で署名
+    let mut post = Post {
         id: post_id.clone(),
-        author_id,
+        author_id: current.user_id().to_string(),
         content,
-        attachments: vec![],
-        mentions: vec![],
-        hashtags: vec![],
+        attachments,
+        mentions,
+        hashtags,
         created_at: Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: parent.map(|parent| parent.id.clone()),
+        root_id: parent.map(|parent| parent.root_id.clone().unwrap_or_else(|| parent.id.clone())),
+        quote_of: quoted.map(|quoted| quoted.id.clone()),
+        visibility,
+        signature: None,
+        key_revoked: false,
     };
+    sign_record(&mut post, current.key_pair());
 
-    // 3. 投稿を保存
+    // 4. 投稿を保存
     crate::storage::repository::post_repository::save_post(&post).await?; // Updated path and added .await
 
-    // 4. iroh-gossipで投稿を発信
+    // 5. iroh-gossipで投稿を発信
     match crate::network::iroh::publish_post(&post).await {
         Ok(_) => Ok(PostResult {
             post_id,
@@ -103,59 +414,555 @@ pub async fn create_post(author_id: String, content: String) -> Result<PostResul
     }
 }
 
+/// 返信作成コマンド
+///
+/// サインイン中のユーザーとして指定された投稿に返信します。返信は返信先と同じスレッドに属します。
+/// フォロワー限定の投稿には返信できません。
+#[command]
+pub async fn create_reply(
+    session: State<'_, SessionState>,
+    parent_id: String,
+    content: String,
+) -> Result<PostResult, PostError> {
+    let current = session.require()?;
+
+    // 入力検証
+    let content = POST_CONTENT.clean(&content)?;
+
+    let parent = post_repository::get_visible_post(&parent_id, Some(current.user_id()))
+        .await?
+        .ok_or(PostError::PostNotFound)?;
+    require_public(&parent, "parent_id")?;
+
+    publish_new_post(
+        current.as_ref(),
+        content,
+        Vec::new(),
+        Some(&parent),
+        None,
+        Visibility::Public,
+    )
+    .await
+}
+
+/// 引用投稿作成コマンド
+///
+/// サインイン中のユーザーとして、指定された投稿を引用した投稿を作成します。
+/// フォロワー限定の投稿は引用できません。
+#[command]
+pub async fn create_quote(
+    session: State<'_, SessionState>,
+    post_id: String,
+    content: String,
+) -> Result<PostResult, PostError> {
+    let current = session.require()?;
+
+    // 入力検証
+    let content = POST_CONTENT.clean(&content)?;
+
+    let quoted = post_repository::get_visible_post(&post_id, Some(current.user_id()))
+        .await?
+        .ok_or(PostError::PostNotFound)?;
+    require_public(&quoted, "post_id")?;
+
+    publish_new_post(
+        current.as_ref(),
+        content,
+        Vec::new(),
+        None,
+        Some(&quoted),
+        Visibility::Public,
+    )
+    .await
+}
+
+/// リポストコマンド
+///
+/// サインイン中のユーザーとして指定された投稿をリポストします。
+/// 同じ投稿を既にリポストしている場合は何もしません。フォロワー限定の投稿はリポストできません。
+/// リポストは同期によってピアにも反映されます。
+#[command]
+pub async fn repost(
+    session: State<'_, SessionState>,
+    post_id: String,
+) -> Result<PostResult, PostError> {
+    let current = session.require()?;
+
+    let post = post_repository::get_visible_post(&post_id, Some(current.user_id()))
+        .await?
+        .ok_or(PostError::PostNotFound)?;
+    require_public(&post, "post_id")?;
+    if let Some(existing) = post_repository::find_repost(current.user_id(), &post_id).await? {
+        return Ok(PostResult {
+            post_id: existing.post_id,
+            success: true,
+            message: Some("Post already reposted".to_string()),
+        });
+    }
+
+    let mut repost = Repost {
+        id: Uuid::new_v4().to_string(),
+        reposter_id: current.user_id().to_string(),
+        post_id: post.id,
+        post_author_id: post.author_id,
+        created_at: Utc::now().timestamp(),
+        signature: None,
+    };
+    sign_record(&mut repost, current.key_pair());
+
+    post_repository::save_repost(&repost).await?;
+
+    Ok(PostResult {
+        post_id,
+        success: true,
+        message: None,
+    })
+}
+
+/// リポスト取り消しコマンド
+///
+/// サインイン中のユーザーによる指定された投稿のリポストを取り消します。
+/// 取り消しは同期によってピアにも反映されます。
+#[command]
+pub async fn unrepost(
+    session: State<'_, SessionState>,
+    post_id: String,
+) -> Result<PostResult, PostError> {
+    let current = session.require()?;
+
+    let repost = post_repository::find_repost(current.user_id(), &post_id)
+        .await?
+        .ok_or(PostError::RepostNotFound)?;
+
+    post_repository::delete_repost(&repost).await?;
+
+    Ok(PostResult {
+        post_id,
+        success: true,
+        message: None,
+    })
+}
+
+/// スレッド取得コマンド
+///
+/// 指定された投稿が属するスレッドを、最初の投稿と返信の木として取得します。
+/// 返信先がまだ同期されていない返信は `parent_missing` を付けて返します。
+/// サインイン中のユーザーがミュート・ブロックしているユーザーの投稿と、
+/// 承認されていないフォロワー限定の投稿は含まれません。
+#[command]
+pub async fn get_thread(
+    session: State<'_, SessionState>,
+    post_id: String,
+) -> Result<Thread, PostError> {
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
+    let filter = moderation_repository::get_filter(viewer_id).await?;
+
+    let post = post_repository::get_visible_post(&post_id, viewer_id)
+        .await?
+        .ok_or(PostError::PostNotFound)?;
+
+    let root_id = post.root_id.clone().unwrap_or_else(|| post.id.clone());
+    let root = if root_id == post.id {
+        Some(post)
+    } else {
+        post_repository::get_visible_post(&root_id, viewer_id).await?
+    };
+    let root = root.filter(|root| !filter.hides(&root.author_id));
+    let replies = post_repository::list_thread_replies(&root_id, viewer_id).await?;
+
+    Ok(Thread::assemble(root_id, root, replies))
+}
+
+/// 返信数取得コマンド
+///
+/// 指定された投稿それぞれへの直接の返信の数を返します。
+#[command]
+pub async fn get_reply_counts(post_ids: Vec<String>) -> Result<HashMap<String, usize>, PostError> {
+    validation::check_count("post_ids", post_ids.len(), MAX_PAGE_SIZE)?;
+
+    let mut counts = HashMap::with_capacity(post_ids.len());
+    for post_id in post_ids {
+        let count = post_repository::count_replies(&post_id).await?;
+        counts.insert(post_id, count);
+    }
+    Ok(counts)
+}
+
+/// リアクション追加コマンド
+///
+/// サインイン中のユーザーとして投稿にリアクションします。
+/// リアクションは投稿とは別のエントリとして保存され、同期によってピアにも反映されます。
+#[command]
+pub async fn add_reaction(
+    session: State<'_, SessionState>,
+    post_id: String,
+    kind: ReactionKind,
+) -> Result<ReactionSummary, PostError> {
+    let current = session.require()?;
+
+    let visible = post_repository::get_visible_post(&post_id, Some(current.user_id())).await?;
+    if visible.is_none() {
+        return Err(PostError::PostNotFound);
+    }
+
+    let mut reaction = Reaction {
+        post_id: post_id.clone(),
+        reactor_id: current.user_id().to_string(),
+        kind,
+        created_at: Utc::now().timestamp(),
+        signature: None,
+    };
+    sign_record(&mut reaction, current.key_pair());
+
+    reaction_repository::save_reaction(&reaction).await?;
+
+    Ok(reaction_repository::summarize_reactions(&post_id, Some(current.user_id())).await?)
+}
+
+/// リアクション取り消しコマンド
+///
+/// サインイン中のユーザーによる投稿へのリアクションを取り消します。
+/// 取り消しは同期によってピアにも反映されます。
+#[command]
+pub async fn remove_reaction(
+    session: State<'_, SessionState>,
+    post_id: String,
+    kind: ReactionKind,
+) -> Result<ReactionSummary, PostError> {
+    let current = session.require()?;
+
+    reaction_repository::remove_reaction(&post_id, current.user_id(), kind).await?;
+
+    Ok(reaction_repository::summarize_reactions(&post_id, Some(current.user_id())).await?)
+}
+
+/// リアクション集計取得コマンド
+///
+/// 指定された投稿それぞれへのリアクションを種類ごとに集計して返します。
+/// サインインしている場合は、自分のリアクションも返します。
+#[command]
+pub async fn get_reactions(
+    session: State<'_, SessionState>,
+    post_ids: Vec<String>,
+) -> Result<HashMap<String, ReactionSummary>, PostError> {
+    validation::check_count("post_ids", post_ids.len(), MAX_PAGE_SIZE)?;
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
+
+    let mut summaries = HashMap::with_capacity(post_ids.len());
+    for post_id in post_ids {
+        let summary = reaction_repository::summarize_reactions(&post_id, viewer_id).await?;
+        summaries.insert(post_id, summary);
+    }
+    Ok(summaries)
+}
+
+/// 投稿編集コマンド
+///
+/// サインイン中のユーザーの投稿の本文を変更します。編集前の投稿は iroh-blobs に保存され、
+/// 編集後の投稿の `revisions` から参照されます。添付ファイルは変更されません。
+/// 編集は同期によってピアにも反映されます。
+#[command]
+pub async fn edit_post(
+    session: State<'_, SessionState>,
+    post_id: String,
+    content: String,
+) -> Result<PostResult, PostError> {
+    let current = session.require()?;
+
+    let post = post_repository::get_post(&post_id)
+        .await?
+        .ok_or(PostError::PostNotFound)?;
+    if post.author_id != current.user_id() {
+        return Err(PostError::NotAuthor);
+    }
+
+    // 入力検証
+    let field = if post.attachments.is_empty() {
+        POST_CONTENT
+    } else {
+        POST_CONTENT.optional()
+    };
+    let content = field.clean(&content)?;
+
+    // 1. 編集前の投稿を保存
+    let previous = Post {
+        key_revoked: false,
+        ..post.clone()
+    };
+    let previous_json = serde_json::to_vec(&previous)
+        .map_err(|e| PostError::Other(format!("Failed to serialize post {}: {}", post_id, e)))?;
+    let previous_hash = blob_store::import_blob(previous_json).await?;
+
+    // 2. 本文を差し替えて署名
+    let mut revisions = post.revisions.clone();
+    revisions.push(PostRevision {
+        hash: previous_hash.to_string(),
+        written_at: post.edited_at.unwrap_or(post.created_at),
+    });
+    let mut updated = Post {
+        hashtags: extract_hashtags(&content),
+        mentions: resolve_mentions(&content).await?,
+        content,
+        edited_at: Some(Utc::now().timestamp()),
+        revisions,
+        signature: None,
+        key_revoked: false,
+        ..post
+    };
+    sign_record(&mut updated, current.key_pair());
+
+    // 3. 同じキーに保存（編集は投稿ドキュメントの同期でピアに反映される）
+    post_repository::save_post(&updated).await?;
+
+    Ok(PostResult {
+        post_id,
+        success: true,
+        message: None,
+    })
+}
+
+/// 投稿削除コマンド
+///
+/// サインイン中のユーザーの投稿を削除します。削除は同期によってピアにも反映されます。
+#[command]
+pub async fn delete_post(
+    session: State<'_, SessionState>,
+    post_id: String,
+) -> Result<PostResult, PostError> {
+    let current = session.require()?;
+
+    let post = post_repository::get_post(&post_id)
+        .await?
+        .ok_or(PostError::PostNotFound)?;
+    if post.author_id != current.user_id() {
+        return Err(PostError::NotAuthor);
+    }
+
+    post_repository::delete_post(&post_id).await?;
+
+    Ok(PostResult {
+        post_id,
+        success: true,
+        message: None,
+    })
+}
+
+/// 投稿編集履歴取得コマンド
+///
+/// 編集された投稿の編集前の版を古い順に取得します。
+/// サインイン中のユーザーが読めないフォロワー限定の投稿の履歴は取得できません。
+#[command]
+pub async fn get_post_history(
+    session: State<'_, SessionState>,
+    post_id: String,
+) -> Result<Vec<Post>, PostError> {
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
+
+    post_repository::get_post_revisions(&post_id, viewer_id)
+        .await?
+        .ok_or(PostError::PostNotFound)
+}
+
 /// 投稿取得コマンド
 ///
-/// すべての投稿を取得します。
+/// すべての投稿とリポストを新しい順に1ページ分取得します。
+/// リポストされた投稿は、最新のリポストの位置にリポストしたユーザーと共に一度だけ含まれ、
+/// 引用投稿には引用元の投稿が、すべての投稿にはリアクションの集計が添えられます。
+/// サインイン中のユーザーがミュート・ブロックしているユーザーの投稿とリポストは含まれません。
+/// フォロワー限定の投稿は、作成者と承認されたフォロワーにだけ含まれます。
+/// 次のページは、前のページの `next_cursor` を `cursor` に指定して取得します。
 #[command]
 pub async fn get_posts(
+    session: State<'_, SessionState>,
     limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Vec<Post>, PostError> {
-    let _limit = limit.unwrap_or(20);
-    let _offset = offset.unwrap_or(0);
+    cursor: Option<String>,
+) -> Result<TimelinePage, PostError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let cursor = parse_cursor(cursor)?;
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
 
-    crate::storage::repository::post_repository::list_posts()
+    post_repository::list_timeline_page(cursor.as_ref(), limit, viewer_id)
         .await
         .map_err(Into::into) // Convert StorageError using From impl
 }
 
+/// ホームタイムライン取得コマンド
+///
+/// サインイン中のユーザー自身の投稿とフォロー中のユーザーの投稿を新しい順に1ページ分取得します。
+/// 次のページは、前のページの `next_cursor` を `cursor` に指定して取得します。
+#[command]
+pub async fn get_home_timeline(
+    session: State<'_, SessionState>,
+    limit: Option<usize>,
+    cursor: Option<String>,
+) -> Result<TimelinePage, PostError> {
+    let current = session.require()?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let cursor = parse_cursor(cursor)?;
+
+    post_repository::list_home_timeline_page(current.user_id(), cursor.as_ref(), limit)
+        .await
+        .map_err(Into::into)
+}
+
 /// ユーザー投稿取得コマンド
 ///
-/// 特定のユーザーの投稿を取得します。
+/// 特定のユーザーの投稿を新しい順に1ページ分取得します。
+/// サインイン中のユーザーがミュート・ブロックしているユーザーの投稿は含まれません。
 #[command]
 pub async fn get_user_posts(
+    session: State<'_, SessionState>,
     user_id: String,
     limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Vec<Post>, PostError> {
-    let _limit = limit.unwrap_or(20);
-    let _offset = offset.unwrap_or(0);
+    cursor: Option<String>,
+) -> Result<PostPage, PostError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let cursor = parse_cursor(cursor)?;
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
+
+    post_repository::list_posts_page(Some(&user_id), cursor.as_ref(), limit, viewer_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// ハッシュタグ別投稿取得コマンド
+///
+/// 指定されたハッシュタグ（先頭の `#` は省略可）が付いた投稿を新しい順に1ページ分取得します。
+/// サインイン中のユーザーがミュート・ブロックしているユーザーの投稿は含まれません。
+#[command]
+pub async fn get_posts_by_hashtag(
+    session: State<'_, SessionState>,
+    tag: String,
+    limit: Option<usize>,
+    cursor: Option<String>,
+) -> Result<PostPage, PostError> {
+    let tag = normalize_hashtag(&tag)
+        .ok_or_else(|| ValidationError::invalid("tag", format!("{} is not a hashtag", tag)))?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let cursor = parse_cursor(cursor)?;
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
+
+    post_repository::list_hashtag_page(&tag, cursor.as_ref(), limit, viewer_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// メンション取得コマンド
+///
+/// 指定されたユーザーをメンションしている投稿を新しい順に1ページ分取得します。
+/// サインイン中のユーザーがミュート・ブロックしているユーザーの投稿は含まれません。
+#[command]
+pub async fn get_mentions_for_user(
+    session: State<'_, SessionState>,
+    user_id: String,
+    limit: Option<usize>,
+    cursor: Option<String>,
+) -> Result<PostPage, PostError> {
+    if public_key_from_user_id(&user_id).is_none() {
+        let reason = format!("{} is not a user ID", user_id);
+        return Err(ValidationError::invalid("user_id", reason).into());
+    }
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let cursor = parse_cursor(cursor)?;
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
 
-    crate::storage::repository::post_repository::list_user_posts(&user_id)
-        .await // Updated path, added .await, removed unused args
-        .map_err(|e: crate::storage::StorageError| PostError::Storage(e.to_string()))
-    // Convert error to string
+    post_repository::list_mention_page(&user_id, cursor.as_ref(), limit, viewer_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// 本文中のメンションをユーザーIDに解決します。
+///
+/// 公開鍵由来のユーザーIDはそのまま使用します。それ以外の名前は、表示名
+/// （空白を除き、大文字と小文字を区別しない）が一致するユーザーが1人だけの場合に
+/// そのユーザーのIDに解決します。解決できないメンションは無視します。
+async fn resolve_mentions(content: &str) -> Result<Vec<String>, PostError> {
+    let mut users = None;
+    let mut mentions = Vec::new();
+
+    for name in extract_mentions(content) {
+        let user_id = if public_key_from_user_id(&name).is_some() {
+            Some(name)
+        } else {
+            if users.is_none() {
+                users = Some(user_repository::list_users().await?);
+            }
+            let key = display_name_key(&name);
+            let mut matches = users
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .filter(|user| display_name_key(&user.display_name) == key);
+            match (matches.next(), matches.next()) {
+                (Some(user), None) => Some(user.id.clone()),
+                _ => None,
+            }
+        };
+
+        if let Some(user_id) = user_id {
+            if !mentions.contains(&user_id) {
+                mentions.push(user_id);
+            }
+        }
+    }
+
+    Ok(mentions)
+}
+
+/// メンションと照合するための表示名の正規形
+fn display_name_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// フロントエンドから渡されたカーソル文字列を解析します。
+fn parse_cursor(cursor: Option<String>) -> Result<Option<PostCursor>, PostError> {
+    cursor
+        .map(|cursor| {
+            cursor
+                .parse()
+                .map_err(|e| ValidationError::invalid("cursor", e).into())
+        })
+        .transpose()
+}
+
+/// フロントエンドから渡された添付ファイルのハッシュを解析します。
+fn parse_hash(hash: &str) -> Result<iroh_blobs::Hash, PostError> {
+    blob_store::parse_hash(hash).map_err(|e| ValidationError::invalid("hash", e.to_string()).into())
 }
 
 /// 投稿検索コマンド
 ///
-/// 指定されたクエリに一致する投稿を検索します。
+/// 指定されたクエリに一致する投稿を、関連度と新しさの順に検索します。
+/// `"..."` で囲んだ語句はフレーズとして、末尾に `*` を付けた語は前方一致で検索します。
+/// サインイン中のユーザーがミュート・ブロックしているユーザーの投稿は含まれません。
+/// フォロワー限定の投稿は検索の対象外です。
 #[command]
-pub async fn search_posts(query: String, limit: Option<usize>) -> Result<Vec<Post>, PostError> {
+pub async fn search_posts(
+    session: State<'_, SessionState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<Post>, PostError> {
     // 入力検証
-    if query.trim().is_empty() {
-        return Err(PostError::Validation(
-            "Search query cannot be empty".to_string(),
-        ));
-    }
+    let query = SEARCH_QUERY.clean(&query)?;
 
-    let _limit = limit.unwrap_or(50);
+    let limit = limit.unwrap_or(50).min(MAX_PAGE_SIZE);
+    let viewer = session.current();
+    let viewer_id = viewer.as_ref().map(|viewer| viewer.user_id());
 
-    // ローカルの投稿からの簡易検索
-    // TODO: Implement search functionality in post_repository
-    // For now, return an empty vec or an error
-    // crate::storage::repository::post_repository::search_posts(&query, limit).await
-    Ok(vec![]) // Placeholder: return empty results
+    // ローカルの全文検索インデックスから検索
+    crate::storage::repository::post_repository::search_posts(&query, limit, viewer_id)
+        .await
+        .map_err(Into::into)
 }
 
 // テストコードは省略
@@ -1,6 +1,28 @@
+use crate::identity::session::{Session, SessionError, SessionState};
+use crate::identity::{envelope, sign_record};
+use crate::media;
+use crate::models::follow::{FollowApproval, FollowCounts, FollowEdge, FollowRequest};
+use crate::models::moderation::{ModerationEntry, ModerationKind};
 use crate::models::user::User;
+use crate::storage::blob_store;
+use crate::storage::post_docs;
+use crate::storage::repository::{follow_repository, moderation_repository, user_repository};
+use crate::validation::{ValidationError, BIO, DISPLAY_NAME};
 use serde::{Deserialize, Serialize};
-use tauri::command;
+use tauri::ipc::{InvokeBody, Request, Response};
+use tauri::{command, State};
+
+/// アバター画像の最大サイズ（バイト）
+const MAX_AVATAR_SIZE: usize = 2 * 1024 * 1024;
+
+/// アバター画像の最大の幅・高さ（ピクセル）
+const MAX_AVATAR_DIMENSION: u32 = 4096;
+
+/// アバターの縮小画像の幅・高さ（ピクセル）
+const AVATAR_THUMBNAIL_SIZE: u32 = 128;
+
+/// アバターに使用できる画像形式
+const AVATAR_MIME_TYPES: &[&str] = &["image/png", "image/jpeg", "image/webp", "image/gif"];
 
 /// プロフィールエラー
 ///
@@ -19,9 +41,21 @@ pub enum ProfileError {
     #[error("User not found")]
     UserNotFound,
 
+    /// アバターが設定されていない
+    #[error("Avatar not found")]
+    AvatarNotFound,
+
+    /// フォローリクエストが見つからない
+    #[error("Follow request not found")]
+    FollowRequestNotFound,
+
     /// 入力検証エラー
     #[error("Validation error: {0}")]
-    Validation(String),
+    Validation(#[from] ValidationError),
+
+    /// 認証エラー
+    #[error("Authentication error: {0}")]
+    Auth(String),
 
     /// その他のエラー
     #[error("{0}")]
@@ -35,13 +69,24 @@ impl From<crate::storage::StorageError> for ProfileError {
     }
 }
 
+impl From<SessionError> for ProfileError {
+    fn from(err: SessionError) -> Self {
+        ProfileError::Auth(err.to_string())
+    }
+}
+
 /// エラーのシリアライズ実装
+///
+/// 入力検証エラーはフィールド名を含む構造化された形で、それ以外は文字列で返します。
 impl Serialize for ProfileError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        match self {
+            ProfileError::Validation(error) => error.serialize(serializer),
+            _ => serializer.serialize_str(self.to_string().as_ref()),
+        }
     }
 }
 
@@ -54,48 +99,37 @@ pub struct ProfileUpdateResult {
 
 /// プロフィール取得コマンド
 ///
-/// 指定されたユーザーIDのプロフィールを取得します。
+/// 指定されたユーザーIDのプロフィールを取得します。`following` と `followers` は
+/// フォロー関係のストアから補完されます。
 #[command]
 pub async fn get_profile(user_id: String) -> Result<Option<User>, ProfileError> {
-    crate::storage::repository::user_repository::get_user(&user_id)
-        .await
-        .map_err(Into::into) // Convert StorageError using From impl
+    match user_repository::get_user(&user_id).await? {
+        Some(user) => Ok(Some(follow_repository::with_follow_graph(user).await?)),
+        None => Ok(None),
+    }
 }
 
 /// プロフィール更新コマンド
 ///
-/// ユーザープロフィールを更新します。
+/// サインイン中のユーザーのプロフィールを更新します。
+/// `approval_required` を指定すると、フォローに承認が必要かどうかを切り替えます。
 #[command]
 pub async fn update_profile(
-    user_id: String,
+    session: State<'_, SessionState>,
     display_name: Option<String>,
     bio: Option<String>,
-    avatar: Option<String>,
+    approval_required: Option<bool>,
 ) -> Result<ProfileUpdateResult, ProfileError> {
     // 入力検証
-    if let Some(ref display_name) = display_name {
-        if display_name.trim().is_empty() {
-            return Err(ProfileError::Validation(
-                "Display name cannot be empty".to_string(),
-            ));
-        }
-        if display_name.len() > 50 {
-            return Err(ProfileError::Validation(
-                "Display name exceeds maximum length of 50 characters".to_string(),
-            ));
-        }
-    }
-
-    if let Some(ref bio) = bio {
-        if bio.len() > 160 {
-            return Err(ProfileError::Validation(
-                "Bio exceeds maximum length of 160 characters".to_string(),
-            ));
-        }
-    }
+    let display_name = display_name
+        .map(|display_name| DISPLAY_NAME.clean(&display_name))
+        .transpose()?;
+    let bio = bio.map(|bio| BIO.clean(&bio)).transpose()?;
 
     // 1. 既存のプロフィールを取得
-    let user = crate::storage::repository::user_repository::get_user(&user_id)
+    let current = session.require()?;
+    let user_id = current.user_id();
+    let user = crate::storage::repository::user_repository::get_user(user_id)
         .await // Updated path and added .await
         .map_err(|e: crate::storage::StorageError| ProfileError::Storage(e.to_string()))? // Convert error to string
         .ok_or(ProfileError::UserNotFound)?;
@@ -111,10 +145,12 @@ pub async fn update_profile(
         updated_user.bio = bio;
     }
 
-    if let Some(avatar) = avatar {
-        updated_user.avatar = Some(avatar);
+    if let Some(approval_required) = approval_required {
+        updated_user.approval_required = approval_required;
     }
 
+    sign_record(&mut updated_user, current.key_pair());
+
     // 3. 更新されたプロフィールを保存
     crate::storage::repository::user_repository::save_user(&updated_user)
         .await // Updated path and added .await
@@ -137,121 +173,562 @@ pub async fn update_profile(
     }
 }
 
-/// フォローコマンド
+/// アバター設定コマンド
 ///
-/// 指定されたユーザーをフォローします。
+/// リクエストの本文をそのまま画像として受け取り、検証して iroh-blobs に取り込み、
+/// 縮小画像とともにサインイン中のユーザーのアバターとして設定します。
+/// `upload_attachment` と同様に、上限を超える画像は読み込む前に拒否されます。
 #[command]
-pub async fn follow_user(
-    user_id: String,
-    target_user_id: String,
+pub async fn set_avatar(
+    session: State<'_, SessionState>,
+    request: Request<'_>,
 ) -> Result<ProfileUpdateResult, ProfileError> {
-    // 自分自身をフォローしようとしていないか確認
-    if user_id == target_user_id {
-        return Err(ProfileError::Validation(
-            "Cannot follow yourself".to_string(),
-        ));
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err(ValidationError::invalid("data", "expected raw bytes").into());
+    };
+    if data.is_empty() {
+        return Err(ValidationError::Empty { field: "data" }.into());
+    }
+    if data.len() > MAX_AVATAR_SIZE {
+        let reason = format!("exceeds maximum size of {} bytes", MAX_AVATAR_SIZE);
+        return Err(ValidationError::invalid("data", reason).into());
+    }
+
+    let info = media::inspect(data, None);
+    if !AVATAR_MIME_TYPES.contains(&info.mime_type.as_str()) {
+        let reason = format!("unsupported image type {}", info.mime_type);
+        return Err(ValidationError::invalid("data", reason).into());
     }
+    match (info.width, info.height) {
+        (Some(width), Some(height))
+            if width <= MAX_AVATAR_DIMENSION && height <= MAX_AVATAR_DIMENSION => {}
+        (Some(_), Some(_)) => {
+            let reason = format!(
+                "exceeds maximum dimensions of {}x{}",
+                MAX_AVATAR_DIMENSION, MAX_AVATAR_DIMENSION
+            );
+            return Err(ValidationError::invalid("data", reason).into());
+        }
+        _ => {
+            let reason = "failed to read image dimensions";
+            return Err(ValidationError::invalid("data", reason).into());
+        }
+    }
+
+    let thumbnail = media::make_thumbnail(data, AVATAR_THUMBNAIL_SIZE)
+        .map_err(|e| ValidationError::invalid("data", e.to_string()))?;
 
-    // 1. 現在のユーザープロフィールを取得
+    let avatar_hash = blob_store::import_blob(data.clone()).await?;
+    let thumbnail_hash = blob_store::import_blob(thumbnail.data).await?;
+
+    let current = session.require()?;
+    update_avatar(
+        &current,
+        Some(avatar_hash.to_string()),
+        Some(thumbnail_hash.to_string()),
+    )
+    .await
+}
+
+/// アバター削除コマンド
+///
+/// サインイン中のユーザーのアバターを外します。
+#[command]
+pub async fn remove_avatar(
+    session: State<'_, SessionState>,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let current = session.require()?;
+    update_avatar(&current, None, None).await
+}
+
+/// アバター取得コマンド
+///
+/// 指定されたユーザーのアバター画像を返します。`thumbnail` が true の場合は縮小画像を返します。
+/// 画像がまだ手元に無い場合はピアから取得します。
+#[command]
+pub async fn get_avatar(
+    user_id: String,
+    thumbnail: Option<bool>,
+) -> Result<Response, ProfileError> {
     let user = crate::storage::repository::user_repository::get_user(&user_id)
-        .await // Updated path and added .await
-        .map_err(|e: crate::storage::StorageError| ProfileError::Storage(e.to_string()))? // Convert error to string
+        .await?
         .ok_or(ProfileError::UserNotFound)?;
 
-    // 2. フォローリストに追加（重複確認）
-    let mut updated_user = user.clone();
+    // 縮小画像が無いプロフィールでは元の画像を返す
+    let hash = if thumbnail.unwrap_or(false) {
+        user.avatar_thumbnail.or(user.avatar)
+    } else {
+        user.avatar
+    }
+    .ok_or(ProfileError::AvatarNotFound)?;
 
-    if !updated_user.following.contains(&target_user_id) {
-        // ターゲットユーザーが存在するか確認
-        let target_exists = crate::storage::repository::user_repository::get_user(&target_user_id)
-            .await // Updated path and added .await
-            .map_err(|e: crate::storage::StorageError| ProfileError::Storage(e.to_string()))? // Convert error to string
-            .is_some();
+    let hash = blob_store::parse_hash(&hash).map_err(|_| ProfileError::AvatarNotFound)?;
+    let bytes = blob_store::fetch_blob(hash, MAX_AVATAR_SIZE as u64).await?;
 
-        if !target_exists {
-            return Err(ProfileError::UserNotFound);
-        }
+    Ok(Response::new(bytes.to_vec()))
+}
 
-        updated_user.following.push(target_user_id.clone());
+/// アバターを差し替えたプロフィールを署名・保存・発信します。
+async fn update_avatar(
+    current: &Session,
+    avatar: Option<String>,
+    avatar_thumbnail: Option<String>,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let mut user = crate::storage::repository::user_repository::get_user(current.user_id())
+        .await?
+        .ok_or(ProfileError::UserNotFound)?;
 
-        // 3. 更新されたプロフィールを保存
-        crate::storage::repository::user_repository::save_user(&updated_user)
-            .await // Updated path and added .await
-            .map_err(|e: crate::storage::StorageError| ProfileError::Storage(e.to_string()))?; // Convert error to string
+    user.avatar = avatar;
+    user.avatar_thumbnail = avatar_thumbnail;
+    sign_record(&mut user, current.key_pair());
 
-        // 4. フォロー関係を発信
-        match crate::network::iroh::publish_follow(&user_id, &target_user_id).await {
-            Ok(_) => Ok(ProfileUpdateResult {
+    crate::storage::repository::user_repository::save_user(&user).await?;
+
+    match crate::network::iroh::publish_profile(&user).await {
+        Ok(_) => Ok(ProfileUpdateResult {
+            success: true,
+            message: None,
+        }),
+        Err(e) => {
+            println!("Warning: Failed to publish profile update: {}", e);
+            Ok(ProfileUpdateResult {
                 success: true,
-                message: None,
-            }),
-            Err(e) => {
-                println!("Warning: Failed to publish follow relationship: {}", e);
-                Ok(ProfileUpdateResult {
-                    success: true,
-                    message: Some(format!("Follow successful but failed to publish: {}", e)),
-                })
-            }
+                message: Some(format!("Avatar updated but failed to publish: {}", e)),
+            })
         }
-    } else {
-        // 既にフォロー済み
-        Ok(ProfileUpdateResult {
+    }
+}
+
+/// フォローコマンド
+///
+/// サインイン中のユーザーとして指定されたユーザーをフォローします。
+/// フォロー関係はプロフィールとは別の署名付きエントリとして保存されます。
+/// 相手が投稿ドキュメントのチケットを公開している場合は、その同期を開始します。
+/// 相手がフォローに承認を必要としている場合は、フォロー関係の代わりにフォローリクエストを送信し、
+/// 承認されるまでフォローしていない状態になります。
+#[command]
+pub async fn follow_user(
+    session: State<'_, SessionState>,
+    target_user_id: String,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let current = session.require()?;
+    let user_id = current.user_id();
+
+    // 自分自身をフォローしようとしていないか確認
+    if user_id == target_user_id {
+        let reason = "cannot follow yourself";
+        return Err(ValidationError::invalid("target_user_id", reason).into());
+    }
+
+    // 1. 既にフォロー済みか確認
+    let previous = follow_repository::get_follow_edge(user_id, &target_user_id).await?;
+    if previous.as_ref().is_some_and(|edge| edge.active) {
+        return Ok(ProfileUpdateResult {
             success: true,
             message: Some("Already following this user".to_string()),
-        })
+        });
+    }
+
+    // 2. ターゲットユーザーが存在するか確認
+    let Some(target) = user_repository::get_user(&target_user_id).await? else {
+        return Err(ProfileError::UserNotFound);
+    };
+
+    // 3. フォロー関係に署名
+    let mut edge = FollowEdge::new(user_id, &target_user_id, true, previous.as_ref());
+    sign_record(&mut edge, current.key_pair());
+
+    // 投稿ドキュメントの同期を開始（失敗してもフォローは続行）
+    // 公開の投稿だけを含むため、承認を待たずに同期します
+    if let Err(e) = post_docs::follow_post_docs_of(&target).await {
+        println!("Warning: Failed to sync post document: {}", e);
+    }
+
+    // フォローに承認が必要なユーザーにはフォローリクエストを送信
+    if target.approval_required {
+        return send_follow_request(&current, edge).await;
+    }
+    follow_repository::save_follow_edge(&edge).await?;
+
+    // 4. フォロー関係を発信
+    match crate::network::iroh::publish_follow(&edge).await {
+        Ok(_) => Ok(ProfileUpdateResult {
+            success: true,
+            message: None,
+        }),
+        Err(e) => {
+            println!("Warning: Failed to publish follow relationship: {}", e);
+            Ok(ProfileUpdateResult {
+                success: true,
+                message: Some(format!("Follow successful but failed to publish: {}", e)),
+            })
+        }
     }
 }
 
 /// フォロー解除コマンド
 ///
-/// 指定されたユーザーのフォローを解除します。
+/// サインイン中のユーザーとして指定されたユーザーのフォローを解除し、
+/// その投稿ドキュメントの同期を停止します。
 #[command]
 pub async fn unfollow_user(
-    user_id: String,
+    session: State<'_, SessionState>,
     target_user_id: String,
 ) -> Result<ProfileUpdateResult, ProfileError> {
-    // 1. 現在のユーザープロフィールを取得
-    let user = crate::storage::repository::user_repository::get_user(&user_id)
-        .await // Updated path and added .await
-        .map_err(|e: crate::storage::StorageError| ProfileError::Storage(e.to_string()))? // Convert error to string
-        .ok_or(ProfileError::UserNotFound)?;
+    let current = session.require()?;
+    let user_id = current.user_id();
 
-    // 2. フォローリストから削除
-    let mut updated_user = user.clone();
+    // 1. フォローしているかどうかを確認
+    let previous = follow_repository::get_follow_edge(user_id, &target_user_id).await?;
+    if !previous.as_ref().is_some_and(|edge| edge.active) {
+        return Ok(ProfileUpdateResult {
+            success: true,
+            message: Some("User was not being followed".to_string()),
+        });
+    }
 
-    // フォローしているかどうかを確認
-    let was_following = updated_user.following.contains(&target_user_id);
+    // 2. フォロー解除を署名して保存
+    let mut edge = FollowEdge::new(user_id, &target_user_id, false, previous.as_ref());
+    sign_record(&mut edge, current.key_pair());
+    follow_repository::save_follow_edge(&edge).await?;
+    post_docs::unfollow_post_doc(&target_user_id).await?;
 
-    updated_user.following.retain(|id| id != &target_user_id);
+    // 3. フォロー解除を発信
+    match crate::network::iroh::publish_unfollow(&edge).await {
+        Ok(_) => Ok(ProfileUpdateResult {
+            success: true,
+            message: None,
+        }),
+        Err(e) => {
+            println!("Warning: Failed to publish unfollow relationship: {}", e);
+            Ok(ProfileUpdateResult {
+                success: true,
+                message: Some(format!("Unfollow successful but failed to publish: {}", e)),
+            })
+        }
+    }
+}
 
-    // 3. 更新されたプロフィールを保存
-    crate::storage::repository::user_repository::save_user(&updated_user)
-        .await // Updated path and added .await
-        .map_err(|e: crate::storage::StorageError| ProfileError::Storage(e.to_string()))?; // Convert error to string
+/// フォローリクエストを署名して保存し、発信します。
+///
+/// 承認されるとフォロー関係として保存されます。承認待ちのリクエストがある場合は何もしません。
+async fn send_follow_request(
+    current: &Session,
+    edge: FollowEdge,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    if follow_repository::get_follow_request(&edge.followee_id, &edge.follower_id)
+        .await?
+        .is_some()
+    {
+        return Ok(ProfileUpdateResult {
+            success: true,
+            message: Some("Follow request already sent".to_string()),
+        });
+    }
 
-    // 4. フォロー解除を発信
-    match crate::network::iroh::publish_unfollow(&user_id, &target_user_id).await {
-        Ok(_) => {
-            if was_following {
-                Ok(ProfileUpdateResult {
-                    success: true,
-                    message: None,
-                })
-            } else {
-                Ok(ProfileUpdateResult {
-                    success: true,
-                    message: Some("User was not being followed".to_string()),
-                })
-            }
+    let mut request = FollowRequest {
+        edge,
+        agreement_key: current.agreement_key(),
+        signature: None,
+    };
+    sign_record(&mut request, current.key_pair());
+    follow_repository::save_follow_request(&request).await?;
+
+    match crate::network::iroh::publish_follow_request(&request).await {
+        Ok(_) => Ok(ProfileUpdateResult {
+            success: true,
+            message: Some("Follow request sent".to_string()),
+        }),
+        Err(e) => {
+            println!("Warning: Failed to publish follow request: {}", e);
+            Ok(ProfileUpdateResult {
+                success: true,
+                message: Some(format!("Follow request saved but failed to publish: {}", e)),
+            })
         }
+    }
+}
+
+/// フォローリクエスト一覧取得コマンド
+///
+/// サインイン中のユーザーへの承認待ちのフォローリクエストを送ったユーザーのIDを新しい順に取得します。
+#[command]
+pub async fn get_follow_requests(
+    session: State<'_, SessionState>,
+) -> Result<Vec<String>, ProfileError> {
+    let current = session.require()?;
+    let requests = follow_repository::list_follow_requests(current.user_id()).await?;
+    Ok(requests
+        .into_iter()
+        .map(|request| request.edge.follower_id)
+        .collect())
+}
+
+/// フォローリクエスト承認コマンド
+///
+/// 指定されたユーザーからのフォローリクエストを承認し、フォロー関係として保存します。
+/// 承認にはフォロワー限定の投稿ドキュメントのチケットをリクエストの鍵宛てに暗号化して含め、
+/// 承認したフォロワーだけがフォロワー限定の投稿を同期できるようにします。
+/// フォロワー限定の投稿ドキュメントはユーザーの現在の鍵ごとのため、現在の鍵でのみ承認できます。
+#[command]
+pub async fn approve_follow_request(
+    session: State<'_, SessionState>,
+    follower_id: String,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let current = session.require()?;
+    let user_id = current.user_id();
+
+    let request = follow_repository::get_follow_request(user_id, &follower_id)
+        .await?
+        .ok_or(ProfileError::FollowRequestNotFound)?;
+    let ticket = post_docs::follower_doc_ticket(user_id).ok_or_else(|| {
+        ProfileError::Other("Follow requests can only be approved with the current key".to_string())
+    })?;
+
+    // 1. チケットをフォロワーの鍵宛てに暗号化して承認に署名
+    let sealed = envelope::seal_to(
+        &request.agreement_key,
+        ticket.as_bytes(),
+        &FollowApproval::ticket_context(&request.edge),
+    )
+    .map_err(|e| ProfileError::Other(format!("Failed to seal follower ticket: {}", e)))?;
+    let mut approval = FollowApproval {
+        edge: request.edge,
+        ticket: sealed,
+        created_at: chrono::Utc::now().timestamp(),
+        signature: None,
+    };
+    sign_record(&mut approval, current.key_pair());
+
+    // 2. 承認とフォロー関係を保存し、リクエストを削除
+    follow_repository::save_follow_approval(&approval).await?;
+    follow_repository::save_follow_edge(&approval.edge).await?;
+    follow_repository::remove_follow_request(user_id, &follower_id).await?;
+
+    // フォロワーがこの端末のユーザーの場合は同期を開始
+    if let Err(e) = post_docs::follow_follower_doc(&approval).await {
+        println!("Warning: Failed to sync follower document: {}", e);
+    }
+
+    // 3. 承認を発信
+    match crate::network::iroh::publish_follow_approval(&approval).await {
+        Ok(_) => Ok(ProfileUpdateResult {
+            success: true,
+            message: None,
+        }),
         Err(e) => {
-            println!("Warning: Failed to publish unfollow relationship: {}", e);
+            println!("Warning: Failed to publish follow approval: {}", e);
             Ok(ProfileUpdateResult {
                 success: true,
-                message: Some(format!("Unfollow successful but failed to publish: {}", e)),
+                message: Some(format!(
+                    "Follow request approved but failed to publish: {}",
+                    e
+                )),
             })
         }
     }
 }
 
+/// フォローリクエスト拒否コマンド
+///
+/// 指定されたユーザーからのフォローリクエストを削除します。拒否はリクエストした側には通知されません。
+#[command]
+pub async fn reject_follow_request(
+    session: State<'_, SessionState>,
+    follower_id: String,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let current = session.require()?;
+    let user_id = current.user_id();
+
+    if follow_repository::get_follow_request(user_id, &follower_id)
+        .await?
+        .is_none()
+    {
+        return Err(ProfileError::FollowRequestNotFound);
+    }
+    follow_repository::remove_follow_request(user_id, &follower_id).await?;
+
+    Ok(ProfileUpdateResult {
+        success: true,
+        message: None,
+    })
+}
+
+/// フォロー一覧取得コマンド
+///
+/// 指定されたユーザーがフォローしているユーザーのIDを取得します。
+#[command]
+pub async fn get_following(user_id: String) -> Result<Vec<String>, ProfileError> {
+    follow_repository::list_following(&user_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// フォロワー一覧取得コマンド
+///
+/// 指定されたユーザーをフォローしているユーザーのIDを取得します。
+#[command]
+pub async fn get_followers(user_id: String) -> Result<Vec<String>, ProfileError> {
+    follow_repository::list_followers(&user_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// フォロー数取得コマンド
+///
+/// 指定されたユーザーのフォロー数とフォロワー数を取得します。
+#[command]
+pub async fn get_follow_counts(user_id: String) -> Result<FollowCounts, ProfileError> {
+    follow_repository::count_follows(&user_id)
+        .await
+        .map_err(Into::into)
+}
+
+/// ミュートコマンド
+///
+/// サインイン中のユーザーとして指定されたユーザーをミュートし、
+/// その投稿とリポストをタイムラインと検索結果に表示しないようにします。
+#[command]
+pub async fn mute_user(
+    session: State<'_, SessionState>,
+    target_user_id: String,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let current = session.require()?;
+    add_moderation(&current, &target_user_id, ModerationKind::Mute).await
+}
+
+/// ミュート解除コマンド
+#[command]
+pub async fn unmute_user(
+    session: State<'_, SessionState>,
+    target_user_id: String,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let current = session.require()?;
+    moderation_repository::remove_moderation(
+        current.user_id(),
+        ModerationKind::Mute,
+        &target_user_id,
+    )
+    .await?;
+
+    Ok(ProfileUpdateResult {
+        success: true,
+        message: None,
+    })
+}
+
+/// ブロックコマンド
+///
+/// サインイン中のユーザーとして指定されたユーザーをブロックします。
+/// ミュートと同様に表示しないことに加え、この端末ではその投稿ドキュメントを同期せず、
+/// そのユーザーが発信したメッセージも受け取りません。
+#[command]
+pub async fn block_user(
+    session: State<'_, SessionState>,
+    target_user_id: String,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let current = session.require()?;
+    let result = add_moderation(&current, &target_user_id, ModerationKind::Block).await?;
+    post_docs::unfollow_post_doc(&target_user_id).await?;
+    Ok(result)
+}
+
+/// ブロック解除コマンド
+///
+/// フォロー中のユーザーの場合は、投稿ドキュメントの同期を再開します。
+#[command]
+pub async fn unblock_user(
+    session: State<'_, SessionState>,
+    target_user_id: String,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let current = session.require()?;
+    let user_id = current.user_id();
+    moderation_repository::remove_moderation(user_id, ModerationKind::Block, &target_user_id)
+        .await?;
+
+    // 他のユーザーがブロックしている場合は同期を再開しない
+    if follow_repository::is_following(user_id, &target_user_id).await?
+        && !moderation_repository::is_blocked_on_node(&target_user_id).await?
+    {
+        if let Some(target) = user_repository::get_user(&target_user_id).await? {
+            if let Err(e) = post_docs::follow_post_docs_of(&target).await {
+                println!("Warning: Failed to sync post document: {}", e);
+            }
+        }
+    }
+
+    Ok(ProfileUpdateResult {
+        success: true,
+        message: None,
+    })
+}
+
+/// ミュート一覧取得コマンド
+///
+/// サインイン中のユーザーがミュートしているユーザーのIDを新しい順に取得します。
+#[command]
+pub async fn get_muted_users(
+    session: State<'_, SessionState>,
+) -> Result<Vec<String>, ProfileError> {
+    let current = session.require()?;
+    list_moderated(&current, ModerationKind::Mute).await
+}
+
+/// ブロック一覧取得コマンド
+///
+/// サインイン中のユーザーがブロックしているユーザーのIDを新しい順に取得します。
+#[command]
+pub async fn get_blocked_users(
+    session: State<'_, SessionState>,
+) -> Result<Vec<String>, ProfileError> {
+    let current = session.require()?;
+    list_moderated(&current, ModerationKind::Block).await
+}
+
+/// サインイン中のユーザーのミュート・ブロックに指定されたユーザーを加えます。
+async fn add_moderation(
+    current: &Session,
+    target_user_id: &str,
+    kind: ModerationKind,
+) -> Result<ProfileUpdateResult, ProfileError> {
+    let user_id = current.user_id();
+    if user_id == target_user_id {
+        let reason = format!("cannot {} yourself", kind);
+        return Err(ValidationError::invalid("target_user_id", reason).into());
+    }
+
+    let existing = moderation_repository::list_moderation(user_id, kind).await?;
+    if existing
+        .iter()
+        .any(|entry| entry.target_id == target_user_id)
+    {
+        return Ok(ProfileUpdateResult {
+            success: true,
+            message: Some(format!("User is already in the {} list", kind)),
+        });
+    }
+
+    let entry = ModerationEntry {
+        user_id: user_id.to_string(),
+        target_id: target_user_id.to_string(),
+        kind,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    moderation_repository::save_moderation(&entry).await?;
+
+    Ok(ProfileUpdateResult {
+        success: true,
+        message: None,
+    })
+}
+
+/// サインイン中のユーザーがミュート・ブロックしているユーザーのIDを取得します。
+async fn list_moderated(
+    current: &Session,
+    kind: ModerationKind,
+) -> Result<Vec<String>, ProfileError> {
+    let entries = moderation_repository::list_moderation(current.user_id(), kind).await?;
+    Ok(entries.into_iter().map(|entry| entry.target_id).collect())
+}
+
 // テストコードは省略
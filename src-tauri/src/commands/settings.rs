@@ -4,6 +4,10 @@ use tauri::command;
 use crate::models::settings::Settings; // Import Settings from models
 use crate::storage::repository::settings_repository; // Import the repository
 use crate::storage::StorageError as InternalStorageError; // Alias internal storage error
+use crate::validation::ValidationError;
+
+/// 選択できるUIテーマ
+const THEMES: &[&str] = &["system", "light", "dark"];
 
 /// 設定エラー
 ///
@@ -16,7 +20,7 @@ pub enum SettingsError {
 
     /// 入力検証エラー
     #[error("Validation error: {0}")]
-    Validation(String),
+    Validation(#[from] ValidationError),
 
     /// その他のエラー
     #[error("{0}")]
@@ -32,12 +36,17 @@ impl From<InternalStorageError> for SettingsError {
 }
 
 /// エラーのシリアライズ実装
+///
+/// 入力検証エラーはフィールド名を含む構造化された形で、それ以外は文字列で返します。
 impl Serialize for SettingsError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        match self {
+            SettingsError::Validation(error) => error.serialize(serializer),
+            _ => serializer.serialize_str(self.to_string().as_ref()),
+        }
     }
 }
 
@@ -80,6 +89,14 @@ pub async fn update_settings(
     autostart: Option<bool>,
     notifications: Option<bool>,
 ) -> Result<SettingsUpdateResult, SettingsError> {
+    // 入力検証
+    if let Some(ref theme) = theme {
+        if !THEMES.contains(&theme.as_str()) {
+            let reason = format!("{} is not one of {}", theme, THEMES.join(", "));
+            return Err(ValidationError::invalid("theme", reason).into());
+        }
+    }
+
     // Get current settings or default if none exist
     let mut current_settings = settings_repository::get_settings(user_id.as_deref())
         .await
@@ -0,0 +1,215 @@
+//! アイデンティティのバックアップ
+//!
+//! 秘密鍵・プロフィール・設定をひとつのJSONにまとめ、パスフレーズで暗号化します。
+//! 別の端末や再インストール後の環境に同じアイデンティティを復元するために使用します。
+
+use base64::{engine::general_purpose, Engine as _};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::identity::keystore::{self, KeystoreError, SealedBox};
+use crate::identity::verify_record;
+use crate::models::settings::Settings;
+use crate::models::user::User;
+
+/// バックアップ形式のバージョン
+const BACKUP_VERSION: u32 = 1;
+
+/// バックアップファイルを識別するための種別
+const BACKUP_KIND: &str = "kukuri-identity-backup";
+
+/// バックアップエラー
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    /// 暗号化・復号エラー（パスフレーズ誤りを含む）
+    #[error(transparent)]
+    Keystore(#[from] KeystoreError),
+
+    /// バックアップの形式が不正
+    #[error("Invalid backup: {0}")]
+    Format(String),
+
+    /// サポートされていないバージョン
+    #[error("Unsupported backup version {0}")]
+    UnsupportedVersion(u32),
+}
+
+/// バックアップファイル（外側は平文、中身は暗号化）
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupFile {
+    kind: String,
+    version: u32,
+    user_id: String,
+    #[serde(flatten)]
+    sealed: SealedBox,
+}
+
+/// 暗号化される中身
+#[derive(Serialize, Deserialize)]
+struct BackupPayload {
+    /// PKCS#8形式の秘密鍵 (Base64)
+    private_key: String,
+    user: User,
+    settings: Option<Settings>,
+}
+
+/// 復元されたアイデンティティ
+pub struct RestoredIdentity {
+    pub pkcs8: Zeroizing<Vec<u8>>,
+    pub user: User,
+    pub settings: Option<Settings>,
+}
+
+/// 秘密鍵・プロフィール・設定をパスフレーズで暗号化したバックアップを作成します。
+pub fn create_backup(
+    pkcs8: &[u8],
+    user: &User,
+    settings: Option<&Settings>,
+    passphrase: &str,
+) -> Result<String, BackupError> {
+    let payload = BackupPayload {
+        private_key: general_purpose::STANDARD.encode(pkcs8),
+        user: user.clone(),
+        settings: settings.cloned(),
+    };
+    let plaintext = Zeroizing::new(
+        serde_json::to_vec(&payload).map_err(|e| BackupError::Format(e.to_string()))?,
+    );
+
+    let file = BackupFile {
+        kind: BACKUP_KIND.to_string(),
+        version: BACKUP_VERSION,
+        user_id: user.id.clone(),
+        sealed: keystore::seal(&plaintext, passphrase, user.id.as_bytes())?,
+    };
+
+    serde_json::to_string_pretty(&file).map_err(|e| BackupError::Format(e.to_string()))
+}
+
+/// バックアップを復号し、秘密鍵とプロフィールの整合性を検証します。
+pub fn restore_backup(backup: &str, passphrase: &str) -> Result<RestoredIdentity, BackupError> {
+    let file: BackupFile =
+        serde_json::from_str(backup).map_err(|e| BackupError::Format(e.to_string()))?;
+    if file.kind != BACKUP_KIND {
+        return Err(BackupError::Format(format!(
+            "Unexpected backup kind {}",
+            file.kind
+        )));
+    }
+    if file.version != BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(file.version));
+    }
+
+    let plaintext = keystore::open(&file.sealed, passphrase, file.user_id.as_bytes())?;
+    let payload: BackupPayload =
+        serde_json::from_slice(&plaintext).map_err(|e| BackupError::Format(e.to_string()))?;
+
+    let pkcs8 = Zeroizing::new(
+        general_purpose::STANDARD
+            .decode(&payload.private_key)
+            .map_err(|e| BackupError::Format(format!("Invalid private key: {}", e)))?,
+    );
+    let key_pair = Ed25519KeyPair::from_pkcs8(&pkcs8)
+        .map_err(|e| BackupError::Format(format!("Invalid private key: {:?}", e)))?;
+
+    // 鍵・ユーザーID・プロフィール署名がすべて一致することを確認
+    // 鍵のローテーション後はユーザーIDが最初の鍵から導出されるため、
+    // 鍵とはプロフィールの公開鍵または委任された端末の鍵で照合する
+    // （鍵の連鎖と委任の署名はプロフィール読み込み時に検証される）
+    let public_key = general_purpose::STANDARD.encode(key_pair.public_key().as_ref());
+    let is_own_key = public_key == payload.user.public_key
+        || payload
+            .user
+            .devices
+            .iter()
+            .any(|device| device.device_key == public_key);
+    if file.user_id != payload.user.id || !is_own_key {
+        return Err(BackupError::Format(
+            "Private key does not match backed up user".to_string(),
+        ));
+    }
+    let is_signed = std::iter::once(&payload.user.public_key)
+        .chain(payload.user.devices.iter().map(|device| &device.device_key))
+        .any(|key| verify_record(&payload.user, key).is_ok());
+    if !is_signed {
+        return Err(BackupError::Format(
+            "Profile signature does not match".to_string(),
+        ));
+    }
+
+    Ok(RestoredIdentity {
+        pkcs8,
+        user: payload.user,
+        settings: payload.settings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::{sign_record, user_id_from_public_key};
+
+    fn sample_identity() -> (Vec<u8>, User) {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng)
+            .unwrap()
+            .as_ref()
+            .to_vec();
+        let key_pair = Ed25519KeyPair::from_pkcs8(&pkcs8).unwrap();
+        let public_key = key_pair.public_key().as_ref();
+
+        let mut user = User {
+            id: user_id_from_public_key(public_key).unwrap(),
+            display_name: "Backup User".to_string(),
+            bio: "バックアップのテスト".to_string(),
+            public_key: general_purpose::STANDARD.encode(public_key),
+            avatar: None,
+            avatar_thumbnail: None,
+            following: vec![],
+            followers: vec![],
+            devices: vec![],
+            post_doc_ticket: None,
+            approval_required: false,
+            created_at: 1_700_000_000,
+            signature: None,
+        };
+        sign_record(&mut user, &key_pair);
+
+        (pkcs8, user)
+    }
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let (pkcs8, user) = sample_identity();
+        let settings = Settings {
+            user_id: Some(user.id.clone()),
+            theme: "dark".to_string(),
+            ..Settings::default()
+        };
+
+        let backup = create_backup(&pkcs8, &user, Some(&settings), "backup passphrase").unwrap();
+        assert!(!backup.contains(&general_purpose::STANDARD.encode(&pkcs8)));
+
+        let restored = restore_backup(&backup, "backup passphrase").unwrap();
+        assert_eq!(&restored.pkcs8[..], &pkcs8[..]);
+        assert_eq!(restored.user.id, user.id);
+        assert_eq!(restored.settings, Some(settings));
+    }
+
+    #[test]
+    fn test_backup_rejects_wrong_passphrase_and_tampering() {
+        let (pkcs8, user) = sample_identity();
+        let backup = create_backup(&pkcs8, &user, None, "backup passphrase").unwrap();
+
+        assert!(matches!(
+            restore_backup(&backup, "wrong passphrase"),
+            Err(BackupError::Keystore(KeystoreError::InvalidPassphrase))
+        ));
+
+        // The user id is bound to the ciphertext as associated data
+        let (_, other) = sample_identity();
+        let tampered = backup.replace(&user.id, &other.id);
+        assert!(restore_backup(&tampered, "backup passphrase").is_err());
+    }
+}
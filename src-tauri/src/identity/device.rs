@@ -0,0 +1,147 @@
+//! 端末のリンク
+//!
+//! 新しい端末で生成した鍵を、既存の端末が `DeviceDelegation` として承認するための
+//! チケット（QRコードやコピー＆ペーストで受け渡す文字列）を扱います。
+//!
+//! 1. 新しい端末がリンク要求チケットを作成する
+//! 2. 既存の端末が要求を承認し、委任をプロフィールに記録して承認チケットを返す
+//! 3. 新しい端末が承認チケットを取り込み、委任された鍵でサインインする
+//!
+//! 両方の端末に同じ確認コードが表示されることで、チケットが改ざんされていないことを確認します。
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::identity::user_id_from_public_key;
+use crate::models::user::DeviceDelegation;
+
+/// リンク要求チケットの接頭辞
+const REQUEST_PREFIX: &str = "kukurilinkreq1";
+
+/// 承認チケットの接頭辞
+const APPROVAL_PREFIX: &str = "kukurilinkok1";
+
+/// チケットエラー
+#[derive(Debug, thiserror::Error)]
+pub enum TicketError {
+    /// チケットの形式が不正
+    #[error("Invalid link ticket: {0}")]
+    Malformed(String),
+}
+
+/// 新しい端末からのリンク要求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkRequest {
+    /// 新しい端末の公開鍵 (Base64)
+    pub device_key: String,
+    pub device_name: String,
+    /// 新しい端末の鍵の投稿ドキュメントの読み取り専用チケット（委任に記録されます）
+    #[serde(default)]
+    pub post_doc_ticket: Option<String>,
+}
+
+/// 既存の端末による承認
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkApproval {
+    pub delegation: DeviceDelegation,
+}
+
+/// リンク要求チケットを作成します。
+pub fn encode_request(request: &LinkRequest) -> String {
+    encode_ticket(REQUEST_PREFIX, request)
+}
+
+/// リンク要求チケットを読み込みます。
+pub fn decode_request(ticket: &str) -> Result<LinkRequest, TicketError> {
+    decode_ticket(REQUEST_PREFIX, ticket)
+}
+
+/// 承認チケットを作成します。
+pub fn encode_approval(approval: &LinkApproval) -> String {
+    encode_ticket(APPROVAL_PREFIX, approval)
+}
+
+/// 承認チケットを読み込みます。
+pub fn decode_approval(ticket: &str) -> Result<LinkApproval, TicketError> {
+    decode_ticket(APPROVAL_PREFIX, ticket)
+}
+
+/// 端末の公開鍵から6桁の確認コードを作成します。
+pub fn confirmation_code(device_key: &str) -> String {
+    let hash = blake3::hash(device_key.as_bytes());
+    let bytes: [u8; 4] = hash.as_bytes()[..4].try_into().unwrap();
+    format!("{:06}", u32::from_be_bytes(bytes) % 1_000_000)
+}
+
+/// リンク完了までの間、端末の鍵をキーストアに保存するためのIDを返します。
+///
+/// 公開鍵から導出したIDを使用するため、プロフィールを持つユーザーとは衝突しません。
+pub fn pending_key_id(device_key: &str) -> Option<String> {
+    let public_key = general_purpose::STANDARD.decode(device_key).ok()?;
+    user_id_from_public_key(&public_key)
+}
+
+fn encode_ticket<T: Serialize>(prefix: &str, value: &T) -> String {
+    // 構造体のシリアライズは失敗しない
+    let json = serde_json::to_vec(value).unwrap();
+    format!(
+        "{}{}",
+        prefix,
+        general_purpose::URL_SAFE_NO_PAD.encode(json)
+    )
+}
+
+fn decode_ticket<T: DeserializeOwned>(prefix: &str, ticket: &str) -> Result<T, TicketError> {
+    let encoded = ticket
+        .trim()
+        .strip_prefix(prefix)
+        .ok_or_else(|| TicketError::Malformed("unexpected ticket type".to_string()))?;
+    let json = general_purpose::URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| TicketError::Malformed(e.to_string()))?;
+    serde_json::from_slice(&json).map_err(|e| TicketError::Malformed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticket_roundtrip() {
+        let request = LinkRequest {
+            device_key: general_purpose::STANDARD.encode([3u8; 32]),
+            device_name: "Desktop".to_string(),
+            post_doc_ticket: Some("docaaa".to_string()),
+        };
+
+        let ticket = encode_request(&request);
+        let decoded = decode_request(&format!("  {}\n", ticket)).unwrap();
+        assert_eq!(decoded.device_key, request.device_key);
+        assert_eq!(decoded.device_name, "Desktop");
+        assert_eq!(decoded.post_doc_ticket.as_deref(), Some("docaaa"));
+
+        // A request ticket is not an approval ticket
+        assert!(decode_approval(&ticket).is_err());
+        assert!(decode_request("kukurilinkreq1!!!").is_err());
+    }
+
+    #[test]
+    fn test_confirmation_code_and_pending_id() {
+        let device_key = general_purpose::STANDARD.encode([3u8; 32]);
+
+        let code = confirmation_code(&device_key);
+        assert_eq!(code.len(), 6);
+        assert_eq!(code, confirmation_code(&device_key));
+        assert_ne!(
+            code,
+            confirmation_code(&general_purpose::STANDARD.encode([4u8; 32]))
+        );
+
+        assert_eq!(
+            pending_key_id(&device_key),
+            user_id_from_public_key(&[3u8; 32])
+        );
+        assert!(pending_key_id("not base64").is_none());
+    }
+}
@@ -0,0 +1,198 @@
+//! 公開鍵宛ての暗号化
+//!
+//! 受信者の X25519 公開鍵宛てにデータを暗号化します。送信者は暗号化ごとに
+//! 使い捨ての鍵を生成し、受信者の鍵との共有鍵から XChaCha20-Poly1305 の鍵を導出します。
+//! 鍵共有用の鍵は署名用の鍵から導出されます（`keystore::UnlockedKey::agreement_secret`）。
+
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+// 共有鍵から暗号鍵を導出する際のコンテキスト文字列
+const ENVELOPE_KEY_CONTEXT: &str = "kukuri 2025-01 envelope key";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+
+/// 暗号化エラー
+#[derive(Debug, thiserror::Error)]
+pub enum EnvelopeError {
+    /// 鍵や暗号文のエンコードが不正
+    #[error("Malformed envelope: {0}")]
+    Malformed(String),
+
+    /// 暗号処理エラー
+    #[error("Crypto error: {0}")]
+    Crypto(String),
+
+    /// 宛先の鍵で復号できない
+    #[error("Envelope is not addressed to this key")]
+    NotAddressed,
+}
+
+/// 公開鍵宛てに暗号化されたデータ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// 送信者の使い捨ての公開鍵 (Base64)
+    pub ephemeral_key: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// 鍵共有用の秘密鍵に対応する公開鍵 (Base64) を返します。
+pub fn agreement_public_key(secret: &StaticSecret) -> String {
+    general_purpose::STANDARD.encode(PublicKey::from(secret).as_bytes())
+}
+
+/// データを `recipient_key` (Base64) 宛てに暗号化します。`aad` は復号時にも同じ値が必要です。
+pub fn seal_to(recipient_key: &str, msg: &[u8], aad: &[u8]) -> Result<Envelope, EnvelopeError> {
+    let recipient = decode_public_key(recipient_key)?;
+
+    let rng = SystemRandom::new();
+    let mut ephemeral_seed = Zeroizing::new([0u8; KEY_LEN]);
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut ephemeral_seed[..])
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| EnvelopeError::Crypto("Failed to generate random bytes".to_string()))?;
+    let ephemeral = StaticSecret::from(*ephemeral_seed);
+    let ephemeral_key = PublicKey::from(&ephemeral);
+
+    let cipher = cipher_for(&ephemeral, &recipient, &ephemeral_key, &recipient)?;
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg, aad })
+        .map_err(|_| EnvelopeError::Crypto("Failed to encrypt data".to_string()))?;
+
+    Ok(Envelope {
+        ephemeral_key: general_purpose::STANDARD.encode(ephemeral_key.as_bytes()),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+/// 自分宛てに暗号化されたデータを復号します。
+pub fn open_with(
+    secret: &StaticSecret,
+    envelope: &Envelope,
+    aad: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, EnvelopeError> {
+    let ephemeral_key = decode_public_key(&envelope.ephemeral_key)?;
+    let nonce = general_purpose::STANDARD
+        .decode(&envelope.nonce)
+        .map_err(|e| EnvelopeError::Malformed(format!("Invalid nonce: {}", e)))?;
+    if nonce.len() != NONCE_LEN {
+        return Err(EnvelopeError::Malformed("Invalid nonce length".to_string()));
+    }
+    let ciphertext = general_purpose::STANDARD
+        .decode(&envelope.ciphertext)
+        .map_err(|e| EnvelopeError::Malformed(format!("Invalid ciphertext: {}", e)))?;
+
+    let cipher = cipher_for(
+        secret,
+        &ephemeral_key,
+        &ephemeral_key,
+        &PublicKey::from(secret),
+    )?;
+    cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| EnvelopeError::NotAddressed)
+}
+
+/// Base64 エンコードされた X25519 公開鍵を読み込みます。
+fn decode_public_key(key: &str) -> Result<PublicKey, EnvelopeError> {
+    let bytes: [u8; KEY_LEN] = general_purpose::STANDARD
+        .decode(key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| EnvelopeError::Malformed("Invalid public key".to_string()))?;
+    Ok(PublicKey::from(bytes))
+}
+
+/// 共有鍵から暗号鍵を導出します。
+///
+/// 共有鍵に加えて使い捨ての公開鍵と受信者の公開鍵をこの順で導出に含め、
+/// 暗号鍵を特定の送信と宛先に結び付けます。
+fn cipher_for(
+    secret: &StaticSecret,
+    peer_key: &PublicKey,
+    ephemeral_key: &PublicKey,
+    recipient_key: &PublicKey,
+) -> Result<XChaCha20Poly1305, EnvelopeError> {
+    let shared = secret.diffie_hellman(peer_key);
+    if !shared.was_contributory() {
+        return Err(EnvelopeError::Malformed("Low-order public key".to_string()));
+    }
+
+    let mut material = Zeroizing::new(Vec::with_capacity(KEY_LEN * 3));
+    material.extend_from_slice(shared.as_bytes());
+    material.extend_from_slice(ephemeral_key.as_bytes());
+    material.extend_from_slice(recipient_key.as_bytes());
+    let key = Zeroizing::new(blake3::derive_key(ENVELOPE_KEY_CONTEXT, &material));
+
+    XChaCha20Poly1305::new_from_slice(&key[..])
+        .map_err(|e| EnvelopeError::Crypto(format!("Invalid key length: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_secret() -> StaticSecret {
+        let mut seed = [0u8; KEY_LEN];
+        SystemRandom::new().fill(&mut seed).unwrap();
+        StaticSecret::from(seed)
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let recipient = generate_secret();
+        let recipient_key = agreement_public_key(&recipient);
+
+        let envelope = seal_to(&recipient_key, b"secret ticket", b"alice:bob").unwrap();
+        assert_ne!(
+            envelope.ciphertext,
+            general_purpose::STANDARD.encode(b"secret ticket")
+        );
+
+        let opened = open_with(&recipient, &envelope, b"alice:bob").unwrap();
+        assert_eq!(&opened[..], b"secret ticket");
+
+        // Every envelope uses a fresh ephemeral key
+        let other = seal_to(&recipient_key, b"secret ticket", b"alice:bob").unwrap();
+        assert_ne!(envelope.ephemeral_key, other.ephemeral_key);
+    }
+
+    #[test]
+    fn test_open_rejects_other_recipient_and_context() {
+        let recipient = generate_secret();
+        let envelope = seal_to(
+            &agreement_public_key(&recipient),
+            b"secret ticket",
+            b"alice:bob",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            open_with(&generate_secret(), &envelope, b"alice:bob"),
+            Err(EnvelopeError::NotAddressed)
+        ));
+        assert!(matches!(
+            open_with(&recipient, &envelope, b"alice:carol"),
+            Err(EnvelopeError::NotAddressed)
+        ));
+        assert!(matches!(
+            seal_to("not a key", b"secret ticket", b"alice:bob"),
+            Err(EnvelopeError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,374 @@
+//! 鍵の連鎖（ローテーションと失効）
+//!
+//! ユーザーIDの元になった鍵を起点に、前の鍵で署名されたローテーション記録を
+//! 順にたどって有効な鍵の連鎖を組み立てます。ローテーション前の鍵による署名も
+//! 有効なままですが、失効記録のある鍵による署名は `KeyStatus::Revoked` として扱います。
+//! 連鎖の鍵から委任された端末の鍵（プロフィールの `devices`）も同じユーザーの鍵として扱います。
+
+use std::collections::HashSet;
+
+use base64::{engine::general_purpose, Engine as _};
+
+use crate::identity::signing::SignatureError;
+use crate::identity::{public_key_b64_from_user_id, verify_record, Signable};
+use crate::models::user::{DeviceDelegation, KeyRevocation, KeyRotation};
+
+/// 署名した鍵の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStatus {
+    /// 現在の鍵
+    Current,
+    /// ローテーション済みだが失効していない鍵
+    Rotated,
+    /// 連鎖の鍵から委任された端末の鍵
+    Delegated,
+    /// 失効した鍵
+    Revoked,
+}
+
+/// ユーザーの鍵の連鎖
+#[derive(Debug, Clone)]
+pub struct KeyChain {
+    /// 古い順の公開鍵 (Base64)。先頭はユーザーIDの元になった鍵
+    keys: Vec<String>,
+    /// 委任された端末の公開鍵 (Base64)
+    devices: Vec<String>,
+    revoked: HashSet<String>,
+}
+
+impl KeyChain {
+    /// ローテーション・失効記録と端末の委任から鍵の連鎖を組み立てます。
+    ///
+    /// 署名が検証できない記録や連鎖につながらない記録は無視されます。
+    /// 公開鍵から導出されたIDでない場合（旧形式のUUIDなど）は `None` を返します。
+    pub fn build(
+        user_id: &str,
+        rotations: &[KeyRotation],
+        revocations: &[KeyRevocation],
+        delegations: &[DeviceDelegation],
+    ) -> Option<Self> {
+        let root = public_key_b64_from_user_id(user_id)?;
+        let mut keys = vec![root];
+
+        loop {
+            let current = keys.last().unwrap();
+            let sequence = keys.len() as u32;
+            let next = rotations.iter().find(|rotation| {
+                rotation.user_id == user_id
+                    && rotation.sequence == sequence
+                    && &rotation.previous_key == current
+                    && is_public_key(&rotation.new_key)
+                    && !keys.contains(&rotation.new_key)
+                    && verify_record(*rotation, current).is_ok()
+            });
+            match next {
+                Some(rotation) => keys.push(rotation.new_key.clone()),
+                None => break,
+            }
+        }
+
+        let mut chain = Self {
+            keys,
+            devices: Vec::new(),
+            revoked: HashSet::new(),
+        };
+        let revocations: Vec<&KeyRevocation> = revocations
+            .iter()
+            .filter(|revocation| revocation.user_id == user_id)
+            .collect();
+
+        // 連鎖の鍵の失効は、対象の鍵自身かそれより新しい鍵による署名のみ有効
+        for revocation in &revocations {
+            let Some(index) = chain
+                .keys
+                .iter()
+                .position(|key| key == &revocation.revoked_key)
+            else {
+                continue;
+            };
+            if chain.keys[index..]
+                .iter()
+                .any(|key| verify_record(*revocation, key).is_ok())
+            {
+                chain.revoked.insert(revocation.revoked_key.clone());
+            }
+        }
+
+        for delegation in delegations {
+            if chain.accepts_delegation(user_id, delegation)
+                && !chain.devices.contains(&delegation.device_key)
+            {
+                chain.devices.push(delegation.device_key.clone());
+            }
+        }
+
+        // 端末の鍵の失効は、端末自身か失効していない連鎖の鍵による署名が有効
+        for revocation in &revocations {
+            if !chain.devices.contains(&revocation.revoked_key) {
+                continue;
+            }
+            let signed_by_device = verify_record(*revocation, &revocation.revoked_key).is_ok();
+            if signed_by_device || chain.signed_by_valid_key(*revocation) {
+                chain.revoked.insert(revocation.revoked_key.clone());
+            }
+        }
+
+        Some(chain)
+    }
+
+    /// 端末の委任が有効かどうかを判定します。
+    ///
+    /// 委任は失効していない連鎖の鍵で署名されている必要があります。
+    /// 失効した鍵による委任は、鍵を不正に入手した第三者が作成した可能性があるため無効です。
+    pub fn accepts_delegation(&self, user_id: &str, delegation: &DeviceDelegation) -> bool {
+        delegation.user_id == user_id
+            && is_public_key(&delegation.device_key)
+            && !self.keys.contains(&delegation.device_key)
+            && self.signed_by_valid_key(delegation)
+    }
+
+    /// 失効していない連鎖の鍵のいずれかで署名されているかどうかを判定します。
+    fn signed_by_valid_key<T: Signable>(&self, record: &T) -> bool {
+        self.keys
+            .iter()
+            .filter(|key| !self.revoked.contains(*key))
+            .any(|key| verify_record(record, key).is_ok())
+    }
+
+    /// 連鎖の最新の鍵を返します。
+    pub fn current_key(&self) -> &str {
+        self.keys.last().unwrap()
+    }
+
+    /// 次のローテーション記録の連番を返します。
+    pub fn next_sequence(&self) -> u32 {
+        self.keys.len() as u32
+    }
+
+    /// 鍵の状態を返します。連鎖に含まれない鍵の場合は `None` を返します。
+    pub fn status(&self, public_key: &str) -> Option<KeyStatus> {
+        if self.revoked.contains(public_key) {
+            Some(KeyStatus::Revoked)
+        } else if public_key == self.current_key() {
+            Some(KeyStatus::Current)
+        } else if self.keys.iter().any(|key| key == public_key) {
+            Some(KeyStatus::Rotated)
+        } else if self.devices.iter().any(|key| key == public_key) {
+            Some(KeyStatus::Delegated)
+        } else {
+            None
+        }
+    }
+
+    /// 連鎖内のいずれかの鍵（委任された端末の鍵を含む）でレコードの署名を検証し、
+    /// 署名した鍵の状態を返します。
+    pub fn verify<T: Signable>(&self, record: &T) -> Result<KeyStatus, SignatureError> {
+        let mut last_error = SignatureError::Missing;
+        for key in self.keys.iter().rev().chain(&self.devices) {
+            match verify_record(record, key) {
+                Ok(()) => return Ok(self.status(key).unwrap()),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+/// Base64エンコードされた32バイトの公開鍵かどうかを判定します。
+fn is_public_key(value: &str) -> bool {
+    general_purpose::STANDARD
+        .decode(value)
+        .is_ok_and(|bytes| bytes.len() == 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::{sign_record, user_id_from_public_key};
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    struct Note {
+        text: String,
+        signature: Option<String>,
+    }
+
+    impl Signable for Note {
+        fn signing_payload(&self) -> Vec<u8> {
+            crate::identity::CanonicalEncoder::new("test/note")
+                .str(&self.text)
+                .finish()
+        }
+
+        fn signature(&self) -> Option<&str> {
+            self.signature.as_deref()
+        }
+
+        fn set_signature(&mut self, signature: String) {
+            self.signature = Some(signature);
+        }
+    }
+
+    fn generate() -> (Ed25519KeyPair, String) {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public_key = general_purpose::STANDARD.encode(key_pair.public_key().as_ref());
+        (key_pair, public_key)
+    }
+
+    fn rotation(
+        user_id: &str,
+        sequence: u32,
+        signer: &Ed25519KeyPair,
+        previous_key: &str,
+        new_key: &str,
+    ) -> KeyRotation {
+        let mut rotation = KeyRotation {
+            user_id: user_id.to_string(),
+            sequence,
+            previous_key: previous_key.to_string(),
+            new_key: new_key.to_string(),
+            created_at: 1_700_000_000,
+            signature: None,
+        };
+        sign_record(&mut rotation, signer);
+        rotation
+    }
+
+    fn revocation(user_id: &str, signer: &Ed25519KeyPair, revoked_key: &str) -> KeyRevocation {
+        let mut revocation = KeyRevocation {
+            user_id: user_id.to_string(),
+            revoked_key: revoked_key.to_string(),
+            revoked_at: 1_700_000_100,
+            reason: Some("compromised".to_string()),
+            signature: None,
+        };
+        sign_record(&mut revocation, signer);
+        revocation
+    }
+
+    fn delegation(user_id: &str, signer: &Ed25519KeyPair, device_key: &str) -> DeviceDelegation {
+        let mut delegation = DeviceDelegation {
+            user_id: user_id.to_string(),
+            device_key: device_key.to_string(),
+            device_name: "laptop".to_string(),
+            created_at: 1_700_000_200,
+            post_doc_ticket: None,
+            signature: None,
+        };
+        sign_record(&mut delegation, signer);
+        delegation
+    }
+
+    fn note(signer: &Ed25519KeyPair) -> Note {
+        let mut note = Note {
+            text: "hello".to_string(),
+            signature: None,
+        };
+        sign_record(&mut note, signer);
+        note
+    }
+
+    #[test]
+    fn test_rotation_chain() {
+        let (root, root_key) = generate();
+        let (second, second_key) = generate();
+        let (third, third_key) = generate();
+        let user_id = user_id_from_public_key(root.public_key().as_ref()).unwrap();
+
+        let rotations = vec![
+            rotation(&user_id, 2, &second, &second_key, &third_key),
+            rotation(&user_id, 1, &root, &root_key, &second_key),
+        ];
+        let chain = KeyChain::build(&user_id, &rotations, &[], &[]).unwrap();
+
+        assert_eq!(chain.current_key(), third_key);
+        assert_eq!(chain.next_sequence(), 3);
+        assert_eq!(chain.verify(&note(&third)).unwrap(), KeyStatus::Current);
+        assert_eq!(chain.verify(&note(&root)).unwrap(), KeyStatus::Rotated);
+
+        let (stranger, _) = generate();
+        assert!(matches!(
+            chain.verify(&note(&stranger)),
+            Err(SignatureError::Mismatch)
+        ));
+    }
+
+    #[test]
+    fn test_ignores_rotation_not_signed_by_previous_key() {
+        let (root, root_key) = generate();
+        let (attacker, attacker_key) = generate();
+        let user_id = user_id_from_public_key(root.public_key().as_ref()).unwrap();
+
+        let forged = rotation(&user_id, 1, &attacker, &root_key, &attacker_key);
+        let chain = KeyChain::build(&user_id, &[forged], &[], &[]).unwrap();
+
+        assert_eq!(chain.current_key(), root_key);
+        assert!(chain.status(&attacker_key).is_none());
+    }
+
+    #[test]
+    fn test_revocation() {
+        let (root, root_key) = generate();
+        let (second, second_key) = generate();
+        let user_id = user_id_from_public_key(root.public_key().as_ref()).unwrap();
+        let rotations = vec![rotation(&user_id, 1, &root, &root_key, &second_key)];
+
+        // An older key cannot revoke a newer one
+        let chain = KeyChain::build(
+            &user_id,
+            &rotations,
+            &[revocation(&user_id, &root, &second_key)],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(chain.status(&second_key), Some(KeyStatus::Current));
+
+        let chain = KeyChain::build(
+            &user_id,
+            &rotations,
+            &[revocation(&user_id, &second, &root_key)],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(chain.verify(&note(&root)).unwrap(), KeyStatus::Revoked);
+        assert_eq!(chain.verify(&note(&second)).unwrap(), KeyStatus::Current);
+    }
+
+    #[test]
+    fn test_device_delegation() {
+        let (root, root_key) = generate();
+        let (second, second_key) = generate();
+        let (device, device_key) = generate();
+        let user_id = user_id_from_public_key(root.public_key().as_ref()).unwrap();
+        let rotations = vec![rotation(&user_id, 1, &root, &root_key, &second_key)];
+
+        let delegated = delegation(&user_id, &root, &device_key);
+        let chain = KeyChain::build(&user_id, &rotations, &[], &[delegated.clone()]).unwrap();
+        assert_eq!(chain.verify(&note(&device)).unwrap(), KeyStatus::Delegated);
+
+        // A device key cannot delegate further devices
+        let (other, other_key) = generate();
+        let chained = delegation(&user_id, &device, &other_key);
+        let chain =
+            KeyChain::build(&user_id, &rotations, &[], &[delegated.clone(), chained]).unwrap();
+        assert!(chain.verify(&note(&other)).is_err());
+
+        // Revoking the delegating key invalidates its delegations
+        let revoked_root = revocation(&user_id, &second, &root_key);
+        let chain =
+            KeyChain::build(&user_id, &rotations, &[revoked_root], &[delegated.clone()]).unwrap();
+        assert!(chain.status(&device_key).is_none());
+
+        // The device itself can be revoked by a chain key
+        let revoked_device = revocation(&user_id, &second, &device_key);
+        let chain = KeyChain::build(&user_id, &rotations, &[revoked_device], &[delegated]).unwrap();
+        assert_eq!(chain.verify(&note(&device)).unwrap(), KeyStatus::Revoked);
+    }
+
+    #[test]
+    fn test_legacy_user_id_has_no_chain() {
+        let legacy_id = uuid::Uuid::new_v4().to_string();
+        assert!(KeyChain::build(&legacy_id, &[], &[], &[]).is_none());
+    }
+}
@@ -0,0 +1,586 @@
+//! 暗号化キーストア
+//!
+//! ユーザーの秘密鍵（PKCS#8）をパスフレーズから導出した鍵（Argon2id）と
+//! AEAD（XChaCha20-Poly1305）で暗号化し、アプリのデータディレクトリに保存します。
+//! 復号済みの鍵はサインイン中のセッション（`identity::session`）がメモリ上にのみ保持します。
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use iroh_docs::{Author, NamespaceSecret};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+use x25519_dalek::StaticSecret;
+use zeroize::Zeroizing;
+
+use crate::identity::seed::{seed_from_pkcs8, SEED_LEN};
+use crate::identity::user_id::is_valid_user_id;
+
+/// キーファイルの形式バージョン
+const KEYSTORE_VERSION: u32 = 1;
+
+/// パスフレーズの最小文字数
+pub const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+// Argon2idのパラメータ（OWASP推奨値）
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+
+// iroh-docsの作成者鍵を導出する際のコンテキスト文字列
+const AUTHOR_KEY_CONTEXT: &str = "kukuri 2025-01 iroh-docs author key";
+
+// 投稿ドキュメントの名前空間の鍵を導出する際のコンテキスト文字列
+const POST_NAMESPACE_KEY_CONTEXT: &str = "kukuri 2025-01 iroh-docs post namespace key";
+
+// フォロワー限定の投稿ドキュメントの名前空間の鍵を導出する際のコンテキスト文字列
+const FOLLOWER_NAMESPACE_KEY_CONTEXT: &str = "kukuri 2025-01 iroh-docs follower namespace key";
+
+// 鍵共有（X25519）の秘密鍵を導出する際のコンテキスト文字列
+const AGREEMENT_KEY_CONTEXT: &str = "kukuri 2025-01 x25519 agreement key";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+static KEYSTORE: OnceLock<Keystore> = OnceLock::new();
+
+/// キーストアエラー
+///
+/// キーストア操作中に発生する可能性のあるエラーを定義します。
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    /// 指定されたユーザーの鍵が存在しない
+    #[error("Key not found for user {0}")]
+    NotFound(String),
+
+    /// ユーザーIDの形式が不正
+    #[error("Invalid user ID {0:?}")]
+    InvalidUserId(String),
+
+    /// パスフレーズが正しくない
+    #[error("Invalid passphrase")]
+    InvalidPassphrase,
+
+    /// パスフレーズが短すぎる
+    #[error("Passphrase must be at least {} characters", MIN_PASSPHRASE_LENGTH)]
+    WeakPassphrase,
+
+    /// キーファイルの形式が不正
+    #[error("Invalid key file: {0}")]
+    Format(String),
+
+    /// 暗号処理エラー
+    #[error("Crypto error: {0}")]
+    Crypto(String),
+
+    /// ファイルシステムエラー
+    #[error("File system error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// 鍵導出パラメータ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfParams {
+    algorithm: String,
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+/// パスフレーズで暗号化されたデータ
+///
+/// キーファイルとバックアップファイルで共通の形式です。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SealedBox {
+    kdf: KdfParams,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// ディスク上のキーファイル
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    user_id: String,
+    public_key: String,
+    /// 鍵から導出したiroh-docsの作成者ID
+    #[serde(default)]
+    author_id: Option<String>,
+    #[serde(flatten)]
+    sealed: SealedBox,
+}
+
+/// 復号済みの鍵
+///
+/// 復号に使用したPKCS#8のバイト列はキーペアの作成後にゼロ化されます。
+pub struct UnlockedKey {
+    key_pair: Ed25519KeyPair,
+    seed: Zeroizing<[u8; SEED_LEN]>,
+}
+
+impl UnlockedKey {
+    fn new(pkcs8: Zeroizing<Vec<u8>>) -> Result<Self, KeystoreError> {
+        let key_pair = Ed25519KeyPair::from_pkcs8(&pkcs8)
+            .map_err(|e| KeystoreError::Format(format!("Invalid PKCS#8 key: {:?}", e)))?;
+        let seed = seed_from_pkcs8(&pkcs8).map_err(|e| KeystoreError::Format(e.to_string()))?;
+        Ok(Self { key_pair, seed })
+    }
+
+    pub fn key_pair(&self) -> &Ed25519KeyPair {
+        &self.key_pair
+    }
+
+    /// シードから導出したiroh-docsの作成者を返します。
+    ///
+    /// ドキュメントのエントリをユーザー単位で帰属・絞り込みするために使用します。
+    pub fn author(&self) -> Author {
+        derive_author(&self.seed)
+    }
+
+    /// シードから導出した投稿ドキュメントの名前空間の秘密鍵を返します。
+    ///
+    /// 同じ鍵を持つすべての端末が同じ投稿ドキュメントに書き込めます。
+    pub fn post_namespace(&self) -> NamespaceSecret {
+        let namespace_seed =
+            Zeroizing::new(blake3::derive_key(POST_NAMESPACE_KEY_CONTEXT, &self.seed));
+        NamespaceSecret::from_bytes(&namespace_seed)
+    }
+
+    /// シードから導出したフォロワー限定の投稿ドキュメントの名前空間の秘密鍵を返します。
+    ///
+    /// 読み取り用のチケットは承認したフォロワーにだけ暗号化して渡します。
+    pub fn follower_namespace(&self) -> NamespaceSecret {
+        let namespace_seed = Zeroizing::new(blake3::derive_key(
+            FOLLOWER_NAMESPACE_KEY_CONTEXT,
+            &self.seed,
+        ));
+        NamespaceSecret::from_bytes(&namespace_seed)
+    }
+
+    /// シードから導出した鍵共有用の秘密鍵を返します。
+    ///
+    /// フォロワー宛てに暗号化されたデータ（`identity::envelope`）の復号に使用します。
+    pub fn agreement_secret(&self) -> StaticSecret {
+        StaticSecret::from(blake3::derive_key(AGREEMENT_KEY_CONTEXT, &self.seed))
+    }
+}
+
+/// 暗号化キーストア
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl Keystore {
+    /// 指定ディレクトリをルートとするキーストアを作成します。
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// ユーザーのキーファイルのパスを返します。
+    ///
+    /// キーストアのディレクトリの外を指さないよう、公開鍵由来のIDと旧形式のUUID以外は拒否します。
+    fn key_path(&self, user_id: &str) -> Result<PathBuf, KeystoreError> {
+        if !is_valid_user_id(user_id) {
+            return Err(KeystoreError::InvalidUserId(user_id.to_string()));
+        }
+        Ok(self.dir.join(format!("{}.json", user_id)))
+    }
+
+    /// ユーザーの鍵が保存されているかどうかを返します。不正なユーザーIDでは `false` を返します。
+    pub fn contains(&self, user_id: &str) -> bool {
+        self.key_path(user_id).is_ok_and(|path| path.is_file())
+    }
+
+    /// 鍵が保存されているユーザーIDの一覧を返します。
+    pub fn list_user_ids(&self) -> Result<Vec<String>, KeystoreError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut user_ids = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                let user_id = path.file_stem().and_then(|stem| stem.to_str());
+                if let Some(user_id) = user_id.filter(|user_id| is_valid_user_id(user_id)) {
+                    user_ids.push(user_id.to_string());
+                }
+            }
+        }
+        Ok(user_ids)
+    }
+
+    /// PKCS#8形式の秘密鍵をパスフレーズで暗号化して保存します。
+    pub fn store(
+        &self,
+        user_id: &str,
+        pkcs8: &[u8],
+        passphrase: &str,
+    ) -> Result<(), KeystoreError> {
+        let path = self.key_path(user_id)?;
+        if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+            return Err(KeystoreError::WeakPassphrase);
+        }
+
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map_err(|e| KeystoreError::Format(format!("Invalid PKCS#8 key: {:?}", e)))?;
+
+        let seed = seed_from_pkcs8(pkcs8).map_err(|e| KeystoreError::Format(e.to_string()))?;
+        let sealed = seal(pkcs8, passphrase, user_id.as_bytes())?;
+
+        let key_file = KeyFile {
+            version: KEYSTORE_VERSION,
+            user_id: user_id.to_string(),
+            public_key: general_purpose::STANDARD.encode(key_pair.public_key().as_ref()),
+            author_id: Some(derive_author(&seed).id().to_string()),
+            sealed,
+        };
+
+        write_key_file(&path, &key_file)
+    }
+
+    /// パスフレーズで鍵を復号します。
+    pub fn unlock(&self, user_id: &str, passphrase: &str) -> Result<UnlockedKey, KeystoreError> {
+        let key_file = self.read_key_file(user_id)?;
+        let pkcs8 = open(&key_file.sealed, passphrase, user_id.as_bytes())?;
+        let unlocked = UnlockedKey::new(pkcs8)?;
+
+        let public_key =
+            general_purpose::STANDARD.encode(unlocked.key_pair().public_key().as_ref());
+        if public_key != key_file.public_key {
+            return Err(KeystoreError::Format(
+                "Decrypted key does not match stored public key".to_string(),
+            ));
+        }
+
+        Ok(unlocked)
+    }
+
+    /// 現在のパスフレーズを確認し、新しいパスフレーズで鍵を再暗号化します。
+    pub fn change_passphrase(
+        &self,
+        user_id: &str,
+        old_passphrase: &str,
+        new_passphrase: &str,
+    ) -> Result<(), KeystoreError> {
+        let pkcs8 = self.export_pkcs8(user_id, old_passphrase)?;
+        self.store(user_id, &pkcs8, new_passphrase)
+    }
+
+    /// パスフレーズを確認し、復号したPKCS#8形式の秘密鍵を返します。
+    ///
+    /// アイデンティティのバックアップ作成に使用します。
+    pub fn export_pkcs8(
+        &self,
+        user_id: &str,
+        passphrase: &str,
+    ) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+        let key_file = self.read_key_file(user_id)?;
+        open(&key_file.sealed, passphrase, user_id.as_bytes())
+    }
+
+    /// 保存されている鍵を削除します。
+    pub fn remove(&self, user_id: &str) -> Result<(), KeystoreError> {
+        let path = self.key_path(user_id)?;
+        if !path.is_file() {
+            return Err(KeystoreError::NotFound(user_id.to_string()));
+        }
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    fn read_key_file(&self, user_id: &str) -> Result<KeyFile, KeystoreError> {
+        let path = self.key_path(user_id)?;
+        if !path.is_file() {
+            return Err(KeystoreError::NotFound(user_id.to_string()));
+        }
+
+        let bytes = std::fs::read(&path)?;
+        let key_file: KeyFile =
+            serde_json::from_slice(&bytes).map_err(|e| KeystoreError::Format(e.to_string()))?;
+
+        if key_file.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::Format(format!(
+                "Unsupported key file version {}",
+                key_file.version
+            )));
+        }
+        if key_file.user_id != user_id {
+            return Err(KeystoreError::Format(format!(
+                "Key file for {} claims user {}",
+                user_id, key_file.user_id
+            )));
+        }
+
+        Ok(key_file)
+    }
+}
+
+/// シードからiroh-docsの作成者を導出します。
+///
+/// iroh-docsは作成者の秘密鍵をノードのストアに平文で保存するため、
+/// ユーザーの署名鍵そのものではなく、用途を分離した派生鍵を使用します。
+fn derive_author(seed: &[u8; SEED_LEN]) -> Author {
+    let author_seed = Zeroizing::new(blake3::derive_key(AUTHOR_KEY_CONTEXT, seed));
+    Author::from_bytes(&author_seed)
+}
+
+/// パスフレーズからAEAD暗号を作成します。
+fn cipher_for(kdf: &KdfParams, passphrase: &str) -> Result<XChaCha20Poly1305, KeystoreError> {
+    if kdf.algorithm != "argon2id" {
+        return Err(KeystoreError::Format(format!(
+            "Unsupported KDF {}",
+            kdf.algorithm
+        )));
+    }
+
+    let salt = general_purpose::STANDARD
+        .decode(&kdf.salt)
+        .map_err(|e| KeystoreError::Format(format!("Invalid salt: {}", e)))?;
+    let params = Params::new(
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        Some(KEY_LEN),
+    )
+    .map_err(|e| KeystoreError::Format(format!("Invalid KDF parameters: {}", e)))?;
+
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key[..])
+        .map_err(|e| KeystoreError::Crypto(format!("Key derivation failed: {}", e)))?;
+
+    XChaCha20Poly1305::new_from_slice(&key[..])
+        .map_err(|e| KeystoreError::Crypto(format!("Invalid key length: {}", e)))
+}
+
+/// データをパスフレーズで暗号化します。`aad` は復号時にも同じ値が必要です。
+pub(crate) fn seal(msg: &[u8], passphrase: &str, aad: &[u8]) -> Result<SealedBox, KeystoreError> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| KeystoreError::Crypto("Failed to generate random bytes".to_string()))?;
+
+    let kdf = KdfParams {
+        algorithm: "argon2id".to_string(),
+        salt: general_purpose::STANDARD.encode(salt),
+        memory_kib: ARGON2_MEMORY_KIB,
+        iterations: ARGON2_ITERATIONS,
+        parallelism: ARGON2_PARALLELISM,
+    };
+    let cipher = cipher_for(&kdf, passphrase)?;
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg, aad })
+        .map_err(|_| KeystoreError::Crypto("Failed to encrypt data".to_string()))?;
+
+    Ok(SealedBox {
+        kdf,
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+/// 暗号化されたデータを復号します。認証タグが一致しない場合はパスフレーズ誤りとみなします。
+pub(crate) fn open(
+    sealed: &SealedBox,
+    passphrase: &str,
+    aad: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, KeystoreError> {
+    let nonce = general_purpose::STANDARD
+        .decode(&sealed.nonce)
+        .map_err(|e| KeystoreError::Format(format!("Invalid nonce: {}", e)))?;
+    if nonce.len() != NONCE_LEN {
+        return Err(KeystoreError::Format("Invalid nonce length".to_string()));
+    }
+    let ciphertext = general_purpose::STANDARD
+        .decode(&sealed.ciphertext)
+        .map_err(|e| KeystoreError::Format(format!("Invalid ciphertext: {}", e)))?;
+
+    let cipher = cipher_for(&sealed.kdf, passphrase)?;
+    cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| KeystoreError::InvalidPassphrase)
+}
+
+/// 一時ファイル経由でキーファイルを書き込みます。
+fn write_key_file(path: &Path, key_file: &KeyFile) -> Result<(), KeystoreError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let bytes =
+        serde_json::to_vec_pretty(key_file).map_err(|e| KeystoreError::Format(e.to_string()))?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, bytes)?;
+    std::fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+/// アプリのデータディレクトリにキーストアを初期化します。
+///
+/// `initialize_iroh` と同じ `app_data_dir` 配下の `keystore` ディレクトリを使用します。
+pub fn initialize_keystore<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+) -> Result<(), KeystoreError> {
+    if KEYSTORE.get().is_some() {
+        return Ok(());
+    }
+
+    let base_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| KeystoreError::Io(std::io::Error::other(e.to_string())))?;
+    let keystore_dir = base_dir.join("keystore");
+    std::fs::create_dir_all(&keystore_dir)?;
+
+    let _ = KEYSTORE.set(Keystore::new(keystore_dir));
+    Ok(())
+}
+
+/// 初期化済みのキーストアを取得します。
+/// Panics if the keystore has not been initialized yet.
+pub fn get_keystore() -> &'static Keystore {
+    KEYSTORE
+        .get()
+        .expect("Keystore has not been initialized. Call initialize_keystore during setup.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::user_id::user_id_from_public_key;
+
+    fn generate_pkcs8() -> Vec<u8> {
+        let rng = SystemRandom::new();
+        Ed25519KeyPair::generate_pkcs8(&rng)
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    fn user_id_of(pkcs8: &[u8]) -> String {
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8).unwrap();
+        user_id_from_public_key(key_pair.public_key().as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_store_and_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = Keystore::new(dir.path());
+        let pkcs8 = generate_pkcs8();
+        let alice = user_id_of(&pkcs8);
+
+        keystore
+            .store(&alice, &pkcs8, "correct horse battery")
+            .unwrap();
+        assert!(keystore.contains(&alice));
+        assert_eq!(keystore.list_user_ids().unwrap(), vec![alice.clone()]);
+
+        // The private key must not be stored in plaintext
+        let raw = std::fs::read(dir.path().join(format!("{}.json", alice))).unwrap();
+        let encoded = general_purpose::STANDARD.encode(&pkcs8);
+        assert!(!String::from_utf8_lossy(&raw).contains(&encoded));
+
+        let unlocked = keystore.unlock(&alice, "correct horse battery").unwrap();
+        let expected = Ed25519KeyPair::from_pkcs8(&pkcs8).unwrap();
+        assert_eq!(
+            unlocked.key_pair().public_key().as_ref(),
+            expected.public_key().as_ref()
+        );
+        // The iroh author is derived from, but distinct from, the signing key
+        let author_id = unlocked.author().id();
+        assert_eq!(author_id, unlocked.author().id());
+        assert_ne!(author_id.as_bytes(), expected.public_key().as_ref());
+        assert!(String::from_utf8_lossy(&raw).contains(&author_id.to_string()));
+        // So is the post document namespace, which differs from the author key
+        let namespace = unlocked.post_namespace();
+        assert_eq!(namespace.id(), unlocked.post_namespace().id());
+        assert_ne!(namespace.to_bytes(), unlocked.author().to_bytes());
+        assert_ne!(namespace.id(), unlocked.follower_namespace().id());
+        assert_eq!(
+            unlocked.agreement_secret().to_bytes(),
+            unlocked.agreement_secret().to_bytes()
+        );
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = Keystore::new(dir.path());
+        let pkcs8 = generate_pkcs8();
+        let bob = user_id_of(&pkcs8);
+        keystore.store(&bob, &pkcs8, "first passphrase").unwrap();
+
+        assert!(matches!(
+            keystore.unlock(&bob, "wrong passphrase"),
+            Err(KeystoreError::InvalidPassphrase)
+        ));
+        assert!(matches!(
+            keystore.change_passphrase(&bob, "wrong passphrase", "second passphrase"),
+            Err(KeystoreError::InvalidPassphrase)
+        ));
+
+        keystore
+            .change_passphrase(&bob, "first passphrase", "second passphrase")
+            .unwrap();
+        assert!(matches!(
+            keystore.unlock(&bob, "first passphrase"),
+            Err(KeystoreError::InvalidPassphrase)
+        ));
+        assert!(keystore.unlock(&bob, "second passphrase").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_weak_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = Keystore::new(dir.path());
+        let pkcs8 = generate_pkcs8();
+
+        assert!(matches!(
+            keystore.store(&user_id_of(&pkcs8), &pkcs8, "short"),
+            Err(KeystoreError::WeakPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_path_like_user_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = Keystore::new(dir.path().join("keys"));
+        let pkcs8 = generate_pkcs8();
+
+        for user_id in ["../x", "a/b", "a\\b", "..", ""] {
+            assert!(matches!(
+                keystore.store(user_id, &pkcs8, "correct horse battery"),
+                Err(KeystoreError::InvalidUserId(_))
+            ));
+            assert!(!keystore.contains(user_id));
+            assert!(matches!(
+                keystore.unlock(user_id, "correct horse battery"),
+                Err(KeystoreError::InvalidUserId(_))
+            ));
+            assert!(matches!(
+                keystore.remove(user_id),
+                Err(KeystoreError::InvalidUserId(_))
+            ));
+        }
+        assert!(!dir.path().join("x.json").exists());
+    }
+}
@@ -0,0 +1,18 @@
+//! ユーザーアイデンティティ（鍵と署名）に関する処理
+
+pub mod backup;
+pub mod device;
+pub mod envelope;
+pub mod key_chain;
+pub mod keystore;
+pub mod seed;
+pub mod session;
+pub mod signing;
+pub mod user_id;
+
+pub use key_chain::{KeyChain, KeyStatus};
+pub use signing::{sign_record, verify_record, CanonicalEncoder, Signable};
+pub use user_id::{
+    is_legacy_user_id, is_valid_user_id, public_key_b64_from_user_id, public_key_from_user_id,
+    user_id_from_public_key,
+};
@@ -0,0 +1,124 @@
+//! Ed25519シードとリカバリーフレーズ
+//!
+//! キーストアはPKCS#8形式で秘密鍵を保持しますが、バックアップやリカバリーには
+//! 32バイトのシードを使用します。シードはBIP39の単語列（24語）として表現できます。
+
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use zeroize::Zeroizing;
+
+/// Ed25519シードの長さ
+pub const SEED_LEN: usize = 32;
+
+// RFC 8410 の OneAsymmetricKey (PKCS#8 v2) のうち、シードの直前までのバイト列
+const PKCS8_V2_PREFIX: [u8; 16] = [
+    0x30, 0x53, 0x02, 0x01, 0x01, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+// シードと公開鍵の間に入る [1] publicKey のヘッダー
+const PKCS8_V2_PUBLIC_KEY_HEADER: [u8; 5] = [0xa1, 0x23, 0x03, 0x21, 0x00];
+
+/// シード処理エラー
+#[derive(Debug, thiserror::Error)]
+pub enum SeedError {
+    /// PKCS#8の形式が想定と異なる
+    #[error("Unsupported PKCS#8 encoding")]
+    UnsupportedPkcs8,
+
+    /// リカバリーフレーズが不正
+    #[error("Invalid recovery phrase: {0}")]
+    InvalidMnemonic(String),
+}
+
+/// PKCS#8 v2形式の秘密鍵からシードを取り出します。
+pub fn seed_from_pkcs8(pkcs8: &[u8]) -> Result<Zeroizing<[u8; SEED_LEN]>, SeedError> {
+    let expected_len = PKCS8_V2_PREFIX.len() + SEED_LEN + PKCS8_V2_PUBLIC_KEY_HEADER.len() + 32;
+    if pkcs8.len() != expected_len || !pkcs8.starts_with(&PKCS8_V2_PREFIX) {
+        return Err(SeedError::UnsupportedPkcs8);
+    }
+
+    let mut seed = Zeroizing::new([0u8; SEED_LEN]);
+    seed.copy_from_slice(&pkcs8[PKCS8_V2_PREFIX.len()..PKCS8_V2_PREFIX.len() + SEED_LEN]);
+    Ok(seed)
+}
+
+/// シードからPKCS#8 v2形式の秘密鍵を組み立てます。
+pub fn pkcs8_from_seed(seed: &[u8; SEED_LEN]) -> Result<Zeroizing<Vec<u8>>, SeedError> {
+    let key_pair =
+        Ed25519KeyPair::from_seed_unchecked(seed).map_err(|_| SeedError::UnsupportedPkcs8)?;
+
+    let mut pkcs8 = Zeroizing::new(Vec::with_capacity(85));
+    pkcs8.extend_from_slice(&PKCS8_V2_PREFIX);
+    pkcs8.extend_from_slice(seed);
+    pkcs8.extend_from_slice(&PKCS8_V2_PUBLIC_KEY_HEADER);
+    pkcs8.extend_from_slice(key_pair.public_key().as_ref());
+    Ok(pkcs8)
+}
+
+/// PKCS#8形式の秘密鍵からリカバリーフレーズ（BIP39、24語）を作成します。
+pub fn mnemonic_from_pkcs8(pkcs8: &[u8]) -> Result<Zeroizing<String>, SeedError> {
+    let seed = seed_from_pkcs8(pkcs8)?;
+    let mnemonic = bip39::Mnemonic::from_entropy(&seed[..])
+        .map_err(|e| SeedError::InvalidMnemonic(e.to_string()))?;
+    Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// リカバリーフレーズからPKCS#8形式の秘密鍵を復元します。
+pub fn pkcs8_from_mnemonic(phrase: &str) -> Result<Zeroizing<Vec<u8>>, SeedError> {
+    // 入力時の大文字や余分な空白を許容する
+    let normalized = Zeroizing::new(
+        phrase
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    let mnemonic = bip39::Mnemonic::parse_normalized(&normalized)
+        .map_err(|e| SeedError::InvalidMnemonic(e.to_string()))?;
+    let entropy = Zeroizing::new(mnemonic.to_entropy());
+
+    let seed: &[u8; SEED_LEN] = entropy[..]
+        .try_into()
+        .map_err(|_| SeedError::InvalidMnemonic(format!("expected {} words", SEED_LEN * 3 / 4)))?;
+    pkcs8_from_seed(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pkcs8_seed_roundtrip() {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+
+        let seed = seed_from_pkcs8(pkcs8.as_ref()).unwrap();
+        let rebuilt = pkcs8_from_seed(&seed).unwrap();
+        assert_eq!(&rebuilt[..], pkcs8.as_ref());
+        assert!(Ed25519KeyPair::from_pkcs8(&rebuilt).is_ok());
+    }
+
+    #[test]
+    fn test_mnemonic_roundtrip() {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+
+        let phrase = mnemonic_from_pkcs8(pkcs8.as_ref()).unwrap();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+
+        let restored = pkcs8_from_mnemonic(&phrase).unwrap();
+        assert_eq!(&restored[..], pkcs8.as_ref());
+    }
+
+    #[test]
+    fn test_rejects_invalid_mnemonic() {
+        assert!(pkcs8_from_mnemonic("not a valid recovery phrase").is_err());
+
+        // Valid BIP39 phrase, but only 12 words (128-bit entropy)
+        let short = bip39::Mnemonic::from_entropy(&[0u8; 16])
+            .unwrap()
+            .to_string();
+        assert!(matches!(
+            pkcs8_from_mnemonic(&short),
+            Err(SeedError::InvalidMnemonic(_))
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! サインインセッション
+//!
+//! サインイン中のユーザーと復号済みの鍵を保持します。
+//! Tauriの管理状態（`app.manage`）として登録され、投稿やプロフィール更新などの
+//! コマンドはフロントエンドから渡されたIDではなく、このセッションのユーザーとして動作します。
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock};
+
+use ring::signature::Ed25519KeyPair;
+
+use crate::identity::envelope;
+use crate::identity::keystore::UnlockedKey;
+
+/// セッションエラー
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    /// サインインしていない
+    #[error("Not signed in")]
+    NotSignedIn,
+}
+
+/// サインイン中のユーザー
+pub struct Session {
+    user_id: String,
+    key: UnlockedKey,
+    /// このセッションでアップロードされた添付ファイルのハッシュ
+    uploads: Mutex<HashSet<String>>,
+}
+
+impl Session {
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    pub fn key_pair(&self) -> &Ed25519KeyPair {
+        self.key.key_pair()
+    }
+
+    /// 鍵共有用の公開鍵 (Base64) を返します。
+    pub fn agreement_key(&self) -> String {
+        envelope::agreement_public_key(&self.key.agreement_secret())
+    }
+
+    /// このセッションでアップロードされた添付ファイルのハッシュを記録します。
+    pub fn record_upload(&self, hash: String) {
+        self.uploads.lock().unwrap().insert(hash);
+    }
+
+    /// 添付ファイルがこのセッションでアップロードされたかどうかを返します。
+    pub fn is_uploaded(&self, hash: &str) -> bool {
+        self.uploads.lock().unwrap().contains(hash)
+    }
+}
+
+/// セッションの管理状態
+///
+/// 同時にサインインできるユーザーは1人です。
+#[derive(Default)]
+pub struct SessionState {
+    current: RwLock<Option<Arc<Session>>>,
+}
+
+impl SessionState {
+    /// セッションを開始します。既存のセッションは破棄されます。
+    pub fn start(&self, user_id: String, key: UnlockedKey) -> Arc<Session> {
+        let session = Arc::new(Session {
+            user_id,
+            key,
+            uploads: Mutex::default(),
+        });
+        *self.current.write().unwrap() = Some(session.clone());
+        session
+    }
+
+    /// セッションを終了し、復号済みの鍵を破棄します。
+    pub fn end(&self) -> Option<Arc<Session>> {
+        self.current.write().unwrap().take()
+    }
+
+    /// 現在のセッションを返します。
+    pub fn current(&self) -> Option<Arc<Session>> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// 現在のセッションを返します。サインインしていない場合はエラーを返します。
+    pub fn require(&self) -> Result<Arc<Session>, SessionError> {
+        self.current().ok_or(SessionError::NotSignedIn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::keystore::Keystore;
+
+    #[test]
+    fn test_start_and_end_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = Keystore::new(dir.path());
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let alice = uuid::Uuid::new_v4().to_string();
+        keystore
+            .store(&alice, pkcs8.as_ref(), "correct horse battery")
+            .unwrap();
+
+        let state = SessionState::default();
+        assert!(matches!(state.require(), Err(SessionError::NotSignedIn)));
+
+        let key = keystore.unlock(&alice, "correct horse battery").unwrap();
+        state.start(alice.clone(), key);
+        assert_eq!(state.require().unwrap().user_id(), alice);
+
+        assert!(state.end().is_some());
+        assert!(state.current().is_none());
+    }
+}
@@ -0,0 +1,213 @@
+//! レコード署名と検証
+//!
+//! `Post` や `User` などのレコードを正規化したバイト列に対して
+//! Ed25519 署名を作成・検証します。
+
+use base64::{engine::general_purpose, Engine as _};
+use ring::signature::{Ed25519KeyPair, UnparsedPublicKey, ED25519};
+
+/// 署名エラー
+///
+/// 署名の作成・検証中に発生する可能性のあるエラーを定義します。
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureError {
+    /// 署名が付与されていない
+    #[error("Record is not signed")]
+    Missing,
+
+    /// 署名または公開鍵のエンコードが不正
+    #[error("Malformed signature or public key: {0}")]
+    Malformed(String),
+
+    /// 署名が公開鍵と一致しない
+    #[error("Signature does not match public key")]
+    Mismatch,
+}
+
+/// 署名対象のレコード
+///
+/// `signing_payload` は署名フィールド自身を含まない正規化されたバイト列を返す必要があります。
+pub trait Signable {
+    fn signing_payload(&self) -> Vec<u8>;
+    fn signature(&self) -> Option<&str>;
+    fn set_signature(&mut self, signature: String);
+}
+
+/// 正規化エンコーダー
+///
+/// ドメインタグと長さ付きフィールドを連結し、
+/// シリアライザの実装に依存しない一意なバイト列を作成します。
+pub struct CanonicalEncoder {
+    buf: Vec<u8>,
+}
+
+impl CanonicalEncoder {
+    /// ドメインタグ（例: `"kukuri/post/v1"`）を先頭に書き込んだエンコーダーを作成します。
+    pub fn new(domain: &str) -> Self {
+        let mut encoder = Self { buf: Vec::new() };
+        encoder.write_bytes(domain.as_bytes());
+        encoder
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf
+            .extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn str(mut self, value: &str) -> Self {
+        self.write_bytes(value.as_bytes());
+        self
+    }
+
+    pub fn opt_str(mut self, value: Option<&str>) -> Self {
+        match value {
+            Some(value) => {
+                self.buf.push(1);
+                self.write_bytes(value.as_bytes());
+            }
+            None => self.buf.push(0),
+        }
+        self
+    }
+
+    pub fn strs(mut self, values: &[String]) -> Self {
+        self.buf
+            .extend_from_slice(&(values.len() as u32).to_be_bytes());
+        for value in values {
+            self.write_bytes(value.as_bytes());
+        }
+        self
+    }
+
+    pub fn i64(mut self, value: i64) -> Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn opt_i64(mut self, value: Option<i64>) -> Self {
+        match value {
+            Some(value) => {
+                self.buf.push(1);
+                self.buf.extend_from_slice(&value.to_be_bytes());
+            }
+            None => self.buf.push(0),
+        }
+        self
+    }
+
+    /// 要素数に続けて、各要素を `encode` で書き込みます。
+    ///
+    /// 空のリストは `strs` の空のリストと同じバイト列になります。
+    pub fn list<T>(mut self, values: &[T], encode: impl Fn(Self, &T) -> Self) -> Self {
+        self.buf
+            .extend_from_slice(&(values.len() as u32).to_be_bytes());
+        for value in values {
+            self = encode(self, value);
+        }
+        self
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// レコードに署名し、Base64エンコードした署名を設定します。
+pub fn sign_record<T: Signable>(record: &mut T, key_pair: &Ed25519KeyPair) {
+    let signature = key_pair.sign(&record.signing_payload());
+    record.set_signature(general_purpose::STANDARD.encode(signature.as_ref()));
+}
+
+/// レコードの署名をBase64エンコードされた公開鍵で検証します。
+pub fn verify_record<T: Signable>(record: &T, public_key_b64: &str) -> Result<(), SignatureError> {
+    let signature_b64 = record.signature().ok_or(SignatureError::Missing)?;
+
+    let signature = general_purpose::STANDARD
+        .decode(signature_b64)
+        .map_err(|e| SignatureError::Malformed(format!("signature: {}", e)))?;
+    let public_key = general_purpose::STANDARD
+        .decode(public_key_b64)
+        .map_err(|e| SignatureError::Malformed(format!("public key: {}", e)))?;
+
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(&record.signing_payload(), &signature)
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::KeyPair;
+
+    struct Note {
+        body: String,
+        signature: Option<String>,
+    }
+
+    impl Signable for Note {
+        fn signing_payload(&self) -> Vec<u8> {
+            CanonicalEncoder::new("kukuri/test/v1")
+                .str(&self.body)
+                .finish()
+        }
+
+        fn signature(&self) -> Option<&str> {
+            self.signature.as_deref()
+        }
+
+        fn set_signature(&mut self, signature: String) {
+            self.signature = Some(signature);
+        }
+    }
+
+    fn generate_key_pair() -> Ed25519KeyPair {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn test_sign_and_verify_roundtrip() {
+        let key_pair = generate_key_pair();
+        let public_key = general_purpose::STANDARD.encode(key_pair.public_key().as_ref());
+
+        let mut note = Note {
+            body: "こんにちは".to_string(),
+            signature: None,
+        };
+        assert!(matches!(
+            verify_record(&note, &public_key),
+            Err(SignatureError::Missing)
+        ));
+
+        sign_record(&mut note, &key_pair);
+        assert!(verify_record(&note, &public_key).is_ok());
+
+        note.body = "forged".to_string();
+        assert!(matches!(
+            verify_record(&note, &public_key),
+            Err(SignatureError::Mismatch)
+        ));
+    }
+
+    #[test]
+    fn test_canonical_encoding_is_unambiguous() {
+        let a = CanonicalEncoder::new("d").str("ab").str("c").finish();
+        let b = CanonicalEncoder::new("d").str("a").str("bc").finish();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_empty_list_matches_empty_strs() {
+        let strs = CanonicalEncoder::new("d").strs(&[]).finish();
+        let list = CanonicalEncoder::new("d")
+            .list(&[] as &[i64], |encoder, value| encoder.i64(*value))
+            .finish();
+        assert_eq!(strs, list);
+
+        let some = CanonicalEncoder::new("d").opt_i64(Some(0)).finish();
+        let none = CanonicalEncoder::new("d").opt_i64(None).finish();
+        assert_ne!(some, none);
+    }
+}
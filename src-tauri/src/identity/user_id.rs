@@ -0,0 +1,83 @@
+//! 公開鍵から導出されるユーザーID
+//!
+//! ユーザーIDはEd25519公開鍵をiroh-docsの `AuthorId` と同じ形式（base32）で
+//! エンコードしたものです。IDから公開鍵を復元できるため、
+//! 任意のピアがプロフィールや投稿の帰属を検証できます。
+
+use std::str::FromStr;
+
+use base64::{engine::general_purpose, Engine as _};
+use iroh_docs::AuthorId;
+
+/// 公開鍵のバイト列からユーザーIDを作成します。
+///
+/// 公開鍵が32バイトでない場合は `None` を返します。
+pub fn user_id_from_public_key(public_key: &[u8]) -> Option<String> {
+    let bytes: &[u8; 32] = public_key.try_into().ok()?;
+    Some(AuthorId::from(bytes).to_string())
+}
+
+/// ユーザーIDから公開鍵のバイト列を復元します。
+///
+/// 公開鍵から導出されたIDでない場合（旧形式のUUIDなど）は `None` を返します。
+pub fn public_key_from_user_id(user_id: &str) -> Option<[u8; 32]> {
+    AuthorId::from_str(user_id)
+        .ok()
+        .map(|author_id| *author_id.as_bytes())
+}
+
+/// ユーザーIDに対応するBase64エンコードの公開鍵を返します。
+pub fn public_key_b64_from_user_id(user_id: &str) -> Option<String> {
+    public_key_from_user_id(user_id).map(|key| general_purpose::STANDARD.encode(key))
+}
+
+/// 旧形式（ランダムUUID）のユーザーIDかどうかを判定します。
+pub fn is_legacy_user_id(user_id: &str) -> bool {
+    uuid::Uuid::parse_str(user_id).is_ok()
+}
+
+/// 公開鍵由来または旧形式のユーザーIDかどうかを判定します。
+///
+/// キーファイルのパスなどに使用するため、英数字とハイフン以外を含むIDは拒否します。
+pub fn is_valid_user_id(user_id: &str) -> bool {
+    let well_formed = user_id
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-');
+    well_formed && (public_key_from_user_id(user_id).is_some() || is_legacy_user_id(user_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_id_roundtrip() {
+        let public_key = [7u8; 32];
+        let user_id = user_id_from_public_key(&public_key).unwrap();
+
+        assert_eq!(public_key_from_user_id(&user_id), Some(public_key));
+        assert!(!is_legacy_user_id(&user_id));
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        assert!(user_id_from_public_key(&[1u8; 16]).is_none());
+
+        let legacy_id = uuid::Uuid::new_v4().to_string();
+        assert!(is_legacy_user_id(&legacy_id));
+        assert!(public_key_from_user_id(&legacy_id).is_none());
+    }
+
+    #[test]
+    fn test_valid_user_ids() {
+        let user_id = user_id_from_public_key(&[7u8; 32]).unwrap();
+        assert!(is_valid_user_id(&user_id));
+        assert!(is_valid_user_id(&uuid::Uuid::new_v4().to_string()));
+
+        assert!(!is_valid_user_id(""));
+        assert!(!is_valid_user_id("../x"));
+        assert!(!is_valid_user_id("alice/../../x"));
+        let braced = format!("{{{}}}", uuid::Uuid::new_v4());
+        assert!(!is_valid_user_id(&braced));
+    }
+}
@@ -1,13 +1,26 @@
 mod commands;
+mod identity;
+mod media;
 mod models;
 pub mod network;
 pub mod storage;
+mod validation;
 // Tokio Runtime is usually managed by tauri::async_runtime
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        // サインインセッション（復号済みの鍵を保持）
+        .manage(crate::identity::session::SessionState::default())
         .setup(|app| {
+            // 暗号化キーストアを初期化（アプリデータディレクトリ配下）
+            crate::identity::keystore::initialize_keystore(app.handle())?;
+
+            // 投稿の全文検索インデックスを開く（失敗しても検索以外は動作する）
+            if let Err(err) = crate::storage::search_index::initialize_search_index(app.handle()) {
+                eprintln!("Failed to open search index: {:?}", err);
+            }
+
             let handle = app.handle().clone(); // Clone the handle
                                                // Spawn an async task to initialize the Iroh node
                                                // This prevents blocking the main thread during setup
@@ -20,6 +33,39 @@ pub fn run() {
                 } else {
                     println!("Iroh node initialized successfully.");
 
+                    // 添付画像のプレビュー作成タスクを開始
+                    crate::media::pipeline::start_preview_worker();
+
+                    // 旧形式のキーで保存された投稿を時刻順のキーへ移行
+                    match crate::storage::repository::post_repository::migrate_post_keys().await {
+                        Ok(0) => {}
+                        Ok(count) => println!("Migrated {} posts to time-ordered keys.", count),
+                        Err(err) => eprintln!("Failed to migrate post keys: {:?}", err),
+                    }
+
+                    // インデックスより前に保存された投稿のハッシュタグ・メンションのインデックスを作成
+                    match crate::storage::repository::post_repository::backfill_post_indexes().await
+                    {
+                        Ok(0) => {}
+                        Ok(count) => println!("Backfilled {} post index entries.", count),
+                        Err(err) => eprintln!("Failed to backfill post indexes: {:?}", err),
+                    }
+
+                    // 旧形式(UUID)のユーザーIDを公開鍵由来のIDへ移行
+                    match crate::commands::auth::migrate_legacy_users().await {
+                        Ok(0) => {}
+                        Ok(count) => println!("Migrated {} legacy users.", count),
+                        Err(err) => eprintln!("Failed to migrate legacy users: {:?}", err),
+                    }
+
+                    // 検索インデックスが空の場合は既存の投稿から作成
+                    match crate::storage::repository::post_repository::rebuild_search_index().await
+                    {
+                        Ok(0) => {}
+                        Ok(count) => println!("Indexed {} posts for search.", count),
+                        Err(err) => eprintln!("Failed to build search index: {:?}", err),
+                    }
+
                     // Start document subscription service
                     println!("Starting document subscription service...");
                     let mut subscription_service =
@@ -38,16 +84,61 @@ pub fn run() {
             commands::auth::create_user,
             commands::auth::sign_in,
             commands::auth::list_users,
+            commands::auth::sign_out,
+            commands::auth::current_user,
+            commands::auth::change_passphrase,
+            commands::auth::export_identity,
+            commands::auth::import_identity,
+            commands::auth::recover_identity,
+            commands::auth::rotate_key,
+            commands::auth::revoke_key,
+            commands::auth::request_device_link,
+            commands::auth::approve_device_link,
+            commands::auth::complete_device_link,
             // 投稿コマンド
             commands::post::create_post,
+            commands::post::create_post_with_attachments,
+            commands::post::upload_attachment,
+            commands::post::get_attachment,
+            commands::post::get_attachment_preview,
+            commands::post::edit_post,
+            commands::post::delete_post,
+            commands::post::get_post_history,
+            commands::post::create_reply,
+            commands::post::create_quote,
+            commands::post::repost,
+            commands::post::unrepost,
+            commands::post::add_reaction,
+            commands::post::remove_reaction,
+            commands::post::get_reactions,
+            commands::post::get_thread,
+            commands::post::get_reply_counts,
             commands::post::get_posts,
+            commands::post::get_home_timeline,
             commands::post::get_user_posts,
             commands::post::search_posts,
+            commands::post::get_posts_by_hashtag,
+            commands::post::get_mentions_for_user,
             // プロフィールコマンド
             commands::profile::get_profile,
             commands::profile::update_profile,
+            commands::profile::set_avatar,
+            commands::profile::remove_avatar,
+            commands::profile::get_avatar,
             commands::profile::follow_user,
             commands::profile::unfollow_user,
+            commands::profile::get_follow_requests,
+            commands::profile::approve_follow_request,
+            commands::profile::reject_follow_request,
+            commands::profile::get_following,
+            commands::profile::get_followers,
+            commands::profile::get_follow_counts,
+            commands::profile::mute_user,
+            commands::profile::unmute_user,
+            commands::profile::block_user,
+            commands::profile::unblock_user,
+            commands::profile::get_muted_users,
+            commands::profile::get_blocked_users,
             // 設定コマンド
             commands::settings::get_settings,
             commands::settings::update_settings,
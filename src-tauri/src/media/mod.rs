@@ -0,0 +1,115 @@
+//! メディアファイルの解析と変換
+//!
+//! 添付ファイルのバイト列から MIME タイプと画像の大きさを判定します。
+//! 判定はファイルの内容（マジックナンバー）に基づき、ファイル名の拡張子は
+//! 内容から判定できない場合にのみ使用します。
+//! 画像の縮小は `thumbnail`、添付画像のプレビュー作成は `preview` と `pipeline` で行います。
+
+pub mod pipeline;
+pub mod preview;
+pub mod thumbnail;
+
+pub use preview::render_preview;
+pub use thumbnail::{make_thumbnail, Thumbnail, THUMBNAIL_MIME_TYPE};
+
+/// メディアエラー
+#[derive(Debug, thiserror::Error)]
+pub enum MediaError {
+    /// 画像を読み込めない
+    #[error("Failed to decode image: {0}")]
+    Decode(String),
+
+    /// 画像を書き出せない
+    #[error("Failed to encode image: {0}")]
+    Encode(String),
+}
+
+/// 判定できないファイルの MIME タイプ
+pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// メディアファイルの情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaInfo {
+    pub mime_type: String,
+    /// 画像の幅（画像以外は `None`）
+    pub width: Option<u32>,
+    /// 画像の高さ（画像以外は `None`）
+    pub height: Option<u32>,
+}
+
+impl MediaInfo {
+    /// 画像かどうか
+    pub fn is_image(&self) -> bool {
+        self.mime_type.starts_with("image/")
+    }
+}
+
+/// ファイルの内容から MIME タイプと画像の大きさを判定します。
+///
+/// `name` は内容から判定できない場合に拡張子を参照するためのファイル名です。
+pub fn inspect(data: &[u8], name: Option<&str>) -> MediaInfo {
+    let mime_type = infer::get(data)
+        .map(|kind| kind.mime_type().to_string())
+        .or_else(|| name.and_then(mime_type_from_name).map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_MIME_TYPE.to_string());
+
+    let size = if mime_type.starts_with("image/") {
+        imagesize::blob_size(data).ok()
+    } else {
+        None
+    };
+
+    MediaInfo {
+        mime_type,
+        width: size.map(|size| size.width as u32),
+        height: size.map(|size| size.height as u32),
+    }
+}
+
+/// 内容から判定できないテキスト形式のファイルの MIME タイプを拡張子から判定します。
+fn mime_type_from_name(name: &str) -> Option<&'static str> {
+    let (_, extension) = name.rsplit_once('.')?;
+    match extension.to_ascii_lowercase().as_str() {
+        "txt" => Some("text/plain"),
+        "md" => Some("text/markdown"),
+        "csv" => Some("text/csv"),
+        "json" => Some("application/json"),
+        "svg" => Some("image/svg+xml"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1x1 の透明な PNG
+    const PNG_1X1: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1F,
+        0x15, 0xC4, 0x89, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9C, 0x63, 0x00,
+        0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0D, 0x0A, 0x2D, 0xB4, 0x00, 0x00, 0x00, 0x00, 0x49,
+        0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn test_inspect_image() {
+        let info = inspect(PNG_1X1, Some("photo.jpg"));
+        assert_eq!(info.mime_type, "image/png");
+        assert_eq!((info.width, info.height), (Some(1), Some(1)));
+        assert!(info.is_image());
+    }
+
+    #[test]
+    fn test_inspect_other_files() {
+        let info = inspect(b"hello", Some("notes.TXT"));
+        assert_eq!(info.mime_type, "text/plain");
+        assert_eq!((info.width, info.height), (None, None));
+
+        assert_eq!(inspect(b"hello", None).mime_type, DEFAULT_MIME_TYPE);
+        assert_eq!(
+            inspect(b"hello", Some("archive")).mime_type,
+            DEFAULT_MIME_TYPE
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! 添付画像のプレビュー作成処理
+//!
+//! 添付画像が iroh-blobs に追加されるとハッシュがキューに入り、バックグラウンドの
+//! タスクが縮小画像と blurhash を作成して元の画像に紐付けます。
+//! キューは起動時に `start_preview_worker` で開始し、開始前に追加された画像は
+//! プレビューの取得時に作成されます。
+
+use std::sync::OnceLock;
+
+use iroh_blobs::Hash;
+use tokio::sync::mpsc;
+
+use super::{render_preview, THUMBNAIL_MIME_TYPE};
+use crate::models::post::{AttachmentPreview, PreviewImage};
+use crate::storage::blob_store;
+use crate::storage::{StorageError, StorageResult};
+
+/// プレビュー作成待ちの画像のハッシュを送るキュー
+static PREVIEW_QUEUE: OnceLock<mpsc::UnboundedSender<Hash>> = OnceLock::new();
+
+/// プレビュー作成タスクを開始します。2回目以降の呼び出しは何もしません。
+pub fn start_preview_worker() {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<Hash>();
+    if PREVIEW_QUEUE.set(sender).is_err() {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        while let Some(hash) = receiver.recv().await {
+            if let Err(e) = ensure_preview(hash).await {
+                eprintln!("Failed to generate preview for {}: {}", hash, e);
+            }
+        }
+    });
+}
+
+/// 画像のプレビュー作成をキューに入れます。
+///
+/// タスクが開始されていない場合は何もしません（プレビューは取得時に作成されます）。
+pub fn enqueue_preview(hash: Hash) {
+    if let Some(queue) = PREVIEW_QUEUE.get() {
+        let _ = queue.send(hash);
+    }
+}
+
+/// 画像のプレビューを返します。まだ作成されていない場合は作成して保存します。
+///
+/// 元の画像が手元に無い場合や、画像として読み込めない場合は `None` を返します。
+pub async fn ensure_preview(hash: Hash) -> StorageResult<Option<AttachmentPreview>> {
+    if let Some(preview) = blob_store::get_preview(hash).await? {
+        return Ok(Some(preview));
+    }
+
+    let Some(data) = blob_store::read_blob(hash).await? else {
+        return Ok(None);
+    };
+    if !super::inspect(&data, None).is_image() {
+        return Ok(None);
+    }
+
+    let rendered = match tokio::task::spawn_blocking(move || render_preview(&data))
+        .await
+        .map_err(|e| StorageError::Internal(format!("Preview task failed: {}", e)))?
+    {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            eprintln!("Skipping preview for {}: {}", hash, e);
+            return Ok(None);
+        }
+    };
+
+    let mut thumbnails = Vec::with_capacity(rendered.thumbnails.len());
+    for thumbnail in rendered.thumbnails {
+        let thumbnail_hash = blob_store::import_blob(thumbnail.data).await?;
+        thumbnails.push(PreviewImage {
+            hash: thumbnail_hash.to_string(),
+            mime_type: THUMBNAIL_MIME_TYPE.to_string(),
+            width: thumbnail.width,
+            height: thumbnail.height,
+        });
+    }
+
+    let preview = AttachmentPreview {
+        original: hash.to_string(),
+        width: rendered.width,
+        height: rendered.height,
+        blurhash: rendered.blurhash,
+        thumbnails,
+    };
+    blob_store::save_preview(hash, &preview).await?;
+
+    Ok(Some(preview))
+}
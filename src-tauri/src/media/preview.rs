@@ -0,0 +1,124 @@
+//! 添付画像のプレビュー作成
+//!
+//! タイムラインで元の画像を読み込まずに済むよう、複数の大きさの縮小画像と
+//! 読み込み中に表示する blurhash を作成します。
+
+use image::imageops::FilterType;
+
+use super::thumbnail::{decode, shrink};
+use super::{MediaError, Thumbnail};
+
+/// 縮小画像の幅・高さ（ピクセル）。小さい順に並べます。
+pub const PREVIEW_SIZES: &[u32] = &[320, 960];
+
+/// blurhash の横・縦の成分数
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+/// blurhash の計算に使う画像の幅・高さ（ピクセル）
+const BLURHASH_SAMPLE_SIZE: u32 = 32;
+
+/// 作成したプレビュー
+#[derive(Debug, Clone)]
+pub struct RenderedPreview {
+    /// 元の画像の幅
+    pub width: u32,
+    /// 元の画像の高さ
+    pub height: u32,
+    pub blurhash: String,
+    /// `PREVIEW_SIZES` のうち元の画像より小さいものについての縮小画像
+    pub thumbnails: Vec<Thumbnail>,
+}
+
+/// 画像から縮小画像と blurhash を作成します。
+///
+/// 画像の読み込みと縮小は重い処理のため、非同期タスクからは `spawn_blocking` で呼び出してください。
+pub fn render_preview(data: &[u8]) -> Result<RenderedPreview, MediaError> {
+    let image = decode(data)?;
+    let (width, height) = (image.width(), image.height());
+    if width == 0 || height == 0 {
+        return Err(MediaError::Decode("Image has no pixels".to_string()));
+    }
+
+    let mut thumbnails = Vec::new();
+    for &size in PREVIEW_SIZES {
+        if width <= size && height <= size {
+            break;
+        }
+        thumbnails.push(shrink(&image, size)?);
+    }
+
+    let sample = image
+        .resize(
+            BLURHASH_SAMPLE_SIZE,
+            BLURHASH_SAMPLE_SIZE,
+            FilterType::Triangle,
+        )
+        .to_rgba8();
+    let (components_x, components_y) = BLURHASH_COMPONENTS;
+    let blurhash = blurhash::encode(
+        components_x,
+        components_y,
+        sample.width(),
+        sample.height(),
+        sample.as_raw(),
+    )
+    .map_err(|e| MediaError::Encode(e.to_string()))?;
+
+    Ok(RenderedPreview {
+        width,
+        height,
+        blurhash,
+        thumbnails,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            width,
+            height,
+            Rgba([200, 80, 40, 255]),
+        ));
+        let mut data = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_render_preview_sizes() {
+        let preview = render_preview(&png(2000, 1000)).unwrap();
+        assert_eq!((preview.width, preview.height), (2000, 1000));
+        let sizes: Vec<(u32, u32)> = preview
+            .thumbnails
+            .iter()
+            .map(|thumbnail| (thumbnail.width, thumbnail.height))
+            .collect();
+        assert_eq!(sizes, vec![(320, 160), (960, 480)]);
+
+        // Images smaller than a preview size are not enlarged
+        let preview = render_preview(&png(500, 400)).unwrap();
+        assert_eq!(preview.thumbnails.len(), 1);
+        assert!(render_preview(&png(100, 100))
+            .unwrap()
+            .thumbnails
+            .is_empty());
+    }
+
+    #[test]
+    fn test_render_preview_blurhash() {
+        let preview = render_preview(&png(64, 48)).unwrap();
+        // 1 size character, 1 maximum value character, 4 DC characters and 2 per AC component
+        assert_eq!(preview.blurhash.len(), 4 + 2 * (4 * 3 - 1) + 2);
+        assert!(matches!(
+            render_preview(b"not an image"),
+            Err(MediaError::Decode(_))
+        ));
+    }
+}
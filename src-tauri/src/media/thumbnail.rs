@@ -0,0 +1,96 @@
+//! 画像の縮小
+//!
+//! アバターや添付画像の縮小版を作成します。縮小版は透過を保持できるよう PNG で保存します。
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, ImageReader};
+
+use super::MediaError;
+
+/// 縮小した画像
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    /// PNG エンコードされた画像
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 縮小版の MIME タイプ
+pub const THUMBNAIL_MIME_TYPE: &str = "image/png";
+
+/// 画像を縦横比を保ったまま `max_size` 以内に縮小します。
+///
+/// 元の画像が `max_size` 以内の場合は拡大せず、そのまま PNG に変換します。
+pub fn make_thumbnail(data: &[u8], max_size: u32) -> Result<Thumbnail, MediaError> {
+    shrink(&decode(data)?, max_size)
+}
+
+/// 画像を読み込みます。形式は内容から判定します。
+pub(super) fn decode(data: &[u8]) -> Result<DynamicImage, MediaError> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| MediaError::Decode(e.to_string()))?
+        .decode()
+        .map_err(|e| MediaError::Decode(e.to_string()))
+}
+
+/// 読み込み済みの画像を `max_size` 以内に縮小して PNG に変換します。
+pub(super) fn shrink(image: &DynamicImage, max_size: u32) -> Result<Thumbnail, MediaError> {
+    let resized;
+    let image = if image.width() > max_size || image.height() > max_size {
+        resized = image.thumbnail(max_size, max_size);
+        &resized
+    } else {
+        image
+    };
+
+    let mut encoded = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Png)
+        .map_err(|e| MediaError::Encode(e.to_string()))?;
+
+    Ok(Thumbnail {
+        data: encoded,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(width, height));
+        let mut data = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_make_thumbnail_keeps_aspect_ratio() {
+        let thumbnail = make_thumbnail(&png(400, 200), 100).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (100, 50));
+        assert_eq!(
+            image::guess_format(&thumbnail.data).unwrap(),
+            ImageFormat::Png
+        );
+
+        // Small images are not enlarged
+        let thumbnail = make_thumbnail(&png(40, 20), 100).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (40, 20));
+    }
+
+    #[test]
+    fn test_make_thumbnail_rejects_non_images() {
+        assert!(matches!(
+            make_thumbnail(b"not an image", 100),
+            Err(MediaError::Decode(_))
+        ));
+    }
+}
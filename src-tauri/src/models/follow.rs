@@ -0,0 +1,188 @@
+use crate::identity::envelope::Envelope;
+use crate::identity::{CanonicalEncoder, Signable};
+use serde::{Deserialize, Serialize};
+
+/// フォロー関係
+///
+/// フォローする側のユーザーの鍵で署名され、フォロー関係ごとに別のエントリとして
+/// 保存されます。フォロー解除は `active` が `false` の関係として記録するため、
+/// 他のピアから届いた解除も署名で検証できます。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FollowEdge {
+    /// フォローする側のユーザーのID
+    pub follower_id: String,
+    /// フォローされる側のユーザーのID
+    pub followee_id: String,
+    /// フォロー中かどうか（`false` はフォロー解除）
+    pub active: bool,
+    pub created_at: i64,
+    /// フォローする側のユーザーの鍵による署名 (Base64)
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl FollowEdge {
+    /// 未署名のフォロー関係を現在時刻で作成します。
+    ///
+    /// `previous` が同じ秒以降に作成されている場合も、それより新しい時刻にします。
+    pub fn new(
+        follower_id: &str,
+        followee_id: &str,
+        active: bool,
+        previous: Option<&FollowEdge>,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            follower_id: follower_id.to_string(),
+            followee_id: followee_id.to_string(),
+            active,
+            created_at: previous.map_or(now, |edge| now.max(edge.created_at + 1)),
+            signature: None,
+        }
+    }
+
+    /// `other` より新しい関係かどうかを返します。
+    ///
+    /// 同じ時刻の場合はフォロー解除を優先し、どのピアでも同じ結果になるようにします。
+    pub fn supersedes(&self, other: &FollowEdge) -> bool {
+        (self.created_at, !self.active) > (other.created_at, !other.active)
+    }
+}
+
+impl Signable for FollowEdge {
+    fn signing_payload(&self) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/follow/v1")
+            .str(&self.follower_id)
+            .str(&self.followee_id)
+            .str(if self.active { "follow" } else { "unfollow" })
+            .i64(self.created_at)
+            .finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// フォローリクエスト
+///
+/// フォローに承認が必要なユーザーへのフォローは、承認されるまでこの形で保留されます。
+/// `edge` は承認後にそのまま有効なフォロー関係になります。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FollowRequest {
+    /// フォローする側のユーザーが署名したフォロー関係
+    pub edge: FollowEdge,
+    /// フォロワー限定の投稿ドキュメントのチケットを受け取る鍵共有用の公開鍵 (Base64)
+    pub agreement_key: String,
+    /// フォローする側のユーザーの鍵による署名 (Base64)
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl Signable for FollowRequest {
+    fn signing_payload(&self) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/follow-request/v1")
+            .str(&self.edge.follower_id)
+            .str(&self.edge.followee_id)
+            .i64(self.edge.created_at)
+            .opt_str(self.edge.signature.as_deref())
+            .str(&self.agreement_key)
+            .finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// フォローリクエストの承認
+///
+/// フォローされる側のユーザーの鍵で署名され、承認したフォロー関係と、
+/// フォロワー限定の投稿ドキュメントのチケットをリクエストの鍵宛てに暗号化したものを含みます。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FollowApproval {
+    /// 承認したフォロー関係（フォローする側のユーザーの署名付き）
+    pub edge: FollowEdge,
+    /// フォロワー限定の投稿ドキュメントの読み取り専用チケット（暗号化済み）
+    pub ticket: Envelope,
+    pub created_at: i64,
+    /// フォローされる側のユーザーの鍵による署名 (Base64)
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl FollowApproval {
+    /// チケットの暗号化に結び付ける値を返します。
+    ///
+    /// 別のフォロー関係の承認にチケットを付け替えても復号できないようにします。
+    pub fn ticket_context(edge: &FollowEdge) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/follow-approval-ticket/v1")
+            .str(&edge.follower_id)
+            .str(&edge.followee_id)
+            .i64(edge.created_at)
+            .finish()
+    }
+}
+
+impl Signable for FollowApproval {
+    fn signing_payload(&self) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/follow-approval/v1")
+            .str(&self.edge.follower_id)
+            .str(&self.edge.followee_id)
+            .i64(self.edge.created_at)
+            .opt_str(self.edge.signature.as_deref())
+            .str(&self.ticket.ephemeral_key)
+            .str(&self.ticket.nonce)
+            .str(&self.ticket.ciphertext)
+            .i64(self.created_at)
+            .finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// フォロー数とフォロワー数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FollowCounts {
+    pub following: usize,
+    pub followers: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(active: bool, created_at: i64) -> FollowEdge {
+        FollowEdge {
+            follower_id: "alice".to_string(),
+            followee_id: "bob".to_string(),
+            active,
+            created_at,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_newer_edge_supersedes() {
+        assert!(edge(false, 2).supersedes(&edge(true, 1)));
+        assert!(!edge(true, 1).supersedes(&edge(false, 2)));
+        // Unfollow wins a tie so every peer converges on the same edge
+        assert!(edge(false, 1).supersedes(&edge(true, 1)));
+        assert!(!edge(true, 1).supersedes(&edge(false, 1)));
+        assert!(!edge(true, 1).supersedes(&edge(true, 1)));
+    }
+}
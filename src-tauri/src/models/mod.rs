@@ -1,3 +1,6 @@
+pub mod follow;
+pub mod moderation;
 pub mod post;
+pub mod reaction;
 pub mod settings;
 pub mod user;
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// ミュート・ブロックの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationKind {
+    /// タイムラインと検索結果に表示しない
+    Mute,
+    /// ミュートに加えて、投稿ドキュメントを同期せずゴシップも受け取らない
+    Block,
+}
+
+impl ModerationKind {
+    /// キーに使う名前を返します。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModerationKind::Mute => "mute",
+            ModerationKind::Block => "block",
+        }
+    }
+}
+
+impl std::fmt::Display for ModerationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// ミュート・ブロックしたユーザー
+///
+/// ミュート・ブロックしたユーザーの設定として、この端末だけに保存されます。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationEntry {
+    /// ミュート・ブロックしたユーザーのID
+    pub user_id: String,
+    /// ミュート・ブロックされたユーザーのID
+    pub target_id: String,
+    pub kind: ModerationKind,
+    pub created_at: i64,
+}
+
+/// 閲覧中のユーザーのミュート・ブロックによる絞り込み
+#[derive(Debug, Clone, Default)]
+pub struct ModerationFilter {
+    muted: HashSet<String>,
+    blocked: HashSet<String>,
+}
+
+impl ModerationFilter {
+    /// ミュート・ブロックの一覧から作成します。
+    pub fn new<'a>(entries: impl IntoIterator<Item = &'a ModerationEntry>) -> Self {
+        let mut filter = Self::default();
+        for entry in entries {
+            let target_id = entry.target_id.clone();
+            match entry.kind {
+                ModerationKind::Mute => filter.muted.insert(target_id),
+                ModerationKind::Block => filter.blocked.insert(target_id),
+            };
+        }
+        filter
+    }
+
+    /// ユーザーの投稿やリポストを表示しないかどうかを返します。
+    pub fn hides(&self, user_id: &str) -> bool {
+        self.muted.contains(user_id) || self.blocked.contains(user_id)
+    }
+
+    /// ユーザーをブロックしているかどうかを返します。
+    pub fn blocks(&self, user_id: &str) -> bool {
+        self.blocked.contains(user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(target_id: &str, kind: ModerationKind) -> ModerationEntry {
+        ModerationEntry {
+            user_id: "alice".to_string(),
+            target_id: target_id.to_string(),
+            kind,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_filter_hides_muted_and_blocked_users() {
+        let entries = [
+            entry("bob", ModerationKind::Mute),
+            entry("carol", ModerationKind::Block),
+        ];
+        let filter = ModerationFilter::new(&entries);
+
+        assert!(filter.hides("bob"));
+        assert!(!filter.blocks("bob"));
+        assert!(filter.hides("carol"));
+        assert!(filter.blocks("carol"));
+        assert!(!filter.hides("dave"));
+
+        assert!(!ModerationFilter::default().hides("bob"));
+    }
+}
@@ -1,15 +1,125 @@
+use crate::identity::{CanonicalEncoder, Signable};
+use crate::models::reaction::ReactionSummary;
 use crate::storage::traits::{HasId, PostEntry as PostTrait}; // Correct path and renamed trait
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Post {
     pub id: String,
     pub author_id: String,
     pub content: String,
-    pub attachments: Vec<String>,
+    pub attachments: Vec<Attachment>,
     pub mentions: Vec<String>,
     pub hashtags: Vec<String>,
     pub created_at: i64,
+    /// 最後に編集された日時（編集されていない場合は `None`）
+    #[serde(default)]
+    pub edited_at: Option<i64>,
+    /// 編集前の版（古い順）
+    #[serde(default)]
+    pub revisions: Vec<PostRevision>,
+    /// 返信先の投稿ID
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// 返信が属するスレッドの最初の投稿のID（返信でない投稿は `None`）
+    #[serde(default)]
+    pub root_id: Option<String>,
+    /// 引用した投稿のID
+    #[serde(default)]
+    pub quote_of: Option<String>,
+    /// 公開範囲
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// 作成者の鍵による署名 (Base64)
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// 署名した鍵が失効済みかどうか（読み込み時に設定され、署名対象外）
+    ///
+    /// 保存された値や他のピアから受け取った値は読み込まず、常に検証結果から設定します。
+    #[serde(default, skip_deserializing)]
+    pub key_revoked: bool,
+}
+
+/// 投稿の公開範囲
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// 誰でも読める投稿（ゴシップで配信され、投稿ドキュメントで共有されます）
+    #[default]
+    Public,
+    /// 承認したフォロワーだけが読める投稿（フォロワー限定の投稿ドキュメントでだけ共有されます）
+    Followers,
+}
+
+impl Visibility {
+    /// 署名対象に使う名前を返します。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Followers => "followers",
+        }
+    }
+}
+
+/// 添付ファイルのメタデータ
+///
+/// ファイルの内容は iroh-blobs に保存され、`hash` で参照されます。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// iroh-blobs のハッシュ
+    pub hash: String,
+    pub mime_type: String,
+    /// バイト数
+    pub size: u64,
+    /// 画像の幅（画像以外は `None`）
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// 画像の高さ（画像以外は `None`）
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// 元のファイル名
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// 投稿の編集前の版
+///
+/// 編集前の署名済みの投稿は JSON として iroh-blobs に保存され、`hash` で参照されます。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostRevision {
+    /// 編集前の投稿の iroh-blobs のハッシュ
+    pub hash: String,
+    /// 編集前の版が書かれた日時（作成日時または前回の編集日時）
+    pub written_at: i64,
+}
+
+/// 添付画像のプレビュー
+///
+/// 元の画像から各端末で作成され、縮小画像は元の画像とは別の blob として保存されます。
+/// 署名されたレコードではないため、ドキュメントでは共有しません。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentPreview {
+    /// 元の画像の iroh-blobs のハッシュ
+    pub original: String,
+    /// 元の画像の幅
+    pub width: u32,
+    /// 元の画像の高さ
+    pub height: u32,
+    /// 読み込み中に表示するプレースホルダー
+    pub blurhash: String,
+    /// 縮小画像（小さい順）。元の画像が十分小さい場合は空になります。
+    pub thumbnails: Vec<PreviewImage>,
+}
+
+/// プレビュー用の縮小画像
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreviewImage {
+    /// iroh-blobs のハッシュ
+    pub hash: String,
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
 }
 
 impl HasId for Post {
@@ -32,3 +142,531 @@ impl PostTrait for Post {
         self.created_at
     }
 }
+
+/// 編集・返信・引用をしていない投稿は、それらの項目を追加する前と同じバイト列になります。
+/// フォロワー限定の投稿は、すべての項目に続けて公開範囲を加えます。
+impl Signable for Post {
+    fn signing_payload(&self) -> Vec<u8> {
+        let encoder = CanonicalEncoder::new("kukuri/post/v1")
+            .str(&self.id)
+            .str(&self.author_id)
+            .str(&self.content)
+            .list(&self.attachments, |encoder, attachment| {
+                encoder
+                    .str(&attachment.hash)
+                    .str(&attachment.mime_type)
+                    .i64(attachment.size as i64)
+                    .opt_i64(attachment.width.map(i64::from))
+                    .opt_i64(attachment.height.map(i64::from))
+                    .opt_str(attachment.name.as_deref())
+            })
+            .strs(&self.mentions)
+            .strs(&self.hashtags)
+            .i64(self.created_at);
+
+        let is_private = self.visibility != Visibility::Public;
+        let is_quote = is_private || self.quote_of.is_some();
+        let is_reply = is_quote || self.reply_to.is_some() || self.root_id.is_some();
+        if self.edited_at.is_none() && self.revisions.is_empty() && !is_reply {
+            return encoder.finish();
+        }
+        let encoder = encoder
+            .opt_i64(self.edited_at)
+            .list(&self.revisions, |encoder, revision| {
+                encoder.str(&revision.hash).i64(revision.written_at)
+            });
+
+        if !is_reply {
+            return encoder.finish();
+        }
+        let encoder = encoder
+            .opt_str(self.reply_to.as_deref())
+            .opt_str(self.root_id.as_deref());
+
+        if !is_quote {
+            return encoder.finish();
+        }
+        let encoder = encoder.opt_str(self.quote_of.as_deref());
+
+        if !is_private {
+            return encoder.finish();
+        }
+        encoder.str(self.visibility.as_str()).finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// 本文からハッシュタグを抽出します。
+///
+/// `#` または `＃` に続く文字・数字・`_` の並びをハッシュタグとし、日本語のタグにも対応します。
+/// 英数字などの直後の `#`（`C#` など）や数字だけのタグは無視します。
+/// タグは正規化（小文字化）され、重複は除かれます。
+pub fn extract_hashtags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in extract_marked(content, &['#', '＃']) {
+        if let Some(tag) = normalize_hashtag(tag) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+/// 本文からメンションされた名前を抽出します。
+///
+/// `@` または `＠` に続く文字・数字・`_` の並びを名前とします。
+/// 英数字などの直後の `@`（メールアドレスなど）は無視します。重複は除かれます。
+pub fn extract_mentions(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in extract_marked(content, &['@', '＠']) {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// ハッシュタグを正規化します。
+///
+/// 先頭の `#` を取り除いて小文字にします。タグとして使えない文字を含む場合や、
+/// 数字だけの場合は `None` を返します。
+pub fn normalize_hashtag(tag: &str) -> Option<String> {
+    let tag = tag.trim();
+    let tag = tag
+        .strip_prefix('#')
+        .or_else(|| tag.strip_prefix('＃'))
+        .unwrap_or(tag);
+    if tag.is_empty() || !tag.chars().all(is_tag_char) || tag.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(tag.to_lowercase())
+}
+
+/// ハッシュタグやメンションに使える文字かどうか
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 記号の直後に続く、タグに使える文字の並びを抽出します。
+fn extract_marked<'a>(content: &'a str, markers: &[char]) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut previous: Option<char> = None;
+
+    for (offset, c) in content.char_indices() {
+        let at_boundary = previous.is_none_or(|p| !is_tag_char(p) && !markers.contains(&p));
+        previous = Some(c);
+        if !markers.contains(&c) || !at_boundary {
+            continue;
+        }
+
+        let rest = &content[offset + c.len_utf8()..];
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| !is_tag_char(c))
+            .map_or(rest.len(), |(end, _)| end);
+        if end > 0 {
+            found.push(&rest[..end]);
+        }
+    }
+
+    found
+}
+
+/// タイムライン上の位置を表すカーソル
+///
+/// 投稿は `created_at` の降順（同時刻は `author_id`、`id` の降順）に並び、
+/// カーソルより後ろ（古い側）の投稿が次のページになります。
+/// 並び順は投稿のキーの順序と一致します。
+/// 文字列 `"<created_at>:<author_id>:<id>"` としてシリアライズされます。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct PostCursor {
+    pub created_at: i64,
+    pub author_id: String,
+    pub id: String,
+}
+
+impl PostCursor {
+    /// 投稿の位置を表すカーソルを作成します。
+    pub fn of(post: &Post) -> Self {
+        Self {
+            created_at: post.created_at,
+            author_id: post.author_id.clone(),
+            id: post.id.clone(),
+        }
+    }
+
+    /// リポストの位置を表すカーソルを作成します。
+    pub fn of_repost(repost: &Repost) -> Self {
+        Self {
+            created_at: repost.created_at,
+            author_id: repost.reposter_id.clone(),
+            id: repost.id.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for PostCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.created_at, self.author_id, self.id)
+    }
+}
+
+impl std::str::FromStr for PostCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid cursor: {}", s);
+        let mut parts = s.splitn(3, ':');
+        let created_at = parts
+            .next()
+            .and_then(|created_at| created_at.parse().ok())
+            .ok_or_else(invalid)?;
+        let author_id = parts.next().ok_or_else(invalid)?;
+        let id = parts.next().ok_or_else(invalid)?;
+        Ok(Self {
+            created_at,
+            author_id: author_id.to_string(),
+            id: id.to_string(),
+        })
+    }
+}
+
+impl From<PostCursor> for String {
+    fn from(cursor: PostCursor) -> Self {
+        cursor.to_string()
+    }
+}
+
+impl TryFrom<String> for PostCursor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// リポスト
+///
+/// 他の投稿をそのまま共有する記録で、リポストしたユーザーの鍵で署名されます。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Repost {
+    pub id: String,
+    /// リポストしたユーザーのID
+    pub reposter_id: String,
+    /// リポストされた投稿のID
+    pub post_id: String,
+    /// リポストされた投稿の作成者のID
+    pub post_author_id: String,
+    pub created_at: i64,
+    /// リポストしたユーザーの鍵による署名 (Base64)
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl Signable for Repost {
+    fn signing_payload(&self) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/repost/v1")
+            .str(&self.id)
+            .str(&self.reposter_id)
+            .str(&self.post_id)
+            .str(&self.post_author_id)
+            .i64(self.created_at)
+            .finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// タイムラインの項目
+///
+/// 投稿は一度だけ表示され、リポストされた投稿は最新のリポストの位置に表示されます。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineItem {
+    pub post: Post,
+    /// 引用した投稿（同期されていない場合や引用でない場合は `None`）
+    pub quoted: Option<Post>,
+    /// 投稿をリポストしたユーザーのID（新しい順）
+    pub reposted_by: Vec<String>,
+    /// 最新のリポストの日時（リポストされていない場合は `None`）
+    pub reposted_at: Option<i64>,
+    /// 投稿へのリアクションの集計
+    pub reactions: ReactionSummary,
+}
+
+/// タイムラインのページ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelinePage {
+    /// 新しい順の項目
+    pub items: Vec<TimelineItem>,
+    /// 次のページを取得するためのカーソル（続きが無い場合は `None`）
+    pub next_cursor: Option<PostCursor>,
+}
+
+/// 投稿のページ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostPage {
+    /// 新しい順の投稿
+    pub posts: Vec<Post>,
+    /// 次のページを取得するためのカーソル（続きが無い場合は `None`）
+    pub next_cursor: Option<PostCursor>,
+}
+
+/// スレッド
+///
+/// スレッドの最初の投稿と、それに連なる返信の木です。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Thread {
+    /// スレッドの最初の投稿のID
+    pub root_id: String,
+    /// 最初の投稿（まだ同期されていない場合や削除された場合は `None`）
+    pub root: Option<Post>,
+    /// 最初の投稿への返信と、返信先が見つからない返信（古い順）
+    pub replies: Vec<ThreadNode>,
+}
+
+/// スレッド内の返信
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadNode {
+    pub post: Post,
+    /// 返信先の投稿がまだ同期されていない（または削除された）かどうか
+    pub parent_missing: bool,
+    /// この投稿への返信（古い順）
+    pub replies: Vec<ThreadNode>,
+}
+
+impl Thread {
+    /// スレッドの投稿から返信の木を組み立てます。
+    ///
+    /// `replies` のうち `root_id` が一致しない投稿は無視します。返信先がスレッド内に
+    /// 見つからない返信は、`parent_missing` を付けて最初の投稿への返信と同じ階層に置きます。
+    pub fn assemble(root_id: String, root: Option<Post>, replies: Vec<Post>) -> Self {
+        let mut ids = HashSet::new();
+        let replies: Vec<Post> = replies
+            .into_iter()
+            .filter(|post| post.id != root_id && post.root_id.as_deref() == Some(&root_id))
+            .filter(|post| ids.insert(post.id.clone()))
+            .collect();
+
+        let mut children: HashMap<String, Vec<Post>> = HashMap::new();
+        let mut orphans = Vec::new();
+        for post in replies {
+            let parent = post.reply_to.clone().unwrap_or_else(|| root_id.clone());
+            if parent == root_id || ids.contains(&parent) {
+                children.entry(parent).or_default().push(post);
+            } else {
+                orphans.push(post);
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut top: Vec<ThreadNode> = children
+            .remove(&root_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|post| build_node(post, false, &mut children, &mut visited))
+            .collect();
+        for post in orphans {
+            top.push(build_node(post, true, &mut children, &mut visited));
+        }
+        // 返信が循環していて最初の投稿から辿れない返信
+        let mut unreachable: Vec<Post> = children.values().flatten().cloned().collect();
+        unreachable.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        for post in unreachable {
+            if !visited.contains(&post.id) {
+                top.push(build_node(post, true, &mut children, &mut visited));
+            }
+        }
+        sort_nodes(&mut top);
+
+        Thread {
+            root_id,
+            root,
+            replies: top,
+        }
+    }
+}
+
+/// 投稿とその子孫の返信からスレッドの節を作ります。
+fn build_node(
+    post: Post,
+    parent_missing: bool,
+    children: &mut HashMap<String, Vec<Post>>,
+    visited: &mut HashSet<String>,
+) -> ThreadNode {
+    visited.insert(post.id.clone());
+    let mut replies = Vec::new();
+    for reply in children.remove(&post.id).unwrap_or_default() {
+        if !visited.contains(&reply.id) {
+            replies.push(build_node(reply, false, children, visited));
+        }
+    }
+    sort_nodes(&mut replies);
+
+    ThreadNode {
+        post,
+        parent_missing,
+        replies,
+    }
+}
+
+/// 返信を古い順に並べます。
+fn sort_nodes(nodes: &mut [ThreadNode]) {
+    nodes.sort_by(|a, b| (a.post.created_at, &a.post.id).cmp(&(b.post.created_at, &b.post.id)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_hashtags() {
+        assert_eq!(
+            extract_hashtags("#Rust と ＃日本語タグ、#rust #東京_2025 です"),
+            vec!["rust", "日本語タグ", "東京_2025"]
+        );
+        assert!(extract_hashtags("C# と a#b と #123 と ## と #").is_empty());
+        assert_eq!(normalize_hashtag("#Kukuri"), Some("kukuri".to_string()));
+        assert_eq!(normalize_hashtag("ラーメン"), Some("ラーメン".to_string()));
+        assert_eq!(normalize_hashtag("two words"), None);
+    }
+
+    #[test]
+    fn test_extract_mentions() {
+        assert_eq!(
+            extract_mentions("@alice と ＠たろう さん、@alice."),
+            vec!["alice", "たろう"]
+        );
+        assert!(extract_mentions("mail@example.com と @ だけ").is_empty());
+    }
+
+    fn reply(id: &str, reply_to: &str, root_id: &str, created_at: i64) -> Post {
+        Post {
+            id: id.to_string(),
+            author_id: "alice".to_string(),
+            content: String::new(),
+            attachments: Vec::new(),
+            mentions: Vec::new(),
+            hashtags: Vec::new(),
+            created_at,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: Some(reply_to.to_string()),
+            root_id: Some(root_id.to_string()),
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
+        }
+    }
+
+    fn ids(nodes: &[ThreadNode]) -> Vec<&str> {
+        nodes.iter().map(|node| node.post.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_assemble_thread() {
+        let thread = Thread::assemble(
+            "root".to_string(),
+            None,
+            vec![
+                reply("c", "a", "root", 3),
+                reply("b", "root", "root", 2),
+                reply("a", "root", "root", 1),
+                reply("d", "a", "root", 4),
+                reply("other", "root", "another-root", 5),
+            ],
+        );
+
+        assert_eq!(ids(&thread.replies), vec!["a", "b"]);
+        assert_eq!(ids(&thread.replies[0].replies), vec!["c", "d"]);
+        assert!(thread.replies.iter().all(|node| !node.parent_missing));
+    }
+
+    #[test]
+    fn test_assemble_thread_with_missing_parents() {
+        let thread = Thread::assemble(
+            "root".to_string(),
+            None,
+            vec![
+                reply("a", "root", "root", 1),
+                // The parent has not synced yet
+                reply("b", "missing", "root", 2),
+                reply("c", "b", "root", 3),
+                // Replies that form a cycle are still returned once
+                reply("x", "y", "root", 4),
+                reply("y", "x", "root", 5),
+            ],
+        );
+
+        assert_eq!(ids(&thread.replies), vec!["a", "b", "x"]);
+        assert!(thread.replies[1].parent_missing);
+        assert_eq!(ids(&thread.replies[1].replies), vec!["c"]);
+        assert!(thread.replies[2].parent_missing);
+        assert_eq!(ids(&thread.replies[2].replies), vec!["y"]);
+        assert!(thread.replies[2].replies[0].replies.is_empty());
+    }
+
+    #[test]
+    fn test_cursor_roundtrip_and_order() {
+        let cursor: PostCursor = "1700000000:alice:0b6f1c2e-uuid".parse().unwrap();
+        assert_eq!(cursor.created_at, 1_700_000_000);
+        assert_eq!(cursor.author_id, "alice");
+        assert_eq!(cursor.id, "0b6f1c2e-uuid");
+        assert_eq!(cursor.to_string(), "1700000000:alice:0b6f1c2e-uuid");
+
+        let json = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(json, "\"1700000000:alice:0b6f1c2e-uuid\"");
+        assert_eq!(serde_json::from_str::<PostCursor>(&json).unwrap(), cursor);
+
+        let older: PostCursor = "1699999999:zed:ffff".parse().unwrap();
+        assert!(older < cursor);
+        let same_time: PostCursor = "1700000000:bob:0000".parse().unwrap();
+        assert!(same_time > cursor);
+        assert!("no-separator".parse::<PostCursor>().is_err());
+        assert!("1700000000:id".parse::<PostCursor>().is_err());
+        assert!("abc:alice:id".parse::<PostCursor>().is_err());
+    }
+
+    #[test]
+    fn test_visibility_is_signed() {
+        let public = reply("a", "root", "root", 1);
+        let followers = Post {
+            visibility: Visibility::Followers,
+            ..public.clone()
+        };
+        assert_ne!(public.signing_payload(), followers.signing_payload());
+
+        // Posts saved before the field existed read as public
+        let mut json = serde_json::to_value(&public).unwrap();
+        json.as_object_mut().unwrap().remove("visibility");
+        let parsed: Post = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.visibility, Visibility::Public);
+        assert_eq!(parsed.signing_payload(), public.signing_payload());
+    }
+
+    #[test]
+    fn test_key_revoked_is_not_deserialized() {
+        let post = Post {
+            key_revoked: true,
+            ..reply("a", "root", "root", 1)
+        };
+        let json = serde_json::to_string(&post).unwrap();
+        let parsed: Post = serde_json::from_str(&json).unwrap();
+        assert!(!parsed.key_revoked);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::identity::{CanonicalEncoder, Signable};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// リアクションの種類
+///
+/// いいねと少数の絵文字から選びます。フロントエンドで絵文字として表示されます。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactionKind {
+    /// いいね (👍)
+    Like,
+    /// ❤️
+    Heart,
+    /// 😂
+    Laugh,
+    /// 😮
+    Surprised,
+    /// 😢
+    Sad,
+    /// 🎉
+    Celebrate,
+}
+
+impl ReactionKind {
+    /// すべてのリアクションの種類
+    pub const ALL: [ReactionKind; 6] = [
+        ReactionKind::Like,
+        ReactionKind::Heart,
+        ReactionKind::Laugh,
+        ReactionKind::Surprised,
+        ReactionKind::Sad,
+        ReactionKind::Celebrate,
+    ];
+
+    /// キーや署名に使う名前を返します。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReactionKind::Like => "like",
+            ReactionKind::Heart => "heart",
+            ReactionKind::Laugh => "laugh",
+            ReactionKind::Surprised => "surprised",
+            ReactionKind::Sad => "sad",
+            ReactionKind::Celebrate => "celebrate",
+        }
+    }
+}
+
+impl std::fmt::Display for ReactionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ReactionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ReactionKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| format!("Unknown reaction: {}", s))
+    }
+}
+
+/// 投稿へのリアクション
+///
+/// 投稿とは別のエントリとして保存され、リアクションしたユーザーの鍵で署名されます。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reaction {
+    /// リアクションした投稿のID
+    pub post_id: String,
+    /// リアクションしたユーザーのID
+    pub reactor_id: String,
+    pub kind: ReactionKind,
+    pub created_at: i64,
+    /// リアクションしたユーザーの鍵による署名 (Base64)
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl Signable for Reaction {
+    fn signing_payload(&self) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/reaction/v1")
+            .str(&self.post_id)
+            .str(&self.reactor_id)
+            .str(self.kind.as_str())
+            .i64(self.created_at)
+            .finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// 投稿へのリアクションの集計
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReactionSummary {
+    /// 種類ごとのリアクションの数（リアクションの無い種類は含まない）
+    pub counts: BTreeMap<ReactionKind, usize>,
+    /// 閲覧中のユーザーがしたリアクション
+    pub mine: Vec<ReactionKind>,
+}
+
+impl ReactionSummary {
+    /// リアクションを集計します。
+    ///
+    /// 同じユーザーによる同じ種類のリアクションは1つとして数えます。
+    pub fn tally(reactions: &[Reaction], viewer: Option<&str>) -> Self {
+        let mut summary = Self::default();
+        let mut seen = std::collections::HashSet::new();
+
+        for reaction in reactions {
+            if !seen.insert((reaction.reactor_id.as_str(), reaction.kind)) {
+                continue;
+            }
+            *summary.counts.entry(reaction.kind).or_default() += 1;
+            if viewer == Some(reaction.reactor_id.as_str()) {
+                summary.mine.push(reaction.kind);
+            }
+        }
+        summary.mine.sort();
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reaction(reactor_id: &str, kind: ReactionKind) -> Reaction {
+        Reaction {
+            post_id: "post".to_string(),
+            reactor_id: reactor_id.to_string(),
+            kind,
+            created_at: 0,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_reaction_kind_names() {
+        for kind in ReactionKind::ALL {
+            assert_eq!(kind.as_str().parse::<ReactionKind>(), Ok(kind));
+            assert_eq!(
+                serde_json::to_string(&kind).unwrap(),
+                format!("\"{}\"", kind)
+            );
+        }
+        assert!("thumbs_down".parse::<ReactionKind>().is_err());
+    }
+
+    #[test]
+    fn test_tally_reactions() {
+        let reactions = vec![
+            reaction("alice", ReactionKind::Like),
+            reaction("bob", ReactionKind::Like),
+            reaction("bob", ReactionKind::Laugh),
+            // Duplicate entries are counted once
+            reaction("bob", ReactionKind::Like),
+        ];
+
+        let summary = ReactionSummary::tally(&reactions, Some("bob"));
+        assert_eq!(summary.counts.get(&ReactionKind::Like), Some(&2));
+        assert_eq!(summary.counts.get(&ReactionKind::Laugh), Some(&1));
+        assert_eq!(summary.counts.get(&ReactionKind::Heart), None);
+        assert_eq!(summary.mine, vec![ReactionKind::Like, ReactionKind::Laugh]);
+
+        assert!(ReactionSummary::tally(&reactions, None).mine.is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::identity::{CanonicalEncoder, Signable};
 use crate::storage::traits::HasId; // Correct path for the trait
 use serde::{Deserialize, Serialize};
 
@@ -7,10 +8,50 @@ pub struct User {
     pub display_name: String,
     pub bio: String,
     pub public_key: String,
+    /// アバター画像の blob ハッシュ
     pub avatar: Option<String>,
+    /// アバターの縮小画像の blob ハッシュ
+    #[serde(default)]
+    pub avatar_thumbnail: Option<String>,
+    /// フォロー中のユーザー
+    ///
+    /// フォロー関係は `FollowEdge` として別に保存され、取得時にそこから補完されます。
+    /// 保存されたプロフィールでは旧形式のデータの移行前を除き空です。
     pub following: Vec<String>,
+    /// フォロワー（取得時にフォロー関係から補完）
     pub followers: Vec<String>,
+    /// この鍵から委任された他の端末の鍵
+    #[serde(default)]
+    pub devices: Vec<DeviceDelegation>,
+    /// 現在の鍵の投稿ドキュメントの読み取り専用チケット（フォローすると同期されます）
+    ///
+    /// 委任された端末の投稿ドキュメントのチケットは各委任に含まれます。
+    #[serde(default)]
+    pub post_doc_ticket: Option<String>,
+    /// フォローに承認が必要かどうか（フォロワー限定の投稿は承認したフォロワーにだけ共有されます）
+    #[serde(default)]
+    pub approval_required: bool,
     pub created_at: i64,
+    /// `public_key` に対応する秘密鍵による自己署名 (Base64)
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl User {
+    /// フォローすると同期する投稿ドキュメントのチケットを返します。
+    ///
+    /// 現在の鍵のドキュメントに続けて、委任された端末のドキュメントを返します。
+    pub fn post_doc_tickets(&self) -> Vec<&str> {
+        self.post_doc_ticket
+            .iter()
+            .chain(
+                self.devices
+                    .iter()
+                    .filter_map(|device| device.post_doc_ticket.as_ref()),
+            )
+            .map(String::as_str)
+            .collect()
+    }
 }
 
 impl HasId for User {
@@ -18,3 +59,198 @@ impl HasId for User {
         &self.id
     }
 }
+
+/// `followers` は他のユーザーの操作で変化するため署名対象に含めません。
+/// 端末の委任は各委任の署名で内容を束縛します。委任もアバターの縮小画像も無い場合は
+/// 以前と同じバイト列になり、投稿ドキュメントのチケットは有る場合だけ末尾に加えます。
+/// フォローに承認が必要な場合は、チケットの有無に続けてその旨を加えます。
+impl Signable for User {
+    fn signing_payload(&self) -> Vec<u8> {
+        let encoder = CanonicalEncoder::new("kukuri/user/v1")
+            .str(&self.id)
+            .str(&self.display_name)
+            .str(&self.bio)
+            .str(&self.public_key)
+            .opt_str(self.avatar.as_deref())
+            .strs(&self.following)
+            .i64(self.created_at);
+
+        if self.devices.is_empty()
+            && self.avatar_thumbnail.is_none()
+            && self.post_doc_ticket.is_none()
+            && !self.approval_required
+        {
+            return encoder.finish();
+        }
+        let device_signatures: Vec<String> = self
+            .devices
+            .iter()
+            .map(|device| device.signature.clone().unwrap_or_default())
+            .collect();
+        let encoder = encoder
+            .strs(&device_signatures)
+            .opt_str(self.avatar_thumbnail.as_deref());
+        if self.approval_required {
+            return encoder
+                .opt_str(self.post_doc_ticket.as_deref())
+                .str("approval_required")
+                .finish();
+        }
+        match &self.post_doc_ticket {
+            Some(ticket) => encoder.str(ticket).finish(),
+            None => encoder.finish(),
+        }
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// 端末の委任
+///
+/// ユーザーの鍵の連鎖に含まれる鍵で署名され、別の端末の鍵（`device_key`）で
+/// 同じユーザーとして投稿やプロフィール更新を行うことを許可します。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceDelegation {
+    pub user_id: String,
+    /// 委任先の端末の公開鍵 (Base64)
+    pub device_key: String,
+    pub device_name: String,
+    pub created_at: i64,
+    /// 委任先の端末の鍵の投稿ドキュメントの読み取り専用チケット
+    ///
+    /// 端末の投稿は端末の鍵から導出した投稿ドキュメントに書き込まれるため、
+    /// フォローするユーザーはこのチケットからも同期します。
+    #[serde(default)]
+    pub post_doc_ticket: Option<String>,
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+/// 投稿ドキュメントのチケットは有る場合だけ末尾に加えるため、チケットの無い委任は
+/// 以前と同じバイト列になります。
+impl Signable for DeviceDelegation {
+    fn signing_payload(&self) -> Vec<u8> {
+        let encoder = CanonicalEncoder::new("kukuri/device-delegation/v1")
+            .str(&self.user_id)
+            .str(&self.device_key)
+            .str(&self.device_name)
+            .i64(self.created_at);
+        match &self.post_doc_ticket {
+            Some(ticket) => encoder.str(ticket).finish(),
+            None => encoder.finish(),
+        }
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// 旧形式（UUID）のユーザーIDから公開鍵由来のIDへの対応付け
+///
+/// 移行後のIDの鍵で署名されるため、IDの持ち主だけが作成できます。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserAlias {
+    pub legacy_id: String,
+    pub user_id: String,
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl Signable for UserAlias {
+    fn signing_payload(&self) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/user-alias/v1")
+            .str(&self.legacy_id)
+            .str(&self.user_id)
+            .finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// 鍵のローテーション記録
+///
+/// `previous_key` の秘密鍵で署名され、ユーザーの有効な鍵を `new_key` に引き継ぎます。
+/// `sequence` は1から始まる連番で、ユーザーIDの元になった鍵からの順序を表します。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub user_id: String,
+    pub sequence: u32,
+    /// 引き継ぎ元の公開鍵 (Base64)
+    pub previous_key: String,
+    /// 引き継ぎ先の公開鍵 (Base64)
+    pub new_key: String,
+    pub created_at: i64,
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl Signable for KeyRotation {
+    fn signing_payload(&self) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/key-rotation/v1")
+            .str(&self.user_id)
+            .i64(self.sequence as i64)
+            .str(&self.previous_key)
+            .str(&self.new_key)
+            .i64(self.created_at)
+            .finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
+
+/// 鍵の失効記録
+///
+/// 失効させる鍵自身、または鍵の連鎖でそれより新しい鍵で署名されます。
+/// 失効した鍵で署名されたコンテンツは検証時にフラグが付けられます。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRevocation {
+    pub user_id: String,
+    /// 失効させる公開鍵 (Base64)
+    pub revoked_key: String,
+    pub revoked_at: i64,
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl Signable for KeyRevocation {
+    fn signing_payload(&self) -> Vec<u8> {
+        CanonicalEncoder::new("kukuri/key-revocation/v1")
+            .str(&self.user_id)
+            .str(&self.revoked_key)
+            .i64(self.revoked_at)
+            .opt_str(self.reason.as_deref())
+            .finish()
+    }
+
+    fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    fn set_signature(&mut self, signature: String) {
+        self.signature = Some(signature);
+    }
+}
@@ -3,9 +3,12 @@ use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use tokio::task::AbortHandle;
 
-use crate::models::post::Post;
+use crate::models::follow::{FollowApproval, FollowEdge, FollowRequest};
+use crate::models::post::{Post, Visibility};
 use crate::models::user::User;
+use crate::storage::repository::moderation_repository::is_blocked_on_node;
 
 /// メッセージタイプ
 ///
@@ -17,15 +20,33 @@ enum MessageType {
     /// プロフィール更新
     UpdateProfile(User),
     /// フォロー関係
-    Follow { from_id: String, to_id: String },
+    Follow(FollowEdge),
     /// フォロー解除
-    Unfollow { from_id: String, to_id: String },
+    Unfollow(FollowEdge),
+    /// フォローリクエスト
+    FollowRequest(FollowRequest),
+    /// フォローリクエストの承認
+    FollowApproval(FollowApproval),
     /// 同期リクエスト
     SyncRequest { heads: Vec<String> },
     /// 同期レスポンス
     SyncResponse { changes: Vec<u8> },
 }
 
+impl MessageType {
+    /// メッセージを発信したユーザーのIDを返します。
+    fn sender_id(&self) -> Option<&str> {
+        match self {
+            MessageType::NewPost(post) => Some(&post.author_id),
+            MessageType::UpdateProfile(user) => Some(&user.id),
+            MessageType::Follow(edge) | MessageType::Unfollow(edge) => Some(&edge.follower_id),
+            MessageType::FollowRequest(request) => Some(&request.edge.follower_id),
+            MessageType::FollowApproval(approval) => Some(&approval.edge.followee_id),
+            MessageType::SyncRequest { .. } | MessageType::SyncResponse { .. } => None,
+        }
+    }
+}
+
 /// 実際のネットワーク実装
 struct IrohNetwork {
     /// iroh-gossipのエンドポイント
@@ -52,6 +73,10 @@ pub struct NetworkStatus {
 // グローバルなネットワークインスタンス
 static NETWORK: Lazy<Arc<Mutex<Option<IrohNetwork>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
 
+// フォロー関係を購読中のユーザーと、その受信タスク
+static FOLLOW_EDGE_SUBSCRIPTIONS: Lazy<Mutex<HashMap<String, Vec<AbortHandle>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 /// ネットワークの初期化
 ///
 /// アプリケーションの起動時に呼び出され、
@@ -185,7 +210,9 @@ async fn publish_message(topic_name: &str, message: &MessageType) -> Result<(),
 /// メッセージ受信ハンドラーの登録
 ///
 /// 指定されたトピックのメッセージを受信するハンドラーを登録します。
-pub async fn subscribe_to_topic<F>(topic_name: &str, handler: F) -> Result<(), String>
+/// この端末のユーザーがブロックしているユーザーが発信したメッセージは渡しません。
+/// 返すハンドルで受信タスクを中止すると購読も終了します。
+pub async fn subscribe_to_topic<F>(topic_name: &str, handler: F) -> Result<AbortHandle, String>
 where
     F: FnMut(MessageType) -> Result<(), String> + Send + 'static,
 {
@@ -207,7 +234,7 @@ where
     }; // ここでnetwork_guardがドロップされる
 
     // メッセージ受信ハンドラーの登録
-    tokio::spawn(async move {
+    let task = tokio::spawn(async move {
         let mut handler = handler;
         let mut receiver = topic.split().1;
 
@@ -217,6 +244,19 @@ where
             {
                 // メッセージのデシリアライズ
                 if let Ok(message) = serde_json::from_slice::<MessageType>(&msg.content) {
+                    // この端末のユーザーがブロックしているユーザーのメッセージは無視
+                    // （ブロックを確認できない場合も受け入れない）
+                    if let Some(sender_id) = message.sender_id() {
+                        match is_blocked_on_node(sender_id).await {
+                            Ok(false) => {}
+                            Ok(true) => continue,
+                            Err(e) => {
+                                eprintln!("Dropping message from {}: {}", sender_id, e);
+                                continue;
+                            }
+                        }
+                    }
+
                     // ハンドラーの呼び出し
                     if let Err(e) = handler(message) {
                         eprintln!("Error handling message: {}", e);
@@ -226,13 +266,17 @@ where
         }
     });
 
-    Ok(())
+    Ok(task.abort_handle())
 }
 
 /// 投稿の発信
 ///
 /// 新しい投稿をP2Pネットワークに発信します。
+/// フォロワー限定の投稿はフォロワー限定の投稿ドキュメントでだけ共有するため、発信しません。
 pub async fn publish_post(post: &Post) -> Result<(), String> {
+    if post.visibility != Visibility::Public {
+        return Ok(());
+    }
     let message = MessageType::NewPost(post.clone());
 
     // グローバルフィードに発信
@@ -255,28 +299,128 @@ pub async fn publish_profile(user: &User) -> Result<(), String> {
 
 /// フォロー関係の発信
 ///
-/// フォロー関係をP2Pネットワークに発信します。
-pub async fn publish_follow(from_id: &str, to_id: &str) -> Result<(), String> {
-    let message = MessageType::Follow {
-        from_id: from_id.to_string(),
-        to_id: to_id.to_string(),
-    };
-
-    let topic_name = format!("user/{}/following", from_id);
-    publish_message(&topic_name, &message).await
+/// 署名済みのフォロー関係をフォローする側とされる側の両方のトピックに発信します。
+pub async fn publish_follow(edge: &FollowEdge) -> Result<(), String> {
+    publish_follow_edge(MessageType::Follow(edge.clone()), edge).await
 }
 
 /// フォロー解除の発信
 ///
-/// フォロー解除をP2Pネットワークに発信します。
-pub async fn publish_unfollow(from_id: &str, to_id: &str) -> Result<(), String> {
-    let message = MessageType::Unfollow {
-        from_id: from_id.to_string(),
-        to_id: to_id.to_string(),
-    };
+/// 署名済みのフォロー解除をフォローする側とされる側の両方のトピックに発信します。
+pub async fn publish_unfollow(edge: &FollowEdge) -> Result<(), String> {
+    publish_follow_edge(MessageType::Unfollow(edge.clone()), edge).await
+}
 
-    let topic_name = format!("user/{}/following", from_id);
-    publish_message(&topic_name, &message).await
+/// フォローリクエストの発信
+///
+/// 署名済みのフォローリクエストをフォローする側とされる側の両方のトピックに発信します。
+pub async fn publish_follow_request(request: &FollowRequest) -> Result<(), String> {
+    publish_follow_edge(MessageType::FollowRequest(request.clone()), &request.edge).await
+}
+
+/// フォローリクエストの承認の発信
+///
+/// 署名済みの承認をフォローする側とされる側の両方のトピックに発信します。
+pub async fn publish_follow_approval(approval: &FollowApproval) -> Result<(), String> {
+    publish_follow_edge(
+        MessageType::FollowApproval(approval.clone()),
+        &approval.edge,
+    )
+    .await
+}
+
+/// フォロー関係のメッセージを `following` と `followers` のトピックに発信します。
+async fn publish_follow_edge(message: MessageType, edge: &FollowEdge) -> Result<(), String> {
+    let following_topic = format!("user/{}/following", edge.follower_id);
+    publish_message(&following_topic, &message).await?;
+
+    let followers_topic = format!("user/{}/followers", edge.followee_id);
+    publish_message(&followers_topic, &message).await
+}
+
+/// フォロー関係の購読
+///
+/// 指定されたユーザーへのフォロー・フォロー解除・フォローリクエストと、
+/// 指定されたユーザーのフォローリクエストの承認を受信し、署名を検証して
+/// フォロー関係のストアに反映します。承認を受け取ると、そのユーザーの
+/// フォロワー限定の投稿ドキュメントの同期を開始します。
+/// 購読はユーザーごとに1つだけで、既に購読中の場合は何もしません。
+pub async fn subscribe_to_follow_edges(user_id: &str) -> Result<(), String> {
+    {
+        let mut subscriptions = FOLLOW_EDGE_SUBSCRIPTIONS.lock().unwrap();
+        if subscriptions.contains_key(user_id) {
+            return Ok(());
+        }
+        subscriptions.insert(user_id.to_string(), Vec::new());
+    }
+
+    let mut handles = Vec::new();
+    for topic_name in [
+        format!("user/{}/followers", user_id),
+        format!("user/{}/following", user_id),
+    ] {
+        let subscribed = subscribe_to_topic(&topic_name, |message| {
+            tokio::spawn(async move {
+                if let Err(e) = apply_follow_message(message).await {
+                    eprintln!("Ignoring follow message: {}", e);
+                }
+            });
+            Ok(())
+        })
+        .await;
+        match subscribed {
+            Ok(handle) => handles.push(handle),
+            Err(e) => {
+                handles.iter().for_each(AbortHandle::abort);
+                FOLLOW_EDGE_SUBSCRIPTIONS.lock().unwrap().remove(user_id);
+                return Err(e);
+            }
+        }
+    }
+
+    // 購読中に購読が終了された場合は受信タスクを中止
+    match FOLLOW_EDGE_SUBSCRIPTIONS.lock().unwrap().get_mut(user_id) {
+        Some(active) => active.extend(handles),
+        None => handles.iter().for_each(AbortHandle::abort),
+    }
+    Ok(())
+}
+
+/// フォロー関係の購読の終了
+///
+/// `subscribe_to_follow_edges` で開始した受信タスクを中止します。
+pub fn unsubscribe_from_follow_edges(user_id: &str) {
+    let handles = FOLLOW_EDGE_SUBSCRIPTIONS.lock().unwrap().remove(user_id);
+    for handle in handles.into_iter().flatten() {
+        handle.abort();
+    }
+}
+
+/// 受信したフォロー関係のメッセージをストアに反映します。
+///
+/// 承認はこの端末のユーザー宛てのものだけフォロワー限定の投稿ドキュメントの同期を開始します。
+async fn apply_follow_message(message: MessageType) -> Result<(), String> {
+    use crate::storage::post_docs;
+    use crate::storage::repository::follow_repository;
+
+    let result = match message {
+        MessageType::Follow(edge) | MessageType::Unfollow(edge) => {
+            follow_repository::apply_remote_edge(&edge).await
+        }
+        MessageType::FollowRequest(request) => {
+            follow_repository::apply_remote_request(&request).await
+        }
+        MessageType::FollowApproval(approval) => {
+            match follow_repository::apply_remote_approval(&approval).await {
+                Ok(_) => post_docs::follow_follower_doc(&approval)
+                    .await
+                    .map(|_| true),
+                Err(e) => Err(e),
+            }
+        }
+        _ => return Ok(()),
+    };
+    result.map(|_| ()).map_err(|e| e.to_string())
 }
 
 #[cfg(test)]
@@ -291,5 +435,21 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    async fn test_follow_edge_subscription_is_not_duplicated() {
+        initialize_network().await.unwrap();
+        let user_id = "follow-edge-subscription-test";
+
+        subscribe_to_follow_edges(user_id).await.unwrap();
+        subscribe_to_follow_edges(user_id).await.unwrap();
+        assert_eq!(FOLLOW_EDGE_SUBSCRIPTIONS.lock().unwrap()[user_id].len(), 2);
+
+        unsubscribe_from_follow_edges(user_id);
+        assert!(!FOLLOW_EDGE_SUBSCRIPTIONS
+            .lock()
+            .unwrap()
+            .contains_key(user_id));
+    }
+
     // 他のテストケースは実際の実装に合わせて追加
 }
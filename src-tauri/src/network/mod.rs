@@ -1,4 +1,7 @@
 pub mod iroh;
 
 // 必要な関数を再エクスポート
-pub use iroh::{publish_follow, publish_profile, publish_unfollow};
+pub use iroh::{
+    publish_follow, publish_profile, publish_unfollow, subscribe_to_follow_edges,
+    unsubscribe_from_follow_edges,
+};
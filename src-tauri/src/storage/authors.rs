@@ -0,0 +1,62 @@
+//! Per-user iroh-docs authors.
+//!
+//! Each kukuri user writes document entries as their own iroh-docs author instead
+//! of the node's default author. The author is derived from the user's key (see
+//! `identity::keystore::UnlockedKey::author`) and its ID is recorded in the
+//! user's key file, so it can be recreated on any device holding the keystore.
+//! The author is imported into the node when the user signs in.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use iroh_docs::{Author, AuthorId};
+
+use crate::storage::error::{StorageError, StorageResult};
+use crate::storage::get_default_author_with_retry;
+use crate::storage::iroh_node::IrohNode;
+use crate::storage::state::get_iroh_node;
+
+lazy_static::lazy_static! {
+    /// Authors imported for local users during this run, keyed by user ID.
+    static ref USER_AUTHORS: RwLock<HashMap<String, AuthorId>> = RwLock::new(HashMap::new());
+}
+
+/// Imports a user's author into the node and binds it to `user_id`.
+///
+/// Importing an author that already exists is a no-op.
+pub async fn import_user_author(user_id: &str, author: Author) -> StorageResult<AuthorId> {
+    let iroh = get_iroh_node();
+    let author_id = author.id();
+
+    iroh.authors
+        .import(author)
+        .await
+        .map_err(|e| StorageError::Internal(format!("Failed to import author: {}", e)))?;
+
+    USER_AUTHORS
+        .write()
+        .unwrap()
+        .insert(user_id.to_string(), author_id);
+
+    Ok(author_id)
+}
+
+/// Returns the author bound to a local user, if one has been imported.
+pub fn user_author(user_id: &str) -> Option<AuthorId> {
+    USER_AUTHORS.read().unwrap().get(user_id).copied()
+}
+
+/// Resolves the author to write entries for `user_id` with.
+///
+/// Uses the user's own author when it has been imported on this node. Records of
+/// other users (e.g. profiles relayed from the network), global settings and
+/// users that have not signed in fall back to the node's default author.
+pub(crate) async fn author_for_user(
+    node: &IrohNode,
+    user_id: Option<&str>,
+) -> StorageResult<AuthorId> {
+    match user_id.and_then(user_author) {
+        Some(author_id) => Ok(author_id),
+        None => get_default_author_with_retry(node).await,
+    }
+}
@@ -0,0 +1,256 @@
+//! Content-addressed blobs for post attachments and other media.
+//!
+//! Blobs are imported into the node's persistent iroh-blobs store and referenced
+//! from records by their BLAKE3 hash. Blobs referenced this way are not document
+//! entries, so syncing a document does not download them; a blob that is missing
+//! locally is fetched on demand from the peers this node syncs documents with.
+//!
+//! Previews derived from an image blob are linked to the original through a named
+//! tag (`preview:<hash>`) that points at a JSON description of the derived blobs.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_lite::StreamExt;
+use iroh::{NodeAddr, PublicKey};
+use iroh_blobs::rpc::client::blobs::{
+    BlobStatus, DownloadMode, DownloadOptions, DownloadProgressEvent,
+};
+use iroh_blobs::util::SetTagOption;
+use iroh_blobs::{BlobFormat, Hash, HashAndFormat};
+
+use crate::models::post::AttachmentPreview;
+use crate::storage::error::{StorageError, StorageResult};
+use crate::storage::state::{get_iroh_node, get_post_doc, get_user_doc};
+
+/// How long to wait for a blob to be downloaded from peers.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Prefix of the tags linking an original blob to its preview.
+const PREVIEW_TAG_PREFIX: &str = "preview:";
+
+/// Parses a blob hash as stored in records.
+pub fn parse_hash(hash: &str) -> StorageResult<Hash> {
+    Hash::from_str(hash).map_err(|e| StorageError::InvalidKey(format!("Invalid blob hash: {}", e)))
+}
+
+/// Imports bytes into the blob store and returns their hash.
+///
+/// The blob is tagged so it is kept by garbage collection. Importing the same
+/// bytes again is a no-op.
+pub async fn import_blob(data: impl Into<Bytes>) -> StorageResult<Hash> {
+    let outcome = get_iroh_node()
+        .blobs
+        .add_bytes(data)
+        .await
+        .map_err(|e| StorageError::Internal(format!("Failed to import blob: {}", e)))?;
+
+    Ok(outcome.hash)
+}
+
+/// Reads a blob from the local store, returning `None` if it is not available locally.
+pub async fn read_blob(hash: Hash) -> StorageResult<Option<Bytes>> {
+    let iroh = get_iroh_node();
+
+    let available = iroh
+        .blobs
+        .has(hash)
+        .await
+        .map_err(|e| StorageError::Internal(format!("Failed to query blob {}: {}", hash, e)))?;
+    if !available {
+        return Ok(None);
+    }
+
+    let bytes = iroh
+        .blobs
+        .read_to_bytes(hash)
+        .await
+        .map_err(|e| StorageError::Internal(format!("Failed to read blob {}: {}", hash, e)))?;
+
+    Ok(Some(bytes))
+}
+
+/// Returns the size of a blob that is complete in the local store.
+pub async fn local_blob_size(hash: Hash) -> StorageResult<Option<u64>> {
+    let status = get_iroh_node()
+        .blobs
+        .status(hash)
+        .await
+        .map_err(|e| StorageError::Internal(format!("Failed to query blob {}: {}", hash, e)))?;
+
+    match status {
+        BlobStatus::Complete { size } => Ok(Some(size)),
+        _ => Ok(None),
+    }
+}
+
+/// Reads a blob of at most `max_size` bytes, downloading it from document sync
+/// peers if it is missing locally.
+///
+/// The size is checked against the actual bytes: a download is aborted as soon
+/// as the peer announces or sends more than `max_size` bytes, and a larger local
+/// blob is not read. Returns `StorageError::Validation` for blobs that are too
+/// large, `StorageError::NotFound` if no peer is known and `StorageError::Timeout`
+/// if the download does not finish within `FETCH_TIMEOUT`.
+pub async fn fetch_blob(hash: Hash, max_size: u64) -> StorageResult<Bytes> {
+    match local_blob_size(hash).await? {
+        Some(size) => check_blob_size(hash, size, max_size)?,
+        None => download_blob(hash, max_size).await?,
+    }
+
+    read_blob(hash)
+        .await?
+        .ok_or_else(|| StorageError::NotFound(format!("Blob {} missing after download", hash)))
+}
+
+/// Downloads a blob of at most `max_size` bytes from document sync peers.
+///
+/// Whatever was stored of an aborted download is discarded.
+async fn download_blob(hash: Hash, max_size: u64) -> StorageResult<()> {
+    let nodes = sync_peers().await;
+    if nodes.is_empty() {
+        return Err(StorageError::NotFound(format!(
+            "Blob {} is not available locally and no peers are known",
+            hash
+        )));
+    }
+
+    let options = DownloadOptions {
+        format: BlobFormat::Raw,
+        nodes,
+        tag: SetTagOption::Auto,
+        mode: DownloadMode::Queued,
+    };
+    let mut progress = get_iroh_node()
+        .blobs
+        .download_with_opts(hash, options)
+        .await
+        .map_err(|e| StorageError::Internal(format!("Failed to download blob {}: {}", hash, e)))?;
+    let download = async {
+        while let Some(event) = progress.next().await {
+            let event = event.map_err(|e| {
+                StorageError::NotFound(format!("Failed to download blob {}: {}", hash, e))
+            })?;
+            match event {
+                DownloadProgressEvent::Found { size, .. } => check_blob_size(hash, size, max_size)?,
+                DownloadProgressEvent::Progress { offset, .. } => {
+                    check_blob_size(hash, offset, max_size)?
+                }
+                DownloadProgressEvent::AllDone(_) => return Ok(()),
+                DownloadProgressEvent::Abort(e) => {
+                    return Err(StorageError::NotFound(format!(
+                        "Failed to download blob {}: {}",
+                        hash, e
+                    )))
+                }
+                _ => {}
+            }
+        }
+        Err(StorageError::NotFound(format!(
+            "Download of blob {} ended unexpectedly",
+            hash
+        )))
+    };
+    let downloaded = tokio::time::timeout(FETCH_TIMEOUT, download)
+        .await
+        .map_err(|_| StorageError::Timeout)
+        .and_then(|result| result);
+    drop(progress);
+
+    // The announced size is not verified, so check the stored blob as well
+    let checked = match downloaded {
+        Ok(()) => match local_blob_size(hash).await? {
+            Some(size) => check_blob_size(hash, size, max_size),
+            None => Err(StorageError::NotFound(format!(
+                "Blob {} missing after download",
+                hash
+            ))),
+        },
+        Err(e) => Err(e),
+    };
+    if checked.is_err() {
+        if let Err(e) = get_iroh_node().blobs.delete_blob(hash).await {
+            eprintln!("Failed to discard blob {}: {}", hash, e);
+        }
+    }
+    checked
+}
+
+/// Fails when a blob is, or is being downloaded to be, larger than `max_size` bytes.
+fn check_blob_size(hash: Hash, size: u64, max_size: u64) -> StorageResult<()> {
+    if size > max_size {
+        return Err(StorageError::Validation(format!(
+            "Blob {} exceeds maximum size of {} bytes",
+            hash, max_size
+        )));
+    }
+    Ok(())
+}
+
+/// Stores the preview derived from the blob `original`.
+///
+/// The derived blobs must already be imported; this stores the description and
+/// replaces any previous link for the same original.
+pub async fn save_preview(original: Hash, preview: &AttachmentPreview) -> StorageResult<()> {
+    let json = serde_json::to_vec(preview)?;
+    let hash = import_blob(json).await?;
+
+    get_iroh_node()
+        .blobs
+        .tags()
+        .set(preview_tag(original), HashAndFormat::raw(hash))
+        .await
+        .map_err(|e| {
+            StorageError::Internal(format!("Failed to link preview of {}: {}", original, e))
+        })
+}
+
+/// Returns the preview derived from the blob `original`, if one was generated on this node.
+pub async fn get_preview(original: Hash) -> StorageResult<Option<AttachmentPreview>> {
+    let tag = get_iroh_node()
+        .blobs
+        .tags()
+        .get(preview_tag(original))
+        .await
+        .map_err(|e| {
+            StorageError::Internal(format!("Failed to look up preview of {}: {}", original, e))
+        })?;
+    let Some(tag) = tag else {
+        return Ok(None);
+    };
+
+    match read_blob(tag.hash).await? {
+        Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+fn preview_tag(original: Hash) -> String {
+    format!("{}{}", PREVIEW_TAG_PREFIX, original)
+}
+
+/// Collects the peers the user and post documents are synced with.
+async fn sync_peers() -> Vec<NodeAddr> {
+    let mut nodes: Vec<NodeAddr> = Vec::new();
+
+    for doc in [get_post_doc(), get_user_doc()] {
+        let peers = match doc.get_sync_peers().await {
+            Ok(peers) => peers.unwrap_or_default(),
+            Err(e) => {
+                eprintln!("Failed to get sync peers of document {}: {}", doc.id(), e);
+                continue;
+            }
+        };
+        for peer in peers {
+            let Ok(node_id) = PublicKey::from_bytes(&peer) else {
+                continue;
+            };
+            if !nodes.iter().any(|node| node.node_id == node_id) {
+                nodes.push(NodeAddr::from(node_id));
+            }
+        }
+    }
+
+    nodes
+}
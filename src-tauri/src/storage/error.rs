@@ -31,6 +31,12 @@ pub enum StorageError {
     #[error("Data validation failed: {0}")]
     Validation(String),
 
+    #[error("Signature verification failed: {0}")]
+    InvalidSignature(String),
+
+    #[error("Search index operation failed: {0}")]
+    Search(String),
+
     #[error("Operation timed out")]
     Timeout,
 
@@ -38,6 +44,12 @@ pub enum StorageError {
     Internal(String),
 }
 
+impl From<tantivy::TantivyError> for StorageError {
+    fn from(err: tantivy::TantivyError) -> Self {
+        StorageError::Search(err.to_string())
+    }
+}
+
 // Helper type for Tauri command results
 pub type StorageResult<T> = Result<T, StorageError>;
 
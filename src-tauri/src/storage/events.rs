@@ -9,6 +9,7 @@ use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
 use crate::storage::iroh_node::{POST_NAMESPACE_ID, USER_NAMESPACE_ID};
+use crate::storage::repository::{follow_repository, post_repository};
 use crate::storage::state::get_iroh_node;
 
 /// ドキュメント変更監視サービス
@@ -211,6 +212,9 @@ async fn handle_user_document_event(
                 entry.key()
             );
 
+            // フォロー関係の変更をホームタイムラインに反映
+            follow_repository::record_remote_entry(entry.key());
+
             // フロントエンドに通知
             app_handle.emit(
                 "document_event:user_updated",
@@ -309,6 +313,18 @@ async fn handle_post_document_event(
                 entry.key()
             );
 
+            // ホームタイムラインと検索インデックスを更新
+            post_repository::update_home_timelines(entry.key(), entry.content_len());
+            if let Err(e) = post_repository::index_remote_entry(
+                entry.key(),
+                entry.content_hash(),
+                entry.content_len(),
+            )
+            .await
+            {
+                warn!("Failed to index remote post entry: {}", e);
+            }
+
             app_handle.emit(
                 "document_event:post_updated",
                 json!({
@@ -323,6 +339,10 @@ async fn handle_post_document_event(
         LiveEvent::ContentReady { hash } => {
             debug!("Post content ready: {}", hash);
 
+            if let Err(e) = post_repository::index_ready_content(hash).await {
+                warn!("Failed to index post content {}: {}", hash, e);
+            }
+
             app_handle.emit(
                 "post-content-ready",
                 json!({
@@ -0,0 +1,239 @@
+//! Materialized home timelines.
+//!
+//! A user's home timeline holds their own posts and the posts of the users they
+//! follow. Instead of scanning the post document on every request, the positions
+//! of those posts are collected from the post keys once per user and then kept up
+//! to date as posts are saved, deleted or received from peers. Following someone
+//! drops the timeline so it is collected again with the new author's earlier
+//! posts; unfollowing removes their posts in place.
+//!
+//! Only positions are kept, in memory. Posts are read and verified by
+//! `post_repository::list_home_timeline_page` when a page is listed.
+//!
+//! Collecting a timeline reads keys asynchronously, so posts saved or received
+//! meanwhile are recorded in a pending timeline that the collected positions are
+//! merged into. A follow change during the scan discards the pending timeline.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::Mutex;
+
+use crate::models::follow::FollowEdge;
+use crate::models::post::PostCursor;
+
+lazy_static::lazy_static! {
+    /// Materialized home timelines, keyed by the ID of the user they belong to.
+    static ref HOME_TIMELINES: Mutex<HashMap<String, HomeTimeline>> = Mutex::new(HashMap::new());
+
+    /// Home timelines being collected, keyed by the ID of the user they belong to.
+    static ref COLLECTING: Mutex<HashMap<String, HomeTimeline>> = Mutex::new(HashMap::new());
+}
+
+/// Positions of the posts in one user's home timeline.
+#[derive(Debug, Clone, Default)]
+pub struct HomeTimeline {
+    /// The user and the users they follow.
+    authors: HashSet<String>,
+    positions: BTreeSet<PostCursor>,
+}
+
+impl HomeTimeline {
+    /// Creates a timeline of the posts by `authors`. Positions of posts by other
+    /// authors are ignored.
+    pub fn new(
+        authors: impl IntoIterator<Item = String>,
+        positions: impl IntoIterator<Item = PostCursor>,
+    ) -> Self {
+        let mut timeline = Self {
+            authors: authors.into_iter().collect(),
+            positions: BTreeSet::new(),
+        };
+        for position in positions {
+            timeline.insert(&position);
+        }
+        timeline
+    }
+
+    /// Adds a post if its author belongs in the timeline.
+    pub fn insert(&mut self, position: &PostCursor) {
+        if self.authors.contains(&position.author_id) {
+            self.positions.insert(position.clone());
+        }
+    }
+
+    /// Removes a post.
+    pub fn remove(&mut self, position: &PostCursor) {
+        self.positions.remove(position);
+    }
+
+    /// Removes an author and their posts.
+    pub fn remove_author(&mut self, author_id: &str) {
+        self.authors.remove(author_id);
+        self.positions
+            .retain(|position| position.author_id != author_id);
+    }
+
+    /// Returns up to `limit` positions older than `cursor` (or the newest
+    /// positions when `cursor` is `None`), newest first.
+    pub fn page(&self, cursor: Option<&PostCursor>, limit: usize) -> Vec<PostCursor> {
+        let upper = cursor.map_or(Bound::Unbounded, |cursor| Bound::Excluded(cursor.clone()));
+        self.positions
+            .range((Bound::Unbounded, upper))
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Returns one page of a user's home timeline positions, or `None` if the
+/// timeline has not been collected yet.
+pub fn page(user_id: &str, cursor: Option<&PostCursor>, limit: usize) -> Option<Vec<PostCursor>> {
+    HOME_TIMELINES
+        .lock()
+        .unwrap()
+        .get(user_id)
+        .map(|timeline| timeline.page(cursor, limit))
+}
+
+/// Starts collecting a user's home timeline of the posts by `authors`.
+///
+/// Posts recorded until `finish_collect` are kept in a pending timeline.
+pub fn begin_collect(user_id: &str, authors: impl IntoIterator<Item = String>) {
+    let timeline = HomeTimeline::new(authors, std::iter::empty());
+    COLLECTING
+        .lock()
+        .unwrap()
+        .insert(user_id.to_string(), timeline);
+}
+
+/// Merges the collected positions into the pending timeline and stores it,
+/// replacing any earlier one of the user.
+///
+/// Nothing is stored if the follows of the user changed during the scan; the
+/// timeline is then collected again on the next request.
+pub fn finish_collect(user_id: &str, positions: impl IntoIterator<Item = PostCursor>) {
+    let Some(mut timeline) = COLLECTING.lock().unwrap().remove(user_id) else {
+        return;
+    };
+    for position in positions {
+        timeline.insert(&position);
+    }
+    HOME_TIMELINES
+        .lock()
+        .unwrap()
+        .insert(user_id.to_string(), timeline);
+}
+
+/// Drops a user's home timeline so it is collected again on the next request.
+pub fn invalidate(user_id: &str) {
+    HOME_TIMELINES.lock().unwrap().remove(user_id);
+    COLLECTING.lock().unwrap().remove(user_id);
+}
+
+/// Adds a post to every home timeline its author belongs in.
+pub fn record_post(position: &PostCursor) {
+    for timelines in [&*HOME_TIMELINES, &*COLLECTING] {
+        for timeline in timelines.lock().unwrap().values_mut() {
+            timeline.insert(position);
+        }
+    }
+}
+
+/// Removes a post from every home timeline.
+pub fn remove_post(position: &PostCursor) {
+    for timelines in [&*HOME_TIMELINES, &*COLLECTING] {
+        for timeline in timelines.lock().unwrap().values_mut() {
+            timeline.remove(position);
+        }
+    }
+}
+
+/// Applies a follow or unfollow to the follower's home timeline.
+pub fn apply_follow(edge: &FollowEdge) {
+    if edge.active {
+        // The followee's earlier posts have to be collected from the post keys
+        invalidate(&edge.follower_id);
+        return;
+    }
+    for timelines in [&*HOME_TIMELINES, &*COLLECTING] {
+        if let Some(timeline) = timelines.lock().unwrap().get_mut(&edge.follower_id) {
+            timeline.remove_author(&edge.followee_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(created_at: i64, author_id: &str) -> PostCursor {
+        PostCursor {
+            created_at,
+            author_id: author_id.to_string(),
+            id: format!("{}-{}", author_id, created_at),
+        }
+    }
+
+    #[test]
+    fn test_pages_positions_of_authors() {
+        let authors = ["alice".to_string(), "bob".to_string()];
+        let mut timeline = HomeTimeline::new(
+            authors,
+            [
+                position(1, "alice"),
+                position(2, "carol"),
+                position(3, "bob"),
+            ],
+        );
+        timeline.insert(&position(4, "alice"));
+        timeline.insert(&position(5, "carol"));
+
+        let first = timeline.page(None, 2);
+        assert_eq!(first, vec![position(4, "alice"), position(3, "bob")]);
+        let rest = timeline.page(first.last(), 2);
+        assert_eq!(rest, vec![position(1, "alice")]);
+        assert!(timeline.page(rest.last(), 2).is_empty());
+    }
+
+    #[test]
+    fn test_removes_posts_and_authors() {
+        let authors = ["alice".to_string(), "bob".to_string()];
+        let mut timeline = HomeTimeline::new(
+            authors,
+            [position(1, "alice"), position(2, "bob"), position(3, "bob")],
+        );
+
+        timeline.remove(&position(3, "bob"));
+        assert_eq!(
+            timeline.page(None, 10),
+            vec![position(2, "bob"), position(1, "alice")]
+        );
+
+        timeline.remove_author("bob");
+        timeline.insert(&position(4, "bob"));
+        assert_eq!(timeline.page(None, 10), vec![position(1, "alice")]);
+    }
+
+    #[test]
+    fn test_collect_keeps_posts_recorded_during_scan() {
+        let user_id = "collect-user";
+        begin_collect(user_id, [user_id.to_string(), "dave".to_string()]);
+        assert!(page(user_id, None, 10).is_none());
+
+        // Saved while the post keys are being read
+        record_post(&position(3, "dave"));
+        finish_collect(user_id, [position(1, user_id), position(2, "erin")]);
+        assert_eq!(
+            page(user_id, None, 10),
+            Some(vec![position(3, "dave"), position(1, user_id)])
+        );
+
+        // A follow during the scan discards the collected timeline
+        let other_id = "collect-other";
+        begin_collect(other_id, [other_id.to_string()]);
+        invalidate(other_id);
+        finish_collect(other_id, [position(1, other_id)]);
+        assert!(page(other_id, None, 10).is_none());
+    }
+}
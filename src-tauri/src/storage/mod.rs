@@ -1,8 +1,13 @@
 //! Storage layer implementation using iroh.
 
+pub mod authors;
+pub mod blob_store;
 mod error;
 pub mod events;
+pub mod home_timeline;
 pub mod iroh_node; // Make iroh_node public for tests
+pub mod post_docs;
+pub mod search_index;
 pub mod state; // Make state public for initialization in lib.rs
 pub mod traits; // Make traits module public // Add events module for document subscription
 
@@ -0,0 +1,484 @@
+//! Per-author post documents.
+//!
+//! Every user owns a post document: an iroh-docs namespace whose secret is
+//! derived from their key (see `identity::keystore::UnlockedKey::post_namespace`),
+//! so restoring the key restores write access to it. Entries a signed-in user
+//! writes to the local post document are written to their own document as well,
+//! and a read-only ticket for the document of their current key is published in
+//! their signed profile (`User::post_doc_ticket`).
+//!
+//! Every key has its own document, so a linked device writes to the document of
+//! the device key; its ticket is recorded in the device's delegation
+//! (`DeviceDelegation::post_doc_ticket`). When a user rotates their key, the
+//! entries of the previous key's document are copied into the new one, whose
+//! ticket replaces the previous ticket in the profile.
+//!
+//! Following a user imports every document listed in their profile
+//! (`User::post_doc_tickets`) and keeps them syncing. Their entries are copied
+//! into the local post document, which every read goes through (see
+//! `post_repository::import_followed_entry`). Unfollowing leaves the documents,
+//! so the follow graph is the replication graph.
+//!
+//! Users also own a follower document, derived the same way from their key
+//! (`UnlockedKey::follower_namespace`), which holds their followers-only posts
+//! and nothing else. Its ticket is never published: approving a follow request
+//! seals it to the follower's agreement key (`models::follow::FollowApproval`),
+//! and the follower imports the document once they open the approval.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use futures_lite::StreamExt;
+use iroh_docs::rpc::client::docs::{LiveEvent, ShareMode};
+use iroh_docs::rpc::AddrInfoOptions;
+use iroh_docs::store::Query;
+use iroh_docs::{AuthorId, Capability, DocTicket, NamespaceSecret};
+use tokio::task::JoinHandle;
+use x25519_dalek::StaticSecret;
+
+use crate::identity::envelope;
+use crate::models::follow::FollowApproval;
+use crate::models::post::Visibility;
+use crate::models::user::User;
+use crate::storage::authors::author_for_user;
+use crate::storage::error::{StorageError, StorageResult};
+use crate::storage::repository::moderation_repository::is_blocked_on_node;
+use crate::storage::repository::post_repository;
+use crate::storage::state::{get_iroh_node, get_post_doc, DocType};
+
+lazy_static::lazy_static! {
+    /// Post documents of the users signed in during this run, keyed by user ID.
+    static ref OWN_POST_DOCS: RwLock<HashMap<String, DocType>> = RwLock::new(HashMap::new());
+
+    /// Imported post documents of followed users, keyed by the owner's user ID.
+    static ref FOLLOWED_POST_DOCS: Mutex<HashMap<String, Vec<FollowedPostDoc>>> =
+        Mutex::new(HashMap::new());
+
+    /// Follower documents of the users signed in during this run with their
+    /// current key, keyed by user ID, with their read-only tickets.
+    static ref OWN_FOLLOWER_DOCS: RwLock<HashMap<String, (DocType, String)>> =
+        RwLock::new(HashMap::new());
+
+    /// Imported follower documents of users who approved a follow request,
+    /// keyed by the owner's user ID.
+    static ref FOLLOWED_FOLLOWER_DOCS: Mutex<HashMap<String, Vec<FollowedPostDoc>>> =
+        Mutex::new(HashMap::new());
+
+    /// Agreement secrets of the users signed in during this run, keyed by user ID,
+    /// used to open the follower document tickets sealed to them.
+    static ref AGREEMENT_SECRETS: RwLock<HashMap<String, StaticSecret>> =
+        RwLock::new(HashMap::new());
+}
+
+/// A followed user's post document and the task copying its entries.
+struct FollowedPostDoc {
+    doc: DocType,
+    task: JoinHandle<()>,
+}
+
+/// Opens a user's own post document and returns a read-only ticket for it.
+///
+/// The ticket only carries this node's ID, so it stays the same across runs;
+/// peers find the node's addresses through discovery.
+pub async fn open_own_post_doc(user_id: &str, secret: NamespaceSecret) -> StorageResult<String> {
+    let (doc, ticket) = import_own_doc(secret).await?;
+
+    OWN_POST_DOCS
+        .write()
+        .unwrap()
+        .insert(user_id.to_string(), doc);
+
+    Ok(ticket)
+}
+
+/// Returns a read-only ticket for the post document of a key that is not
+/// signed in yet, such as the key of a device waiting to be linked.
+pub async fn post_doc_ticket(secret: NamespaceSecret) -> StorageResult<String> {
+    let (_, ticket) = import_own_doc(secret).await?;
+    Ok(ticket)
+}
+
+/// Imports a document this node can write to and shares a read-only ticket for it.
+async fn import_own_doc(secret: NamespaceSecret) -> StorageResult<(DocType, String)> {
+    let iroh = get_iroh_node();
+
+    let doc = iroh
+        .docs
+        .import_namespace(Capability::Write(secret))
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    let ticket = doc
+        .share(ShareMode::Read, AddrInfoOptions::Id)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?
+        .to_string();
+
+    Ok((doc, ticket))
+}
+
+/// Returns the post document of a user signed in on this node, if it is open.
+pub fn own_post_doc(user_id: &str) -> Option<DocType> {
+    OWN_POST_DOCS.read().unwrap().get(user_id).cloned()
+}
+
+/// Opens a user's own follower document and returns a read-only ticket for it.
+///
+/// Only the document of the user's current key is opened, as that is the one
+/// approved followers are given.
+pub async fn open_own_follower_doc(
+    user_id: &str,
+    secret: NamespaceSecret,
+) -> StorageResult<String> {
+    let (doc, ticket) = import_own_doc(secret).await?;
+
+    OWN_FOLLOWER_DOCS
+        .write()
+        .unwrap()
+        .insert(user_id.to_string(), (doc, ticket.clone()));
+
+    Ok(ticket)
+}
+
+/// Returns the follower document ticket of a user signed in on this node, if
+/// their follower document is open.
+pub fn follower_doc_ticket(user_id: &str) -> Option<String> {
+    OWN_FOLLOWER_DOCS
+        .read()
+        .unwrap()
+        .get(user_id)
+        .map(|(_, ticket)| ticket.clone())
+}
+
+/// Registers the agreement secret of a user signed in on this node.
+pub fn register_agreement_secret(user_id: &str, secret: StaticSecret) {
+    AGREEMENT_SECRETS
+        .write()
+        .unwrap()
+        .insert(user_id.to_string(), secret);
+}
+
+/// Forgets the documents and agreement secret of a user whose session ended.
+///
+/// Their entries are no longer mirrored to their own documents, and follower
+/// document tickets sealed to them can no longer be opened, until they sign in
+/// again.
+pub fn close_session(user_id: &str) {
+    OWN_POST_DOCS.write().unwrap().remove(user_id);
+    OWN_FOLLOWER_DOCS.write().unwrap().remove(user_id);
+    AGREEMENT_SECRETS.write().unwrap().remove(user_id);
+}
+
+/// Returns the follower document of a user signed in on this node with their
+/// current key, if it is open.
+pub fn own_follower_doc(user_id: &str) -> Option<DocType> {
+    OWN_FOLLOWER_DOCS
+        .read()
+        .unwrap()
+        .get(user_id)
+        .map(|(doc, _)| doc.clone())
+}
+
+/// Copies the entries of a user's documents of their previous key into the
+/// documents of the key they are signed in with now.
+///
+/// Only the ticket of the current key's post document is published, so after a
+/// key rotation the entries written under the previous key would otherwise only
+/// reach followers who imported the previous document. Entries are written as
+/// the user's current author. Returns the number of copied entries.
+pub async fn carry_over_own_docs(
+    user_id: &str,
+    previous_post_doc: Option<DocType>,
+    previous_follower_doc: Option<DocType>,
+) -> StorageResult<usize> {
+    let author_id = author_for_user(get_iroh_node(), Some(user_id)).await?;
+    let pairs = [
+        (previous_post_doc, own_post_doc(user_id)),
+        (previous_follower_doc, own_follower_doc(user_id)),
+    ];
+
+    let mut copied = 0;
+    for (from, to) in pairs {
+        let (Some(from), Some(to)) = (from, to) else {
+            continue;
+        };
+        if from.id() == to.id() {
+            continue;
+        }
+
+        let mut stream = from
+            .get_many(Query::single_latest_per_key())
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+        while let Some(entry_result) = stream.next().await {
+            let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+            let key = entry.key().to_vec();
+            if entry.content_len() == 0 {
+                to.set_bytes(author_id, key, Bytes::new())
+                    .await
+                    .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+            } else {
+                to.set_hash(author_id, key, entry.content_hash(), entry.content_len())
+                    .await
+                    .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+            }
+            copied += 1;
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Writes an entry to the post document, and to the own document of `user_id`
+/// matching the visibility when they are signed in on this node.
+///
+/// Followers-only entries are only written to the follower document, so writing
+/// one fails when it is not open. An empty value writes a tombstone.
+pub async fn set_post_entry(
+    user_id: &str,
+    author_id: AuthorId,
+    key: Vec<u8>,
+    value: impl Into<Bytes>,
+    visibility: Visibility,
+) -> StorageResult<()> {
+    let value = value.into();
+
+    let own_doc = match visibility {
+        Visibility::Public => own_post_doc(user_id),
+        Visibility::Followers => {
+            let follower_doc = OWN_FOLLOWER_DOCS
+                .read()
+                .unwrap()
+                .get(user_id)
+                .map(|(doc, _)| doc.clone());
+            let Some(doc) = follower_doc else {
+                return Err(StorageError::Validation(format!(
+                    "Follower document of {} is not open; followers-only posts need the current key",
+                    user_id
+                )));
+            };
+            Some(doc)
+        }
+    };
+
+    get_post_doc()
+        .set_bytes(author_id, key.clone(), value.clone())
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    if let Some(doc) = own_doc {
+        doc.set_bytes(author_id, key, value)
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    }
+
+    Ok(())
+}
+
+/// Imports every post document listed in a followed user's profile and starts
+/// copying their entries into the post document (see `follow_post_doc`).
+///
+/// Every document is tried, and the first error is returned.
+pub async fn follow_post_docs_of(owner: &User) -> StorageResult<()> {
+    let mut result = Ok(());
+    for ticket in owner.post_doc_tickets() {
+        if let Err(e) = follow_post_doc(&owner.id, ticket).await {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+/// Imports a followed user's post document from a ticket in their profile and
+/// starts copying its entries into the post document.
+///
+/// Entries synced during earlier runs are copied first. Following a document
+/// that is already imported does nothing, and the documents of users blocked on
+/// this node are refused.
+async fn follow_post_doc(owner_id: &str, ticket: &str) -> StorageResult<()> {
+    if is_blocked_on_node(owner_id).await? {
+        return Err(StorageError::Validation(format!(
+            "Refusing to sync the post document of blocked user {}",
+            owner_id
+        )));
+    }
+    let ticket = DocTicket::from_str(ticket).map_err(|e| {
+        StorageError::Validation(format!(
+            "Invalid post document ticket of {}: {}",
+            owner_id, e
+        ))
+    })?;
+    sync_followed_doc(&FOLLOWED_POST_DOCS, owner_id, ticket).await
+}
+
+/// Opens the follower document ticket in an approval of a follow request by a
+/// user signed in on this node, imports the document and starts copying its
+/// entries into the post document.
+///
+/// Approvals for users not signed in during this run are skipped.
+pub async fn follow_follower_doc(approval: &FollowApproval) -> StorageResult<()> {
+    let edge = &approval.edge;
+    let Some(secret) = AGREEMENT_SECRETS
+        .read()
+        .unwrap()
+        .get(&edge.follower_id)
+        .cloned()
+    else {
+        return Ok(());
+    };
+    if is_blocked_on_node(&edge.followee_id).await? {
+        return Err(StorageError::Validation(format!(
+            "Refusing to sync the follower document of blocked user {}",
+            edge.followee_id
+        )));
+    }
+
+    let ticket = envelope::open_with(
+        &secret,
+        &approval.ticket,
+        &FollowApproval::ticket_context(edge),
+    )
+    .map_err(|e| {
+        StorageError::Validation(format!(
+            "Cannot open follower document ticket of {}: {}",
+            edge.followee_id, e
+        ))
+    })?;
+    let ticket = std::str::from_utf8(&ticket)
+        .ok()
+        .and_then(|ticket| DocTicket::from_str(ticket).ok())
+        .ok_or_else(|| {
+            StorageError::Validation(format!(
+                "Invalid follower document ticket of {}",
+                edge.followee_id
+            ))
+        })?;
+
+    sync_followed_doc(&FOLLOWED_FOLLOWER_DOCS, &edge.followee_id, ticket).await
+}
+
+/// Imports a followed document and starts copying its entries into the post
+/// document, recording it in `followed_docs`.
+///
+/// Entries synced during earlier runs are copied first. A document that is
+/// already imported for the owner is left as is; an owner can have several
+/// documents, one for each of their keys.
+async fn sync_followed_doc(
+    followed_docs: &Mutex<HashMap<String, Vec<FollowedPostDoc>>>,
+    owner_id: &str,
+    ticket: DocTicket,
+) -> StorageResult<()> {
+    let namespace_id = ticket.capability.id();
+    if followed_docs
+        .lock()
+        .unwrap()
+        .get(owner_id)
+        .is_some_and(|docs| {
+            docs.iter()
+                .any(|followed| followed.doc.id() == namespace_id)
+        })
+    {
+        return Ok(());
+    }
+
+    let iroh = get_iroh_node();
+    let doc = iroh
+        .docs
+        .import(ticket)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    // Subscribe before copying, so no entry falls between the two
+    let mut events = doc
+        .subscribe()
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    copy_entries(owner_id, &doc).await?;
+
+    let owner = owner_id.to_string();
+    let task = tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let result = match event {
+                Ok(LiveEvent::InsertRemote { entry, .. }) => {
+                    post_repository::import_followed_entry(
+                        &owner,
+                        entry.key(),
+                        entry.content_hash(),
+                        entry.content_len(),
+                    )
+                    .await
+                }
+                Ok(LiveEvent::ContentReady { hash }) => {
+                    post_repository::index_ready_content(hash).await
+                }
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    eprintln!("Post document of {} stopped: {}", owner, e);
+                    break;
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("Failed to copy post entry of {}: {}", owner, e);
+            }
+        }
+    });
+
+    let mut followed_docs = followed_docs.lock().unwrap();
+    let docs = followed_docs.entry(owner_id.to_string()).or_default();
+    // Another call may have imported the same document meanwhile
+    if let Some(previous) = docs
+        .iter()
+        .position(|followed| followed.doc.id() == namespace_id)
+    {
+        docs.remove(previous).task.abort();
+    }
+    docs.push(FollowedPostDoc { doc, task });
+
+    Ok(())
+}
+
+/// Stops syncing a followed user's post documents and follower documents.
+///
+/// Entries already copied stay in the post document.
+pub async fn unfollow_post_doc(owner_id: &str) -> StorageResult<()> {
+    for followed_docs in [&*FOLLOWED_POST_DOCS, &*FOLLOWED_FOLLOWER_DOCS] {
+        let Some(docs) = followed_docs.lock().unwrap().remove(owner_id) else {
+            continue;
+        };
+
+        for followed in docs {
+            followed.task.abort();
+            followed
+                .doc
+                .leave()
+                .await
+                .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies every entry of a followed user's post document into the post document.
+async fn copy_entries(owner_id: &str, doc: &DocType) -> StorageResult<()> {
+    let mut stream = doc
+        .get_many(Query::single_latest_per_key())
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    while let Some(entry_result) = stream.next().await {
+        let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+        post_repository::import_followed_entry(
+            owner_id,
+            entry.key(),
+            entry.content_hash(),
+            entry.content_len(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
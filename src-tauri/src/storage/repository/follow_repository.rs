@@ -0,0 +1,611 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use futures_lite::StreamExt;
+use iroh_docs::store::Query;
+use serde::de::DeserializeOwned;
+
+use crate::identity::{KeyChain, Signable};
+use crate::models::follow::{FollowApproval, FollowCounts, FollowEdge, FollowRequest};
+use crate::models::user::User;
+use crate::storage::authors::author_for_user;
+use crate::storage::error::{StorageError, StorageResult};
+use crate::storage::home_timeline;
+use crate::storage::repository::user_repository::{get_key_chain, get_user};
+use crate::storage::state::{get_iroh_node, get_user_doc};
+
+/// Prefix of follow edges indexed by follower, keyed `follow:<follower_id>:<followee_id>`.
+///
+/// Each edge is its own entry in the user document, so following someone never
+/// rewrites a `User` record and concurrent edges from different users never
+/// clobber each other.
+const FOLLOW_KEY_PREFIX: &[u8] = b"follow:";
+
+/// Prefix of follow edges indexed by followee, keyed `follower:<followee_id>:<follower_id>`.
+///
+/// Holds the same signed edge as the `follow:` entry so followers can be listed
+/// without scanning every edge.
+const FOLLOWER_KEY_PREFIX: &[u8] = b"follower:";
+
+/// Prefix of pending follow requests, keyed `follow_request:<followee_id>:<follower_id>`.
+///
+/// A user whose profile requires approval is not followed until they approve
+/// the request; rejecting or approving it writes a tombstone.
+const FOLLOW_REQUEST_KEY_PREFIX: &[u8] = b"follow_request:";
+
+/// Prefix of follow approvals, keyed `follow_approval:<follower_id>:<followee_id>`.
+///
+/// Holds the followee's signed approval of one edge, which carries the ticket of
+/// their follower document sealed to the follower.
+const FOLLOW_APPROVAL_KEY_PREFIX: &[u8] = b"follow_approval:";
+
+/// Constructs a key prefix scoped to one user.
+fn user_prefix(prefix: &[u8], user_id: &str) -> Vec<u8> {
+    [prefix, user_id.as_bytes(), b":"].concat()
+}
+
+/// Constructs the key of an edge in the follower index.
+fn follow_key(follower_id: &str, followee_id: &str) -> Vec<u8> {
+    [
+        user_prefix(FOLLOW_KEY_PREFIX, follower_id),
+        followee_id.as_bytes().to_vec(),
+    ]
+    .concat()
+}
+
+/// Constructs the key of an edge in the followee index.
+fn follower_key(followee_id: &str, follower_id: &str) -> Vec<u8> {
+    [
+        user_prefix(FOLLOWER_KEY_PREFIX, followee_id),
+        follower_id.as_bytes().to_vec(),
+    ]
+    .concat()
+}
+
+/// Constructs the key of a pending follow request.
+fn follow_request_key(followee_id: &str, follower_id: &str) -> Vec<u8> {
+    [
+        user_prefix(FOLLOW_REQUEST_KEY_PREFIX, followee_id),
+        follower_id.as_bytes().to_vec(),
+    ]
+    .concat()
+}
+
+/// Constructs the key of a follow approval.
+fn follow_approval_key(follower_id: &str, followee_id: &str) -> Vec<u8> {
+    [
+        user_prefix(FOLLOW_APPROVAL_KEY_PREFIX, follower_id),
+        followee_id.as_bytes().to_vec(),
+    ]
+    .concat()
+}
+
+/// Saves a follow edge under both the follower and the followee index.
+///
+/// The edge must already be signed with the follower's key. Edges relayed from
+/// other users are written with the node's default author.
+pub async fn save_follow_edge(edge: &FollowEdge) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let author_id = author_for_user(iroh, Some(&edge.follower_id)).await?;
+    let value_bytes = serde_json::to_vec(edge).map_err(StorageError::Serialization)?;
+
+    for key in [
+        follow_key(&edge.follower_id, &edge.followee_id),
+        follower_key(&edge.followee_id, &edge.follower_id),
+    ] {
+        doc.set_bytes(author_id, key, value_bytes.clone())
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    }
+    home_timeline::apply_follow(edge);
+
+    Ok(())
+}
+
+/// Drops the home timeline of the follower of a follow edge entry inserted by a
+/// remote peer, so it is collected again with the changed follows.
+///
+/// The entry is not read or verified; entries other than follow edges are ignored.
+pub fn record_remote_entry(key: &[u8]) {
+    let Some(rest) = key.strip_prefix(FOLLOW_KEY_PREFIX) else {
+        return;
+    };
+    let Some(follower_id) = std::str::from_utf8(rest)
+        .ok()
+        .and_then(|rest| rest.split(':').next())
+    else {
+        return;
+    };
+
+    home_timeline::invalidate(follower_id);
+}
+
+/// Gets the newest verified edge from one user to another, active or not.
+pub async fn get_follow_edge(
+    follower_id: &str,
+    followee_id: &str,
+) -> StorageResult<Option<FollowEdge>> {
+    let query = Query::all().key_exact(follow_key(follower_id, followee_id));
+    let edges = read_edges(query, |edge| {
+        follow_key(&edge.follower_id, &edge.followee_id)
+    })
+    .await?;
+
+    Ok(edges.into_iter().next())
+}
+
+/// Returns whether one user currently follows another.
+pub async fn is_following(follower_id: &str, followee_id: &str) -> StorageResult<bool> {
+    Ok(get_follow_edge(follower_id, followee_id)
+        .await?
+        .is_some_and(|edge| edge.active))
+}
+
+/// Returns whether one user currently follows another with the followee's
+/// approval of the current edge.
+///
+/// Only approved followers may read the followee's followers-only posts.
+pub async fn is_approved_follower(follower_id: &str, followee_id: &str) -> StorageResult<bool> {
+    let Some(edge) = get_follow_edge(follower_id, followee_id).await? else {
+        return Ok(false);
+    };
+    if !edge.active {
+        return Ok(false);
+    }
+
+    Ok(get_follow_approval(follower_id, followee_id)
+        .await?
+        .is_some_and(|approval| approval.edge.created_at == edge.created_at))
+}
+
+/// Stores an edge received from another peer if it verifies and is newer than
+/// the edge already stored.
+///
+/// Returns whether the edge was stored.
+pub async fn apply_remote_edge(edge: &FollowEdge) -> StorageResult<bool> {
+    let chain = get_key_chain(&edge.follower_id).await?;
+    if let Err(e) = chain
+        .as_ref()
+        .ok_or_else(|| format!("unknown user {}", edge.follower_id))
+        .and_then(|chain| chain.verify(edge).map_err(|e| e.to_string()))
+    {
+        return Err(StorageError::InvalidSignature(format!(
+            "Follow edge from {} to {}: {}",
+            edge.follower_id, edge.followee_id, e
+        )));
+    }
+
+    // Following a user who requires approval takes their approval of this very edge
+    if edge.active && requires_approval(&edge.followee_id).await? {
+        let approved = get_follow_approval(&edge.follower_id, &edge.followee_id)
+            .await?
+            .is_some_and(|approval| approval.edge.created_at == edge.created_at);
+        if !approved {
+            eprintln!(
+                "Dropping unapproved follow edge from {} to {}",
+                edge.follower_id, edge.followee_id
+            );
+            return Ok(false);
+        }
+    }
+
+    let current = get_follow_edge(&edge.follower_id, &edge.followee_id).await?;
+    if current.is_some_and(|current| !edge.supersedes(&current)) {
+        return Ok(false);
+    }
+
+    save_follow_edge(edge).await?;
+    Ok(true)
+}
+
+/// Returns whether following a user takes their approval.
+///
+/// Users whose profile is not known here are followed without one.
+pub async fn requires_approval(user_id: &str) -> StorageResult<bool> {
+    Ok(get_user(user_id)
+        .await?
+        .is_some_and(|user| user.approval_required))
+}
+
+/// Saves a pending follow request signed by the follower.
+pub async fn save_follow_request(request: &FollowRequest) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let edge = &request.edge;
+    let author_id = author_for_user(iroh, Some(&edge.follower_id)).await?;
+    let value_bytes = serde_json::to_vec(request).map_err(StorageError::Serialization)?;
+
+    doc.set_bytes(
+        author_id,
+        follow_request_key(&edge.followee_id, &edge.follower_id),
+        value_bytes,
+    )
+    .await
+    .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    Ok(())
+}
+
+/// Removes a pending follow request by writing a tombstone as the followee.
+pub async fn remove_follow_request(followee_id: &str, follower_id: &str) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let author_id = author_for_user(iroh, Some(followee_id)).await?;
+    doc.set_bytes(
+        author_id,
+        follow_request_key(followee_id, follower_id),
+        Bytes::new(),
+    )
+    .await
+    .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    Ok(())
+}
+
+/// Gets the verified pending follow request from one user to another.
+pub async fn get_follow_request(
+    followee_id: &str,
+    follower_id: &str,
+) -> StorageResult<Option<FollowRequest>> {
+    let query =
+        Query::single_latest_per_key().key_exact(follow_request_key(followee_id, follower_id));
+    let requests = read_requests(query).await?;
+    Ok(requests.into_iter().next())
+}
+
+/// Lists the verified pending follow requests to a user, newest first.
+pub async fn list_follow_requests(followee_id: &str) -> StorageResult<Vec<FollowRequest>> {
+    let query = Query::single_latest_per_key()
+        .key_prefix(user_prefix(FOLLOW_REQUEST_KEY_PREFIX, followee_id));
+    let mut requests = read_requests(query).await?;
+    requests.sort_by(|a, b| b.edge.created_at.cmp(&a.edge.created_at));
+    Ok(requests)
+}
+
+/// Stores a follow request received from another peer if it verifies and has
+/// not been answered yet.
+///
+/// A request is answered when a tombstone was written after it was made, or
+/// when its edge, or a newer one, is already stored. Returns whether the
+/// request was stored.
+pub async fn apply_remote_request(request: &FollowRequest) -> StorageResult<bool> {
+    let edge = &request.edge;
+    if let Err(e) = verify_request(request).await {
+        return Err(StorageError::InvalidSignature(format!(
+            "Follow request from {} to {}: {}",
+            edge.follower_id, edge.followee_id, e
+        )));
+    }
+
+    let query = Query::single_latest_per_key()
+        .key_exact(follow_request_key(&edge.followee_id, &edge.follower_id));
+    let stored = read_latest::<FollowRequest>(query).await?;
+    let answered = stored.iter().any(|entry| match &entry.record {
+        Some(current) => current.edge.created_at >= edge.created_at,
+        None => entry.timestamp >= edge.created_at.max(0) as u64 * 1_000_000,
+    });
+    if answered {
+        return Ok(false);
+    }
+    let current = get_follow_edge(&edge.follower_id, &edge.followee_id).await?;
+    if current.is_some_and(|current| !edge.supersedes(&current)) {
+        return Ok(false);
+    }
+
+    save_follow_request(request).await?;
+    Ok(true)
+}
+
+/// Saves a follow approval signed by the followee.
+pub async fn save_follow_approval(approval: &FollowApproval) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let edge = &approval.edge;
+    let author_id = author_for_user(iroh, Some(&edge.followee_id)).await?;
+    let value_bytes = serde_json::to_vec(approval).map_err(StorageError::Serialization)?;
+
+    doc.set_bytes(
+        author_id,
+        follow_approval_key(&edge.follower_id, &edge.followee_id),
+        value_bytes,
+    )
+    .await
+    .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    Ok(())
+}
+
+/// Gets the verified approval of one user following another.
+pub async fn get_follow_approval(
+    follower_id: &str,
+    followee_id: &str,
+) -> StorageResult<Option<FollowApproval>> {
+    let query =
+        Query::single_latest_per_key().key_exact(follow_approval_key(follower_id, followee_id));
+    let approvals = read_approvals(query).await?;
+    Ok(approvals.into_iter().next())
+}
+
+/// Lists the verified approvals a user has received, ordered by followee.
+pub async fn list_follow_approvals(follower_id: &str) -> StorageResult<Vec<FollowApproval>> {
+    let query = Query::single_latest_per_key()
+        .key_prefix(user_prefix(FOLLOW_APPROVAL_KEY_PREFIX, follower_id));
+    read_approvals(query).await
+}
+
+/// Stores an approval received from another peer if it verifies and is newer
+/// than the approval already stored, together with the edge it approves.
+///
+/// Returns whether the approval was stored.
+pub async fn apply_remote_approval(approval: &FollowApproval) -> StorageResult<bool> {
+    let edge = &approval.edge;
+    if let Err(e) = verify_approval(approval).await {
+        return Err(StorageError::InvalidSignature(format!(
+            "Follow approval from {} to {}: {}",
+            edge.followee_id, edge.follower_id, e
+        )));
+    }
+
+    let current = get_follow_approval(&edge.follower_id, &edge.followee_id).await?;
+    if current.is_some_and(|current| current.created_at >= approval.created_at) {
+        return Ok(false);
+    }
+    save_follow_approval(approval).await?;
+
+    let current_edge = get_follow_edge(&edge.follower_id, &edge.followee_id).await?;
+    if current_edge.is_none_or(|current| edge.supersedes(&current)) {
+        save_follow_edge(edge).await?;
+    }
+    Ok(true)
+}
+
+/// Lists the IDs of the users a user follows.
+pub async fn list_following(user_id: &str) -> StorageResult<Vec<String>> {
+    let query = Query::all().key_prefix(user_prefix(FOLLOW_KEY_PREFIX, user_id));
+    let edges = read_edges(query, |edge| {
+        follow_key(&edge.follower_id, &edge.followee_id)
+    })
+    .await?;
+
+    Ok(edges
+        .into_iter()
+        .filter(|edge| edge.active)
+        .map(|edge| edge.followee_id)
+        .collect())
+}
+
+/// Lists the IDs of the users following a user.
+pub async fn list_followers(user_id: &str) -> StorageResult<Vec<String>> {
+    let query = Query::all().key_prefix(user_prefix(FOLLOWER_KEY_PREFIX, user_id));
+    let edges = read_edges(query, |edge| {
+        follower_key(&edge.followee_id, &edge.follower_id)
+    })
+    .await?;
+
+    Ok(edges
+        .into_iter()
+        .filter(|edge| edge.active)
+        .map(|edge| edge.follower_id)
+        .collect())
+}
+
+/// Counts the users a user follows and the users following them.
+pub async fn count_follows(user_id: &str) -> StorageResult<FollowCounts> {
+    Ok(FollowCounts {
+        following: list_following(user_id).await?.len(),
+        followers: list_followers(user_id).await?.len(),
+    })
+}
+
+/// Replaces a profile's `following` and `followers` with the lists derived from
+/// the follow edges.
+pub async fn with_follow_graph(mut user: User) -> StorageResult<User> {
+    user.following = list_following(&user.id).await?;
+    user.followers = list_followers(&user.id).await?;
+    Ok(user)
+}
+
+/// Reads the verified pending requests matching a query.
+///
+/// Requests stored under a key that does not match their content, or whose edge
+/// is no newer than the stored edge (they were approved, or the follower has
+/// unfollowed since), are dropped.
+async fn read_requests(query: impl Into<Query>) -> StorageResult<Vec<FollowRequest>> {
+    let mut requests = Vec::new();
+    for entry in read_latest::<FollowRequest>(query).await? {
+        let Some(request) = entry.record else {
+            continue;
+        };
+        let edge = &request.edge;
+        if entry.key != follow_request_key(&edge.followee_id, &edge.follower_id) {
+            continue;
+        }
+        if let Err(e) = verify_request(&request).await {
+            eprintln!(
+                "Dropping unverified follow request from {} to {}: {}",
+                edge.follower_id, edge.followee_id, e
+            );
+            continue;
+        }
+        let current = get_follow_edge(&edge.follower_id, &edge.followee_id).await?;
+        if current.is_some_and(|current| !edge.supersedes(&current)) {
+            continue;
+        }
+        requests.push(request);
+    }
+    Ok(requests)
+}
+
+/// Reads the verified approvals matching a query.
+async fn read_approvals(query: impl Into<Query>) -> StorageResult<Vec<FollowApproval>> {
+    let mut approvals = Vec::new();
+    for entry in read_latest::<FollowApproval>(query).await? {
+        let Some(approval) = entry.record else {
+            continue;
+        };
+        let edge = &approval.edge;
+        if entry.key != follow_approval_key(&edge.follower_id, &edge.followee_id) {
+            continue;
+        }
+        if let Err(e) = verify_approval(&approval).await {
+            eprintln!(
+                "Dropping unverified follow approval from {} to {}: {}",
+                edge.followee_id, edge.follower_id, e
+            );
+            continue;
+        }
+        approvals.push(approval);
+    }
+    Ok(approvals)
+}
+
+/// Verifies a follow request and the edge it carries, both signed by the follower.
+async fn verify_request(request: &FollowRequest) -> Result<(), String> {
+    if !request.edge.active {
+        return Err("the edge is an unfollow".to_string());
+    }
+    verify_signed(&request.edge.follower_id, &request.edge).await?;
+    verify_signed(&request.edge.follower_id, request).await
+}
+
+/// Verifies an approval signed by the followee and the edge it approves.
+async fn verify_approval(approval: &FollowApproval) -> Result<(), String> {
+    if !approval.edge.active {
+        return Err("the edge is an unfollow".to_string());
+    }
+    verify_signed(&approval.edge.follower_id, &approval.edge).await?;
+    verify_signed(&approval.edge.followee_id, approval).await
+}
+
+/// Verifies a record against the key chain of the user who signed it.
+async fn verify_signed<T: Signable>(user_id: &str, record: &T) -> Result<(), String> {
+    get_key_chain(user_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("unknown user {}", user_id))
+        .and_then(|chain| chain.verify(record).map_err(|e| e.to_string()))
+        .map(|_| ())
+}
+
+/// The latest entry under a key; `record` is `None` for a tombstone.
+struct LatestEntry<T> {
+    key: Vec<u8>,
+    /// Entry timestamp in microseconds
+    timestamp: u64,
+    record: Option<T>,
+}
+
+/// Reads the entries matching a query without verifying them, skipping entries
+/// whose content cannot be read or parsed.
+async fn read_latest<T: DeserializeOwned>(
+    query: impl Into<Query>,
+) -> StorageResult<Vec<LatestEntry<T>>> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let mut stream = doc
+        .get_many(query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    let mut entries = Vec::new();
+    while let Some(entry_result) = stream.next().await {
+        let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+        let record = if entry.content_len() == 0 {
+            None
+        } else {
+            let Ok(content_bytes) = iroh.blobs.read_to_bytes(entry.content_hash()).await else {
+                continue;
+            };
+            let Ok(record) = serde_json::from_slice::<T>(&content_bytes) else {
+                continue;
+            };
+            Some(record)
+        };
+        entries.push(LatestEntry {
+            key: entry.key().to_vec(),
+            timestamp: entry.timestamp(),
+            record,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Reads the verified edges matching a query, keeping the newest edge per key.
+///
+/// Every author's entry is read rather than only the latest one, so an older edge
+/// relayed late by another peer cannot shadow a newer one. `key_of` gives the key
+/// an edge belongs under; entries stored under any other key are dropped.
+/// Edges are returned ordered by key.
+async fn read_edges(
+    query: impl Into<Query>,
+    key_of: impl Fn(&FollowEdge) -> Vec<u8>,
+) -> StorageResult<Vec<FollowEdge>> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let mut chains: HashMap<String, Option<KeyChain>> = HashMap::new();
+    let mut newest: HashMap<Vec<u8>, FollowEdge> = HashMap::new();
+
+    let mut stream = doc
+        .get_many(query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    while let Some(entry_result) = stream.next().await {
+        let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+        if entry.content_len() == 0 {
+            continue;
+        }
+        let Ok(content_bytes) = iroh.blobs.read_to_bytes(entry.content_hash()).await else {
+            continue;
+        };
+        let Ok(edge) = serde_json::from_slice::<FollowEdge>(&content_bytes) else {
+            continue;
+        };
+
+        // The key is not signed, so it must agree with the signed content
+        if entry.key() != key_of(&edge) {
+            eprintln!(
+                "Dropping follow edge stored under mismatched key {:?}",
+                String::from_utf8_lossy(entry.key())
+            );
+            continue;
+        }
+        if newest
+            .get(entry.key())
+            .is_some_and(|current| !edge.supersedes(current))
+        {
+            continue;
+        }
+
+        if !chains.contains_key(&edge.follower_id) {
+            let chain = get_key_chain(&edge.follower_id).await?;
+            chains.insert(edge.follower_id.clone(), chain);
+        }
+        let verified = chains[&edge.follower_id]
+            .as_ref()
+            .is_some_and(|chain| chain.verify(&edge).is_ok());
+        if verified {
+            newest.insert(entry.key().to_vec(), edge);
+        } else {
+            eprintln!(
+                "Dropping unverified follow edge from {} to {}",
+                edge.follower_id, edge.followee_id
+            );
+        }
+    }
+
+    let mut edges: Vec<(Vec<u8>, FollowEdge)> = newest.into_iter().collect();
+    edges.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(edges.into_iter().map(|(_, edge)| edge).collect())
+}
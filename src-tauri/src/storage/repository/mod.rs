@@ -1,5 +1,8 @@
 //! Data repository implementations using iroh-docs.
 
+pub mod follow_repository;
+pub mod moderation_repository;
 pub mod post_repository;
+pub mod reaction_repository;
 pub mod settings_repository;
 pub mod user_repository;
@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use futures_lite::StreamExt;
+use iroh_docs::store::Query;
+use tokio::sync::Mutex;
+
+use crate::models::moderation::{ModerationEntry, ModerationFilter, ModerationKind};
+use crate::storage::authors::author_for_user;
+use crate::storage::error::{StorageError, StorageResult};
+use crate::storage::state::{get_iroh_node, get_settings_doc};
+
+/// Prefix of mute and block entries, keyed `moderation:<user_id>:<kind>:<target_id>`.
+///
+/// The entries live in the settings document, which is never shared, so whom a
+/// user mutes or blocks stays on their own node. Each target is its own entry,
+/// so changing one never rewrites the rest of the list.
+const MODERATION_KEY_PREFIX: &[u8] = b"moderation:";
+
+lazy_static::lazy_static! {
+    /// Users blocked by any user of this node, mapped to the users blocking them.
+    ///
+    /// Loaded from the settings document on first use and kept up to date by
+    /// `save_moderation` and `remove_moderation`, which hold the lock while
+    /// writing so the set never misses a change. Checking incoming gossip
+    /// against it does not read the document.
+    static ref NODE_BLOCKS: Mutex<Option<HashMap<String, HashSet<String>>>> = Mutex::new(None);
+}
+
+/// Constructs the key prefix of a user's entries of one kind.
+fn kind_prefix(user_id: &str, kind: ModerationKind) -> Vec<u8> {
+    let prefix = format!("{}:{}:", user_id, kind);
+    [MODERATION_KEY_PREFIX, prefix.as_bytes()].concat()
+}
+
+/// Constructs the key of a user's entry for one target.
+fn moderation_key(user_id: &str, kind: ModerationKind, target_id: &str) -> Vec<u8> {
+    [kind_prefix(user_id, kind), target_id.as_bytes().to_vec()].concat()
+}
+
+/// Saves a mute or block entry, replacing an earlier entry of the same kind.
+pub async fn save_moderation(entry: &ModerationEntry) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_settings_doc();
+    let mut blocks = NODE_BLOCKS.lock().await;
+
+    let author_id = author_for_user(iroh, Some(&entry.user_id)).await?;
+    let key = moderation_key(&entry.user_id, entry.kind, &entry.target_id);
+    let value_bytes = serde_json::to_vec(entry).map_err(StorageError::Serialization)?;
+
+    doc.set_bytes(author_id, key, value_bytes)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    if let (Some(blocks), ModerationKind::Block) = (blocks.as_mut(), entry.kind) {
+        blocks
+            .entry(entry.target_id.clone())
+            .or_default()
+            .insert(entry.user_id.clone());
+    }
+
+    Ok(())
+}
+
+/// Removes a mute or block entry by writing a tombstone.
+pub async fn remove_moderation(
+    user_id: &str,
+    kind: ModerationKind,
+    target_id: &str,
+) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_settings_doc();
+    let mut blocks = NODE_BLOCKS.lock().await;
+
+    let author_id = author_for_user(iroh, Some(user_id)).await?;
+    let key = moderation_key(user_id, kind, target_id);
+
+    doc.set_bytes(author_id, key, Bytes::new())
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    if let (Some(blocks), ModerationKind::Block) = (blocks.as_mut(), kind) {
+        if let Some(blockers) = blocks.get_mut(target_id) {
+            blockers.remove(user_id);
+            if blockers.is_empty() {
+                blocks.remove(target_id);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the users a user mutes or blocks, newest first.
+pub async fn list_moderation(
+    user_id: &str,
+    kind: ModerationKind,
+) -> StorageResult<Vec<ModerationEntry>> {
+    let mut entries = read_entries(kind_prefix(user_id, kind)).await?;
+    entries.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(entries)
+}
+
+/// Loads the mute and block filter of a viewer. Nothing is hidden without one.
+pub async fn get_filter(viewer: Option<&str>) -> StorageResult<ModerationFilter> {
+    let Some(viewer) = viewer else {
+        return Ok(ModerationFilter::default());
+    };
+
+    let mut entries = list_moderation(viewer, ModerationKind::Mute).await?;
+    entries.extend(list_moderation(viewer, ModerationKind::Block).await?);
+    Ok(ModerationFilter::new(&entries))
+}
+
+/// Returns whether any user of this node blocks a user.
+///
+/// Syncing documents and receiving gossip are shared by every user of the node,
+/// so a block by one of them applies to all. The blocks are read from the
+/// settings document once and answered from memory afterwards.
+pub async fn is_blocked_on_node(user_id: &str) -> StorageResult<bool> {
+    let mut blocks = NODE_BLOCKS.lock().await;
+    if blocks.is_none() {
+        let mut loaded: HashMap<String, HashSet<String>> = HashMap::new();
+        for entry in read_entries(MODERATION_KEY_PREFIX.to_vec()).await? {
+            if entry.kind == ModerationKind::Block {
+                loaded
+                    .entry(entry.target_id)
+                    .or_default()
+                    .insert(entry.user_id);
+            }
+        }
+        *blocks = Some(loaded);
+    }
+
+    Ok(blocks
+        .as_ref()
+        .is_some_and(|blocks| blocks.contains_key(user_id)))
+}
+
+/// Reads the entries under a key prefix, skipping tombstones and entries stored
+/// under a key that does not match their content.
+async fn read_entries(prefix: Vec<u8>) -> StorageResult<Vec<ModerationEntry>> {
+    let iroh = get_iroh_node();
+    let doc = get_settings_doc();
+
+    let query = Query::single_latest_per_key().key_prefix(prefix);
+    let mut stream = doc
+        .get_many(query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    let mut entries = Vec::new();
+    while let Some(entry_result) = stream.next().await {
+        let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+        if entry.content_len() == 0 {
+            continue;
+        }
+
+        let content_bytes = iroh
+            .blobs
+            .read_to_bytes(entry.content_hash())
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+        let moderation: ModerationEntry =
+            serde_json::from_slice(&content_bytes).map_err(StorageError::Serialization)?;
+        if entry.key()
+            == moderation_key(&moderation.user_id, moderation.kind, &moderation.target_id)
+        {
+            entries.push(moderation);
+        }
+    }
+
+    Ok(entries)
+}
@@ -1,47 +1,434 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
 use anyhow::anyhow;
 use bytes::Bytes;
 use futures_lite::StreamExt;
-use iroh_docs::store::Query;
+use iroh_blobs::Hash;
+use iroh_docs::store::{Query, SortDirection};
 
-use crate::models::post::Post;
+use crate::identity::{KeyChain, KeyStatus};
+use crate::models::moderation::ModerationFilter;
+use crate::models::post::{
+    Post, PostCursor, PostPage, Repost, TimelineItem, TimelinePage, Visibility,
+};
+use crate::storage::authors::author_for_user;
+use crate::storage::blob_store;
 use crate::storage::error::{StorageError, StorageResult};
-use crate::storage::state::{get_iroh_node, get_post_doc};
-use crate::storage::get_default_author_with_retry;
+use crate::storage::home_timeline;
+use crate::storage::post_docs::set_post_entry;
+use crate::storage::repository::follow_repository::{is_approved_follower, list_following};
+use crate::storage::repository::moderation_repository::get_filter;
+use crate::storage::repository::reaction_repository::{reactor_of_key, summarize_reactions};
+use crate::storage::repository::user_repository::get_key_chain;
+use crate::storage::search_index::get_search_index;
+use crate::storage::state::{get_iroh_node, get_post_doc, get_settings_doc};
+
+/// Prefix of post entries, keyed `post_ts:<time>:<author_id>:<post_id>`.
+///
+/// Keys sort in timeline order, so key-ordered queries over this prefix walk
+/// the timeline without reading entry content.
+const POST_KEY_PREFIX: &[u8] = b"post_ts:";
+
+/// Prefix of post ID references, keyed `post_id:<post_id>`, whose content is
+/// the key of the post entry.
+const POST_REF_KEY_PREFIX: &[u8] = b"post_id:";
+
+/// Prefix of hashtag index entries, keyed `tag:<tag>:<time>:<author_id>:<post_id>`.
+///
+/// Index entries hold the same content as the post entry (stored once as a
+/// blob), so tag timelines are read like the main timeline.
+const HASHTAG_KEY_PREFIX: &[u8] = b"tag:";
+
+/// Prefix of mention index entries, keyed `mention:<user_id>:<time>:<author_id>:<post_id>`.
+const MENTION_KEY_PREFIX: &[u8] = b"mention:";
+
+/// Prefix of thread index entries, keyed `thread:<root_id>:<time>:<author_id>:<post_id>`,
+/// holding every reply in a thread.
+const THREAD_KEY_PREFIX: &[u8] = b"thread:";
+
+/// Prefix of reply index entries, keyed `reply:<parent_id>:<time>:<author_id>:<post_id>`,
+/// holding the direct replies to a post.
+const REPLY_KEY_PREFIX: &[u8] = b"reply:";
+
+/// Prefix of repost entries, keyed `repost_ts:<time>:<reposter_id>:<repost_id>`.
+const REPOST_KEY_PREFIX: &[u8] = b"repost_ts:";
+
+/// Prefix of the index of reposts of a post, keyed
+/// `reposts_of:<post_id>:<time>:<reposter_id>:<repost_id>`.
+const REPOSTS_OF_KEY_PREFIX: &[u8] = b"reposts_of:";
+
+/// Upper bound on the number of reposters listed for a post in a timeline.
+const MAX_LISTED_REPOSTS: usize = 50;
+
+/// Upper bound on the number of replies read when assembling a thread.
+const MAX_THREAD_REPLIES: usize = 1000;
+
+/// Largest earlier version of a post that is fetched from peers, in bytes.
+const MAX_REVISION_SIZE: u64 = 256 * 1024;
+
+/// Prefix of post entries written before time-ordered keys, keyed `post:<post_id>`.
+const LEGACY_POST_KEY_PREFIX: &[u8] = b"post:";
+
+/// Key of the settings document entry recording that the index entries of
+/// posts saved before the indexes existed were written (see `backfill_post_indexes`).
+const INDEX_BACKFILL_KEY: &[u8] = b"migration:post_indexes:v1";
+
+lazy_static::lazy_static! {
+    /// Keys of remote post entries whose content had not arrived yet when they
+    /// were inserted, keyed by content hash, so they can be indexed for search
+    /// once the content is ready.
+    static ref PENDING_SEARCH_ENTRIES: Mutex<HashMap<Hash, Vec<u8>>> = Mutex::new(HashMap::new());
+}
+
+/// Constructs a time-ordered iroh-docs key for a timeline position under `prefix`.
+///
+/// The timestamp is written as fixed-width hex with the sign bit flipped, so
+/// byte order matches numeric order for negative timestamps as well.
+fn position_key(prefix: &[u8], position: &PostCursor) -> Vec<u8> {
+    let time = (position.created_at as u64) ^ (1 << 63);
+    let key = format!("{:016x}:{}:{}", time, position.author_id, position.id);
+    [prefix, key.as_bytes()].concat()
+}
+
+/// Parses the timeline position from a time-ordered key under `prefix`.
+fn parse_position(key: &[u8], prefix: &[u8]) -> Option<PostCursor> {
+    let key = std::str::from_utf8(key.strip_prefix(prefix)?).ok()?;
+    let mut parts = key.splitn(3, ':');
+    let time = u64::from_str_radix(parts.next()?, 16).ok()?;
+    Some(PostCursor {
+        created_at: (time ^ (1 << 63)) as i64,
+        author_id: parts.next()?.to_string(),
+        id: parts.next()?.to_string(),
+    })
+}
+
+/// Constructs key prefixes that together cover the time-ordered entries under
+/// `prefix` older than `cursor`, in descending key order.
+///
+/// iroh-docs queries only filter keys by prefix, so instead of walking down
+/// from the newest entry, the range below the cursor is split along the hex
+/// digits of its timestamp: first the entries at the cursor's own timestamp,
+/// which still need comparing with the cursor, then, from the last digit to the
+/// first, the prefixes with a smaller digit at that place. Only the prefixes up
+/// to the end of a page are queried.
+fn prefixes_before(prefix: &[u8], cursor: &PostCursor) -> Vec<Vec<u8>> {
+    let key = position_key(prefix, cursor);
+    let time_len = key[prefix.len()..]
+        .iter()
+        .position(|&byte| byte == b':')
+        .unwrap_or(0);
+    let time = &key[prefix.len()..prefix.len() + time_len];
+
+    let mut prefixes = vec![key[..prefix.len() + time_len + 1].to_vec()];
+    for place in (0..time.len()).rev() {
+        let Some(digit) = (time[place] as char).to_digit(16) else {
+            continue;
+        };
+        for smaller in (0..digit).rev() {
+            let smaller = std::char::from_digit(smaller, 16).unwrap() as u8;
+            prefixes.push([prefix, &time[..place], &[smaller]].concat());
+        }
+    }
+    prefixes
+}
+
+/// Constructs the time-ordered iroh-docs key for a post.
+fn post_key(position: &PostCursor) -> Vec<u8> {
+    position_key(POST_KEY_PREFIX, position)
+}
+
+/// Parses the timeline position from a time-ordered post key.
+fn parse_post_key(key: &[u8]) -> Option<PostCursor> {
+    parse_position(key, POST_KEY_PREFIX)
+}
+
+/// Constructs the iroh-docs key for a post ID reference.
+fn post_ref_key(post_id: &str) -> Vec<u8> {
+    [POST_REF_KEY_PREFIX, post_id.as_bytes()].concat()
+}
+
+/// Constructs the key prefix of a hashtag's index entries.
+fn hashtag_prefix(tag: &str) -> Vec<u8> {
+    [HASHTAG_KEY_PREFIX, tag.as_bytes(), b":"].concat()
+}
+
+/// Constructs the key prefix of the index entries of posts mentioning a user.
+fn mention_prefix(user_id: &str) -> Vec<u8> {
+    [MENTION_KEY_PREFIX, user_id.as_bytes(), b":"].concat()
+}
+
+/// Constructs the key prefix of the index entries of the replies in a thread.
+fn thread_prefix(root_id: &str) -> Vec<u8> {
+    [THREAD_KEY_PREFIX, root_id.as_bytes(), b":"].concat()
+}
+
+/// Constructs the key prefix of the index entries of the direct replies to a post.
+fn reply_prefix(parent_id: &str) -> Vec<u8> {
+    [REPLY_KEY_PREFIX, parent_id.as_bytes(), b":"].concat()
+}
+
+/// Constructs the key prefix of the index entries of the reposts of a post.
+fn reposts_of_prefix(post_id: &str) -> Vec<u8> {
+    [REPOSTS_OF_KEY_PREFIX, post_id.as_bytes(), b":"].concat()
+}
+
+/// Constructs the keys of the hashtag, mention, thread and reply index entries of a post.
+fn index_keys(post: &Post) -> Vec<Vec<u8>> {
+    let position = PostCursor::of(post);
+    let tags = post.hashtags.iter().map(|tag| hashtag_prefix(tag));
+    let mentions = post.mentions.iter().map(|user_id| mention_prefix(user_id));
+    let thread = post.root_id.iter().map(|root_id| thread_prefix(root_id));
+    let reply = post.reply_to.iter().map(|id| reply_prefix(id));
+    tags.chain(mentions)
+        .chain(thread)
+        .chain(reply)
+        .map(|prefix| position_key(&prefix, &position))
+        .collect()
+}
+
+/// Verifies a post's signature against the key chain of its author.
+///
+/// Signatures by any key of the chain are accepted; `post.key_revoked` is set
+/// when the signing key has since been revoked.
+fn verify_post(post: &mut Post, chain: Option<&KeyChain>) -> StorageResult<()> {
+    let chain = chain.ok_or_else(|| {
+        StorageError::InvalidSignature(format!(
+            "Author ID {} of post {} is not derived from a public key",
+            post.author_id, post.id
+        ))
+    })?;
+
+    let status = chain
+        .verify(post)
+        .map_err(|e| StorageError::InvalidSignature(format!("Post {}: {}", post.id, e)))?;
+    post.key_revoked = status == KeyStatus::Revoked;
+
+    Ok(())
+}
 
-const POST_KEY_PREFIX: &[u8] = b"post:";
+/// The readers of followers-only posts from one viewer's point of view.
+///
+/// Followers-only posts are kept in the post document shared by every account on
+/// this node, so each read path checks them against the viewer: they are shown to
+/// their author and to users the author approved as followers only. Approvals
+/// are looked up once per author.
+struct Audience<'a> {
+    viewer: Option<&'a str>,
+    approved: HashMap<String, bool>,
+}
+
+impl<'a> Audience<'a> {
+    fn new(viewer: Option<&'a str>) -> Self {
+        Self {
+            viewer,
+            approved: HashMap::new(),
+        }
+    }
+
+    /// Returns whether the viewer may read a post.
+    async fn can_read(&mut self, post: &Post) -> StorageResult<bool> {
+        if post.visibility == Visibility::Public {
+            return Ok(true);
+        }
+        let Some(viewer) = self.viewer else {
+            return Ok(false);
+        };
+        if viewer == post.author_id {
+            return Ok(true);
+        }
 
-/// Constructs the iroh-docs key for a post.
-fn post_key(post_id: &str) -> Vec<u8> {
-    [POST_KEY_PREFIX, post_id.as_bytes()].concat()
+        if let Some(&approved) = self.approved.get(&post.author_id) {
+            return Ok(approved);
+        }
+        let approved = is_approved_follower(viewer, &post.author_id).await?;
+        self.approved.insert(post.author_id.clone(), approved);
+        Ok(approved)
+    }
 }
 
 /// Saves or updates a post in the iroh-docs store.
 ///
-/// This function uses the default author associated with the iroh node.
+/// The post must already be signed with the author's key (see
+/// `identity::sign_record`). It is stored under its time-ordered key together
+/// with a reference from its ID and an index entry for:
+///
+/// - each of its hashtags,
+/// - each user it mentions,
+/// - its thread root, when it is a reply,
+/// - its parent, when it is a reply.
+///
+/// Reposts of the post are indexed by `save_repost`.
+///
+/// The entries are written as the post author's own iroh author when it is
+/// available on this node, and also to the author's own post document when they
+/// are signed in, or their follower document for a followers-only post (see
+/// `storage::post_docs`).
+///
+/// When the post replaces an earlier version (an edit), index entries the
+/// post no longer has are tombstoned.
 pub async fn save_post(post: &Post) -> StorageResult<()> {
     let iroh = get_iroh_node();
-    let doc = get_post_doc();
 
-    let author_id = get_default_author_with_retry(iroh).await?;
+    let author_id = author_for_user(iroh, Some(&post.author_id)).await?;
 
-    let key = post_key(&post.id);
+    let key = post_key(&PostCursor::of(post));
     let value_bytes = serde_json::to_vec(post).map_err(StorageError::Serialization)?;
 
-    // Call set_bytes on the Doc handle
-    doc.set_bytes(author_id, key, value_bytes)
-        .await
-        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    let new_index_keys = index_keys(post);
+    if let Ok(Some(previous)) = read_post_at(&key).await {
+        for stale_key in index_keys(&previous) {
+            if !new_index_keys.contains(&stale_key) {
+                set_post_entry(
+                    &post.author_id,
+                    author_id,
+                    stale_key,
+                    Bytes::new(),
+                    post.visibility,
+                )
+                .await?;
+            }
+        }
+    }
+
+    set_post_entry(
+        &post.author_id,
+        author_id,
+        key.clone(),
+        value_bytes.clone(),
+        post.visibility,
+    )
+    .await?;
+    set_post_entry(
+        &post.author_id,
+        author_id,
+        post_ref_key(&post.id),
+        key,
+        post.visibility,
+    )
+    .await?;
+    for index_key in new_index_keys {
+        set_post_entry(
+            &post.author_id,
+            author_id,
+            index_key,
+            value_bytes.clone(),
+            post.visibility,
+        )
+        .await?;
+    }
+
+    if let Some(index) = get_search_index() {
+        if let Err(e) = index.index_post(post) {
+            eprintln!("Failed to index post {} for search: {}", post.id, e);
+        }
+    }
+    home_timeline::record_post(&PostCursor::of(post));
 
     Ok(())
 }
 
+/// Resolves a post ID to the key of its entry.
+async fn resolve_post_key(post_id: &str) -> StorageResult<Option<Vec<u8>>> {
+    let iroh = get_iroh_node();
+    let doc = get_post_doc();
+
+    let query = Query::single_latest_per_key().key_exact(post_ref_key(post_id));
+    let entry = match doc
+        .get_one(query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?
+    {
+        Some(entry) if entry.content_len() > 0 => entry,
+        _ => return Ok(None),
+    };
+
+    let key = iroh
+        .blobs
+        .read_to_bytes(entry.content_hash())
+        .await
+        .map_err(|_| {
+            StorageError::NotFound(format!(
+                "Content not found for post reference {} (hash: {})",
+                post_id,
+                entry.content_hash()
+            ))
+        })?;
+
+    Ok(Some(key.to_vec()))
+}
+
 /// Retrieves a post from the iroh-docs store by post ID.
+///
+/// Returns `StorageError::InvalidSignature` if the post is not signed by a key
+/// of its author's key chain.
 pub async fn get_post(post_id: &str) -> StorageResult<Option<Post>> {
+    let Some(key) = resolve_post_key(post_id).await? else {
+        return Ok(None);
+    };
+
+    match read_post_at(&key).await? {
+        Some(mut post) => {
+            let chain = get_key_chain(&post.author_id).await?;
+            verify_post(&mut post, chain.as_ref())?;
+
+            Ok(Some(post))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Retrieves a post like `get_post`, returning `None` when `viewer` may not read
+/// it because it is a followers-only post of a user who did not approve them.
+pub async fn get_visible_post(post_id: &str, viewer: Option<&str>) -> StorageResult<Option<Post>> {
+    let Some(post) = get_post(post_id).await? else {
+        return Ok(None);
+    };
+
+    if Audience::new(viewer).can_read(&post).await? {
+        Ok(Some(post))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Retrieves the earlier versions of an edited post, oldest first.
+///
+/// Each version is read from the blob referenced by the post's revision list,
+/// fetching it from peers if needed, and is verified like the post itself.
+/// Returns `None` if the post does not exist or `viewer` may not read it.
+pub async fn get_post_revisions(
+    post_id: &str,
+    viewer: Option<&str>,
+) -> StorageResult<Option<Vec<Post>>> {
+    let Some(post) = get_visible_post(post_id, viewer).await? else {
+        return Ok(None);
+    };
+    let chain = get_key_chain(&post.author_id).await?;
+
+    let mut revisions = Vec::with_capacity(post.revisions.len());
+    for revision in &post.revisions {
+        let hash = blob_store::parse_hash(&revision.hash)?;
+        let bytes = blob_store::fetch_blob(hash, MAX_REVISION_SIZE).await?;
+        let mut version: Post = serde_json::from_slice(&bytes)?;
+        if version.id != post.id || version.author_id != post.author_id {
+            return Err(StorageError::Validation(format!(
+                "Revision {} does not belong to post {}",
+                revision.hash, post.id
+            )));
+        }
+        verify_post(&mut version, chain.as_ref())?;
+        revisions.push(version);
+    }
+
+    Ok(Some(revisions))
+}
+
+/// Reads the post stored under a key without verifying its signature.
+async fn read_post_at(key: &[u8]) -> StorageResult<Option<Post>> {
     let iroh = get_iroh_node();
     let doc = get_post_doc();
 
-    let key = post_key(post_id);
-
     // Query for the latest entry matching the exact key
     let query = Query::single_latest_per_key().key_exact(key);
     // Query on the Doc handle
@@ -63,8 +450,8 @@ pub async fn get_post(post_id: &str) -> StorageResult<Option<Post>> {
                 .await
                 .map_err(|_| {
                     StorageError::NotFound(format!(
-                        "Content not found for post {} (hash: {})",
-                        post_id,
+                        "Content not found for post {:?} (hash: {})",
+                        String::from_utf8_lossy(key),
                         entry.content_hash()
                     ))
                 })?;
@@ -78,38 +465,1046 @@ pub async fn get_post(post_id: &str) -> StorageResult<Option<Post>> {
     }
 }
 
-/// Deletes a post by setting an empty entry (tombstone).
+/// Deletes a post by setting empty entries (tombstones) for the post, its ID
+/// reference and its hashtag and mention index entries.
+///
+/// The tombstones are written as the post author's own iroh author when it is
+/// available on this node, like the entries they replace.
 pub async fn delete_post(post_id: &str) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+
+    let Some(key) = resolve_post_key(post_id).await? else {
+        return Ok(());
+    };
+    let (mut keys, visibility) = match read_post_at(&key).await? {
+        Some(post) => (index_keys(&post), post.visibility),
+        None => (Vec::new(), Visibility::Public),
+    };
+    let position = parse_post_key(&key);
+    if let Some(position) = &position {
+        home_timeline::remove_post(position);
+    }
+    let post_author_id = position
+        .map(|position| position.author_id)
+        .unwrap_or_default();
+    keys.push(key);
+    keys.push(post_ref_key(post_id));
+
+    let author_id = author_for_user(iroh, Some(&post_author_id)).await?;
+
+    // Setting empty bytes acts as a tombstone
+    for key in keys {
+        set_post_entry(&post_author_id, author_id, key, Bytes::new(), visibility).await?;
+    }
+
+    if let Some(index) = get_search_index() {
+        index.remove_post(post_id)?;
+    }
+
+    Ok(())
+}
+
+/// Searches posts in the local full-text index (see `storage::search_index`).
+///
+/// Returns up to `limit` verified posts, best match first. Returns no posts when
+/// the index is not available. Posts by users `viewer` mutes or blocks, and
+/// followers-only posts `viewer` may not read, are left out after the search,
+/// so fewer than `limit` posts may be returned.
+pub async fn search_posts(
+    query: &str,
+    limit: usize,
+    viewer: Option<&str>,
+) -> StorageResult<Vec<Post>> {
+    let Some(index) = get_search_index() else {
+        return Ok(Vec::new());
+    };
+    let filter = get_filter(viewer).await?;
+    let mut audience = Audience::new(viewer);
+
+    let mut posts = Vec::new();
+    for post_id in index.search(query, limit)? {
+        match get_post(&post_id).await {
+            Ok(Some(post)) if filter.hides(&post.author_id) => {}
+            Ok(Some(post)) => {
+                if audience.can_read(&post).await? {
+                    posts.push(post);
+                }
+            }
+            // Stale index entry of a post deleted without a tombstone event
+            Ok(None) => index.remove_post(&post_id)?,
+            Err(e) => eprintln!("Dropping search result {}: {}", post_id, e),
+        }
+    }
+
+    Ok(posts)
+}
+
+/// Indexes all posts for search if the search index is empty.
+///
+/// Returns the number of indexed posts. Used to build the index for posts saved
+/// before it existed, or after its directory was removed.
+pub async fn rebuild_search_index() -> StorageResult<usize> {
+    let Some(index) = get_search_index() else {
+        return Ok(0);
+    };
+    if !index.is_empty() {
+        return Ok(0);
+    }
+
+    // The index is shared by every viewer, so only posts anyone may read are listed
+    let posts = list_posts(None).await?;
+    index.index_posts(&posts)?;
+
+    Ok(posts.len())
+}
+
+/// Updates the search index for a post document entry inserted by a remote peer.
+///
+/// Tombstones remove the post from the index. If the entry's content has not
+/// been downloaded yet, the entry is indexed by `index_ready_content` once it is.
+/// Entries other than posts are ignored.
+pub async fn index_remote_entry(
+    key: &[u8],
+    content_hash: Hash,
+    content_len: u64,
+) -> StorageResult<()> {
+    let Some(index) = get_search_index() else {
+        return Ok(());
+    };
+    let Some(position) = parse_post_key(key) else {
+        return Ok(());
+    };
+
+    if content_len == 0 {
+        return index.remove_post(&position.id);
+    }
+
+    match get_iroh_node().blobs.read_to_bytes(content_hash).await {
+        Ok(bytes) => index_post_content(&position, &bytes).await,
+        Err(_) => {
+            PENDING_SEARCH_ENTRIES
+                .lock()
+                .unwrap()
+                .insert(content_hash, key.to_vec());
+            Ok(())
+        }
+    }
+}
+
+/// Updates the materialized home timelines for a post document entry inserted
+/// by a remote peer.
+///
+/// Only the key is needed, so the entry is added before its content arrives; it
+/// is verified when a timeline page is read. Entries other than posts are ignored.
+pub fn update_home_timelines(key: &[u8], content_len: u64) {
+    let Some(position) = parse_post_key(key) else {
+        return;
+    };
+
+    if content_len == 0 {
+        home_timeline::remove_post(&position);
+    } else {
+        home_timeline::record_post(&position);
+    }
+}
+
+/// Returns the ID of the user who writes a post document entry, read from its key.
+///
+/// Position keys end in `<author_id>:<id>`, and reaction keys name the reactor.
+/// Post ID references and legacy keys have no owner.
+fn entry_owner(key: &[u8]) -> Option<&str> {
+    let positioned = [
+        POST_KEY_PREFIX,
+        HASHTAG_KEY_PREFIX,
+        MENTION_KEY_PREFIX,
+        THREAD_KEY_PREFIX,
+        REPLY_KEY_PREFIX,
+        REPOST_KEY_PREFIX,
+        REPOSTS_OF_KEY_PREFIX,
+    ];
+    if !positioned.iter().any(|prefix| key.starts_with(prefix)) {
+        return reactor_of_key(key);
+    }
+
+    let key = std::str::from_utf8(key).ok()?;
+    let mut parts = key.rsplitn(3, ':');
+    let _id = parts.next()?;
+    let owner = parts.next()?;
+    parts.next()?;
+    Some(owner)
+}
+
+/// Copies an entry of a followed user's post document into the post document.
+///
+/// Only entries the owner writes are copied, so a followed document cannot
+/// speak for anyone else; the content is still verified whenever it is read.
+/// The post ID reference of a post is written here rather than copied. Copied
+/// entries update the home timelines and the search index like entries
+/// inserted by remote peers.
+pub async fn import_followed_entry(
+    owner_id: &str,
+    key: &[u8],
+    content_hash: Hash,
+    content_len: u64,
+) -> StorageResult<()> {
+    if entry_owner(key) != Some(owner_id) {
+        return Ok(());
+    }
+
     let iroh = get_iroh_node();
     let doc = get_post_doc();
+    let author_id = author_for_user(iroh, None).await?;
 
-    let author_id = iroh
-        .authors
-        .default()
+    if content_len == 0 {
+        doc.set_bytes(author_id, key.to_vec(), Bytes::new())
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    } else {
+        doc.set_hash(author_id, key.to_vec(), content_hash, content_len)
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    }
+    if let Some(position) = parse_post_key(key) {
+        let reference = if content_len == 0 {
+            Bytes::new()
+        } else {
+            Bytes::copy_from_slice(key)
+        };
+        doc.set_bytes(author_id, post_ref_key(&position.id), reference)
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    }
+
+    update_home_timelines(key, content_len);
+    index_remote_entry(key, content_hash, content_len).await
+}
+
+/// Indexes a remote post entry waiting for its content, once the content is ready.
+pub async fn index_ready_content(content_hash: Hash) -> StorageResult<()> {
+    let key = PENDING_SEARCH_ENTRIES.lock().unwrap().remove(&content_hash);
+    let Some(position) = key.as_deref().and_then(parse_post_key) else {
+        return Ok(());
+    };
+
+    let bytes = get_iroh_node()
+        .blobs
+        .read_to_bytes(content_hash)
         .await
-        .map_err(|e| StorageError::Internal(format!("Failed to get default author: {}", e)))?;
+        .map_err(|_| {
+            StorageError::NotFound(format!(
+                "Content not found for post (hash: {})",
+                content_hash
+            ))
+        })?;
+    index_post_content(&position, &bytes).await
+}
 
-    let key = post_key(post_id);
+/// Verifies a post stored at `position` and adds it to the search index.
+async fn index_post_content(position: &PostCursor, bytes: &[u8]) -> StorageResult<()> {
+    let Some(index) = get_search_index() else {
+        return Ok(());
+    };
 
-    // Setting empty bytes acts as a tombstone
-    // Call set_bytes on the Doc handle
-    doc.set_bytes(author_id, key, Bytes::new())
+    let mut post: Post = serde_json::from_slice(bytes)?;
+    if &PostCursor::of(&post) != position {
+        return Err(StorageError::Validation(format!(
+            "Post {} is stored under a mismatched key",
+            post.id
+        )));
+    }
+    let chain = get_key_chain(&post.author_id).await?;
+    verify_post(&mut post, chain.as_ref())?;
+
+    index.index_post(&post)
+}
+
+/// Lists all non-deleted posts, leaving out posts by users `viewer` mutes or blocks
+/// and followers-only posts `viewer` may not read.
+/// Posts whose signature does not verify against the author's key chain are logged and dropped;
+/// posts signed by revoked keys are kept and flagged with `key_revoked`.
+/// Note: This iterates through all post keys. For large datasets, consider pagination or indexing.
+pub async fn list_posts(viewer: Option<&str>) -> StorageResult<Vec<Post>> {
+    let filter = get_filter(viewer).await?;
+    let mut audience = Audience::new(viewer);
+    let mut chains: HashMap<String, Option<KeyChain>> = HashMap::new();
+    let mut posts = Vec::new();
+
+    for mut post in list_unverified_posts().await? {
+        if filter.hides(&post.author_id) || !audience.can_read(&post).await? {
+            continue;
+        }
+        if !chains.contains_key(&post.author_id) {
+            let chain = get_key_chain(&post.author_id).await?;
+            chains.insert(post.author_id.clone(), chain);
+        }
+
+        match verify_post(&mut post, chains[&post.author_id].as_ref()) {
+            Ok(()) => posts.push(post),
+            Err(e) => eprintln!("Dropping unverified post: {}", e),
+        }
+    }
+
+    // Sort posts by creation time (descending, newest first)
+    posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    Ok(posts)
+}
+
+/// Lists one page of posts, newest first, optionally restricted to one author,
+/// leaving out posts by users `viewer` mutes or blocks.
+///
+/// Returns up to `limit` verified posts older than `cursor` (or the newest posts
+/// when `cursor` is `None`). Entries are walked in descending key order, and
+/// the position and author are read from the key, so only the blobs of posts
+/// on the page are read. Posts that fail verification are skipped, so a page can
+/// be cut short only at the end of the timeline.
+pub async fn list_posts_page(
+    author_id: Option<&str>,
+    cursor: Option<&PostCursor>,
+    limit: usize,
+    viewer: Option<&str>,
+) -> StorageResult<PostPage> {
+    let filter = get_filter(viewer).await?;
+    list_page(
+        POST_KEY_PREFIX,
+        author_id,
+        cursor,
+        limit,
+        &filter,
+        viewer,
+        |_| true,
+    )
+    .await
+}
+
+/// Lists one page of posts tagged with a normalized hashtag, newest first,
+/// leaving out posts by users `viewer` mutes or blocks.
+///
+/// Paging works like `list_posts_page`.
+pub async fn list_hashtag_page(
+    tag: &str,
+    cursor: Option<&PostCursor>,
+    limit: usize,
+    viewer: Option<&str>,
+) -> StorageResult<PostPage> {
+    let filter = get_filter(viewer).await?;
+    list_page(
+        &hashtag_prefix(tag),
+        None,
+        cursor,
+        limit,
+        &filter,
+        viewer,
+        |post| post.hashtags.iter().any(|t| t == tag),
+    )
+    .await
+}
+
+/// Lists one page of posts mentioning a user, newest first, leaving out posts
+/// by users `viewer` mutes or blocks.
+///
+/// Paging works like `list_posts_page`.
+pub async fn list_mention_page(
+    user_id: &str,
+    cursor: Option<&PostCursor>,
+    limit: usize,
+    viewer: Option<&str>,
+) -> StorageResult<PostPage> {
+    let filter = get_filter(viewer).await?;
+    list_page(
+        &mention_prefix(user_id),
+        None,
+        cursor,
+        limit,
+        &filter,
+        viewer,
+        |post| post.mentions.iter().any(|m| m == user_id),
+    )
+    .await
+}
+
+/// Lists the replies in the thread started by `root_id`, newest first, leaving
+/// out replies by users `viewer` mutes or blocks.
+///
+/// Returns at most `MAX_THREAD_REPLIES` of the newest replies. The root post
+/// itself is not included.
+pub async fn list_thread_replies(root_id: &str, viewer: Option<&str>) -> StorageResult<Vec<Post>> {
+    let filter = get_filter(viewer).await?;
+    let page = list_page(
+        &thread_prefix(root_id),
+        None,
+        None,
+        MAX_THREAD_REPLIES,
+        &filter,
+        viewer,
+        |post| post.root_id.as_deref() == Some(root_id),
+    )
+    .await?;
+    Ok(page.posts)
+}
+
+/// Counts the direct replies to a post.
+///
+/// Only the reply index keys are read, so the count includes replies whose
+/// signatures have not been verified yet.
+pub async fn count_replies(post_id: &str) -> StorageResult<usize> {
+    let doc = get_post_doc();
+
+    let query = Query::single_latest_per_key().key_prefix(reply_prefix(post_id));
+    let mut stream = doc
+        .get_many(query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    let mut count = 0;
+    while let Some(entry_result) = stream.next().await {
+        let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+        if entry.content_len() > 0 {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Lists one page of the time-ordered entries under `prefix`, newest first.
+///
+/// Only the entries older than `cursor` are queried (see `prefixes_before`).
+/// Index entries are not signed, so posts are only returned when `belongs`
+/// confirms that their signed content matches the index. Entries by users
+/// `filter` hides are skipped by the author in their key, before their content
+/// is read, and followers-only posts `viewer` may not read are skipped once
+/// verified.
+async fn list_page(
+    prefix: &[u8],
+    author_id: Option<&str>,
+    cursor: Option<&PostCursor>,
+    limit: usize,
+    filter: &ModerationFilter,
+    viewer: Option<&str>,
+    belongs: impl Fn(&Post) -> bool,
+) -> StorageResult<PostPage> {
+    let iroh = get_iroh_node();
+    let doc = get_post_doc();
+
+    let mut audience = Audience::new(viewer);
+    let mut chains: HashMap<String, Option<KeyChain>> = HashMap::new();
+    let mut posts = Vec::new();
+
+    let ranges = match cursor {
+        Some(cursor) => prefixes_before(prefix, cursor),
+        None => vec![prefix.to_vec()],
+    };
+    for range in ranges {
+        if posts.len() >= limit {
+            break;
+        }
+        let query = Query::single_latest_per_key()
+            .key_prefix(range)
+            .sort_direction(SortDirection::Desc);
+        let mut stream = doc
+            .get_many(query)
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+        while let Some(entry_result) = stream.next().await {
+            if posts.len() >= limit {
+                break;
+            }
+            let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+            // Skip tombstones
+            if entry.content_len() == 0 {
+                continue;
+            }
+            let Some(position) = parse_position(entry.key(), prefix) else {
+                continue;
+            };
+            if cursor.is_some_and(|cursor| &position >= cursor)
+                || author_id.is_some_and(|author_id| position.author_id != author_id)
+                || filter.hides(&position.author_id)
+            {
+                continue;
+            }
+
+            let content_bytes = match iroh.blobs.read_to_bytes(entry.content_hash()).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    eprintln!(
+                        "Failed to read content for post (key: {:?}, hash: {}): {}",
+                        String::from_utf8_lossy(entry.key()),
+                        entry.content_hash(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let mut post: Post = match serde_json::from_slice(&content_bytes) {
+                Ok(post) => post,
+                Err(e) => {
+                    eprintln!(
+                        "Failed to deserialize post content (key: {:?}): {}",
+                        String::from_utf8_lossy(entry.key()),
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            // The key is not signed, so it must agree with the signed content
+            if PostCursor::of(&post) != position || !belongs(&post) {
+                eprintln!(
+                    "Dropping post stored under mismatched key {:?}",
+                    String::from_utf8_lossy(entry.key())
+                );
+                continue;
+            }
+
+            if !chains.contains_key(&post.author_id) {
+                let chain = get_key_chain(&post.author_id).await?;
+                chains.insert(post.author_id.clone(), chain);
+            }
+            if let Err(e) = verify_post(&mut post, chains[&post.author_id].as_ref()) {
+                eprintln!("Dropping unverified post: {}", e);
+                continue;
+            }
+            if audience.can_read(&post).await? {
+                posts.push(post);
+            }
+        }
+    }
+
+    let next_cursor = if posts.len() == limit {
+        posts.last().map(PostCursor::of)
+    } else {
+        None
+    };
+
+    Ok(PostPage { posts, next_cursor })
+}
+
+/// Saves a repost under its time-ordered key and in the index of reposts of the post.
+///
+/// The repost must already be signed with the reposter's key.
+pub async fn save_repost(repost: &Repost) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+
+    let author_id = author_for_user(iroh, Some(&repost.reposter_id)).await?;
+    let position = PostCursor::of_repost(repost);
+    let value_bytes = serde_json::to_vec(repost)?;
+
+    for key in [
+        position_key(REPOST_KEY_PREFIX, &position),
+        position_key(&reposts_of_prefix(&repost.post_id), &position),
+    ] {
+        set_post_entry(
+            &repost.reposter_id,
+            author_id,
+            key,
+            value_bytes.clone(),
+            Visibility::Public,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Deletes a repost by writing tombstones over its entries.
+pub async fn delete_repost(repost: &Repost) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+
+    let author_id = author_for_user(iroh, Some(&repost.reposter_id)).await?;
+    let position = PostCursor::of_repost(repost);
+
+    for key in [
+        position_key(REPOST_KEY_PREFIX, &position),
+        position_key(&reposts_of_prefix(&repost.post_id), &position),
+    ] {
+        set_post_entry(
+            &repost.reposter_id,
+            author_id,
+            key,
+            Bytes::new(),
+            Visibility::Public,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Lists the verified reposts of a post, newest first.
+pub async fn list_reposts_of(post_id: &str, limit: usize) -> StorageResult<Vec<Repost>> {
+    read_reposts(&reposts_of_prefix(post_id), None, limit, |repost| {
+        repost.post_id == post_id
+    })
+    .await
+}
+
+/// Finds a user's repost of a post.
+pub async fn find_repost(reposter_id: &str, post_id: &str) -> StorageResult<Option<Repost>> {
+    let reposts = list_reposts_of(post_id, usize::MAX).await?;
+    Ok(reposts
+        .into_iter()
+        .find(|repost| repost.reposter_id == reposter_id))
+}
+
+/// Lists one page of the timeline of posts and reposts, newest first.
+///
+/// Each post is shown once: a reposted post is placed at its newest repost,
+/// together with the users who reposted it, and is skipped at its own position
+/// and at older reposts. Quoted posts are attached when they are available, and
+/// reaction counts are attached with `viewer`'s own reactions marked. Posts and
+/// reposts by users `viewer` mutes or blocks are left out.
+/// Paging works like `list_posts_page`, with the cursor pointing at the
+/// timeline position of the last item.
+pub async fn list_timeline_page(
+    cursor: Option<&PostCursor>,
+    limit: usize,
+    viewer: Option<&str>,
+) -> StorageResult<TimelinePage> {
+    enum Entry {
+        Post(Post),
+        Repost(Repost),
+    }
+
+    if limit == 0 {
+        return Ok(TimelinePage {
+            items: Vec::new(),
+            next_cursor: None,
+        });
+    }
+
+    let filter = get_filter(viewer).await?;
+    let mut reposts_of: HashMap<String, Vec<Repost>> = HashMap::new();
+    let mut items = Vec::new();
+    let mut cursor = cursor.cloned();
+
+    loop {
+        // Both sources are read up to `limit` past the cursor, so the first
+        // `limit` merged entries are the next entries of the timeline.
+        let posts = list_page(
+            POST_KEY_PREFIX,
+            None,
+            cursor.as_ref(),
+            limit,
+            &filter,
+            viewer,
+            |_| true,
+        )
+        .await?;
+        let reposts = read_reposts(REPOST_KEY_PREFIX, cursor.as_ref(), limit, |_| true).await?;
+
+        let mut entries: Vec<(PostCursor, Entry)> = posts
+            .posts
+            .into_iter()
+            .map(|post| (PostCursor::of(&post), Entry::Post(post)))
+            .chain(
+                reposts
+                    .into_iter()
+                    .map(|repost| (PostCursor::of_repost(&repost), Entry::Repost(repost))),
+            )
+            .collect();
+        entries.sort_by(|a, b| b.0.cmp(&a.0));
+        entries.truncate(limit);
+        let exhausted = entries.len() < limit;
+
+        for (position, entry) in entries {
+            cursor = Some(position.clone());
+
+            let post_id = match &entry {
+                Entry::Post(post) => post.id.clone(),
+                Entry::Repost(repost) => repost.post_id.clone(),
+            };
+            if !reposts_of.contains_key(&post_id) {
+                let mut reposts = list_reposts_of(&post_id, MAX_LISTED_REPOSTS).await?;
+                // A hidden repost neither shows the post nor moves it
+                reposts.retain(|repost| !filter.hides(&repost.reposter_id));
+                reposts_of.insert(post_id.clone(), reposts);
+            }
+            let reposts = &reposts_of[&post_id];
+
+            let (post, reposted_at) = match entry {
+                // Shown at its newest repost instead
+                Entry::Post(_) if !reposts.is_empty() => continue,
+                Entry::Post(post) if filter.hides(&post.author_id) => continue,
+                Entry::Post(post) => (post, None),
+                Entry::Repost(repost) => {
+                    if reposts.first().map(|newest| &newest.id) != Some(&repost.id) {
+                        continue;
+                    }
+                    if filter.hides(&repost.post_author_id) {
+                        continue;
+                    }
+                    match get_visible_post(&repost.post_id, viewer).await {
+                        Ok(Some(post)) if post.author_id == repost.post_author_id => {
+                            (post, Some(repost.created_at))
+                        }
+                        Ok(_) => continue,
+                        Err(e) => {
+                            eprintln!("Dropping repost of unreadable post {}: {}", post_id, e);
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            let mut reposted_by: Vec<String> = Vec::new();
+            if reposted_at.is_some() {
+                for repost in reposts {
+                    if !reposted_by.contains(&repost.reposter_id) {
+                        reposted_by.push(repost.reposter_id.clone());
+                    }
+                }
+            }
+
+            items.push(timeline_item(post, reposted_by, reposted_at, viewer).await?);
+            if items.len() == limit {
+                return Ok(TimelinePage {
+                    items,
+                    next_cursor: Some(position),
+                });
+            }
+        }
+
+        if exhausted {
+            return Ok(TimelinePage {
+                items,
+                next_cursor: None,
+            });
+        }
+    }
+}
+
+/// Lists one page of a user's home timeline, newest first.
+///
+/// The home timeline holds the user's own posts and the posts of the users they
+/// follow. Positions come from the materialized timeline in
+/// `storage::home_timeline`, which is collected from the post keys on first use,
+/// so only the posts on the page are read and verified. Items are built like
+/// those of `list_timeline_page`, without reposts, and paging works the same way.
+/// Posts by followed users the user has since muted or blocked, and
+/// followers-only posts of users who have not approved them, are left out.
+pub async fn list_home_timeline_page(
+    user_id: &str,
+    cursor: Option<&PostCursor>,
+    limit: usize,
+) -> StorageResult<TimelinePage> {
+    let filter = get_filter(Some(user_id)).await?;
+    let mut audience = Audience::new(Some(user_id));
+    let mut chains: HashMap<String, Option<KeyChain>> = HashMap::new();
+    let mut items = Vec::new();
+    let mut cursor = cursor.cloned();
+
+    while limit > 0 {
+        let positions = match home_timeline::page(user_id, cursor.as_ref(), limit) {
+            Some(positions) => positions,
+            None => {
+                collect_home_timeline(user_id).await?;
+                continue;
+            }
+        };
+        let exhausted = positions.len() < limit;
+
+        for position in positions {
+            cursor = Some(position.clone());
+            if filter.hides(&position.author_id) {
+                continue;
+            }
+
+            let mut post = match read_post_at(&post_key(&position)).await {
+                Ok(Some(post)) => post,
+                Ok(None) => {
+                    home_timeline::remove_post(&position);
+                    continue;
+                }
+                // The content may not have arrived from the peer yet
+                Err(e) => {
+                    eprintln!("Skipping unreadable post {}: {}", position.id, e);
+                    continue;
+                }
+            };
+            if PostCursor::of(&post) != position {
+                home_timeline::remove_post(&position);
+                continue;
+            }
+
+            if !chains.contains_key(&post.author_id) {
+                let chain = get_key_chain(&post.author_id).await?;
+                chains.insert(post.author_id.clone(), chain);
+            }
+            if let Err(e) = verify_post(&mut post, chains[&post.author_id].as_ref()) {
+                eprintln!("Dropping unverified post: {}", e);
+                home_timeline::remove_post(&position);
+                continue;
+            }
+            if !audience.can_read(&post).await? {
+                continue;
+            }
+
+            items.push(timeline_item(post, Vec::new(), None, Some(user_id)).await?);
+            if items.len() == limit {
+                return Ok(TimelinePage {
+                    items,
+                    next_cursor: Some(position),
+                });
+            }
+        }
+
+        if exhausted {
+            break;
+        }
+    }
+
+    Ok(TimelinePage {
+        items,
+        next_cursor: None,
+    })
+}
+
+/// Collects a user's home timeline from the post keys and stores it in
+/// `storage::home_timeline`.
+///
+/// Only keys are read: the author of each post is part of its key. Posts saved
+/// or received during the scan are merged in by `home_timeline::finish_collect`.
+async fn collect_home_timeline(user_id: &str) -> StorageResult<()> {
+    let doc = get_post_doc();
+
+    let mut authors = list_following(user_id).await?;
+    authors.push(user_id.to_string());
+    home_timeline::begin_collect(user_id, authors);
+
+    let mut positions = Vec::new();
+    let query = Query::single_latest_per_key().key_prefix(POST_KEY_PREFIX);
+    let mut stream = doc
+        .get_many(query)
         .await
         .map_err(|e| StorageError::Docs(anyhow!(e)))?;
 
+    while let Some(entry_result) = stream.next().await {
+        let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+        // Skip tombstones
+        if entry.content_len() == 0 {
+            continue;
+        }
+        if let Some(position) = parse_post_key(entry.key()) {
+            positions.push(position);
+        }
+    }
+
+    home_timeline::finish_collect(user_id, positions);
     Ok(())
 }
 
-/// Lists all non-deleted posts.
-/// Note: This iterates through all post keys. For large datasets, consider pagination or indexing.
-pub async fn list_posts() -> StorageResult<Vec<Post>> {
+/// Builds a timeline item for a post, attaching the quoted post when it is
+/// available and the reaction counts with `viewer`'s own reactions marked.
+async fn timeline_item(
+    post: Post,
+    reposted_by: Vec<String>,
+    reposted_at: Option<i64>,
+    viewer: Option<&str>,
+) -> StorageResult<TimelineItem> {
+    let quoted = match &post.quote_of {
+        Some(quote_of) => get_visible_post(quote_of, viewer).await.unwrap_or(None),
+        None => None,
+    };
+    let reactions = summarize_reactions(&post.id, viewer).await?;
+
+    Ok(TimelineItem {
+        post,
+        quoted,
+        reposted_by,
+        reposted_at,
+        reactions,
+    })
+}
+
+/// Reads verified reposts from the time-ordered entries under `prefix`, newest first.
+///
+/// Works like `list_page`: only entries older than `cursor` are queried, and
+/// index entries are only trusted when `belongs`
+/// confirms that the signed repost matches the index.
+async fn read_reposts(
+    prefix: &[u8],
+    cursor: Option<&PostCursor>,
+    limit: usize,
+    belongs: impl Fn(&Repost) -> bool,
+) -> StorageResult<Vec<Repost>> {
+    let iroh = get_iroh_node();
+    let doc = get_post_doc();
+
+    let mut chains: HashMap<String, Option<KeyChain>> = HashMap::new();
+    let mut reposts = Vec::new();
+
+    let ranges = match cursor {
+        Some(cursor) => prefixes_before(prefix, cursor),
+        None => vec![prefix.to_vec()],
+    };
+    for range in ranges {
+        if reposts.len() >= limit {
+            break;
+        }
+        let query = Query::single_latest_per_key()
+            .key_prefix(range)
+            .sort_direction(SortDirection::Desc);
+        let mut stream = doc
+            .get_many(query)
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+        while let Some(entry_result) = stream.next().await {
+            if reposts.len() >= limit {
+                break;
+            }
+            let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+            // Skip tombstones
+            if entry.content_len() == 0 {
+                continue;
+            }
+            let Some(position) = parse_position(entry.key(), prefix) else {
+                continue;
+            };
+            if cursor.is_some_and(|cursor| &position >= cursor) {
+                continue;
+            }
+
+            let Ok(content_bytes) = iroh.blobs.read_to_bytes(entry.content_hash()).await else {
+                continue;
+            };
+            let Ok(repost) = serde_json::from_slice::<Repost>(&content_bytes) else {
+                continue;
+            };
+            if PostCursor::of_repost(&repost) != position || !belongs(&repost) {
+                eprintln!(
+                    "Dropping repost stored under mismatched key {:?}",
+                    String::from_utf8_lossy(entry.key())
+                );
+                continue;
+            }
+
+            if !chains.contains_key(&repost.reposter_id) {
+                let chain = get_key_chain(&repost.reposter_id).await?;
+                chains.insert(repost.reposter_id.clone(), chain);
+            }
+            let verified = chains[&repost.reposter_id]
+                .as_ref()
+                .is_some_and(|chain| chain.verify(&repost).is_ok());
+            if verified {
+                reposts.push(repost);
+            } else {
+                eprintln!("Dropping unverified repost {}", repost.id);
+            }
+        }
+    }
+
+    Ok(reposts)
+}
+
+/// Moves posts stored under legacy `post:<post_id>` keys to time-ordered keys.
+///
+/// Each post is re-saved under its new key and the legacy entry is replaced by a
+/// tombstone. Returns the number of migrated posts; running it again once all
+/// posts have moved does nothing.
+pub async fn migrate_post_keys() -> StorageResult<usize> {
+    let iroh = get_iroh_node();
+    let doc = get_post_doc();
+
+    let legacy_posts = read_post_entries(LEGACY_POST_KEY_PREFIX).await?;
+    for (key, post) in &legacy_posts {
+        save_post(post).await?;
+
+        let author_id = author_for_user(iroh, Some(&post.author_id)).await?;
+        doc.set_bytes(author_id, key.clone(), Bytes::new())
+            .await
+            .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    }
+
+    Ok(legacy_posts.len())
+}
+
+/// Writes the missing hashtag, mention, thread and reply index entries of posts
+/// saved before those indexes existed.
+///
+/// Runs once per node: a marker in the settings document, which is never
+/// shared, records that the backfill completed. Returns the number of index
+/// entries written.
+pub async fn backfill_post_indexes() -> StorageResult<usize> {
+    let iroh = get_iroh_node();
+    let settings_doc = get_settings_doc();
+
+    let marker_query = Query::single_latest_per_key().key_exact(INDEX_BACKFILL_KEY);
+    let marker = settings_doc
+        .get_one(marker_query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+    if marker.is_some() {
+        return Ok(0);
+    }
+
+    let written = write_missing_index_entries().await?;
+
+    let author_id = author_for_user(iroh, None).await?;
+    settings_doc
+        .set_bytes(author_id, INDEX_BACKFILL_KEY.to_vec(), b"done".to_vec())
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    Ok(written)
+}
+
+/// Writes the index entries of stored posts that do not exist yet.
+///
+/// The backfill runs at startup, before anyone signs in, so the entries are
+/// only written to the post document. Returns the number of entries written.
+pub(crate) async fn write_missing_index_entries() -> StorageResult<usize> {
+    let iroh = get_iroh_node();
+    let doc = get_post_doc();
+
+    let mut written = 0;
+    for (_, post) in read_post_entries(POST_KEY_PREFIX).await? {
+        let author_id = author_for_user(iroh, Some(&post.author_id)).await?;
+        let value_bytes = serde_json::to_vec(&post).map_err(StorageError::Serialization)?;
+
+        for index_key in index_keys(&post) {
+            let query = Query::single_latest_per_key().key_exact(&index_key);
+            let existing = doc
+                .get_one(query)
+                .await
+                .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+            if existing.is_some() {
+                continue;
+            }
+            doc.set_bytes(author_id, index_key, value_bytes.clone())
+                .await
+                .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+            written += 1;
+        }
+    }
+
+    Ok(written)
+}
+
+/// Lists all non-deleted posts without verifying their signatures.
+///
+/// Only intended for migrating posts written before signing or ID derivation.
+pub(crate) async fn list_unverified_posts() -> StorageResult<Vec<Post>> {
+    let entries = read_post_entries(POST_KEY_PREFIX).await?;
+    Ok(entries.into_iter().map(|(_, post)| post).collect())
+}
+
+/// Reads all non-deleted posts stored under a key prefix, together with their keys.
+///
+/// Entries whose content is missing or malformed are logged and skipped.
+async fn read_post_entries(prefix: &[u8]) -> StorageResult<Vec<(Vec<u8>, Post)>> {
     let iroh = get_iroh_node();
     let doc = get_post_doc();
 
     let mut posts = Vec::new();
 
     // Query for the latest entry for all keys starting with the prefix
-    let query = Query::single_latest_per_key().key_prefix(POST_KEY_PREFIX);
+    let query = Query::single_latest_per_key().key_prefix(prefix);
     // Call get_many on the Doc handle
     let mut stream = doc
         .get_many(query)
@@ -144,7 +1539,7 @@ pub async fn list_posts() -> StorageResult<Vec<Post>> {
         // Proceed only if reading bytes was successful
         if let Ok(content_bytes) = content_bytes_result {
             match serde_json::from_slice::<Post>(&content_bytes) {
-                Ok(post) => posts.push(post),
+                Ok(post) => posts.push((entry.key().to_vec(), post)),
                 Err(e) => {
                     eprintln!(
                         "Failed to deserialize post content (key: {:?}): {}",
@@ -158,20 +1553,62 @@ pub async fn list_posts() -> StorageResult<Vec<Post>> {
         // If content_bytes_result was Err, we've already logged it and just continue the loop
     }
 
-    // Sort posts by creation time (descending, newest first)
-    posts.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
     Ok(posts)
 }
 
-/// Lists all non-deleted posts by a specific author ID.
+/// Lists all non-deleted posts by a specific author ID, leaving them out when
+/// `viewer` mutes or blocks the author.
 /// Note: This currently lists all posts and filters in memory.
 /// A more efficient approach might involve using iroh-docs authors or indexing.
-pub async fn list_user_posts(author_id_filter: &str) -> StorageResult<Vec<Post>> {
-    let all_posts = list_posts().await?;
+pub async fn list_user_posts(
+    author_id_filter: &str,
+    viewer: Option<&str>,
+) -> StorageResult<Vec<Post>> {
+    let all_posts = list_posts(viewer).await?;
     let user_posts = all_posts
         .into_iter()
         .filter(|post| post.author_id == author_id_filter)
         .collect();
     Ok(user_posts)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(created_at: i64, id: &str) -> PostCursor {
+        PostCursor {
+            created_at,
+            author_id: "alice".to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_prefixes_before_cover_older_keys() {
+        let at = cursor(1_700_000_000, "m");
+        let prefixes = prefixes_before(POST_KEY_PREFIX, &at);
+
+        // Prefixes are disjoint and in descending key order
+        for pair in prefixes.windows(2) {
+            assert!(pair[0] > pair[1]);
+            assert!(!pair[0].starts_with(&pair[1]) && !pair[1].starts_with(&pair[0]));
+        }
+
+        let matching = |position: &PostCursor| {
+            let key = post_key(position);
+            prefixes.iter().filter(|p| key.starts_with(p)).count()
+        };
+        for older in [
+            cursor(1_700_000_000, "a"),
+            cursor(1_699_999_999, "z"),
+            cursor(1_000, "z"),
+            cursor(-5, "z"),
+        ] {
+            assert_eq!(matching(&older), 1, "{:?}", older);
+        }
+        for newer in [cursor(1_700_000_001, "a"), cursor(2_000_000_000, "a")] {
+            assert_eq!(matching(&newer), 0, "{:?}", newer);
+        }
+    }
+}
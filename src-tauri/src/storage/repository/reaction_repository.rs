@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use futures_lite::StreamExt;
+use iroh_docs::store::Query;
+
+use crate::identity::KeyChain;
+use crate::models::post::Visibility;
+use crate::models::reaction::{Reaction, ReactionKind, ReactionSummary};
+use crate::storage::authors::author_for_user;
+use crate::storage::error::{StorageError, StorageResult};
+use crate::storage::post_docs::set_post_entry;
+use crate::storage::repository::user_repository::get_key_chain;
+use crate::storage::state::{get_iroh_node, get_post_doc};
+
+/// Prefix of reaction entries, keyed `reaction:<post_id>:<reactor_id>:<kind>`.
+///
+/// Each reaction is its own entry written by the reactor, so reactions from many
+/// peers never conflict with each other or with the post entry.
+const REACTION_KEY_PREFIX: &[u8] = b"reaction:";
+
+/// Constructs the key prefix of the reactions to a post.
+fn reactions_prefix(post_id: &str) -> Vec<u8> {
+    [REACTION_KEY_PREFIX, post_id.as_bytes(), b":"].concat()
+}
+
+/// Constructs the iroh-docs key for a user's reaction of one kind to a post.
+fn reaction_key(post_id: &str, reactor_id: &str, kind: ReactionKind) -> Vec<u8> {
+    let key = format!("{}:{}", reactor_id, kind);
+    [reactions_prefix(post_id), key.into_bytes()].concat()
+}
+
+/// Returns the ID of the reactor a reaction key belongs to.
+pub fn reactor_of_key(key: &[u8]) -> Option<&str> {
+    let rest = std::str::from_utf8(key.strip_prefix(REACTION_KEY_PREFIX)?).ok()?;
+    // Post IDs never contain `:`, but take the reactor from the right to be safe
+    let mut parts = rest.rsplitn(3, ':');
+    let _kind = parts.next()?;
+    let reactor_id = parts.next()?;
+    parts.next()?;
+    Some(reactor_id)
+}
+
+/// Saves a reaction in the post document.
+///
+/// The reaction must already be signed with the reactor's key. Saving the same
+/// kind of reaction again replaces the earlier entry.
+pub async fn save_reaction(reaction: &Reaction) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+
+    let author_id = author_for_user(iroh, Some(&reaction.reactor_id)).await?;
+    let key = reaction_key(&reaction.post_id, &reaction.reactor_id, reaction.kind);
+    let value_bytes = serde_json::to_vec(reaction).map_err(StorageError::Serialization)?;
+
+    set_post_entry(
+        &reaction.reactor_id,
+        author_id,
+        key,
+        value_bytes,
+        Visibility::Public,
+    )
+    .await
+}
+
+/// Removes a user's reaction of one kind to a post by writing a tombstone.
+pub async fn remove_reaction(
+    post_id: &str,
+    reactor_id: &str,
+    kind: ReactionKind,
+) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+
+    let author_id = author_for_user(iroh, Some(reactor_id)).await?;
+    let key = reaction_key(post_id, reactor_id, kind);
+
+    set_post_entry(reactor_id, author_id, key, Bytes::new(), Visibility::Public).await
+}
+
+/// Lists the verified reactions to a post.
+///
+/// Entries whose key does not match their signed content, or whose signature
+/// does not verify against the reactor's key chain, are dropped.
+pub async fn list_reactions(post_id: &str) -> StorageResult<Vec<Reaction>> {
+    let iroh = get_iroh_node();
+    let doc = get_post_doc();
+
+    let mut chains: HashMap<String, Option<KeyChain>> = HashMap::new();
+    let mut reactions = Vec::new();
+
+    let query = Query::single_latest_per_key().key_prefix(reactions_prefix(post_id));
+    let mut stream = doc
+        .get_many(query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    while let Some(entry_result) = stream.next().await {
+        let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+        // Skip tombstones
+        if entry.content_len() == 0 {
+            continue;
+        }
+        let Ok(content_bytes) = iroh.blobs.read_to_bytes(entry.content_hash()).await else {
+            continue;
+        };
+        let Ok(reaction) = serde_json::from_slice::<Reaction>(&content_bytes) else {
+            continue;
+        };
+
+        // The key is not signed, so it must agree with the signed content
+        if entry.key() != reaction_key(post_id, &reaction.reactor_id, reaction.kind) {
+            eprintln!(
+                "Dropping reaction stored under mismatched key {:?}",
+                String::from_utf8_lossy(entry.key())
+            );
+            continue;
+        }
+
+        if !chains.contains_key(&reaction.reactor_id) {
+            let chain = get_key_chain(&reaction.reactor_id).await?;
+            chains.insert(reaction.reactor_id.clone(), chain);
+        }
+        let verified = chains[&reaction.reactor_id]
+            .as_ref()
+            .is_some_and(|chain| chain.verify(&reaction).is_ok());
+        if verified {
+            reactions.push(reaction);
+        } else {
+            eprintln!(
+                "Dropping unverified reaction by {} to post {}",
+                reaction.reactor_id, post_id
+            );
+        }
+    }
+
+    Ok(reactions)
+}
+
+/// Counts the verified reactions to a post by kind.
+///
+/// `viewer` is the user whose own reactions are listed in `mine`.
+pub async fn summarize_reactions(
+    post_id: &str,
+    viewer: Option<&str>,
+) -> StorageResult<ReactionSummary> {
+    let reactions = list_reactions(post_id).await?;
+    Ok(ReactionSummary::tally(&reactions, viewer))
+}
@@ -4,8 +4,8 @@ use iroh_docs::store::Query;
 
 use crate::models::settings::Settings;
 use crate::storage::error::{StorageError, StorageResult};
+use crate::storage::authors::author_for_user;
 use crate::storage::state::{get_iroh_node, get_settings_doc};
-use crate::storage::get_default_author_with_retry;
 
 const SETTINGS_KEY_PREFIX: &[u8] = b"settings:";
 
@@ -20,11 +20,14 @@ fn settings_key(user_id: Option<&str>) -> Vec<u8> {
 }
 
 /// Saves or updates application settings in the iroh-docs store.
+///
+/// Per-user settings are written as the user's own iroh author when it is available
+/// on this node; global settings use the node's default author.
 pub async fn save_settings(settings: &Settings) -> StorageResult<()> {
     let iroh = get_iroh_node();
     let doc = get_settings_doc();
 
-    let author_id = get_default_author_with_retry(iroh).await?;
+    let author_id = author_for_user(iroh, settings.user_id.as_deref()).await?;
 
     let key = settings_key(settings.user_id.as_deref());
     let value_bytes = serde_json::to_vec(settings).map_err(StorageError::Serialization)?;
@@ -82,11 +85,7 @@ pub async fn delete_settings(user_id: Option<&str>) -> StorageResult<()> {
     let iroh = get_iroh_node();
     let doc = get_settings_doc();
 
-    let author_id = iroh
-        .authors
-        .default()
-        .await
-        .map_err(|e| StorageError::Internal(format!("Failed to get default author: {}", e)))?;
+    let author_id = author_for_user(iroh, user_id).await?;
 
     let key = settings_key(user_id);
 
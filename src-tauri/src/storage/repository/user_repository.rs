@@ -1,27 +1,46 @@
 use anyhow::anyhow;
 use bytes::Bytes;
+use futures_lite::StreamExt;
 use iroh_docs::store::Query;
+use serde::de::DeserializeOwned;
 
-use crate::models::user::User;
+use crate::identity::{
+    is_legacy_user_id, public_key_b64_from_user_id, verify_record, KeyChain, KeyStatus,
+};
+use crate::models::user::{DeviceDelegation, KeyRevocation, KeyRotation, User, UserAlias};
+use crate::storage::authors::author_for_user;
 use crate::storage::error::{StorageError, StorageResult};
 use crate::storage::state::{get_iroh_node, get_user_doc};
-use crate::storage::get_default_author_with_retry;
 
 const USER_PROFILE_KEY_PREFIX: &[u8] = b"user_profile:";
+const USER_ALIAS_KEY_PREFIX: &[u8] = b"user_alias:";
+const KEY_ROTATION_KEY_PREFIX: &[u8] = b"key_rotation:";
+const KEY_REVOCATION_KEY_PREFIX: &[u8] = b"key_revocation:";
 
 /// Constructs the iroh-docs key for a user profile.
 fn user_profile_key(user_id: &str) -> Vec<u8> {
     [USER_PROFILE_KEY_PREFIX, user_id.as_bytes()].concat()
 }
 
+/// Constructs the iroh-docs key for a legacy user ID alias.
+fn user_alias_key(legacy_id: &str) -> Vec<u8> {
+    [USER_ALIAS_KEY_PREFIX, legacy_id.as_bytes()].concat()
+}
+
+/// Constructs the iroh-docs key prefix for a user's key rotation or revocation records.
+fn key_record_prefix(prefix: &[u8], user_id: &str) -> Vec<u8> {
+    [prefix, user_id.as_bytes(), b":"].concat()
+}
+
 /// Saves or updates a user profile in the iroh-docs store.
 ///
-/// This function uses the default author associated with the iroh node.
+/// The profile must already be signed with the user's key (see `identity::sign_record`).
+/// The entry is written as the user's own iroh author when it is available on this node.
 pub async fn save_user(user: &User) -> StorageResult<()> {
     let iroh = get_iroh_node();
     let doc = get_user_doc();
 
-    let author_id = get_default_author_with_retry(iroh).await?;
+    let author_id = author_for_user(iroh, Some(&user.id)).await?;
 
     let key = user_profile_key(&user.id);
     let value_bytes = serde_json::to_vec(user).map_err(StorageError::Serialization)?;
@@ -35,7 +54,103 @@ pub async fn save_user(user: &User) -> StorageResult<()> {
 }
 
 /// Retrieves a user profile from the iroh-docs store by user ID.
+///
+/// The user ID must be derived from the profile's `public_key` and the profile
+/// must be signed by that key; forged or unsigned profiles are reported as
+/// `StorageError::InvalidSignature`. Legacy UUID IDs are resolved through their
+/// migration alias when one exists.
 pub async fn get_user(user_id: &str) -> StorageResult<Option<User>> {
+    let resolved_id = if is_legacy_user_id(user_id) {
+        match get_user_alias(user_id).await? {
+            Some(alias) => alias.user_id,
+            None => user_id.to_string(),
+        }
+    } else {
+        user_id.to_string()
+    };
+
+    match get_unverified_user(&resolved_id).await? {
+        Some(user) => {
+            verify_user(&resolved_id, &user).await?;
+            Ok(Some(user))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Checks that a profile belongs to the ID it is stored under.
+///
+/// The profile's `public_key` must be a non-revoked key of the user's key chain,
+/// i.e. the key the ID is derived from or a key it was rotated to. The profile
+/// may be signed by that key or by a device key delegated in its `devices`.
+async fn verify_user(user_id: &str, user: &User) -> StorageResult<()> {
+    if user.id != user_id {
+        return Err(StorageError::InvalidSignature(format!(
+            "Profile stored under {} claims ID {}",
+            user_id, user.id
+        )));
+    }
+
+    // The ID is self-certifying: it must encode the first key of the chain
+    let chain = build_key_chain(user_id, &user.devices)
+        .await?
+        .ok_or_else(|| {
+            StorageError::InvalidSignature(format!(
+                "User ID {} is not derived from a public key",
+                user_id
+            ))
+        })?;
+    match chain.status(&user.public_key) {
+        Some(KeyStatus::Current) | Some(KeyStatus::Rotated) => {}
+        Some(KeyStatus::Revoked) => {
+            return Err(StorageError::InvalidSignature(format!(
+                "Profile {} names a revoked key",
+                user_id
+            )))
+        }
+        Some(KeyStatus::Delegated) | None => {
+            return Err(StorageError::InvalidSignature(format!(
+                "Profile public key is not in the key chain of {}",
+                user_id
+            )))
+        }
+    }
+
+    match chain.verify(user) {
+        Ok(KeyStatus::Revoked) => Err(StorageError::InvalidSignature(format!(
+            "Profile {} is signed with a revoked key",
+            user_id
+        ))),
+        Ok(_) => Ok(()),
+        Err(e) => Err(StorageError::InvalidSignature(format!(
+            "Profile {}: {}",
+            user_id, e
+        ))),
+    }
+}
+
+/// Lists all user profiles known to this node.
+///
+/// Profiles that fail verification (see `get_user`) are logged and skipped.
+pub async fn list_users() -> StorageResult<Vec<User>> {
+    let mut users: Vec<User> = Vec::new();
+    for user in list_records::<User>(USER_PROFILE_KEY_PREFIX.to_vec()).await? {
+        // A profile copied under another key is listed once
+        if users.iter().any(|u| u.id == user.id) {
+            continue;
+        }
+        match verify_user(&user.id, &user).await {
+            Ok(()) => users.push(user),
+            Err(e) => eprintln!("Skipping unverified profile {}: {}", user.id, e),
+        }
+    }
+    Ok(users)
+}
+
+/// Reads a user profile without verifying its ID or signature.
+///
+/// Only intended for migrating legacy profiles that predate signing.
+pub(crate) async fn get_unverified_user(user_id: &str) -> StorageResult<Option<User>> {
     let iroh = get_iroh_node();
     let doc = get_user_doc();
 
@@ -50,6 +165,11 @@ pub async fn get_user(user_id: &str) -> StorageResult<Option<User>> {
 
     match maybe_entry {
         Some(entry) => {
+            // Treat empty entries (tombstones) as deleted
+            if entry.content_len() == 0 {
+                return Ok(None);
+            }
+
             // Entry found, now get the content bytes from the blobs store
             let content_bytes = iroh
                 .blobs
@@ -77,6 +197,231 @@ pub async fn get_user(user_id: &str) -> StorageResult<Option<User>> {
     }
 }
 
+/// Saves an alias from a legacy UUID user ID to its public-key-derived ID.
+///
+/// The alias must be signed with the key of the new user ID.
+pub async fn save_user_alias(alias: &UserAlias) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let author_id = author_for_user(iroh, Some(&alias.user_id)).await?;
+
+    let key = user_alias_key(&alias.legacy_id);
+    let value_bytes = serde_json::to_vec(alias).map_err(StorageError::Serialization)?;
+
+    doc.set_bytes(author_id, key, value_bytes)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    Ok(())
+}
+
+/// Retrieves and verifies the alias for a legacy UUID user ID.
+pub async fn get_user_alias(legacy_id: &str) -> StorageResult<Option<UserAlias>> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let query = Query::single_latest_per_key().key_exact(user_alias_key(legacy_id));
+    let maybe_entry = doc
+        .get_one(query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    let entry = match maybe_entry {
+        Some(entry) if entry.content_len() > 0 => entry,
+        _ => return Ok(None),
+    };
+
+    let content_bytes = iroh
+        .blobs
+        .read_to_bytes(entry.content_hash())
+        .await
+        .map_err(|_| {
+            StorageError::NotFound(format!(
+                "Content not found for user alias {} (hash: {})",
+                legacy_id,
+                entry.content_hash()
+            ))
+        })?;
+
+    let alias: UserAlias =
+        serde_json::from_slice(&content_bytes).map_err(StorageError::Serialization)?;
+
+    let public_key = public_key_b64_from_user_id(&alias.user_id).ok_or_else(|| {
+        StorageError::InvalidSignature(format!(
+            "Alias for {} points to non key-derived ID {}",
+            legacy_id, alias.user_id
+        ))
+    })?;
+    if alias.legacy_id != legacy_id {
+        return Err(StorageError::InvalidSignature(format!(
+            "Alias stored under {} claims legacy ID {}",
+            legacy_id, alias.legacy_id
+        )));
+    }
+    verify_record(&alias, &public_key)
+        .map_err(|e| StorageError::InvalidSignature(format!("Alias {}: {}", legacy_id, e)))?;
+
+    Ok(Some(alias))
+}
+
+/// Saves a key rotation record to the user document.
+///
+/// The record must be signed with its `previous_key`. Records are stored per
+/// sequence number, so each key can only be rotated once.
+pub async fn save_key_rotation(rotation: &KeyRotation) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let author_id = author_for_user(iroh, Some(&rotation.user_id)).await?;
+
+    let key = [
+        key_record_prefix(KEY_ROTATION_KEY_PREFIX, &rotation.user_id),
+        format!("{:010}", rotation.sequence).into_bytes(),
+    ]
+    .concat();
+    let value_bytes = serde_json::to_vec(rotation).map_err(StorageError::Serialization)?;
+
+    doc.set_bytes(author_id, key, value_bytes)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    Ok(())
+}
+
+/// Saves a key revocation record to the user document.
+///
+/// The record must be signed with the revoked key or a newer key of the chain.
+pub async fn save_key_revocation(revocation: &KeyRevocation) -> StorageResult<()> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let author_id = author_for_user(iroh, Some(&revocation.user_id)).await?;
+
+    let key = [
+        key_record_prefix(KEY_REVOCATION_KEY_PREFIX, &revocation.user_id),
+        revocation.revoked_key.as_bytes().to_vec(),
+    ]
+    .concat();
+    let value_bytes = serde_json::to_vec(revocation).map_err(StorageError::Serialization)?;
+
+    doc.set_bytes(author_id, key, value_bytes)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    Ok(())
+}
+
+/// Builds the key chain of a user from the rotation and revocation records in
+/// the user document and the device delegations of the user's profile.
+///
+/// Records that fail verification are ignored by `KeyChain::build`. Returns
+/// `None` for IDs that are not derived from a public key (legacy UUIDs).
+pub async fn get_key_chain(user_id: &str) -> StorageResult<Option<KeyChain>> {
+    if public_key_b64_from_user_id(user_id).is_none() {
+        return Ok(None);
+    }
+
+    // Delegations carry their own signatures, so the profile need not be verified first
+    let delegations = get_unverified_user(user_id)
+        .await?
+        .map(|user| user.devices)
+        .unwrap_or_default();
+
+    build_key_chain(user_id, &delegations).await
+}
+
+/// Finds the user whose key chain rotated to the given public key.
+///
+/// Rotation records are matched on their `new_key` and only accepted when the
+/// key is part of the user's verified key chain. Returns `None` when no user
+/// rotated to the key, e.g. for the key a user ID was derived from.
+pub async fn find_user_by_rotated_key(public_key: &str) -> StorageResult<Option<String>> {
+    let rotations: Vec<KeyRotation> = list_records(KEY_ROTATION_KEY_PREFIX.to_vec()).await?;
+
+    for rotation in rotations {
+        if rotation.new_key != public_key {
+            continue;
+        }
+        if let Some(chain) = get_key_chain(&rotation.user_id).await? {
+            if chain.status(public_key).is_some() {
+                return Ok(Some(rotation.user_id));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Builds the key chain of a user with the given device delegations.
+async fn build_key_chain(
+    user_id: &str,
+    delegations: &[DeviceDelegation],
+) -> StorageResult<Option<KeyChain>> {
+    if public_key_b64_from_user_id(user_id).is_none() {
+        return Ok(None);
+    }
+
+    let rotations: Vec<KeyRotation> =
+        list_records(key_record_prefix(KEY_ROTATION_KEY_PREFIX, user_id)).await?;
+    let revocations: Vec<KeyRevocation> =
+        list_records(key_record_prefix(KEY_REVOCATION_KEY_PREFIX, user_id)).await?;
+
+    Ok(KeyChain::build(
+        user_id,
+        &rotations,
+        &revocations,
+        delegations,
+    ))
+}
+
+/// Reads and deserializes all non-deleted entries under a key prefix of the user document.
+/// Entries whose content is missing or malformed are logged and skipped.
+async fn list_records<T: DeserializeOwned>(prefix: Vec<u8>) -> StorageResult<Vec<T>> {
+    let iroh = get_iroh_node();
+    let doc = get_user_doc();
+
+    let mut records = Vec::new();
+
+    let query = Query::single_latest_per_key().key_prefix(prefix);
+    let mut stream = doc
+        .get_many(query)
+        .await
+        .map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+    while let Some(entry_result) = stream.next().await {
+        let entry = entry_result.map_err(|e| StorageError::Docs(anyhow!(e)))?;
+
+        // Skip tombstones
+        if entry.content_len() == 0 {
+            continue;
+        }
+
+        let content_bytes = match iroh.blobs.read_to_bytes(entry.content_hash()).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!(
+                    "Failed to read content for key {:?}: {}",
+                    String::from_utf8_lossy(entry.key()),
+                    e
+                );
+                continue;
+            }
+        };
+
+        match serde_json::from_slice::<T>(&content_bytes) {
+            Ok(record) => records.push(record),
+            Err(e) => eprintln!(
+                "Failed to deserialize entry (key: {:?}): {}",
+                String::from_utf8_lossy(entry.key()),
+                e
+            ),
+        }
+    }
+
+    Ok(records)
+}
+
 /// Deletes a user profile by setting an empty entry (tombstone).
 /// Note: This performs a soft delete by overwriting with an empty record.
 /// Consider if a hard delete (`docs.del`) is more appropriate depending on requirements.
@@ -84,7 +429,7 @@ pub async fn delete_user(user_id: &str) -> StorageResult<()> {
     let iroh = get_iroh_node();
     let doc = get_user_doc();
 
-    let author_id = get_default_author_with_retry(iroh).await?;
+    let author_id = author_for_user(iroh, Some(user_id)).await?;
 
     let key = user_profile_key(user_id);
 
@@ -95,6 +440,3 @@ pub async fn delete_user(user_id: &str) -> StorageResult<()> {
 
     Ok(())
 }
-
-// TODO: Add functions for listing users, potentially using Query::prefix()
-// pub async fn list_users() -> StorageResult<Vec<User>> { ... }
@@ -0,0 +1,564 @@
+//! Local full-text search index for posts.
+//!
+//! Posts are indexed in an on-disk tantivy index next to the iroh data. Only the
+//! post ID, the content and the creation time are indexed; search results are
+//! resolved back to posts through `post_repository`, which verifies them.
+//! The index is shared by every account on this node, so followers-only posts
+//! are not indexed.
+//!
+//! Content is tokenized by `CjkTokenizer`: runs of letters and digits become
+//! lowercased words, and runs of CJK characters (kana, kanji, hangul) become
+//! overlapping bigrams, since Japanese text has no spaces between words.
+//!
+//! Queries are made of whitespace-separated words, which must all match:
+//! - `"quoted text"` matches the words as a phrase
+//! - a trailing `*` (`rus*`, `"hello wor"*`) matches the last word as a prefix
+//! - a single CJK character matches as a prefix, as it is shorter than a bigram
+//!
+//! Results are ranked by BM25 relevance boosted by recency (see `recency_boost`).
+
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{
+    BooleanQuery, Occur, PhrasePrefixQuery, PhraseQuery, Query, RegexQuery, TermQuery,
+};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, FAST, INDEXED, STORED,
+    STRING,
+};
+use tantivy::tokenizer::{TextAnalyzer, Token, TokenStream, Tokenizer};
+use tantivy::{
+    doc, DocId, Index, IndexReader, IndexWriter, ReloadPolicy, Score, SegmentReader,
+    TantivyDocument, Term,
+};
+use tauri::Manager;
+
+use crate::models::post::{Post, Visibility};
+use crate::storage::error::{StorageError, StorageResult};
+
+/// Name the content tokenizer is registered under.
+const TOKENIZER_NAME: &str = "kukuri_cjk";
+
+/// Memory budget of the index writer.
+const WRITER_MEMORY_BUDGET: usize = 15_000_000;
+
+/// Maximum relative boost given to a post created right now.
+const RECENCY_WEIGHT: f32 = 1.0;
+
+/// Age in days at which the recency boost has dropped to half.
+const RECENCY_SCALE_DAYS: f32 = 7.0;
+
+static SEARCH_INDEX: OnceLock<PostSearchIndex> = OnceLock::new();
+
+/// On-disk full-text index of post contents.
+pub struct PostSearchIndex {
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    id_field: Field,
+    content_field: Field,
+    created_at_field: Field,
+}
+
+impl PostSearchIndex {
+    /// Opens the index stored in `dir`, creating it if it does not exist.
+    pub fn open(dir: &Path) -> StorageResult<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let mut builder = Schema::builder();
+        let id_field = builder.add_text_field("id", STRING | STORED);
+        let content_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(TOKENIZER_NAME)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        let content_field = builder.add_text_field("content", content_options);
+        let created_at_field = builder.add_i64_field("created_at", INDEXED | FAST);
+        let schema = builder.build();
+
+        let directory = MmapDirectory::open(dir)
+            .map_err(|e| StorageError::Search(format!("Failed to open index directory: {}", e)))?;
+        let index = Index::open_or_create(directory, schema)?;
+        index
+            .tokenizers()
+            .register(TOKENIZER_NAME, TextAnalyzer::from(CjkTokenizer));
+
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY_BUDGET)?;
+        let reader: IndexReader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+
+        Ok(Self {
+            reader,
+            writer: Mutex::new(writer),
+            id_field,
+            content_field,
+            created_at_field,
+        })
+    }
+
+    /// Returns the number of indexed posts.
+    pub fn len(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
+    /// Returns `true` if no posts are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds a post to the index, replacing any earlier version of it.
+    pub fn index_post(&self, post: &Post) -> StorageResult<()> {
+        self.index_posts(std::slice::from_ref(post))
+    }
+
+    /// Adds posts to the index in a single commit, replacing earlier versions of them.
+    ///
+    /// Followers-only posts are only removed from the index.
+    pub fn index_posts(&self, posts: &[Post]) -> StorageResult<()> {
+        let mut writer = self.writer.lock().unwrap();
+        for post in posts {
+            writer.delete_term(Term::from_field_text(self.id_field, &post.id));
+            if post.visibility != Visibility::Public {
+                continue;
+            }
+            writer.add_document(doc!(
+                self.id_field => post.id.as_str(),
+                self.content_field => post.content.as_str(),
+                self.created_at_field => post.created_at,
+            ))?;
+        }
+        writer.commit()?;
+        self.reader.reload()?;
+
+        Ok(())
+    }
+
+    /// Removes a post from the index.
+    pub fn remove_post(&self, post_id: &str) -> StorageResult<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.delete_term(Term::from_field_text(self.id_field, post_id));
+        writer.commit()?;
+        self.reader.reload()?;
+
+        Ok(())
+    }
+
+    /// Searches the index and returns the IDs of up to `limit` matching posts,
+    /// best match first.
+    ///
+    /// A query without any searchable words matches nothing.
+    pub fn search(&self, query: &str, limit: usize) -> StorageResult<Vec<String>> {
+        let Some(query) = self.build_query(query)? else {
+            return Ok(Vec::new());
+        };
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let collector =
+            TopDocs::with_limit(limit).tweak_score(move |segment_reader: &SegmentReader| {
+                let created_at = segment_reader.fast_fields().i64("created_at").ok();
+                move |doc: DocId, score: Score| {
+                    let created_at = created_at
+                        .as_ref()
+                        .and_then(|column| column.first(doc))
+                        .unwrap_or(0);
+                    score * recency_boost(now - created_at)
+                }
+            });
+
+        let searcher = self.reader.searcher();
+        let mut ids = Vec::new();
+        for (_score, address) in searcher.search(&query, &collector)? {
+            let document: TantivyDocument = searcher.doc(address)?;
+            if let Some(id) = document.get_first(self.id_field).and_then(|v| v.as_str()) {
+                ids.push(id.to_string());
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// Builds a tantivy query requiring every term of the user query.
+    fn build_query(&self, query: &str) -> StorageResult<Option<Box<dyn Query>>> {
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+
+        for term in parse_query(query) {
+            let mut terms: Vec<Term> = term
+                .tokens
+                .iter()
+                .map(|token| Term::from_field_text(self.content_field, token))
+                .collect();
+
+            let clause: Box<dyn Query> = match (terms.len(), term.prefix) {
+                (1, false) => Box::new(TermQuery::new(
+                    terms.remove(0),
+                    IndexRecordOption::WithFreqs,
+                )),
+                (1, true) => Box::new(RegexQuery::from_pattern(
+                    &format!("{}.*", escape_regex(&term.tokens[0])),
+                    self.content_field,
+                )?),
+                (_, false) => Box::new(PhraseQuery::new(terms)),
+                (_, true) => Box::new(PhrasePrefixQuery::new(terms)),
+            };
+            clauses.push((Occur::Must, clause));
+        }
+
+        Ok(match clauses.len() {
+            0 => None,
+            1 => clauses.pop().map(|(_, clause)| clause),
+            _ => Some(Box::new(BooleanQuery::new(clauses))),
+        })
+    }
+}
+
+/// Opens the search index in the app's data directory and stores it globally.
+/// This should be called once during Tauri's setup phase.
+pub fn initialize_search_index<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+) -> StorageResult<()> {
+    if SEARCH_INDEX.get().is_some() {
+        return Ok(());
+    }
+
+    let base_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| StorageError::Internal(format!("Failed to get app_data_dir: {}", e)))?;
+    let _ = SEARCH_INDEX.set(PostSearchIndex::open(&base_dir.join("search_index"))?);
+
+    Ok(())
+}
+
+/// Test-only function to initialize the global search index in `dir`.
+#[cfg(test)]
+pub fn initialize_search_index_for_tests(dir: &Path) -> StorageResult<()> {
+    if SEARCH_INDEX.get().is_some() {
+        return Ok(());
+    }
+
+    let _ = SEARCH_INDEX.set(PostSearchIndex::open(dir)?);
+    Ok(())
+}
+
+/// Retrieves the initialized search index, if any.
+///
+/// Storage keeps working without the index; callers skip indexing when it is `None`.
+pub fn get_search_index() -> Option<&'static PostSearchIndex> {
+    SEARCH_INDEX.get()
+}
+
+/// Multiplier applied to the relevance of a post created `age_secs` ago.
+///
+/// Decays from `1 + RECENCY_WEIGHT` for new posts towards 1 for old posts, so
+/// recency breaks ties between similarly relevant posts without burying older
+/// but much better matches.
+fn recency_boost(age_secs: i64) -> f32 {
+    let age_days = age_secs.max(0) as f32 / 86_400.0;
+    1.0 + RECENCY_WEIGHT / (1.0 + age_days / RECENCY_SCALE_DAYS)
+}
+
+/// A term of a search query.
+#[derive(Debug, PartialEq)]
+struct QueryTerm {
+    /// Tokens that must appear consecutively.
+    tokens: Vec<String>,
+    /// Whether the last token matches as a prefix.
+    prefix: bool,
+}
+
+/// Splits a search query into terms.
+fn parse_query(query: &str) -> Vec<QueryTerm> {
+    let mut terms = Vec::new();
+    let mut rest = query.trim_start();
+
+    while !rest.is_empty() {
+        let (text, quoted, remainder) = match rest.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], true, &quoted[end + 1..]),
+                None => (quoted, true, ""),
+            },
+            None => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], false, &rest[end..])
+            }
+        };
+
+        let (text, mut prefix, remainder) = if quoted {
+            match remainder.strip_prefix('*') {
+                Some(remainder) => (text, true, remainder),
+                None => (text, false, remainder),
+            }
+        } else {
+            match text.strip_suffix('*') {
+                Some(text) => (text, true, remainder),
+                None => (text, false, remainder),
+            }
+        };
+
+        let tokens: Vec<String> = tokenize(text).into_iter().map(|t| t.text).collect();
+        if !quoted && tokens.len() == 1 && tokens[0].chars().count() == 1 {
+            prefix |= tokens[0].chars().all(is_cjk);
+        }
+        if !tokens.is_empty() {
+            terms.push(QueryTerm { tokens, prefix });
+        }
+
+        rest = remainder.trim_start();
+    }
+
+    terms
+}
+
+/// Escapes regex metacharacters in a token.
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Returns `true` for characters of scripts written without spaces between words.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+        | '\u{31F0}'..='\u{31FF}' // Katakana phonetic extensions
+        | '\u{3400}'..='\u{4DBF}' // CJK unified ideographs extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+        | '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+    )
+}
+
+/// Splits text into lowercased words and CJK bigrams.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if is_cjk(c) {
+            let mut run = Vec::new();
+            while let Some(&(offset, c)) = chars.peek() {
+                if !is_cjk(c) {
+                    break;
+                }
+                run.push((offset, c));
+                chars.next();
+            }
+            if let [(offset, c)] = run[..] {
+                push_token(&mut tokens, offset, offset + c.len_utf8(), c.to_string());
+            } else {
+                for pair in run.windows(2) {
+                    let (from, first) = pair[0];
+                    let (offset, second) = pair[1];
+                    let text: String = [first, second].iter().collect();
+                    push_token(&mut tokens, from, offset + second.len_utf8(), text);
+                }
+            }
+        } else if c.is_alphanumeric() {
+            let mut end = start;
+            while let Some(&(offset, c)) = chars.peek() {
+                if !c.is_alphanumeric() || is_cjk(c) {
+                    break;
+                }
+                end = offset + c.len_utf8();
+                chars.next();
+            }
+            push_token(&mut tokens, start, end, text[start..end].to_lowercase());
+        } else {
+            chars.next();
+        }
+    }
+
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<Token>, offset_from: usize, offset_to: usize, text: String) {
+    tokens.push(Token {
+        offset_from,
+        offset_to,
+        position: tokens.len(),
+        text,
+        position_length: 1,
+    });
+}
+
+/// Tokenizer splitting post content into words and CJK bigrams (see `tokenize`).
+#[derive(Clone)]
+struct CjkTokenizer;
+
+impl Tokenizer for CjkTokenizer {
+    type TokenStream<'a> = CjkTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        CjkTokenStream {
+            tokens: tokenize(text),
+            next: 0,
+        }
+    }
+}
+
+struct CjkTokenStream {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl TokenStream for CjkTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.next < self.tokens.len() {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.next - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.next - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(text: &str) -> Vec<String> {
+        tokenize(text).into_iter().map(|t| t.text).collect()
+    }
+
+    fn post(id: &str, content: &str, created_at: i64) -> Post {
+        Post {
+            id: id.to_string(),
+            author_id: "author".to_string(),
+            content: content.to_string(),
+            attachments: Vec::new(),
+            mentions: Vec::new(),
+            hashtags: Vec::new(),
+            created_at,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
+        }
+    }
+
+    #[test]
+    fn test_tokenize_words_and_cjk_bigrams() {
+        assert_eq!(texts("Hello, Rust-lang!"), vec!["hello", "rust", "lang"]);
+        assert_eq!(
+            texts("東京都に行く"),
+            vec!["東京", "京都", "都に", "に行", "行く"]
+        );
+        assert_eq!(
+            texts("Rust言語 と iroh"),
+            vec!["rust", "言語", "と", "iroh"]
+        );
+
+        let tokens = tokenize("a 東京");
+        assert_eq!(tokens[1].position, 1);
+        assert_eq!(
+            &"a 東京"[tokens[1].offset_from..tokens[1].offset_to],
+            "東京"
+        );
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query(r#"rust "hello wor"* iro* 東"#),
+            vec![
+                QueryTerm {
+                    tokens: vec!["rust".to_string()],
+                    prefix: false,
+                },
+                QueryTerm {
+                    tokens: vec!["hello".to_string(), "wor".to_string()],
+                    prefix: true,
+                },
+                QueryTerm {
+                    tokens: vec!["iro".to_string()],
+                    prefix: true,
+                },
+                QueryTerm {
+                    tokens: vec!["東".to_string()],
+                    prefix: true,
+                },
+            ]
+        );
+        assert!(parse_query(r#"  !!  "" "#).is_empty());
+    }
+
+    #[test]
+    fn test_recency_boost_decays() {
+        assert_eq!(recency_boost(0), 1.0 + RECENCY_WEIGHT);
+        assert_eq!(recency_boost(-60), recency_boost(0));
+        assert!(recency_boost(86_400) > recency_boost(30 * 86_400));
+        assert!(recency_boost(365 * 86_400) > 1.0);
+    }
+
+    #[test]
+    fn test_search_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = PostSearchIndex::open(dir.path()).unwrap();
+        let now = chrono::Utc::now().timestamp();
+
+        index
+            .index_posts(&[
+                post("old", "今日は東京都で Rust の勉強会", now - 60 * 86_400),
+                post("new", "東京都の天気は晴れ", now),
+                post("phrase", "hello wonderful world", now),
+            ])
+            .unwrap();
+        assert_eq!(index.len(), 3);
+
+        // Newer matches rank first
+        assert_eq!(index.search("東京都", 10).unwrap(), vec!["new", "old"]);
+        assert_eq!(index.search("東京都", 1).unwrap(), vec!["new"]);
+        assert_eq!(index.search("東京都 rust", 10).unwrap(), vec!["old"]);
+        assert_eq!(index.search("勉", 10).unwrap(), vec!["old"]);
+        assert_eq!(index.search("won*", 10).unwrap(), vec!["phrase"]);
+        assert_eq!(
+            index.search(r#""hello wonderful""#, 10).unwrap(),
+            vec!["phrase"]
+        );
+        assert!(index.search(r#""hello world""#, 10).unwrap().is_empty());
+        assert_eq!(
+            index.search(r#""hello wonder"*"#, 10).unwrap(),
+            vec!["phrase"]
+        );
+
+        // Re-indexing replaces the previous version
+        index.index_post(&post("new", "晴れのち曇り", now)).unwrap();
+        assert_eq!(index.search("東京都", 10).unwrap(), vec!["old"]);
+        assert_eq!(index.len(), 3);
+
+        index.remove_post("phrase").unwrap();
+        assert!(index.search("hello", 10).unwrap().is_empty());
+
+        // Followers-only posts are left out
+        let mut private = post("private", "hello followers", now);
+        private.visibility = Visibility::Followers;
+        index.index_post(&private).unwrap();
+        assert!(index.search("hello", 10).unwrap().is_empty());
+        assert_eq!(index.len(), 2);
+    }
+}
@@ -9,7 +9,7 @@ use super::error::StorageError;
 use super::iroh_node::IrohNode;
 
 // Type alias for Document with proper connector type
-type DocType = Doc<FlumeConnector<iroh_docs::rpc::proto::Response, iroh_docs::rpc::proto::Request>>;
+pub(crate) type DocType = Doc<FlumeConnector<iroh_docs::rpc::proto::Response, iroh_docs::rpc::proto::Request>>;
 
 // Global static variables to hold the initialized IrohNode and document handles
 // OnceLock ensures they're initialized only once safely across threads.
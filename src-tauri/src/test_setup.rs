@@ -60,6 +60,9 @@ pub async fn setup_test_environment() -> Result<(), StorageError> {
     
     // Initialize global state
     crate::storage::state::initialize_iroh_for_tests(iroh_node).await?;
+    crate::storage::search_index::initialize_search_index_for_tests(
+        &temp_dir.path().join("search_index"),
+    )?;
     println!("[TEST SETUP] Initialized global state successfully");
     
     // Leak the temp_dir to prevent cleanup
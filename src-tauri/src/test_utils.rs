@@ -1,6 +1,9 @@
 //! Test utilities for integration tests
 
+use crate::identity::{sign_record, user_id_from_public_key, Signable};
 use crate::storage::{iroh_node::IrohNode, state::initialize_iroh_for_tests, StorageError};
+use base64::{engine::general_purpose, Engine as _};
+use ring::signature::{Ed25519KeyPair, KeyPair};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tempfile::TempDir;
@@ -96,6 +99,46 @@ impl TestEnvironment {
     }
 }
 
+/// Signing identity for tests that store users and posts
+///
+/// Repository reads verify signatures, so test records must be signed with a real key.
+pub struct TestIdentity {
+    key_pair: Ed25519KeyPair,
+}
+
+impl TestIdentity {
+    /// Generate a fresh Ed25519 key pair
+    pub fn new() -> Self {
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).expect("Failed to generate key pair");
+        let key_pair =
+            Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).expect("Failed to parse key pair");
+        Self { key_pair }
+    }
+
+    /// Base64-encoded public key, as stored in `User.public_key`
+    pub fn public_key(&self) -> String {
+        general_purpose::STANDARD.encode(self.key_pair.public_key().as_ref())
+    }
+
+    /// User ID derived from the public key, as required for `User.id` and `Post.author_id`
+    pub fn user_id(&self) -> String {
+        user_id_from_public_key(self.key_pair.public_key().as_ref())
+            .expect("Ed25519 public keys are 32 bytes")
+    }
+
+    /// Sign a record with this identity's key
+    pub fn sign<T: Signable>(&self, record: &mut T) {
+        sign_record(record, &self.key_pair);
+    }
+}
+
+impl Default for TestIdentity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Wait for event propagation in tests
 pub async fn wait_for_event_propagation() {
     sleep(Duration::from_millis(100)).await;
@@ -1,6 +1,9 @@
 //! Integration tests for document subscription and event handling
 
-use crate::models::{post::Post, user::User};
+use crate::models::{
+    post::{Post, Visibility},
+    user::User,
+};
 use crate::storage::{
     events::DocumentSubscriptionService,
     repository::{
@@ -9,7 +12,7 @@ use crate::storage::{
     },
     StorageError,
 };
-use crate::test_utils::{wait_for_event_propagation, wait_for_sync};
+use crate::test_utils::{wait_for_event_propagation, wait_for_sync, TestIdentity};
 use crate::test_setup::setup_test_environment;
 use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
@@ -79,6 +82,7 @@ async fn test_repository_operations_trigger_events() -> Result<(), StorageError>
     let _ = env_logger::try_init();
 
     setup_test_environment().await?;
+    let identity = TestIdentity::new();
     let mock_emitter = MockEventEmitter::new();
 
     // Create a channel to monitor document changes
@@ -98,17 +102,23 @@ async fn test_repository_operations_trigger_events() -> Result<(), StorageError>
     });
 
     // Create a user and signal an event
-    let user_id = Uuid::new_v4().to_string();
-    let user = User {
+    let user_id = identity.user_id();
+    let mut user = User {
         id: user_id.clone(),
         display_name: "Subscription Test User".to_string(),
         bio: "Test bio for subscription".to_string(),
-        public_key: "test_public_key".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut user);
 
     save_user(&user).await?;
     tx.send("user_created")
@@ -118,7 +128,7 @@ async fn test_repository_operations_trigger_events() -> Result<(), StorageError>
 
     // Create a post and signal an event
     let post_id = Uuid::new_v4().to_string();
-    let post = Post {
+    let mut post = Post {
         id: post_id.clone(),
         author_id: user_id.clone(),
         content: "Subscription test post".to_string(),
@@ -126,7 +136,16 @@ async fn test_repository_operations_trigger_events() -> Result<(), StorageError>
         mentions: Vec::new(),
         hashtags: Vec::new(),
         created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
     };
+    identity.sign(&mut post);
 
     save_post(&post).await?;
     tx.send("post_created")
@@ -137,6 +156,7 @@ async fn test_repository_operations_trigger_events() -> Result<(), StorageError>
     // Update the user and signal an event
     let mut updated_user = user.clone();
     updated_user.display_name = "Updated Subscription Test User".to_string();
+    identity.sign(&mut updated_user);
     save_user(&updated_user).await?;
     tx.send("user_updated")
         .await
@@ -187,6 +207,7 @@ async fn test_multiple_document_operations() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
 
     setup_test_environment().await?;
+    let identity = TestIdentity::new();
     let mock_emitter = MockEventEmitter::new();
 
     // Create multiple operations and track events
@@ -206,17 +227,24 @@ async fn test_multiple_document_operations() -> Result<(), StorageError> {
 
     // Create multiple users
     for i in 0..3 {
-        let user_id = Uuid::new_v4().to_string();
-        let user = User {
+        let identity = TestIdentity::new();
+        let user_id = identity.user_id();
+        let mut user = User {
             id: user_id.clone(),
             display_name: format!("Multi User {}", i),
             bio: format!("Bio for multi user {}", i),
-            public_key: format!("test_public_key_{}", i),
+            public_key: identity.public_key(),
             avatar: None,
+            avatar_thumbnail: None,
             following: Vec::new(),
             followers: Vec::new(),
+            devices: Vec::new(),
+            post_doc_ticket: None,
+            approval_required: false,
             created_at: chrono::Utc::now().timestamp(),
+            signature: None,
         };
+        identity.sign(&mut user);
 
         save_user(&user).await?;
         tx.send(format!("user_created_{}", i))
@@ -227,22 +255,28 @@ async fn test_multiple_document_operations() -> Result<(), StorageError> {
     wait_for_sync().await;
 
     // Create multiple posts - we'll create posts for a sample user
-    let sample_user_id = Uuid::new_v4().to_string();
-    let sample_user = User {
+    let sample_user_id = identity.user_id();
+    let mut sample_user = User {
         id: sample_user_id.clone(),
         display_name: "Sample User".to_string(),
         bio: "Sample bio".to_string(),
-        public_key: "sample_public_key".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut sample_user);
     save_user(&sample_user).await?;
 
     for post_idx in 0..2 {
         let post_id = Uuid::new_v4().to_string();
-        let post = Post {
+        let mut post = Post {
             id: post_id.clone(),
             author_id: sample_user_id.clone(),
             content: format!("Multi post {}", post_idx),
@@ -250,7 +284,16 @@ async fn test_multiple_document_operations() -> Result<(), StorageError> {
             mentions: Vec::new(),
             hashtags: Vec::new(),
             created_at: chrono::Utc::now().timestamp(),
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
         };
+        identity.sign(&mut post);
 
         save_post(&post).await?;
         tx.send(format!("post_created_{}", post_idx))
@@ -300,6 +343,7 @@ async fn test_event_ordering() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
 
     setup_test_environment().await?;
+    let identity = TestIdentity::new();
     let mock_emitter = MockEventEmitter::new();
 
     // Test that events are emitted in the correct order
@@ -324,17 +368,23 @@ async fn test_event_ordering() -> Result<(), StorageError> {
     });
 
     // Perform operations in sequence
-    let user_id = Uuid::new_v4().to_string();
-    let user = User {
+    let user_id = identity.user_id();
+    let mut user = User {
         id: user_id.clone(),
         display_name: "Order Test User".to_string(),
         bio: "Test bio for order test".to_string(),
-        public_key: "test_public_key_order".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut user);
 
     // Step 1: Create user
     save_user(&user).await?;
@@ -346,6 +396,7 @@ async fn test_event_ordering() -> Result<(), StorageError> {
     // Step 2: Update user
     let mut updated_user = user.clone();
     updated_user.display_name = "Updated Order Test User".to_string();
+    identity.sign(&mut updated_user);
     save_user(&updated_user).await?;
     tx.send("update")
         .await
@@ -1,34 +1,83 @@
 //! Integration tests for document synchronization with iroh-docs
 
-use crate::models::{post::Post, user::User};
+use crate::commands::auth::resolve_recovered_user;
+use crate::identity::envelope::{agreement_public_key, open_with, seal_to};
+use crate::identity::seed;
+use crate::media::pipeline::ensure_preview;
+use crate::models::{
+    follow::{FollowApproval, FollowEdge, FollowRequest},
+    moderation::{ModerationEntry, ModerationKind},
+    post::{Attachment, Post, PostRevision, Repost, Thread, TimelinePage, Visibility},
+    reaction::{Reaction, ReactionKind},
+    user::{DeviceDelegation, KeyRotation, User},
+};
+use crate::storage::authors::author_for_user;
+use crate::storage::blob_store::{fetch_blob, get_preview, import_blob, read_blob};
+use crate::storage::post_docs::{
+    carry_over_own_docs, follow_post_docs_of, open_own_follower_doc, open_own_post_doc,
+    own_post_doc, post_doc_ticket,
+};
+use crate::storage::state::{get_iroh_node, get_post_doc};
 use crate::storage::{
     repository::{
-        post_repository::{get_post, list_posts, list_user_posts, save_post},
-        user_repository::{get_user, save_user},
+        follow_repository::{
+            apply_remote_approval, apply_remote_edge, apply_remote_request, count_follows,
+            get_follow_approval, get_follow_edge, is_approved_follower, is_following,
+            list_follow_requests, list_followers, list_following, requires_approval,
+            save_follow_edge,
+        },
+        moderation_repository::{
+            is_blocked_on_node, list_moderation, remove_moderation, save_moderation,
+        },
+        post_repository::{
+            count_replies, delete_post, delete_repost, find_repost, get_post, get_post_revisions,
+            get_visible_post, import_followed_entry, list_hashtag_page, list_home_timeline_page,
+            list_mention_page, list_posts, list_posts_page, list_reposts_of, list_thread_replies,
+            list_timeline_page, list_user_posts, migrate_post_keys, save_post, save_repost,
+            search_posts, write_missing_index_entries,
+        },
+        reaction_repository::{
+            list_reactions, remove_reaction, save_reaction, summarize_reactions,
+        },
+        user_repository::{get_user, save_key_rotation, save_user},
     },
     StorageError,
 };
 use crate::test_setup::setup_test_environment;
-use crate::test_utils::{wait_for_event_propagation, wait_for_sync};
+use crate::test_utils::{wait_for_event_propagation, wait_for_sync, TestIdentity};
+use base64::{engine::general_purpose, Engine as _};
+use iroh_blobs::Hash;
+use iroh_docs::store::Query;
+use iroh_docs::{Capability, DocTicket, NamespaceSecret};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use std::str::FromStr;
 use uuid::Uuid;
+use x25519_dalek::StaticSecret;
 
 #[tokio::test]
 async fn test_user_document_creation_and_retrieval() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
     setup_test_environment().await?;
+    let identity = TestIdentity::new();
 
     // Create a user
-    let user_id = Uuid::new_v4().to_string();
-    let user = User {
+    let user_id = identity.user_id();
+    let mut user = User {
         id: user_id.clone(),
         display_name: "Sync Test User".to_string(),
         bio: "Test bio for sync".to_string(),
-        public_key: "test_public_key_sync".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut user);
 
     // Save the user
     save_user(&user).await?;
@@ -48,26 +97,33 @@ async fn test_user_document_creation_and_retrieval() -> Result<(), StorageError>
 async fn test_post_document_creation_and_retrieval() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
     setup_test_environment().await?;
+    let identity = TestIdentity::new();
 
     // Create a user first
-    let user_id = Uuid::new_v4().to_string();
-    let user = User {
+    let user_id = identity.user_id();
+    let mut user = User {
         id: user_id.clone(),
         display_name: "Post Author".to_string(),
         bio: "Test author for post sync".to_string(),
-        public_key: "test_public_key_author".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut user);
 
     save_user(&user).await?;
     wait_for_sync().await;
 
     // Create a post
     let post_id = Uuid::new_v4().to_string();
-    let post = Post {
+    let mut post = Post {
         id: post_id.clone(),
         author_id: user_id.clone(),
         content: "Sync test post content".to_string(),
@@ -75,7 +131,16 @@ async fn test_post_document_creation_and_retrieval() -> Result<(), StorageError>
         mentions: Vec::new(),
         hashtags: Vec::new(),
         created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
     };
+    identity.sign(&mut post);
 
     // Save the post
     save_post(&post).await?;
@@ -97,19 +162,26 @@ async fn test_post_document_creation_and_retrieval() -> Result<(), StorageError>
 async fn test_user_document_update() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
     setup_test_environment().await?;
+    let identity = TestIdentity::new();
 
     // Create initial user
-    let user_id = Uuid::new_v4().to_string();
+    let user_id = identity.user_id();
     let mut user = User {
         id: user_id.clone(),
         display_name: "Initial Name".to_string(),
         bio: "Initial bio".to_string(),
-        public_key: "test_public_key_update".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut user);
 
     // Save initial version
     save_user(&user).await?;
@@ -119,6 +191,7 @@ async fn test_user_document_update() -> Result<(), StorageError> {
     user.display_name = "Updated Name".to_string();
     user.bio = "Updated bio".to_string();
     user.avatar = Some("avatar_url".to_string());
+    identity.sign(&mut user);
 
     // Save updated version
     save_user(&user).await?;
@@ -137,19 +210,26 @@ async fn test_user_document_update() -> Result<(), StorageError> {
 async fn test_document_listing_sync() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
     setup_test_environment().await?;
+    let identity = TestIdentity::new();
 
     // Create multiple posts
-    let user_id = Uuid::new_v4().to_string();
-    let user = User {
+    let user_id = identity.user_id();
+    let mut user = User {
         id: user_id.clone(),
         display_name: "List Test Author".to_string(),
         bio: "Author for listing test".to_string(),
-        public_key: "test_public_key_list".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut user);
 
     save_user(&user).await?;
     wait_for_sync().await;
@@ -158,7 +238,7 @@ async fn test_document_listing_sync() -> Result<(), StorageError> {
 
     for i in 0..5 {
         let post_id = Uuid::new_v4().to_string();
-        let post = Post {
+        let mut post = Post {
             id: post_id.clone(),
             author_id: user_id.clone(),
             content: format!("List test post {}", i),
@@ -166,7 +246,16 @@ async fn test_document_listing_sync() -> Result<(), StorageError> {
             mentions: Vec::new(),
             hashtags: Vec::new(),
             created_at: chrono::Utc::now().timestamp() + i,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
         };
+        identity.sign(&mut post);
 
         save_post(&post).await?;
         post_ids.push(post_id);
@@ -176,7 +265,7 @@ async fn test_document_listing_sync() -> Result<(), StorageError> {
     wait_for_sync().await;
 
     // List all posts
-    let all_posts = list_posts().await?;
+    let all_posts = list_posts(None).await?;
     
     // Verify all created posts are in the list
     for post_id in &post_ids {
@@ -194,31 +283,45 @@ async fn test_document_listing_sync() -> Result<(), StorageError> {
 async fn test_posts_by_author_sync() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
     setup_test_environment().await?;
+    let identity1 = TestIdentity::new();
+    let identity2 = TestIdentity::new();
 
     // Create two users
-    let user1_id = Uuid::new_v4().to_string();
-    let user1 = User {
+    let user1_id = identity1.user_id();
+    let mut user1 = User {
         id: user1_id.clone(),
         display_name: "User 1".to_string(),
         bio: "First user".to_string(),
-        public_key: "test_public_key_1".to_string(),
+        public_key: identity1.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity1.sign(&mut user1);
 
-    let user2_id = Uuid::new_v4().to_string();
-    let user2 = User {
+    let user2_id = identity2.user_id();
+    let mut user2 = User {
         id: user2_id.clone(),
         display_name: "User 2".to_string(),
         bio: "Second user".to_string(),
-        public_key: "test_public_key_2".to_string(),
+        public_key: identity2.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity2.sign(&mut user2);
 
     save_user(&user1).await?;
     save_user(&user2).await?;
@@ -230,7 +333,7 @@ async fn test_posts_by_author_sync() -> Result<(), StorageError> {
 
     for i in 0..3 {
         let post_id = Uuid::new_v4().to_string();
-        let post = Post {
+        let mut post = Post {
             id: post_id.clone(),
             author_id: user1_id.clone(),
             content: format!("User 1 post {}", i),
@@ -238,14 +341,23 @@ async fn test_posts_by_author_sync() -> Result<(), StorageError> {
             mentions: Vec::new(),
             hashtags: Vec::new(),
             created_at: chrono::Utc::now().timestamp() + i,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
         };
+        identity1.sign(&mut post);
         save_post(&post).await?;
         user1_post_ids.push(post_id);
     }
 
     for i in 0..2 {
         let post_id = Uuid::new_v4().to_string();
-        let post = Post {
+        let mut post = Post {
             id: post_id.clone(),
             author_id: user2_id.clone(),
             content: format!("User 2 post {}", i),
@@ -253,7 +365,16 @@ async fn test_posts_by_author_sync() -> Result<(), StorageError> {
             mentions: Vec::new(),
             hashtags: Vec::new(),
             created_at: chrono::Utc::now().timestamp() + i,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
         };
+        identity2.sign(&mut post);
         save_post(&post).await?;
         user2_post_ids.push(post_id);
     }
@@ -261,8 +382,8 @@ async fn test_posts_by_author_sync() -> Result<(), StorageError> {
     wait_for_sync().await;
 
     // Get posts by user
-    let user1_posts = list_user_posts(&user1_id).await?;
-    let user2_posts = list_user_posts(&user2_id).await?;
+    let user1_posts = list_user_posts(&user1_id, None).await?;
+    let user2_posts = list_user_posts(&user2_id, None).await?;
 
     // Verify correct posts are returned
     assert_eq!(user1_posts.len(), 3);
@@ -285,19 +406,26 @@ async fn test_posts_by_author_sync() -> Result<(), StorageError> {
 async fn test_concurrent_document_operations() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
     setup_test_environment().await?;
+    let identity = std::sync::Arc::new(TestIdentity::new());
 
     // Create user for posts
-    let user_id = Uuid::new_v4().to_string();
-    let user = User {
+    let user_id = identity.user_id();
+    let mut user = User {
         id: user_id.clone(),
         display_name: "Concurrent Test User".to_string(),
         bio: "User for concurrent test".to_string(),
-        public_key: "test_public_key_concurrent".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut user);
 
     save_user(&user).await?;
     wait_for_sync().await;
@@ -307,9 +435,10 @@ async fn test_concurrent_document_operations() -> Result<(), StorageError> {
 
     for i in 0..5 {
         let user_id_clone = user_id.clone();
+        let identity = identity.clone();
         let handle = tokio::spawn(async move {
             let post_id = Uuid::new_v4().to_string();
-            let post = Post {
+            let mut post = Post {
                 id: post_id.clone(),
                 author_id: user_id_clone,
                 content: format!("Concurrent post {}", i),
@@ -317,7 +446,16 @@ async fn test_concurrent_document_operations() -> Result<(), StorageError> {
                 mentions: Vec::new(),
                 hashtags: Vec::new(),
                 created_at: chrono::Utc::now().timestamp() + i,
+                edited_at: None,
+                revisions: Vec::new(),
+                reply_to: None,
+                root_id: None,
+                quote_of: None,
+                visibility: Visibility::Public,
+                signature: None,
+                key_revoked: false,
             };
+            identity.sign(&mut post);
 
             save_post(&post).await.map(|_| post_id)
         });
@@ -336,7 +474,7 @@ async fn test_concurrent_document_operations() -> Result<(), StorageError> {
     wait_for_sync().await;
 
     // Verify all posts were created
-    let all_posts = list_posts().await?;
+    let all_posts = list_posts(None).await?;
     for post_id in &created_ids {
         assert!(
             all_posts.iter().any(|p| p.id == *post_id),
@@ -352,24 +490,31 @@ async fn test_concurrent_document_operations() -> Result<(), StorageError> {
 async fn test_document_persistence_across_restarts() -> Result<(), StorageError> {
     let _ = env_logger::try_init();
     setup_test_environment().await?;
+    let identity = TestIdentity::new();
 
     // Create data
-    let user_id = Uuid::new_v4().to_string();
-    let user = User {
+    let user_id = identity.user_id();
+    let mut user = User {
         id: user_id.clone(),
         display_name: "Persistence Test User".to_string(),
         bio: "Testing persistence".to_string(),
-        public_key: "test_public_key_persist".to_string(),
+        public_key: identity.public_key(),
         avatar: None,
+        avatar_thumbnail: None,
         following: Vec::new(),
         followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
         created_at: chrono::Utc::now().timestamp(),
+        signature: None,
     };
+    identity.sign(&mut user);
 
     save_user(&user).await?;
 
     let post_id = Uuid::new_v4().to_string();
-    let post = Post {
+    let mut post = Post {
         id: post_id.clone(),
         author_id: user_id.clone(),
         content: "Persistence test post".to_string(),
@@ -377,7 +522,16 @@ async fn test_document_persistence_across_restarts() -> Result<(), StorageError>
         mentions: Vec::new(),
         hashtags: Vec::new(),
         created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
     };
+    identity.sign(&mut post);
 
     save_post(&post).await?;
     wait_for_sync().await;
@@ -407,21 +561,28 @@ async fn test_multiple_users_sync() -> Result<(), StorageError> {
     let mut user_ids = Vec::new();
     
     for i in 0..10 {
-        let user_id = Uuid::new_v4().to_string();
-        let user = User {
+        let identity = TestIdentity::new();
+        let user_id = identity.user_id();
+        let mut user = User {
             id: user_id.clone(),
             display_name: format!("Multi Sync User {}", i),
             bio: format!("Bio for user {}", i),
-            public_key: format!("test_public_key_multi_{}", i),
+            public_key: identity.public_key(),
             avatar: if i % 2 == 0 {
                 Some(format!("avatar_{}.png", i))
             } else {
                 None
             },
+            avatar_thumbnail: None,
             following: Vec::new(),
             followers: Vec::new(),
+            devices: Vec::new(),
+            post_doc_ticket: None,
+            approval_required: false,
             created_at: chrono::Utc::now().timestamp() + i,
+            signature: None,
         };
+        identity.sign(&mut user);
 
         save_user(&user).await?;
         user_ids.push(user_id);
@@ -444,4 +605,1546 @@ async fn test_multiple_users_sync() -> Result<(), StorageError> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_forged_post_is_rejected() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let victim = TestIdentity::new();
+    let forger = TestIdentity::new();
+
+    // A post claiming the victim as author but signed with another key
+    let post_id = Uuid::new_v4().to_string();
+    let mut post = Post {
+        id: post_id.clone(),
+        author_id: victim.user_id(),
+        content: "Forged post".to_string(),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    forger.sign(&mut post);
+
+    save_post(&post).await?;
+    wait_for_sync().await;
+
+    // Reading the post directly reports the forgery
+    assert!(matches!(
+        get_post(&post_id).await,
+        Err(StorageError::InvalidSignature(_))
+    ));
+
+    // Listing drops it
+    let all_posts = list_posts(None).await?;
+    assert!(!all_posts.iter().any(|p| p.id == post_id));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_post_pagination_with_cursor() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+    let user_id = identity.user_id();
+
+    // Five posts, two of them sharing a timestamp
+    let base = chrono::Utc::now().timestamp();
+    for offset in [0, 1, 2, 2, 3] {
+        let mut post = Post {
+            id: Uuid::new_v4().to_string(),
+            author_id: user_id.clone(),
+            content: format!("Paged post at +{}", offset),
+            attachments: Vec::new(),
+            mentions: Vec::new(),
+            hashtags: Vec::new(),
+            created_at: base + offset,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
+        };
+        identity.sign(&mut post);
+        save_post(&post).await?;
+    }
+    wait_for_sync().await;
+
+    let first = list_posts_page(Some(&user_id), None, 2, None).await?;
+    assert_eq!(first.posts.len(), 2);
+    assert_eq!(first.posts[0].created_at, base + 3);
+    let cursor = first.next_cursor.expect("First page should have a cursor");
+
+    let second = list_posts_page(Some(&user_id), Some(&cursor), 2, None).await?;
+    let cursor = second
+        .next_cursor
+        .expect("Second page should have a cursor");
+    let third = list_posts_page(Some(&user_id), Some(&cursor), 2, None).await?;
+    assert_eq!(third.posts.len(), 1);
+    assert!(third.next_cursor.is_none());
+
+    // Pages are disjoint and cover every post in descending order
+    let paged: Vec<Post> = first
+        .posts
+        .into_iter()
+        .chain(second.posts)
+        .chain(third.posts)
+        .collect();
+    assert_eq!(paged.len(), 5);
+    for pair in paged.windows(2) {
+        assert!((pair[0].created_at, &pair[0].id) > (pair[1].created_at, &pair[1].id));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_legacy_post_keys_are_migrated() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: identity.user_id(),
+        content: "Post stored under a legacy key".to_string(),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    identity.sign(&mut post);
+
+    // Write the post the way it was stored before time-ordered keys
+    let iroh = get_iroh_node();
+    let author = iroh.authors.default().await.unwrap();
+    get_post_doc()
+        .set_bytes(
+            author,
+            format!("post:{}", post.id).into_bytes(),
+            serde_json::to_vec(&post).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(get_post(&post.id).await?.is_none());
+
+    assert!(migrate_post_keys().await? >= 1);
+    wait_for_sync().await;
+
+    let migrated = get_post(&post.id)
+        .await?
+        .expect("Migrated post should be found by ID");
+    assert_eq!(migrated.content, post.content);
+    let page = list_posts_page(Some(&post.author_id), None, 10, None).await?;
+    assert_eq!(page.posts.len(), 1);
+
+    // Running the migration again finds nothing to move
+    assert_eq!(migrate_post_keys().await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_missing_index_entries_are_backfilled() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+    let tag = format!("backfill{}", Uuid::new_v4().simple());
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: identity.user_id(),
+        content: format!("Post saved before the hashtag index #{}", tag),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: vec![tag.clone()],
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    identity.sign(&mut post);
+
+    // Write only the post entry, the way it was stored before the indexes existed
+    let iroh = get_iroh_node();
+    let author = iroh.authors.default().await.unwrap();
+    let key = format!(
+        "post_ts:{:016x}:{}:{}",
+        (post.created_at as u64) ^ (1 << 63),
+        post.author_id,
+        post.id
+    );
+    get_post_doc()
+        .set_bytes(author, key.into_bytes(), serde_json::to_vec(&post).unwrap())
+        .await
+        .unwrap();
+    assert!(get_post(&post.id).await?.is_some());
+    let tagged = list_hashtag_page(&tag, None, 10, None).await?;
+    assert!(tagged.posts.is_empty());
+
+    assert!(write_missing_index_entries().await? >= 1);
+    wait_for_sync().await;
+
+    let tagged = list_hashtag_page(&tag, None, 10, None).await?;
+    assert_eq!(tagged.posts.len(), 1);
+    assert_eq!(tagged.posts[0].id, post.id);
+
+    // Running the backfill again does not duplicate the entry
+    write_missing_index_entries().await?;
+    let tagged = list_hashtag_page(&tag, None, 10, None).await?;
+    assert_eq!(tagged.posts.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_posts() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+
+    // A word no other test uses, so results only contain this test's posts
+    let marker = format!("marker{}", Uuid::new_v4().simple());
+    let now = chrono::Utc::now().timestamp();
+    let mut ids = Vec::new();
+    for (offset, content) in [
+        (-86_400, format!("{} 東京都で勉強会", marker)),
+        (0, format!("{} 東京都の天気", marker)),
+        (0, format!("{} unrelated", marker)),
+    ] {
+        let mut post = Post {
+            id: Uuid::new_v4().to_string(),
+            author_id: identity.user_id(),
+            content,
+            attachments: Vec::new(),
+            mentions: Vec::new(),
+            hashtags: Vec::new(),
+            created_at: now + offset,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
+        };
+        identity.sign(&mut post);
+        save_post(&post).await?;
+        ids.push(post.id);
+    }
+
+    let results = search_posts(&format!("{} 東京都", marker), 10, None).await?;
+    let result_ids: Vec<&str> = results.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(result_ids, vec![ids[1].as_str(), ids[0].as_str()]);
+
+    assert_eq!(search_posts(&marker, 2, None).await?.len(), 2);
+    let results = search_posts(&format!("{} 勉", marker), 10, None).await?;
+    assert_eq!(results.len(), 1);
+
+    // Deleted posts are no longer found
+    delete_post(&ids[0]).await?;
+    let results = search_posts(&format!("{} 東京都", marker), 10, None).await?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, ids[1]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hashtag_and_mention_timelines() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+    let mentioned = TestIdentity::new().user_id();
+
+    let tag = format!("タグ{}", Uuid::new_v4().simple());
+    let now = chrono::Utc::now().timestamp();
+    let mut ids = Vec::new();
+    for (offset, hashtags, mentions) in [
+        (0, vec![tag.clone()], vec![]),
+        (1, vec![tag.clone()], vec![mentioned.clone()]),
+        (2, vec![], vec![mentioned.clone()]),
+    ] {
+        let mut post = Post {
+            id: Uuid::new_v4().to_string(),
+            author_id: identity.user_id(),
+            content: format!("Post #{}", tag),
+            attachments: Vec::new(),
+            mentions,
+            hashtags,
+            created_at: now + offset,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
+        };
+        identity.sign(&mut post);
+        save_post(&post).await?;
+        ids.push(post.id);
+    }
+
+    let tagged = list_hashtag_page(&tag, None, 10, None).await?;
+    let tagged_ids: Vec<&str> = tagged.posts.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(tagged_ids, vec![ids[1].as_str(), ids[0].as_str()]);
+
+    let first = list_mention_page(&mentioned, None, 1, None).await?;
+    assert_eq!(first.posts[0].id, ids[2]);
+    let cursor = first.next_cursor.expect("First page should have a cursor");
+    let second = list_mention_page(&mentioned, Some(&cursor), 1, None).await?;
+    assert_eq!(second.posts[0].id, ids[1]);
+
+    // Deleting a post removes it from the tag and mention timelines
+    delete_post(&ids[1]).await?;
+    let tagged = list_hashtag_page(&tag, None, 10, None).await?;
+    assert_eq!(tagged.posts.len(), 1);
+    let mentioning = list_mention_page(&mentioned, None, 10, None).await?;
+    assert_eq!(mentioning.posts.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_post_with_attachment_blob() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+
+    let data = format!("attachment {}", Uuid::new_v4()).into_bytes();
+    let hash = import_blob(data.clone()).await?;
+    assert_eq!(read_blob(hash).await?.as_deref(), Some(data.as_slice()));
+    // Locally available blobs are returned without contacting peers
+    assert_eq!(fetch_blob(hash, 1024).await?.as_ref(), data.as_slice());
+    // Blobs larger than the limit are not read
+    assert!(matches!(
+        fetch_blob(hash, 4).await,
+        Err(StorageError::Validation(_))
+    ));
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: identity.user_id(),
+        content: String::new(),
+        attachments: vec![Attachment {
+            hash: hash.to_string(),
+            mime_type: "text/plain".to_string(),
+            size: data.len() as u64,
+            width: None,
+            height: None,
+            name: Some("notes.txt".to_string()),
+        }],
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    identity.sign(&mut post);
+    save_post(&post).await?;
+
+    let retrieved = get_post(&post.id).await?.expect("Post should exist");
+    assert_eq!(retrieved.attachments, post.attachments);
+
+    // Attachment metadata is covered by the signature
+    post.attachments[0].size += 1;
+    save_post(&post).await?;
+    assert!(matches!(
+        get_post(&post.id).await,
+        Err(StorageError::InvalidSignature(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_user_avatar_blob() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+
+    let avatar = format!("avatar {}", Uuid::new_v4()).into_bytes();
+    let thumbnail = format!("thumbnail {}", Uuid::new_v4()).into_bytes();
+    let avatar_hash = import_blob(avatar.clone()).await?;
+    let thumbnail_hash = import_blob(thumbnail.clone()).await?;
+
+    let mut user = User {
+        id: identity.user_id(),
+        display_name: "Avatar User".to_string(),
+        bio: String::new(),
+        public_key: identity.public_key(),
+        avatar: Some(avatar_hash.to_string()),
+        avatar_thumbnail: Some(thumbnail_hash.to_string()),
+        following: Vec::new(),
+        followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: false,
+        created_at: chrono::Utc::now().timestamp(),
+        signature: None,
+    };
+    identity.sign(&mut user);
+    save_user(&user).await?;
+
+    let retrieved = get_user(&user.id).await?.expect("User should exist");
+    assert_eq!(retrieved.avatar, user.avatar);
+    assert_eq!(retrieved.avatar_thumbnail, user.avatar_thumbnail);
+    assert_eq!(
+        fetch_blob(avatar_hash, u64::MAX).await?.as_ref(),
+        avatar.as_slice()
+    );
+    assert_eq!(
+        fetch_blob(thumbnail_hash, u64::MAX).await?.as_ref(),
+        thumbnail.as_slice()
+    );
+
+    // The thumbnail hash is covered by the signature
+    user.avatar_thumbnail = Some(avatar_hash.to_string());
+    save_user(&user).await?;
+    assert!(matches!(
+        get_user(&user.id).await,
+        Err(StorageError::InvalidSignature(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_attachment_preview_pipeline() -> Result<(), StorageError> {
+    use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+
+    let image =
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(800, 400, Rgba([200, 120, 60, 255])));
+    let mut data = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+        .expect("PNG encoding should succeed");
+    let hash = import_blob(data).await?;
+
+    let preview = ensure_preview(hash)
+        .await?
+        .expect("Preview should be generated");
+    assert_eq!(preview.original, hash.to_string());
+    assert_eq!((preview.width, preview.height), (800, 400));
+    assert!(!preview.blurhash.is_empty());
+    assert_eq!(preview.thumbnails.len(), 1);
+    assert_eq!(
+        (preview.thumbnails[0].width, preview.thumbnails[0].height),
+        (320, 160)
+    );
+
+    // The preview is linked to the original and its thumbnails are stored as blobs
+    assert_eq!(get_preview(hash).await?, Some(preview.clone()));
+    let thumbnail_hash = preview.thumbnails[0].hash.parse().expect("Valid hash");
+    assert!(read_blob(thumbnail_hash).await?.is_some());
+
+    // Non-image blobs have no preview
+    let text = import_blob(format!("not an image {}", Uuid::new_v4()).into_bytes()).await?;
+    assert_eq!(ensure_preview(text).await?, None);
+    assert_eq!(get_preview(text).await?, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_edit_post_keeps_history() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+
+    let old_tag = format!("old{}", Uuid::new_v4().simple());
+    let new_tag = format!("new{}", Uuid::new_v4().simple());
+    let mut original = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: identity.user_id(),
+        content: format!("Original #{}", old_tag),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: vec![old_tag.clone()],
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    identity.sign(&mut original);
+    save_post(&original).await?;
+
+    // Keep the original as a blob and save the edited version under the same key
+    let revision_hash = import_blob(serde_json::to_vec(&original)?).await?;
+    let mut edited = Post {
+        content: format!("Edited #{}", new_tag),
+        hashtags: vec![new_tag.clone()],
+        edited_at: Some(original.created_at + 60),
+        revisions: vec![PostRevision {
+            hash: revision_hash.to_string(),
+            written_at: original.created_at,
+        }],
+        signature: None,
+        ..original.clone()
+    };
+    identity.sign(&mut edited);
+    save_post(&edited).await?;
+
+    let retrieved = get_post(&original.id).await?.expect("Post should exist");
+    assert_eq!(retrieved.content, edited.content);
+    assert_eq!(retrieved.edited_at, edited.edited_at);
+
+    // The edit replaces the post in the timelines of its hashtags
+    let old_tagged = list_hashtag_page(&old_tag, None, 10, None).await?;
+    assert!(old_tagged.posts.is_empty());
+    let tagged = list_hashtag_page(&new_tag, None, 10, None).await?;
+    assert_eq!(tagged.posts.len(), 1);
+    assert_eq!(tagged.posts[0].content, edited.content);
+
+    let history = get_post_revisions(&original.id, None)
+        .await?
+        .expect("Post should exist");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].content, original.content);
+    assert_eq!(history[0].edited_at, None);
+
+    // Revision references are covered by the signature
+    edited.revisions.clear();
+    save_post(&edited).await?;
+    assert!(matches!(
+        get_post(&original.id).await,
+        Err(StorageError::InvalidSignature(_))
+    ));
+
+    delete_post(&original.id).await?;
+    assert!(get_post(&original.id).await?.is_none());
+    assert!(get_post_revisions(&original.id, None).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reply_thread() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+
+    let root_id = Uuid::new_v4().to_string();
+    let first_id = Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp();
+    let make_post = |id: &str, reply_to: Option<&str>, created_at: i64| Post {
+        id: id.to_string(),
+        author_id: identity.user_id(),
+        content: format!("Post {}", id),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at,
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: reply_to.map(str::to_string),
+        root_id: reply_to.map(|_| root_id.clone()),
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+
+    let mut posts = vec![
+        make_post(&root_id, None, now),
+        make_post(&first_id, Some(&root_id), now + 1),
+        make_post(&Uuid::new_v4().to_string(), Some(&first_id), now + 2),
+        // A reply whose parent has not synced
+        make_post(&Uuid::new_v4().to_string(), Some("not-synced"), now + 3),
+    ];
+    for post in &mut posts {
+        identity.sign(post);
+        save_post(post).await?;
+    }
+
+    assert_eq!(count_replies(&root_id).await?, 1);
+    assert_eq!(count_replies(&first_id).await?, 1);
+
+    let replies = list_thread_replies(&root_id, None).await?;
+    assert_eq!(replies.len(), 3);
+    let root = get_post(&root_id).await?;
+    let thread = Thread::assemble(root_id.clone(), root, replies);
+    assert_eq!(
+        thread.root.as_ref().map(|post| post.id.as_str()),
+        Some(root_id.as_str())
+    );
+    assert_eq!(thread.replies.len(), 2);
+    assert_eq!(thread.replies[0].post.id, first_id);
+    assert_eq!(thread.replies[0].replies[0].post.id, posts[2].id);
+    assert!(thread.replies[1].parent_missing);
+
+    // Deleting a reply removes it from the thread and the reply count
+    delete_post(&posts[2].id).await?;
+    assert_eq!(count_replies(&first_id).await?, 0);
+    assert_eq!(list_thread_replies(&root_id, None).await?.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reposts_and_quotes_in_timeline() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let author = TestIdentity::new();
+    let reposters = [TestIdentity::new(), TestIdentity::new()];
+
+    // Far in the future so the posts are at the top of the shared timeline
+    let now = chrono::Utc::now().timestamp() + 1_000_000;
+    let make_post = |created_at: i64, quote_of: Option<&str>| Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: author.user_id(),
+        content: "Worth sharing".to_string(),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at,
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: quote_of.map(str::to_string),
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+
+    let mut original = make_post(now, None);
+    author.sign(&mut original);
+    save_post(&original).await?;
+    let mut quote = make_post(now + 1, Some(&original.id));
+    author.sign(&mut quote);
+    save_post(&quote).await?;
+
+    let mut reposts = Vec::new();
+    for (offset, reposter) in reposters.iter().enumerate() {
+        let mut repost = Repost {
+            id: Uuid::new_v4().to_string(),
+            reposter_id: reposter.user_id(),
+            post_id: original.id.clone(),
+            post_author_id: original.author_id.clone(),
+            created_at: now + 2 + offset as i64,
+            signature: None,
+        };
+        reposter.sign(&mut repost);
+        save_repost(&repost).await?;
+        reposts.push(repost);
+    }
+
+    // A forged repost is ignored
+    let mut forged = reposts[0].clone();
+    forged.id = Uuid::new_v4().to_string();
+    forged.created_at = now + 10;
+    save_repost(&forged).await?;
+
+    assert_eq!(list_reposts_of(&original.id, 10).await?.len(), 2);
+    assert!(find_repost(&reposters[1].user_id(), &original.id)
+        .await?
+        .is_some());
+
+    // The original post is shown once, at its newest repost, above the quote
+    let page = list_timeline_page(None, 2, None).await?;
+    assert_eq!(page.items[0].post.id, original.id);
+    assert_eq!(page.items[0].reposted_at, Some(now + 3));
+    assert_eq!(
+        page.items[0].reposted_by,
+        vec![reposters[1].user_id(), reposters[0].user_id()]
+    );
+    assert_eq!(page.items[1].post.id, quote.id);
+    assert_eq!(
+        page.items[1].quoted.as_ref().map(|post| post.id.as_str()),
+        Some(original.id.as_str())
+    );
+
+    // Paging past the reposts does not show the original post again
+    let cursor = page.next_cursor.expect("page is full");
+    let next = list_timeline_page(Some(&cursor), 100, None).await?;
+    assert!(next.items.iter().all(|item| item.post.id != original.id));
+
+    // Without reposts the post returns to its own position
+    for repost in &reposts {
+        delete_repost(repost).await?;
+    }
+    let page = list_timeline_page(None, 2, None).await?;
+    assert_eq!(page.items[0].post.id, quote.id);
+    assert_eq!(page.items[1].post.id, original.id);
+    assert!(page.items[1].reposted_by.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_post_reactions() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let author = TestIdentity::new();
+    let reactors = [TestIdentity::new(), TestIdentity::new()];
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: author.user_id(),
+        content: "React to this".to_string(),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    author.sign(&mut post);
+    save_post(&post).await?;
+
+    let make_reaction = |reactor: &TestIdentity, kind: ReactionKind| {
+        let mut reaction = Reaction {
+            post_id: post.id.clone(),
+            reactor_id: reactor.user_id(),
+            kind,
+            created_at: chrono::Utc::now().timestamp(),
+            signature: None,
+        };
+        reactor.sign(&mut reaction);
+        reaction
+    };
+    save_reaction(&make_reaction(&reactors[0], ReactionKind::Like)).await?;
+    save_reaction(&make_reaction(&reactors[0], ReactionKind::Laugh)).await?;
+    save_reaction(&make_reaction(&reactors[1], ReactionKind::Like)).await?;
+    // Reacting again with the same kind replaces the earlier entry
+    save_reaction(&make_reaction(&reactors[1], ReactionKind::Like)).await?;
+
+    // A reaction signed by someone else than the reactor is ignored
+    let mut forged = make_reaction(&reactors[0], ReactionKind::Heart);
+    forged.reactor_id = reactors[1].user_id();
+    save_reaction(&forged).await?;
+
+    assert_eq!(list_reactions(&post.id).await?.len(), 3);
+    let summary = summarize_reactions(&post.id, Some(&reactors[0].user_id())).await?;
+    assert_eq!(summary.counts.get(&ReactionKind::Like), Some(&2));
+    assert_eq!(summary.counts.get(&ReactionKind::Laugh), Some(&1));
+    assert_eq!(summary.counts.get(&ReactionKind::Heart), None);
+    assert_eq!(summary.mine, vec![ReactionKind::Like, ReactionKind::Laugh]);
+
+    // The post entry itself is unchanged by reactions
+    let stored = get_post(&post.id).await?.expect("Post should exist");
+    assert_eq!(stored.signature, post.signature);
+
+    remove_reaction(&post.id, &reactors[0].user_id(), ReactionKind::Like).await?;
+    let summary = summarize_reactions(&post.id, Some(&reactors[0].user_id())).await?;
+    assert_eq!(summary.counts.get(&ReactionKind::Like), Some(&1));
+    assert_eq!(summary.mine, vec![ReactionKind::Laugh]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_follow_edges() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let alice = TestIdentity::new();
+    let bob = TestIdentity::new();
+    let carol = TestIdentity::new();
+
+    let make_edge = |from: &TestIdentity, to: &TestIdentity, active: bool, created_at: i64| {
+        let mut edge = FollowEdge {
+            follower_id: from.user_id(),
+            followee_id: to.user_id(),
+            active,
+            created_at,
+            signature: None,
+        };
+        from.sign(&mut edge);
+        edge
+    };
+    let now = chrono::Utc::now().timestamp();
+
+    save_follow_edge(&make_edge(&alice, &bob, true, now)).await?;
+    save_follow_edge(&make_edge(&alice, &carol, true, now)).await?;
+    save_follow_edge(&make_edge(&carol, &bob, true, now)).await?;
+
+    let mut following = list_following(&alice.user_id()).await?;
+    following.sort();
+    let mut expected = vec![bob.user_id(), carol.user_id()];
+    expected.sort();
+    assert_eq!(following, expected);
+
+    let mut followers = list_followers(&bob.user_id()).await?;
+    followers.sort();
+    let mut expected = vec![alice.user_id(), carol.user_id()];
+    expected.sort();
+    assert_eq!(followers, expected);
+
+    let counts = count_follows(&bob.user_id()).await?;
+    assert_eq!((counts.following, counts.followers), (0, 2));
+
+    // An edge signed by someone else than the follower is ignored
+    let mut forged = make_edge(&bob, &carol, true, now);
+    forged.follower_id = alice.user_id();
+    forged.followee_id = bob.user_id();
+    save_follow_edge(&forged).await?;
+    assert!(apply_remote_edge(&forged).await.is_err());
+    let carol_followers = list_followers(&carol.user_id()).await?;
+    assert_eq!(carol_followers, vec![alice.user_id()]);
+
+    // A remote unfollow replaces the follow, but a stale follow does not undo it
+    let unfollow = make_edge(&alice, &bob, false, now + 1);
+    assert!(apply_remote_edge(&unfollow).await?);
+    assert!(!apply_remote_edge(&make_edge(&alice, &bob, true, now)).await?);
+    assert!(!is_following(&alice.user_id(), &bob.user_id()).await?);
+    assert_eq!(
+        get_follow_edge(&alice.user_id(), &bob.user_id()).await?,
+        Some(unfollow)
+    );
+    assert_eq!(list_followers(&bob.user_id()).await?, vec![carol.user_id()]);
+    assert_eq!(
+        list_following(&alice.user_id()).await?,
+        vec![carol.user_id()]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_home_timeline() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let alice = TestIdentity::new();
+    let bob = TestIdentity::new();
+    let carol = TestIdentity::new();
+    let now = chrono::Utc::now().timestamp();
+
+    let make_post = |author: &TestIdentity, content: &str, created_at: i64| {
+        let mut post = Post {
+            id: Uuid::new_v4().to_string(),
+            author_id: author.user_id(),
+            content: content.to_string(),
+            attachments: Vec::new(),
+            mentions: Vec::new(),
+            hashtags: Vec::new(),
+            created_at,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
+        };
+        author.sign(&mut post);
+        post
+    };
+    let make_edge = |active: bool, created_at: i64| {
+        let mut edge = FollowEdge {
+            follower_id: alice.user_id(),
+            followee_id: bob.user_id(),
+            active,
+            created_at,
+            signature: None,
+        };
+        alice.sign(&mut edge);
+        edge
+    };
+    let ids = |page: &TimelinePage| -> Vec<String> {
+        page.items.iter().map(|item| item.post.id.clone()).collect()
+    };
+
+    let alice_old = make_post(&alice, "Alice's first post", now - 3);
+    let bob_post = make_post(&bob, "Bob's post", now - 2);
+    let carol_post = make_post(&carol, "Carol is not followed", now - 1);
+    let alice_new = make_post(&alice, "Alice's second post", now);
+    for post in [&alice_old, &bob_post, &carol_post, &alice_new] {
+        save_post(post).await?;
+    }
+    save_follow_edge(&make_edge(true, now)).await?;
+
+    let alice_id = alice.user_id();
+    let page = list_home_timeline_page(&alice_id, None, 2).await?;
+    assert_eq!(ids(&page), vec![alice_new.id.clone(), bob_post.id.clone()]);
+    let cursor = page.next_cursor.expect("page is full");
+    let page = list_home_timeline_page(&alice_id, Some(&cursor), 2).await?;
+    assert_eq!(ids(&page), vec![alice_old.id.clone()]);
+    assert!(page.next_cursor.is_none());
+
+    // New and deleted posts update the materialized timeline
+    let bob_new = make_post(&bob, "Bob's second post", now + 1);
+    save_post(&bob_new).await?;
+    let page = list_home_timeline_page(&alice_id, None, 1).await?;
+    assert_eq!(ids(&page), vec![bob_new.id.clone()]);
+    delete_post(&bob_new.id).await?;
+    let page = list_home_timeline_page(&alice_id, None, 1).await?;
+    assert_eq!(ids(&page), vec![alice_new.id.clone()]);
+
+    // Unfollowing removes the user's posts
+    save_follow_edge(&make_edge(false, now + 1)).await?;
+    let page = list_home_timeline_page(&alice_id, None, 10).await?;
+    assert_eq!(ids(&page), vec![alice_new.id.clone(), alice_old.id.clone()]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_followed_post_doc_entries() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let alice = TestIdentity::new();
+    let mallory = TestIdentity::new();
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: alice.user_id(),
+        content: "Post from Alice's own document".to_string(),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    alice.sign(&mut post);
+    let post_bytes = serde_json::to_vec(&post).unwrap();
+    let hash = import_blob(post_bytes.clone()).await?;
+    let len = post_bytes.len() as u64;
+    let key = format!(
+        "post_ts:{:016x}:{}:{}",
+        (post.created_at as u64) ^ (1 << 63),
+        post.author_id,
+        post.id
+    )
+    .into_bytes();
+
+    // Another user's document cannot insert entries for Alice
+    import_followed_entry(&mallory.user_id(), &key, hash, len).await?;
+    assert!(get_post(&post.id).await?.is_none());
+
+    import_followed_entry(&alice.user_id(), &key, hash, len).await?;
+    let copied = get_post(&post.id)
+        .await?
+        .expect("Copied post should be found by ID");
+    assert_eq!(copied.content, post.content);
+
+    // A tombstone in the followed document deletes the post
+    import_followed_entry(&alice.user_id(), &key, Hash::new(b""), 0).await?;
+    assert!(get_post(&post.id).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_own_post_doc_mirrors_posts() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+    let user_id = identity.user_id();
+
+    let secret_bytes = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+    let secret = NamespaceSecret::from_bytes(&secret_bytes.try_into().unwrap());
+    let namespace_id = secret.id();
+    let ticket = open_own_post_doc(&user_id, secret).await?;
+    let ticket = DocTicket::from_str(&ticket).expect("ticket should parse");
+    assert_eq!(ticket.capability.id(), namespace_id);
+    assert!(matches!(ticket.capability, Capability::Read(_)));
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: user_id.clone(),
+        content: "Mirrored into the author's document".to_string(),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    identity.sign(&mut post);
+    save_post(&post).await?;
+
+    let doc = own_post_doc(&user_id).expect("own post document should be open");
+    let entry = doc
+        .get_one(Query::all().key_exact(format!("post_id:{}", post.id)))
+        .await
+        .unwrap();
+    assert!(entry.is_some_and(|entry| entry.content_len() > 0));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delegated_device_post_reaches_followers() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let alice = TestIdentity::new();
+    let device = TestIdentity::new();
+    let bob = TestIdentity::new();
+    let now = chrono::Utc::now().timestamp();
+
+    // The device writes to the post document derived from its own key
+    let secret_bytes: [u8; 32] = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()]
+        .concat()
+        .try_into()
+        .unwrap();
+    let device_secret = || NamespaceSecret::from_bytes(&secret_bytes);
+    let ticket = post_doc_ticket(device_secret()).await?;
+    let device_doc = get_iroh_node()
+        .docs
+        .import_namespace(Capability::Write(device_secret()))
+        .await
+        .unwrap();
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: alice.user_id(),
+        content: "Posted from a linked device".to_string(),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at: now,
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    device.sign(&mut post);
+    let key = format!(
+        "post_ts:{:016x}:{}:{}",
+        (post.created_at as u64) ^ (1 << 63),
+        post.author_id,
+        post.id
+    );
+    let author_id = author_for_user(get_iroh_node(), None).await?;
+    device_doc
+        .set_bytes(author_id, key, serde_json::to_vec(&post).unwrap())
+        .await
+        .unwrap();
+    assert!(get_post(&post.id).await?.is_none());
+
+    // Alice's profile lists the device's document in its delegation
+    let mut delegation = DeviceDelegation {
+        user_id: alice.user_id(),
+        device_key: device.public_key(),
+        device_name: "Phone".to_string(),
+        created_at: now,
+        post_doc_ticket: Some(ticket.clone()),
+        signature: None,
+    };
+    alice.sign(&mut delegation);
+    let mut profile = User {
+        id: alice.user_id(),
+        display_name: "Alice".to_string(),
+        bio: String::new(),
+        public_key: alice.public_key(),
+        avatar: None,
+        avatar_thumbnail: None,
+        following: Vec::new(),
+        followers: Vec::new(),
+        devices: vec![delegation],
+        post_doc_ticket: None,
+        approval_required: false,
+        created_at: now,
+        signature: None,
+    };
+    alice.sign(&mut profile);
+    save_user(&profile).await?;
+
+    let mut edge = FollowEdge {
+        follower_id: bob.user_id(),
+        followee_id: alice.user_id(),
+        active: true,
+        created_at: now,
+        signature: None,
+    };
+    bob.sign(&mut edge);
+    save_follow_edge(&edge).await?;
+
+    // Following Alice syncs the device's document as well
+    let stored = get_user(&alice.user_id())
+        .await?
+        .expect("Alice's profile should be stored");
+    assert_eq!(stored.post_doc_tickets(), vec![ticket.as_str()]);
+    follow_post_docs_of(&stored).await?;
+
+    let copied = get_post(&post.id)
+        .await?
+        .expect("The device's post should be copied");
+    assert_eq!(copied.content, post.content);
+    let page = list_home_timeline_page(&bob.user_id(), None, 10).await?;
+    assert!(page.items.iter().any(|item| item.post.id == post.id));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rotated_key_doc_entries_are_carried_over() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let identity = TestIdentity::new();
+    let user_id = identity.user_id();
+
+    let new_secret = || {
+        let bytes = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+        NamespaceSecret::from_bytes(&bytes.try_into().unwrap())
+    };
+    open_own_post_doc(&user_id, new_secret()).await?;
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: user_id.clone(),
+        content: "Written under the previous key".to_string(),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: Vec::new(),
+        created_at: chrono::Utc::now().timestamp(),
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Public,
+        signature: None,
+        key_revoked: false,
+    };
+    identity.sign(&mut post);
+    save_post(&post).await?;
+
+    // The document of the new key takes over the entries of the previous one
+    let previous = own_post_doc(&user_id);
+    open_own_post_doc(&user_id, new_secret()).await?;
+    let copied = carry_over_own_docs(&user_id, previous, None).await?;
+    assert!(copied > 0);
+
+    let doc = own_post_doc(&user_id).expect("own post document should be open");
+    let entry = doc
+        .get_one(Query::all().key_exact(format!("post_id:{}", post.id)))
+        .await
+        .unwrap();
+    assert!(entry.is_some_and(|entry| entry.content_len() > 0));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mute_and_block_lists() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let alice = TestIdentity::new();
+    let bob = TestIdentity::new();
+    let carol = TestIdentity::new();
+    let now = chrono::Utc::now().timestamp();
+    let tag = format!("filtered{}", Uuid::new_v4().simple());
+
+    let mut ids = Vec::new();
+    for author in [&alice, &bob, &carol] {
+        let mut post = Post {
+            id: Uuid::new_v4().to_string(),
+            author_id: author.user_id(),
+            content: format!("Post to be filtered #{}", tag),
+            attachments: Vec::new(),
+            mentions: Vec::new(),
+            hashtags: vec![tag.clone()],
+            created_at: now,
+            edited_at: None,
+            revisions: Vec::new(),
+            reply_to: None,
+            root_id: None,
+            quote_of: None,
+            visibility: Visibility::Public,
+            signature: None,
+            key_revoked: false,
+        };
+        author.sign(&mut post);
+        save_post(&post).await?;
+        ids.push(post.id);
+    }
+
+    let alice_id = alice.user_id();
+    for (target, kind) in [
+        (&bob, ModerationKind::Mute),
+        (&carol, ModerationKind::Block),
+    ] {
+        save_moderation(&ModerationEntry {
+            user_id: alice_id.clone(),
+            target_id: target.user_id(),
+            kind,
+            created_at: now,
+        })
+        .await?;
+    }
+    let muted = list_moderation(&alice_id, ModerationKind::Mute).await?;
+    assert_eq!(muted.len(), 1);
+    assert_eq!(muted[0].target_id, bob.user_id());
+    assert!(is_blocked_on_node(&carol.user_id()).await?);
+    assert!(!is_blocked_on_node(&bob.user_id()).await?);
+
+    let visible = |posts: Vec<Post>| -> Vec<String> {
+        posts
+            .into_iter()
+            .map(|post| post.id)
+            .filter(|id| ids.contains(id))
+            .collect()
+    };
+    assert_eq!(
+        visible(list_posts(Some(&alice_id)).await?),
+        vec![ids[0].clone()]
+    );
+    assert_eq!(visible(list_posts(None).await?).len(), 3);
+    let page = list_timeline_page(None, 100, Some(&alice_id)).await?;
+    let timeline = page.items.into_iter().map(|item| item.post).collect();
+    assert_eq!(visible(timeline), vec![ids[0].clone()]);
+    let tagged = list_hashtag_page(&tag, None, 10, Some(&alice_id)).await?;
+    assert_eq!(visible(tagged.posts), vec![ids[0].clone()]);
+    let bob_page = list_posts_page(Some(&bob.user_id()), None, 10, Some(&alice_id)).await?;
+    assert!(bob_page.posts.is_empty());
+    assert!(list_user_posts(&carol.user_id(), Some(&alice_id))
+        .await?
+        .is_empty());
+    let bob_page = list_posts_page(Some(&bob.user_id()), None, 10, None).await?;
+    assert_eq!(visible(bob_page.posts), vec![ids[1].clone()]);
+
+    // Unmuting shows the user's posts again
+    remove_moderation(&alice_id, ModerationKind::Mute, &bob.user_id()).await?;
+    let mut posts = visible(list_posts(Some(&alice_id)).await?);
+    posts.sort();
+    let mut expected = vec![ids[0].clone(), ids[1].clone()];
+    expected.sort();
+    assert_eq!(posts, expected);
+
+    // Unblocking lifts the block for the whole node
+    remove_moderation(&alice_id, ModerationKind::Block, &carol.user_id()).await?;
+    assert!(!is_blocked_on_node(&carol.user_id()).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_follow_requests_and_approvals() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let alice = TestIdentity::new();
+    let bob = TestIdentity::new();
+    let mallory = TestIdentity::new();
+    let now = chrono::Utc::now().timestamp();
+
+    let mut profile = User {
+        id: alice.user_id(),
+        display_name: "Private Alice".to_string(),
+        bio: String::new(),
+        public_key: alice.public_key(),
+        avatar: None,
+        avatar_thumbnail: None,
+        following: Vec::new(),
+        followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: true,
+        created_at: now,
+        signature: None,
+    };
+    alice.sign(&mut profile);
+    save_user(&profile).await?;
+    assert!(requires_approval(&alice.user_id()).await?);
+
+    let secret_bytes = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+    let bob_secret = StaticSecret::from(<[u8; 32]>::try_from(secret_bytes).unwrap());
+    let mut edge = FollowEdge {
+        follower_id: bob.user_id(),
+        followee_id: alice.user_id(),
+        active: true,
+        created_at: now,
+        signature: None,
+    };
+    bob.sign(&mut edge);
+    let mut request = FollowRequest {
+        edge: edge.clone(),
+        agreement_key: agreement_public_key(&bob_secret),
+        signature: None,
+    };
+    bob.sign(&mut request);
+
+    // Following a user who requires approval waits for the approval
+    assert!(apply_remote_request(&request).await?);
+    assert!(!apply_remote_request(&request).await?);
+    assert!(!apply_remote_edge(&edge).await?);
+    assert!(!is_following(&bob.user_id(), &alice.user_id()).await?);
+    let pending = list_follow_requests(&alice.user_id()).await?;
+    assert_eq!(pending, vec![request.clone()]);
+
+    // A request signed by someone else than the follower is refused
+    let mut forged = request.clone();
+    mallory.sign(&mut forged);
+    assert!(apply_remote_request(&forged).await.is_err());
+
+    let context = FollowApproval::ticket_context(&edge);
+    let mut approval = FollowApproval {
+        edge: edge.clone(),
+        ticket: seal_to(&request.agreement_key, b"follower ticket", &context).unwrap(),
+        created_at: now,
+        signature: None,
+    };
+    alice.sign(&mut approval);
+    assert!(apply_remote_approval(&approval).await?);
+    assert!(is_following(&bob.user_id(), &alice.user_id()).await?);
+    assert!(list_follow_requests(&alice.user_id()).await?.is_empty());
+    assert_eq!(
+        get_follow_approval(&bob.user_id(), &alice.user_id()).await?,
+        Some(approval.clone())
+    );
+
+    // Only the follower can open the ticket, and only for the approved edge
+    let opened = open_with(&bob_secret, &approval.ticket, &context).unwrap();
+    assert_eq!(&opened[..], b"follower ticket");
+    let other_edge = FollowEdge {
+        created_at: now + 1,
+        ..edge.clone()
+    };
+    let other_context = FollowApproval::ticket_context(&other_edge);
+    assert!(open_with(&bob_secret, &approval.ticket, &other_context).is_err());
+
+    // A later follow without its own approval is refused
+    let mut unfollow = FollowEdge {
+        active: false,
+        created_at: now + 1,
+        ..edge.clone()
+    };
+    bob.sign(&mut unfollow);
+    assert!(apply_remote_edge(&unfollow).await?);
+    let mut refollow = FollowEdge {
+        created_at: now + 2,
+        ..edge
+    };
+    bob.sign(&mut refollow);
+    assert!(!apply_remote_edge(&refollow).await?);
+    assert!(!is_following(&bob.user_id(), &alice.user_id()).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_followers_only_posts_are_hidden_from_other_viewers() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let alice = TestIdentity::new();
+    let bob = TestIdentity::new();
+    let carol = TestIdentity::new();
+    let now = chrono::Utc::now().timestamp();
+    let tag = format!("private{}", Uuid::new_v4().simple());
+
+    let mut profile = User {
+        id: alice.user_id(),
+        display_name: "Private Alice".to_string(),
+        bio: String::new(),
+        public_key: alice.public_key(),
+        avatar: None,
+        avatar_thumbnail: None,
+        following: Vec::new(),
+        followers: Vec::new(),
+        devices: Vec::new(),
+        post_doc_ticket: None,
+        approval_required: true,
+        created_at: now,
+        signature: None,
+    };
+    alice.sign(&mut profile);
+    save_user(&profile).await?;
+
+    let secret_bytes = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+    let secret = NamespaceSecret::from_bytes(&secret_bytes.try_into().unwrap());
+    open_own_follower_doc(&alice.user_id(), secret).await?;
+
+    let mut post = Post {
+        id: Uuid::new_v4().to_string(),
+        author_id: alice.user_id(),
+        content: format!("For my followers #{}", tag),
+        attachments: Vec::new(),
+        mentions: Vec::new(),
+        hashtags: vec![tag.clone()],
+        created_at: now,
+        edited_at: None,
+        revisions: Vec::new(),
+        reply_to: None,
+        root_id: None,
+        quote_of: None,
+        visibility: Visibility::Followers,
+        signature: None,
+        key_revoked: false,
+    };
+    alice.sign(&mut post);
+    save_post(&post).await?;
+
+    // Bob follows Alice with her approval; Carol does not follow her
+    let mut edge = FollowEdge {
+        follower_id: bob.user_id(),
+        followee_id: alice.user_id(),
+        active: true,
+        created_at: now,
+        signature: None,
+    };
+    bob.sign(&mut edge);
+    let secret_bytes = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+    let bob_secret = StaticSecret::from(<[u8; 32]>::try_from(secret_bytes).unwrap());
+    let context = FollowApproval::ticket_context(&edge);
+    let ticket = seal_to(&agreement_public_key(&bob_secret), b"ticket", &context).unwrap();
+    let mut approval = FollowApproval {
+        edge,
+        ticket,
+        created_at: now,
+        signature: None,
+    };
+    alice.sign(&mut approval);
+    assert!(apply_remote_approval(&approval).await?);
+    assert!(is_approved_follower(&bob.user_id(), &alice.user_id()).await?);
+    assert!(!is_approved_follower(&carol.user_id(), &alice.user_id()).await?);
+
+    let alice_id = alice.user_id();
+    let bob_id = bob.user_id();
+    let carol_id = carol.user_id();
+    let contains = |posts: &[Post]| posts.iter().any(|listed| listed.id == post.id);
+
+    for reader in [Some(alice_id.as_str()), Some(bob_id.as_str())] {
+        assert!(contains(&list_posts(reader).await?));
+        let page = list_posts_page(Some(&alice_id), None, 10, reader).await?;
+        assert!(contains(&page.posts));
+        let tagged = list_hashtag_page(&tag, None, 10, reader).await?;
+        assert!(contains(&tagged.posts));
+        assert!(get_visible_post(&post.id, reader).await?.is_some());
+        assert!(get_post_revisions(&post.id, reader).await?.is_some());
+    }
+    for reader in [Some(carol_id.as_str()), None] {
+        assert!(!contains(&list_posts(reader).await?));
+        let page = list_posts_page(Some(&alice_id), None, 10, reader).await?;
+        assert!(!contains(&page.posts));
+        let tagged = list_hashtag_page(&tag, None, 10, reader).await?;
+        assert!(!contains(&tagged.posts));
+        let timeline = list_timeline_page(None, 100, reader).await?;
+        assert!(timeline.items.iter().all(|item| item.post.id != post.id));
+        assert!(get_visible_post(&post.id, reader).await?.is_none());
+        assert!(get_post_revisions(&post.id, reader).await?.is_none());
+    }
+
+    // Followers-only posts are kept out of the search index shared by the node
+    assert!(search_posts(&tag, 10, Some(&alice_id)).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_recover_identity_after_key_rotation() -> Result<(), StorageError> {
+    let _ = env_logger::try_init();
+    setup_test_environment().await?;
+    let alice = TestIdentity::new();
+
+    // Rotate Alice's key: the rotation is signed with the key her ID derives from
+    let rng = ring::rand::SystemRandom::new();
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let new_key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+    let mut rotation = KeyRotation {
+        user_id: alice.user_id(),
+        sequence: 1,
+        previous_key: alice.public_key(),
+        new_key: general_purpose::STANDARD.encode(new_key_pair.public_key().as_ref()),
+        created_at: chrono::Utc::now().timestamp(),
+        signature: None,
+    };
+    alice.sign(&mut rotation);
+    save_key_rotation(&rotation).await?;
+
+    // Export the rotated key as a recovery phrase and recover it
+    let mnemonic = seed::mnemonic_from_pkcs8(pkcs8.as_ref()).unwrap();
+    let recovered = seed::pkcs8_from_mnemonic(&mnemonic).unwrap();
+    let recovered_key_pair = Ed25519KeyPair::from_pkcs8(&recovered).unwrap();
+    let user_id = resolve_recovered_user(recovered_key_pair.public_key().as_ref())
+        .await
+        .unwrap();
+    assert_eq!(user_id, alice.user_id());
+
+    // The phrase of the rotated-out key no longer recovers the identity
+    let previous_key = general_purpose::STANDARD
+        .decode(alice.public_key())
+        .unwrap();
+    assert!(resolve_recovered_user(&previous_key).await.is_err());
+
+    // A rotation not signed by the chain does not claim the key
+    let mallory = TestIdentity::new();
+    let rng = ring::rand::SystemRandom::new();
+    let stolen_pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+    let stolen = Ed25519KeyPair::from_pkcs8(stolen_pkcs8.as_ref()).unwrap();
+    let mut forged = KeyRotation {
+        user_id: alice.user_id(),
+        sequence: 2,
+        previous_key: rotation.new_key.clone(),
+        new_key: general_purpose::STANDARD.encode(stolen.public_key().as_ref()),
+        created_at: chrono::Utc::now().timestamp(),
+        signature: None,
+    };
+    mallory.sign(&mut forged);
+    save_key_rotation(&forged).await?;
+    assert_ne!(
+        resolve_recovered_user(stolen.public_key().as_ref())
+            .await
+            .unwrap(),
+        alice.user_id()
+    );
+
+    Ok(())
+}
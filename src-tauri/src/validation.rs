@@ -0,0 +1,253 @@
+//! 入力検証
+//!
+//! コマンドが受け取る文字列の正規化と検証を行います。文字数はバイト数ではなく
+//! 書記素クラスタ（見た目の1文字）の数で数えるため、日本語や絵文字も1文字として扱います。
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// 投稿本文の最大文字数
+pub const MAX_POST_LENGTH: usize = 500;
+
+/// 表示名の最大文字数
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 50;
+
+/// 自己紹介の最大文字数
+pub const MAX_BIO_LENGTH: usize = 160;
+
+/// 検索クエリの最大文字数
+pub const MAX_SEARCH_QUERY_LENGTH: usize = 100;
+
+/// 入力検証エラー
+///
+/// どのフィールドがどの規則に違反したかを示します。フロントエンドには
+/// `{ field, kind, message }` の形でシリアライズされます。
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    /// 空の値
+    #[error("{field} cannot be empty")]
+    Empty { field: &'static str },
+
+    /// 最大文字数の超過
+    #[error("{field} exceeds maximum length of {max} characters")]
+    TooLong {
+        field: &'static str,
+        max: usize,
+        length: usize,
+    },
+
+    /// その他の不正な値
+    #[error("{field} is invalid: {reason}")]
+    Invalid { field: &'static str, reason: String },
+}
+
+impl ValidationError {
+    /// 不正な値のエラーを作成します。
+    pub fn invalid(field: &'static str, reason: impl Into<String>) -> Self {
+        ValidationError::Invalid {
+            field,
+            reason: reason.into(),
+        }
+    }
+
+    /// 違反したフィールドの名前
+    pub fn field(&self) -> &'static str {
+        match self {
+            ValidationError::Empty { field }
+            | ValidationError::TooLong { field, .. }
+            | ValidationError::Invalid { field, .. } => field,
+        }
+    }
+
+    /// 違反した規則の種類
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ValidationError::Empty { .. } => "empty",
+            ValidationError::TooLong { .. } => "too_long",
+            ValidationError::Invalid { .. } => "invalid",
+        }
+    }
+}
+
+impl Serialize for ValidationError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("ValidationError", 3)?;
+        state.serialize_field("field", self.field())?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// 文字列フィールドの検証規則
+#[derive(Debug, Clone, Copy)]
+pub struct TextField {
+    /// エラーに含めるフィールド名
+    pub name: &'static str,
+    /// 最大文字数（書記素クラスタの数）
+    pub max_length: usize,
+    /// 改行とタブを許可するかどうか（許可しない場合は前後の空白も取り除く）
+    pub multiline: bool,
+    /// 空白だけの値を拒否するかどうか
+    pub required: bool,
+}
+
+/// 投稿本文
+pub const POST_CONTENT: TextField = TextField {
+    name: "content",
+    max_length: MAX_POST_LENGTH,
+    multiline: true,
+    required: true,
+};
+
+/// 表示名
+pub const DISPLAY_NAME: TextField = TextField {
+    name: "display_name",
+    max_length: MAX_DISPLAY_NAME_LENGTH,
+    multiline: false,
+    required: true,
+};
+
+/// 自己紹介
+pub const BIO: TextField = TextField {
+    name: "bio",
+    max_length: MAX_BIO_LENGTH,
+    multiline: true,
+    required: false,
+};
+
+/// 検索クエリ
+pub const SEARCH_QUERY: TextField = TextField {
+    name: "query",
+    max_length: MAX_SEARCH_QUERY_LENGTH,
+    multiline: false,
+    required: true,
+};
+
+impl TextField {
+    /// 空でも構わない同じ規則を返します。
+    pub const fn optional(self) -> Self {
+        TextField {
+            required: false,
+            ..self
+        }
+    }
+
+    /// 値を正規化して検証し、保存する値を返します。
+    ///
+    /// NFC に正規化し、制御文字（複数行の場合の改行とタブを除く）を取り除いてから
+    /// 文字数を数えます。
+    pub fn clean(&self, value: &str) -> Result<String, ValidationError> {
+        let normalized = normalize_text(value, self.multiline);
+        let cleaned = if self.multiline {
+            normalized
+        } else {
+            normalized.trim().to_string()
+        };
+
+        if self.required && cleaned.trim().is_empty() {
+            return Err(ValidationError::Empty { field: self.name });
+        }
+        let length = count_characters(&cleaned);
+        if length > self.max_length {
+            return Err(ValidationError::TooLong {
+                field: self.name,
+                max: self.max_length,
+                length,
+            });
+        }
+
+        Ok(cleaned)
+    }
+}
+
+/// 文字列を NFC に正規化し、制御文字を取り除きます。
+///
+/// `multiline` の場合は改行とタブを残し、CRLF と CR は LF に揃えます。
+pub fn normalize_text(value: &str, multiline: bool) -> String {
+    let unified = value.replace("\r\n", "\n").replace('\r', "\n");
+    unified
+        .nfc()
+        .filter(|&c| !c.is_control() || (multiline && (c == '\n' || c == '\t')))
+        .collect()
+}
+
+/// 書記素クラスタの数を数えます。
+pub fn count_characters(value: &str) -> usize {
+    value.graphemes(true).count()
+}
+
+/// 件数の上限を検証します。
+pub fn check_count(field: &'static str, count: usize, max: usize) -> Result<(), ValidationError> {
+    if count > max {
+        return Err(ValidationError::invalid(
+            field,
+            format!("at most {} items are allowed", max),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_characters_not_bytes() {
+        let japanese = "あ".repeat(MAX_POST_LENGTH);
+        assert_eq!(POST_CONTENT.clean(&japanese), Ok(japanese.clone()));
+        assert_eq!(
+            POST_CONTENT.clean(&format!("{}い", japanese)),
+            Err(ValidationError::TooLong {
+                field: "content",
+                max: MAX_POST_LENGTH,
+                length: MAX_POST_LENGTH + 1,
+            })
+        );
+
+        // A family emoji and a flag are single characters
+        assert_eq!(count_characters("👨‍👩‍👧🇯🇵"), 2);
+        // A decomposed character counts once
+        assert_eq!(count_characters("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn test_normalizes_and_strips_control_characters() {
+        assert_eq!(normalize_text("e\u{301}", false), "\u{e9}");
+        assert_eq!(normalize_text("a\u{0}b\u{1b}c\r\nd\te", true), "abc\nd\te");
+        assert_eq!(normalize_text("a\nb\tc", false), "abc");
+        assert_eq!(DISPLAY_NAME.clean("  Alice\n "), Ok("Alice".to_string()));
+    }
+
+    #[test]
+    fn test_empty_values() {
+        assert_eq!(
+            POST_CONTENT.clean(" \n\u{7}"),
+            Err(ValidationError::Empty { field: "content" })
+        );
+        assert_eq!(POST_CONTENT.optional().clean(""), Ok(String::new()));
+        assert_eq!(BIO.clean(""), Ok(String::new()));
+    }
+
+    #[test]
+    fn test_serializes_field_and_kind() {
+        let error = ValidationError::TooLong {
+            field: "bio",
+            max: MAX_BIO_LENGTH,
+            length: 200,
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "field": "bio",
+                "kind": "too_long",
+                "message": "bio exceeds maximum length of 160 characters",
+            })
+        );
+    }
+}
@@ -1,3 +1,4 @@
+import { useEffect } from "react";
 import { BrowserRouter as Router, Routes, Route } from "react-router-dom";
 import { QueryClient, QueryClientProvider } from "@tanstack/react-query";
 import { ThemeProvider } from "./components/theme-provider";
@@ -21,7 +22,12 @@ const queryClient = new QueryClient({
 });
 
 function App() {
-  const { isAuthenticated } = useAuthStore();
+  const { isAuthenticated, restoreSession } = useAuthStore();
+
+  // Restore the backend session after a frontend reload
+  useEffect(() => {
+    restoreSession();
+  }, [restoreSession]);
   
   // Set up document event listeners for real-time updates
   useDocumentEvents();
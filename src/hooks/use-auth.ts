@@ -27,7 +27,7 @@ export function useAuth() {
     try {
       const result = await createUser(input);
       if (result.success) {
-        await storeCreateUser(input.displayName, input.bio);
+        await storeCreateUser(input.displayName, input.passphrase, input.bio);
         return true;
       } else {
         toast({
@@ -51,11 +51,11 @@ export function useAuth() {
   /**
    * 既存のユーザーでサインインします
    */
-  const handleSignIn = useCallback(async (userId: string) => {
+  const handleSignIn = useCallback(async (userId: string, passphrase: string) => {
     try {
-      const result = await signIn(userId);
+      const result = await signIn(userId, passphrase);
       if (result.success) {
-        await storeSignIn(userId);
+        await storeSignIn(userId, passphrase);
         return true;
       } else {
         toast({
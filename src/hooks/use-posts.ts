@@ -13,7 +13,7 @@ import { DEFAULT_PAGE_SIZE } from '@/lib/constants';
  */
 export function usePosts() {
   const { 
-    posts, 
+    timeline, 
     userPosts, 
     isLoading, 
     error,
@@ -28,9 +28,9 @@ export function usePosts() {
   /**
    * 投稿を取得します
    */
-  const fetchPosts = useCallback(async (limit = DEFAULT_PAGE_SIZE, offset = 0) => {
+  const fetchPosts = useCallback(async (limit = DEFAULT_PAGE_SIZE, cursor: string | null = null) => {
     try {
-      await storeFetchPosts(limit, offset);
+      await storeFetchPosts(limit, cursor);
       return true;
     } catch (error) {
       console.error('Error fetching posts:', error);
@@ -46,9 +46,9 @@ export function usePosts() {
   /**
    * 特定のユーザーの投稿を取得します
    */
-  const fetchUserPosts = useCallback(async (userId: string, limit = DEFAULT_PAGE_SIZE, offset = 0) => {
+  const fetchUserPosts = useCallback(async (userId: string, limit = DEFAULT_PAGE_SIZE, cursor: string | null = null) => {
     try {
-      await storeFetchUserPosts(userId, limit, offset);
+      await storeFetchUserPosts(userId, limit, cursor);
       return true;
     } catch (error) {
       console.error('Error fetching user posts:', error);
@@ -75,7 +75,7 @@ export function usePosts() {
     }
 
     try {
-      const result = await createPost(user.id, input);
+      const result = await createPost(input);
       if (result.success) {
         await storeCreatePost(input.content);
         return true;
@@ -118,15 +118,15 @@ export function usePosts() {
   /**
    * 現在のユーザーの投稿を取得します
    */
-  const fetchCurrentUserPosts = useCallback(async (limit = DEFAULT_PAGE_SIZE, offset = 0) => {
+  const fetchCurrentUserPosts = useCallback(async (limit = DEFAULT_PAGE_SIZE, cursor: string | null = null) => {
     if (!user) {
       return false;
     }
-    return fetchUserPosts(user.id, limit, offset);
+    return fetchUserPosts(user.id, limit, cursor);
   }, [user, fetchUserPosts]);
 
   return {
-    posts,
+    timeline,
     userPosts,
     isLoading,
     error,
@@ -14,6 +14,16 @@ export const MAX_PAGE_SIZE = 100;
 export const MAX_POST_LENGTH = 500;
 export const MAX_SEARCH_QUERY_LENGTH = 100;
 
+// リアクションの種類と表示する絵文字（バックエンドの ReactionKind と同じ順序）
+export const REACTION_EMOJI = {
+  like: "👍",
+  heart: "❤️",
+  laugh: "😂",
+  surprised: "😮",
+  sad: "😢",
+  celebrate: "🎉",
+} as const;
+
 // ユーザープロフィール
 export const MAX_DISPLAY_NAME_LENGTH = 50;
 export const MAX_BIO_LENGTH = 160;
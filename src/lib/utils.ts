@@ -1,6 +1,40 @@
 import { type ClassValue, clsx } from "clsx";
 import { twMerge } from "tailwind-merge";
+import { z } from "zod";
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
-}
\ No newline at end of file
+}
+
+// Intl.Segmenter は ES2022 の型定義に含まれるため、使用する部分だけ型を宣言する
+type GraphemeSegmenter = { segment(input: string): Iterable<unknown> };
+const Segmenter = (
+  Intl as unknown as {
+    Segmenter?: new (locale?: string, options?: { granularity: "grapheme" }) => GraphemeSegmenter;
+  }
+).Segmenter;
+
+/**
+ * バックエンドと同じく、NFC に正規化した文字列の書記素クラスタ（見た目の1文字）の数を数えます
+ *
+ * Intl.Segmenter が無い環境ではコードポイントの数で代用します。
+ */
+export function countCharacters(value: string): number {
+  const normalized = value.normalize("NFC");
+  if (!Segmenter) {
+    return Array.from(normalized).length;
+  }
+  return Array.from(new Segmenter(undefined, { granularity: "grapheme" }).segment(normalized)).length;
+}
+
+/**
+ * 文字数の上限を書記素クラスタで数える文字列のZodスキーマを作成します
+ *
+ * `required` の場合は空白だけの文字列を拒否します。
+ */
+export function textSchema(max: number, { required = false }: { required?: boolean } = {}) {
+  return z
+    .string()
+    .refine((value) => !required || value.trim().length > 0, { message: "Must not be empty" })
+    .refine((value) => countCharacters(value) <= max, { message: `Must be at most ${max} characters` });
+}
@@ -1,30 +1,138 @@
 import { z } from "zod";
+import { userIdSchema } from "./user";
+import { MAX_POST_LENGTH, MAX_SEARCH_QUERY_LENGTH } from "@/lib/constants";
+import { textSchema } from "@/lib/utils";
+
+// 添付ファイルのメタデータのZodスキーマ
+export const attachmentSchema = z.object({
+  hash: z.string(),
+  mimeType: z.string(),
+  size: z.number().nonnegative(),
+  width: z.number().nullable().optional(),
+  height: z.number().nullable().optional(),
+  name: z.string().nullable().optional(),
+});
+
+export type Attachment = z.infer<typeof attachmentSchema>;
+
+// 添付画像のプレビュー用の縮小画像
+export const previewImageSchema = z.object({
+  hash: z.string(),
+  mimeType: z.string(),
+  width: z.number(),
+  height: z.number(),
+});
+
+export type PreviewImage = z.infer<typeof previewImageSchema>;
+
+// 添付画像のプレビュー（縮小画像は小さい順、blurhash は読み込み中のプレースホルダー）
+export const attachmentPreviewSchema = z.object({
+  original: z.string(),
+  width: z.number(),
+  height: z.number(),
+  blurhash: z.string(),
+  thumbnails: z.array(previewImageSchema).default([]),
+});
+
+export type AttachmentPreview = z.infer<typeof attachmentPreviewSchema>;
+
+// 投稿の編集前の版のZodスキーマ
+export const postRevisionSchema = z.object({
+  hash: z.string(),
+  writtenAt: z.number(),
+});
+
+export type PostRevision = z.infer<typeof postRevisionSchema>;
+
+// 投稿の公開範囲（followers は承認したフォロワーだけに共有）
+export const visibilitySchema = z.enum(["public", "followers"]);
+
+export type Visibility = z.infer<typeof visibilitySchema>;
 
 // 投稿のZodスキーマ
 export const postSchema = z.object({
   id: z.string().uuid(),
-  authorId: z.string().uuid(),
-  content: z.string().min(1).max(500),
-  attachments: z.array(z.string()).default([]),
+  authorId: userIdSchema,
+  content: textSchema(MAX_POST_LENGTH, { required: true }),
+  attachments: z.array(attachmentSchema).default([]),
   mentions: z.array(z.string()).default([]),
   hashtags: z.array(z.string()).default([]),
   createdAt: z.number(),
+  // 最後に編集された日時と編集前の版（古い順）
+  editedAt: z.number().nullable().optional(),
+  revisions: z.array(postRevisionSchema).default([]),
+  // 返信先の投稿IDとスレッドの最初の投稿のID（返信でない投稿は null）
+  replyTo: z.string().nullable().optional(),
+  rootId: z.string().nullable().optional(),
+  // 引用した投稿のID
+  quoteOf: z.string().nullable().optional(),
+  visibility: visibilitySchema.default("public"),
+  // 失効した鍵で署名された投稿
+  keyRevoked: z.boolean().default(false),
 });
 
 // 型定義の抽出
 export type Post = z.infer<typeof postSchema>;
 
+// スレッド内の返信（parentMissing は返信先がまだ同期されていないことを示す）
+export interface ThreadNode {
+  post: Post;
+  parentMissing: boolean;
+  replies: ThreadNode[];
+}
+
+// スレッド（root は最初の投稿、まだ同期されていない場合は null）
+export interface Thread {
+  rootId: string;
+  root: Post | null;
+  replies: ThreadNode[];
+}
+
+// リアクションの種類
+export const reactionKindSchema = z.enum(["like", "heart", "laugh", "surprised", "sad", "celebrate"]);
+
+export type ReactionKind = z.infer<typeof reactionKindSchema>;
+
+// 投稿へのリアクションの集計（mine は閲覧中のユーザーがしたリアクション）
+export interface ReactionSummary {
+  counts: Partial<Record<ReactionKind, number>>;
+  mine: ReactionKind[];
+}
+
+// タイムラインの項目（リポストされた投稿は最新のリポストの位置に一度だけ含まれる）
+export interface TimelineItem {
+  post: Post;
+  // 引用した投稿（同期されていない場合は null）
+  quoted?: Post | null;
+  // リポストしたユーザーのID（新しい順）
+  repostedBy: string[];
+  repostedAt?: number | null;
+  reactions: ReactionSummary;
+}
+
+// タイムラインのページ
+export interface TimelinePage {
+  items: TimelineItem[];
+  nextCursor?: string | null;
+}
+
+// 投稿のページ（nextCursor は次のページを取得するためのカーソル）
+export interface PostPage {
+  posts: Post[];
+  nextCursor?: string | null;
+}
+
 // 投稿作成のためのスキーマ
 export const createPostSchema = z.object({
-  content: z.string().min(1).max(500),
-  attachments: z.array(z.string()).optional(),
+  content: textSchema(MAX_POST_LENGTH, { required: true }),
+  visibility: visibilitySchema.optional(),
 });
 
 export type CreatePostInput = z.infer<typeof createPostSchema>;
 
 // 投稿検索のためのスキーマ
 export const searchPostsSchema = z.object({
-  query: z.string().min(1).max(100),
+  query: textSchema(MAX_SEARCH_QUERY_LENGTH, { required: true }),
   limit: z.number().positive().max(100).optional(),
 });
 
@@ -1,8 +1,9 @@
 import { z } from "zod";
+import { userIdSchema } from "./user";
 
 // アプリケーション設定のZodスキーマ
 export const settingsSchema = z.object({
-  userId: z.string().uuid().optional(),
+  userId: userIdSchema.optional(),
   selectedRelays: z.array(z.string().url()).default([]),
   theme: z.enum(["light", "dark", "system"]).default("system"),
   language: z.string().default("ja"),
@@ -1,13 +1,36 @@
 import { z } from "zod";
+import { MAX_BIO_LENGTH, MAX_DISPLAY_NAME_LENGTH } from "@/lib/constants";
+import { textSchema } from "@/lib/utils";
+
+// ユーザーID（Ed25519公開鍵のbase32エンコード）のZodスキーマ
+export const userIdSchema = z.string().regex(/^[a-z2-7]{52}$/);
+
+// 端末の委任のZodスキーマ
+export const deviceDelegationSchema = z.object({
+  userId: userIdSchema,
+  deviceKey: z.string(),
+  deviceName: z.string(),
+  createdAt: z.number(),
+  // 端末の鍵の投稿ドキュメントの読み取り専用チケット
+  postDocTicket: z.string().nullable().optional(),
+});
 
 // ユーザープロフィールのZodスキーマ
 export const userSchema = z.object({
-  id: z.string().uuid(),
-  displayName: z.string().min(1).max(50),
-  bio: z.string().max(160).default(""),
+  id: userIdSchema,
+  displayName: textSchema(MAX_DISPLAY_NAME_LENGTH, { required: true }),
+  bio: textSchema(MAX_BIO_LENGTH).default(""),
+  // アバター画像と縮小画像の blob ハッシュ
   avatar: z.string().nullable().optional(),
+  avatarThumbnail: z.string().nullable().optional(),
   following: z.array(z.string()).default([]),
   followers: z.array(z.string()).default([]),
+  // この端末の鍵から委任された他の端末
+  devices: z.array(deviceDelegationSchema).optional(),
+  // 投稿ドキュメントの読み取り専用チケット
+  postDocTicket: z.string().nullable().optional(),
+  // フォローに承認が必要かどうか
+  approvalRequired: z.boolean().default(false),
   createdAt: z.number(),
   updatedAt: z.number().optional(),
 });
@@ -16,18 +39,27 @@ export const userSchema = z.object({
 export type User = z.infer<typeof userSchema>;
 
 // ユーザープロフィール作成のためのスキーマ
+// キーストアのパスフレーズ（バックエンドの MIN_PASSPHRASE_LENGTH と合わせる）
+export const passphraseSchema = z.string().min(8);
+
 export const createUserSchema = z.object({
-  displayName: z.string().min(1).max(50),
-  bio: z.string().max(160).optional(),
+  displayName: textSchema(MAX_DISPLAY_NAME_LENGTH, { required: true }),
+  bio: textSchema(MAX_BIO_LENGTH).optional(),
+  passphrase: passphraseSchema,
 });
 
 export type CreateUserInput = z.infer<typeof createUserSchema>;
 
 // プロフィール更新のためのスキーマ
 export const updateProfileSchema = z.object({
-  displayName: z.string().min(1).max(50).optional(),
-  bio: z.string().max(160).optional(),
-  avatar: z.string().optional(),
+  displayName: textSchema(MAX_DISPLAY_NAME_LENGTH, { required: true }).optional(),
+  bio: textSchema(MAX_BIO_LENGTH).optional(),
+  approvalRequired: z.boolean().optional(),
 });
 
-export type UpdateProfileInput = z.infer<typeof updateProfileSchema>;
\ No newline at end of file
+export type UpdateProfileInput = z.infer<typeof updateProfileSchema>;
+// フォロー数とフォロワー数
+export interface FollowCounts {
+  following: number;
+  followers: number;
+}
@@ -4,16 +4,29 @@ import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
 import { useAuthStore } from '@/stores/auth-store';
 import { toast } from '@/hooks/use-toast';
+import {
+  completeDeviceLink,
+  importIdentity,
+  recoverIdentity,
+  requestDeviceLink,
+  DeviceLinkTicket,
+} from '@/services/auth-service';
 
-type AuthMode = 'login' | 'register';
+type AuthMode = 'login' | 'register' | 'import' | 'link';
 
 const AuthPage: React.FC = () => {
   const [mode, setMode] = useState<AuthMode>('login');
   const [displayName, setDisplayName] = useState('');
   const [bio, setBio] = useState('');
+  const [passphrase, setPassphrase] = useState('');
   const [existingUsers, setExistingUsers] = useState<{ id: string; displayName: string }[]>([]);
   const [selectedUserId, setSelectedUserId] = useState<string | null>(null);
-  const { createUser, signIn, isLoading } = useAuthStore();
+  const [backupFile, setBackupFile] = useState<File | null>(null);
+  const [recoveryPhrase, setRecoveryPhrase] = useState('');
+  const [deviceName, setDeviceName] = useState('');
+  const [linkRequest, setLinkRequest] = useState<DeviceLinkTicket | null>(null);
+  const [approvalTicket, setApprovalTicket] = useState('');
+  const { createUser, signIn, restoreSession, isLoading } = useAuthStore();
 
   // Fetch existing users on component mount
   useEffect(() => {
@@ -44,7 +57,16 @@ const AuthPage: React.FC = () => {
       return;
     }
 
-    await createUser(displayName, bio);
+    if (passphrase.length < 8) {
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: 'Passphrase must be at least 8 characters',
+      });
+      return;
+    }
+
+    await createUser(displayName, passphrase, bio);
   };
 
   const handleLogin = async (e: React.FormEvent) => {
@@ -58,7 +80,62 @@ const AuthPage: React.FC = () => {
       return;
     }
 
-    await signIn(selectedUserId);
+    await signIn(selectedUserId, passphrase);
+  };
+
+  const handleImport = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!backupFile && !recoveryPhrase.trim()) {
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: 'Select a backup file or enter a recovery phrase',
+      });
+      return;
+    }
+
+    try {
+      // バックアップファイルを優先し、無ければリカバリーフレーズから復元
+      const result = backupFile
+        ? await importIdentity(await backupFile.text(), passphrase)
+        : await recoverIdentity(recoveryPhrase, passphrase, displayName.trim() || undefined);
+
+      await signIn(result.userId, passphrase);
+    } catch (error) {
+      toast({
+        variant: 'destructive',
+        title: 'Import failed',
+        description: error instanceof Error ? error.message : String(error),
+      });
+    }
+  };
+
+  const handleRequestLink = async (e: React.FormEvent) => {
+    e.preventDefault();
+    try {
+      setLinkRequest(await requestDeviceLink(deviceName.trim() || 'New device', passphrase));
+    } catch (error) {
+      toast({
+        variant: 'destructive',
+        title: 'Link failed',
+        description: error instanceof Error ? error.message : String(error),
+      });
+    }
+  };
+
+  const handleCompleteLink = async (e: React.FormEvent) => {
+    e.preventDefault();
+    try {
+      // リンクが完了するとバックエンドでセッションが開始される
+      await completeDeviceLink(approvalTicket, passphrase);
+      await restoreSession();
+    } catch (error) {
+      toast({
+        variant: 'destructive',
+        title: 'Link failed',
+        description: error instanceof Error ? error.message : String(error),
+      });
+    }
   };
 
   return (
@@ -93,11 +170,118 @@ const AuthPage: React.FC = () => {
                     disabled={isLoading}
                   />
                 </div>
+                <div className="grid gap-2">
+                  <Input
+                    id="passphrase"
+                    type="password"
+                    placeholder="Passphrase (8+ characters)"
+                    value={passphrase}
+                    onChange={(e) => setPassphrase(e.target.value)}
+                    disabled={isLoading}
+                    required
+                  />
+                </div>
                 <Button type="submit" disabled={isLoading}>
                   {isLoading ? 'Creating Account...' : 'Create Account'}
                 </Button>
               </div>
             </form>
+          ) : mode === 'link' ? (
+            linkRequest ? (
+              <form onSubmit={handleCompleteLink}>
+                <div className="grid gap-4">
+                  <p className="text-sm">
+                    Approve this ticket in Settings on a device that is already signed in,
+                    and check that it shows the code <span className="font-mono font-medium">{linkRequest.code}</span>.
+                  </p>
+                  <p className="p-2 bg-card rounded text-xs font-mono break-all">{linkRequest.ticket}</p>
+                  <Input
+                    id="approvalTicket"
+                    placeholder="Approval ticket"
+                    value={approvalTicket}
+                    onChange={(e) => setApprovalTicket(e.target.value)}
+                    disabled={isLoading}
+                    required
+                  />
+                  <Button type="submit" disabled={isLoading || !approvalTicket}>
+                    Complete Link
+                  </Button>
+                </div>
+              </form>
+            ) : (
+              <form onSubmit={handleRequestLink}>
+                <div className="grid gap-4">
+                  <Input
+                    id="deviceName"
+                    placeholder="Device name (e.g. Laptop)"
+                    value={deviceName}
+                    onChange={(e) => setDeviceName(e.target.value)}
+                    disabled={isLoading}
+                  />
+                  <Input
+                    id="linkPassphrase"
+                    type="password"
+                    placeholder="Passphrase for this device (8+ characters)"
+                    value={passphrase}
+                    onChange={(e) => setPassphrase(e.target.value)}
+                    disabled={isLoading}
+                    required
+                  />
+                  <Button type="submit" disabled={isLoading || passphrase.length < 8}>
+                    Create Link Ticket
+                  </Button>
+                </div>
+              </form>
+            )
+          ) : mode === 'import' ? (
+            <form onSubmit={handleImport}>
+              <div className="grid gap-4">
+                <div className="grid gap-2">
+                  <p className="text-sm">Backup file</p>
+                  <Input
+                    id="backupFile"
+                    type="file"
+                    accept="application/json,.json"
+                    onChange={(e) => setBackupFile(e.target.files?.[0] ?? null)}
+                    disabled={isLoading}
+                  />
+                </div>
+                <div className="grid gap-2">
+                  <Input
+                    id="recoveryPhrase"
+                    placeholder="...or 24-word recovery phrase"
+                    value={recoveryPhrase}
+                    onChange={(e) => setRecoveryPhrase(e.target.value)}
+                    disabled={isLoading || !!backupFile}
+                  />
+                </div>
+                {!backupFile && recoveryPhrase && (
+                  <div className="grid gap-2">
+                    <Input
+                      id="recoveryDisplayName"
+                      placeholder="Display Name (if profile is not found)"
+                      value={displayName}
+                      onChange={(e) => setDisplayName(e.target.value)}
+                      disabled={isLoading}
+                    />
+                  </div>
+                )}
+                <div className="grid gap-2">
+                  <Input
+                    id="importPassphrase"
+                    type="password"
+                    placeholder="Passphrase"
+                    value={passphrase}
+                    onChange={(e) => setPassphrase(e.target.value)}
+                    disabled={isLoading}
+                    required
+                  />
+                </div>
+                <Button type="submit" disabled={isLoading || !passphrase}>
+                  {isLoading ? 'Importing...' : 'Import Identity'}
+                </Button>
+              </div>
+            </form>
           ) : (
             <form onSubmit={handleLogin}>
               <div className="grid gap-4">
@@ -124,7 +308,18 @@ const AuthPage: React.FC = () => {
                   )}
                 </div>
                 {existingUsers.length > 0 && (
-                  <Button type="submit" disabled={isLoading || !selectedUserId}>
+                  <Input
+                    id="loginPassphrase"
+                    type="password"
+                    placeholder="Passphrase"
+                    value={passphrase}
+                    onChange={(e) => setPassphrase(e.target.value)}
+                    disabled={isLoading}
+                    required
+                  />
+                )}
+                {existingUsers.length > 0 && (
+                  <Button type="submit" disabled={isLoading || !selectedUserId || !passphrase}>
                     {isLoading ? 'Signing In...' : 'Sign In'}
                   </Button>
                 )}
@@ -133,6 +328,16 @@ const AuthPage: React.FC = () => {
           )}
         </div>
         <div className="text-center text-sm">
+          {mode !== 'import' && (
+            <Button variant="link" onClick={() => setMode('import')}>
+              Import an existing identity
+            </Button>
+          )}
+          {mode !== 'link' && (
+            <Button variant="link" onClick={() => setMode('link')}>
+              Link to another device
+            </Button>
+          )}
           {mode === 'login' ? (
             <Button variant="link" onClick={() => setMode('register')}>
               Create a new account
@@ -1,25 +1,45 @@
 import React, { useEffect, useState } from 'react';
 import { useAuthStore } from '@/stores/auth-store';
-import { usePostStore, Post } from '@/stores/post-store';
+import { usePostStore, Post, Attachment, ReactionKind, ReactionSummary } from '@/stores/post-store';
 import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
 import { useProfileStore } from '@/stores/profile-store';
 import { Link } from 'react-router-dom';
+import { getAttachment, getAttachmentPreview } from '@/services/post-service';
+import { REACTION_EMOJI } from '@/lib/constants';
+
+// 1件の投稿に添付できるファイル数の上限（バックエンドと同じ値）
+const MAX_ATTACHMENTS = 4;
+
+// タイムラインに表示する画像の幅（これ以上の縮小画像があればそれを使う）
+const PREVIEW_DISPLAY_WIDTH = 480;
+
+// 一度に返信数を取得する投稿数の上限（バックエンドの MAX_PAGE_SIZE と同じ値）
+const REPLY_COUNT_BATCH_SIZE = 100;
 
 const HomePage: React.FC = () => {
   const { user } = useAuthStore();
-  const { posts, fetchPosts, createPost, isLoading } = usePostStore();
+  const { feed, setFeed, timeline, nextCursor, fetchPosts, createPost, replyCounts, fetchReplyCounts, isLoading } = usePostStore();
   const { profiles, fetchProfile } = useProfileStore();
   const [newPostContent, setNewPostContent] = useState('');
+  const [files, setFiles] = useState<File[]>([]);
 
   useEffect(() => {
     fetchPosts();
   }, [fetchPosts]);
 
-  // Fetch profiles for post authors
+  // Fetch profiles for post authors, quoted authors and reposters
   useEffect(() => {
     const fetchMissingProfiles = async () => {
-      const uniqueAuthorIds = [...new Set(posts.map(post => post.authorId))];
+      const uniqueAuthorIds = [
+        ...new Set(
+          timeline.flatMap(item => [
+            item.post.authorId,
+            ...(item.quoted ? [item.quoted.authorId] : []),
+            ...item.repostedBy,
+          ])
+        ),
+      ];
       
       for (const authorId of uniqueAuthorIds) {
         if (!profiles[authorId]) {
@@ -28,17 +48,26 @@ const HomePage: React.FC = () => {
       }
     };
 
-    if (posts.length > 0) {
+    if (timeline.length > 0) {
       fetchMissingProfiles();
     }
-  }, [posts, profiles, fetchProfile]);
+  }, [timeline, profiles, fetchProfile]);
+
+  // 返信数がまだ無い投稿の返信数を取得
+  useEffect(() => {
+    const missing = timeline.map(item => item.post.id).filter(id => replyCounts[id] === undefined);
+    for (let i = 0; i < missing.length; i += REPLY_COUNT_BATCH_SIZE) {
+      fetchReplyCounts(missing.slice(i, i + REPLY_COUNT_BATCH_SIZE));
+    }
+  }, [timeline, replyCounts, fetchReplyCounts]);
 
   const handleCreatePost = async (e: React.FormEvent) => {
     e.preventDefault();
-    if (!newPostContent.trim()) return;
+    if (!newPostContent.trim() && files.length === 0) return;
     
-    await createPost(newPostContent);
+    await createPost(newPostContent, files);
     setNewPostContent('');
+    setFiles([]);
   };
 
   // タイムスタンプをフォーマットする関数（投稿カードで使用）
@@ -46,6 +75,17 @@ const HomePage: React.FC = () => {
   return (
     <div className="container mx-auto max-w-3xl">
       <h1 className="text-2xl font-bold mb-6">Home Timeline</h1>
+
+      {user && (
+        <div className="flex gap-2 mb-4">
+          <Button variant={feed === 'home' ? 'default' : 'outline'} size="sm" onClick={() => setFeed('home')}>
+            Following
+          </Button>
+          <Button variant={feed === 'all' ? 'default' : 'outline'} size="sm" onClick={() => setFeed('all')}>
+            All posts
+          </Button>
+        </div>
+      )}
       
       {user && (
         <div className="bg-card rounded-lg p-4 mb-6 shadow-sm">
@@ -58,8 +98,15 @@ const HomePage: React.FC = () => {
                 disabled={isLoading}
                 className="min-h-[80px] resize-none"
               />
-              <div className="flex justify-end">
-                <Button type="submit" disabled={isLoading || !newPostContent.trim()}>
+              <div className="flex items-center justify-between">
+                <input
+                  type="file"
+                  multiple
+                  disabled={isLoading}
+                  onChange={(e) => setFiles(Array.from(e.target.files ?? []).slice(0, MAX_ATTACHMENTS))}
+                  className="text-sm"
+                />
+                <Button type="submit" disabled={isLoading || (!newPostContent.trim() && files.length === 0)}>
                   {isLoading ? 'Posting...' : 'Post'}
                 </Button>
               </div>
@@ -69,19 +116,34 @@ const HomePage: React.FC = () => {
       )}
       
       <div className="space-y-4">
-        {posts.length === 0 ? (
+        {timeline.length === 0 ? (
           <div className="text-center py-8 text-muted-foreground">
             No posts yet. Be the first to post something!
           </div>
         ) : (
-          posts.map((post) => (
+          timeline.map(({ post, quoted, repostedBy, reactions }) => (
             <PostCard 
               key={post.id} 
               post={post} 
               authorName={profiles[post.authorId]?.displayName || 'Unknown User'} 
+              isOwn={post.authorId === user?.id}
+              canReply={!!user}
+              replyCount={replyCounts[post.id] ?? 0}
+              quoted={quoted}
+              quotedAuthorName={quoted ? profiles[quoted.authorId]?.displayName || 'Unknown User' : ''}
+              repostedByNames={repostedBy.map(id => profiles[id]?.displayName || 'Unknown User')}
+              isReposted={!!user && repostedBy.includes(user.id)}
+              reactions={reactions}
             />
           ))
         )}
+        {nextCursor && (
+          <div className="flex justify-center">
+            <Button variant="outline" disabled={isLoading} onClick={() => fetchPosts(undefined, nextCursor)}>
+              {isLoading ? 'Loading...' : 'Load more'}
+            </Button>
+          </div>
+        )}
       </div>
     </div>
   );
@@ -90,20 +152,126 @@ const HomePage: React.FC = () => {
 interface PostCardProps {
   post: Post;
   authorName: string;
+  // サインイン中のユーザーの投稿（編集・削除できる）
+  isOwn: boolean;
+  canReply: boolean;
+  replyCount: number;
+  // 引用した投稿（引用でない場合や同期されていない場合は null）
+  quoted?: Post | null;
+  quotedAuthorName: string;
+  // リポストしたユーザーの表示名（新しい順）
+  repostedByNames: string[];
+  // サインイン中のユーザーがリポストしている
+  isReposted: boolean;
+  reactions: ReactionSummary;
 }
 
-const PostCard: React.FC<PostCardProps> = ({ post, authorName }) => {
+const PostCard: React.FC<PostCardProps> = ({
+  post,
+  authorName,
+  isOwn,
+  canReply,
+  replyCount,
+  quoted,
+  quotedAuthorName,
+  repostedByNames,
+  isReposted,
+  reactions,
+}) => {
+  const { editPost, deletePost, createReply, createQuote, repost, unrepost, toggleReaction, isLoading } =
+    usePostStore();
+  const [isEditing, setIsEditing] = useState(false);
+  const [draft, setDraft] = useState(post.content);
+  const [isReplying, setIsReplying] = useState(false);
+  const [reply, setReply] = useState('');
+  const [isQuoting, setIsQuoting] = useState(false);
+  const [quote, setQuote] = useState('');
+
+  const handleQuote = async () => {
+    if (await createQuote(post.id, quote)) {
+      setQuote('');
+      setIsQuoting(false);
+    }
+  };
+
+  const handleReply = async () => {
+    if (await createReply(post.id, reply)) {
+      setReply('');
+      setIsReplying(false);
+    }
+  };
+
+  const handleSave = async () => {
+    if (await editPost(post.id, draft)) {
+      setIsEditing(false);
+    }
+  };
+
   return (
     <div className="bg-card rounded-lg p-4 shadow-sm">
+      {repostedByNames.length > 0 && (
+        <p className="mb-1 text-xs text-muted-foreground">
+          Reposted by {repostedByNames.slice(0, 3).join(', ')}
+          {repostedByNames.length > 3 && ` and ${repostedByNames.length - 3} others`}
+        </p>
+      )}
       <div className="flex justify-between items-start mb-2">
         <Link to={`/profile/${post.authorId}`} className="font-medium hover:underline">
           {authorName}
         </Link>
         <span className="text-xs text-muted-foreground">
           {new Date(post.createdAt).toLocaleString()}
+          {post.editedAt && ' (edited)'}
         </span>
       </div>
-      <p className="whitespace-pre-wrap">{post.content}</p>
+      {post.replyTo && (
+        <p className="mb-1 text-xs text-muted-foreground">Replying to a post</p>
+      )}
+      {isEditing ? (
+        <div className="flex flex-col space-y-2">
+          <Input value={draft} onChange={(e) => setDraft(e.target.value)} disabled={isLoading} />
+          <div className="flex justify-end gap-2">
+            <Button variant="outline" size="sm" disabled={isLoading} onClick={() => setIsEditing(false)}>
+              Cancel
+            </Button>
+            <Button size="sm" disabled={isLoading || draft === post.content} onClick={handleSave}>
+              Save
+            </Button>
+          </div>
+        </div>
+      ) : (
+        <p className="whitespace-pre-wrap">{post.content}</p>
+      )}
+      {post.quoteOf && (
+        <div className="mt-2 rounded-md border p-2 text-sm">
+          {quoted ? (
+            <>
+              <Link to={`/profile/${quoted.authorId}`} className="font-medium hover:underline">
+                {quotedAuthorName}
+              </Link>
+              <p className="whitespace-pre-wrap">{quoted.content}</p>
+            </>
+          ) : (
+            <span className="text-muted-foreground">The quoted post is not available</span>
+          )}
+        </div>
+      )}
+      {post.visibility === "followers" && (
+        <p className="mt-1 text-xs text-muted-foreground">Followers only</p>
+      )}
+      {post.keyRevoked && (
+        <p className="mt-1 text-xs text-destructive">
+          Signed with a revoked key
+        </p>
+      )}
+
+      {post.attachments.length > 0 && (
+        <div className="mt-2 grid grid-cols-2 gap-2">
+          {post.attachments.map((attachment) => (
+            <AttachmentPreview key={attachment.hash} postId={post.id} attachment={attachment} />
+          ))}
+        </div>
+      )}
       
       {post.hashtags.length > 0 && (
         <div className="mt-2 flex flex-wrap gap-1">
@@ -114,8 +282,173 @@ const PostCard: React.FC<PostCardProps> = ({ post, authorName }) => {
           ))}
         </div>
       )}
+
+      {!isEditing && (
+        <div className="mt-2 flex flex-wrap gap-1">
+          {(Object.keys(REACTION_EMOJI) as ReactionKind[]).map((kind) => {
+            const count = reactions.counts[kind] ?? 0;
+            const mine = reactions.mine.includes(kind);
+            // サインインしていない場合はリアクションのある種類だけを表示する
+            if (!canReply && count === 0) return null;
+            return (
+              <Button
+                key={kind}
+                variant={mine ? 'secondary' : 'ghost'}
+                size="sm"
+                disabled={!canReply}
+                onClick={() => toggleReaction(post.id, kind)}
+              >
+                {REACTION_EMOJI[kind]}
+                {count > 0 && <span className="ml-1 text-xs">{count}</span>}
+              </Button>
+            );
+          })}
+        </div>
+      )}
+
+      {!isEditing && (
+        <div className="mt-2 flex justify-end gap-2">
+          <span className="mr-auto self-center text-xs text-muted-foreground">
+            {replyCount === 1 ? '1 reply' : `${replyCount} replies`}
+          </span>
+          {canReply && (
+            <>
+              <Button variant="ghost" size="sm" disabled={isLoading} onClick={() => setIsReplying(!isReplying)}>
+                Reply
+              </Button>
+              <Button
+                variant="ghost"
+                size="sm"
+                disabled={isLoading}
+                onClick={() => (isReposted ? unrepost(post.id) : repost(post.id))}
+              >
+                {isReposted ? 'Undo repost' : 'Repost'}
+              </Button>
+              <Button variant="ghost" size="sm" disabled={isLoading} onClick={() => setIsQuoting(!isQuoting)}>
+                Quote
+              </Button>
+            </>
+          )}
+          {isOwn && (
+            <>
+              <Button
+                variant="ghost"
+                size="sm"
+                disabled={isLoading}
+                onClick={() => {
+                  setDraft(post.content);
+                  setIsEditing(true);
+                }}
+              >
+                Edit
+              </Button>
+              <Button variant="ghost" size="sm" disabled={isLoading} onClick={() => deletePost(post.id)}>
+                Delete
+              </Button>
+            </>
+          )}
+        </div>
+      )}
+
+      {isReplying && (
+        <div className="mt-2 flex gap-2">
+          <Input
+            placeholder="Write a reply..."
+            value={reply}
+            onChange={(e) => setReply(e.target.value)}
+            disabled={isLoading}
+          />
+          <Button size="sm" disabled={isLoading || !reply.trim()} onClick={handleReply}>
+            Reply
+          </Button>
+        </div>
+      )}
+
+      {isQuoting && (
+        <div className="mt-2 flex gap-2">
+          <Input
+            placeholder="Add a comment..."
+            value={quote}
+            onChange={(e) => setQuote(e.target.value)}
+            disabled={isLoading}
+          />
+          <Button size="sm" disabled={isLoading || !quote.trim()} onClick={handleQuote}>
+            Quote
+          </Button>
+        </div>
+      )}
     </div>
   );
 };
 
+interface AttachmentPreviewProps {
+  postId: string;
+  attachment: Attachment;
+}
+
+const AttachmentPreview: React.FC<AttachmentPreviewProps> = ({ postId, attachment }) => {
+  const [url, setUrl] = useState<string | null>(null);
+  const [failed, setFailed] = useState(false);
+
+  // 添付ファイルを取得してオブジェクトURLを作成（手元に無い場合はピアから取得される）
+  // 画像は元の画像の代わりに縮小画像を読み込む
+  useEffect(() => {
+    let cancelled = false;
+    let objectUrl: string | null = null;
+
+    const load = async (): Promise<Blob> => {
+      if (attachment.mimeType.startsWith('image/')) {
+        const preview = await getAttachmentPreview(postId, attachment.hash);
+        const thumbnail =
+          preview?.thumbnails.find((image) => image.width >= PREVIEW_DISPLAY_WIDTH) ??
+          preview?.thumbnails[preview.thumbnails.length - 1];
+        if (thumbnail) {
+          return getAttachment(postId, thumbnail);
+        }
+      }
+      return getAttachment(postId, attachment);
+    };
+
+    load()
+      .then((blob) => {
+        if (cancelled) return;
+        objectUrl = URL.createObjectURL(blob);
+        setUrl(objectUrl);
+      })
+      .catch(() => {
+        if (!cancelled) setFailed(true);
+      });
+
+    return () => {
+      cancelled = true;
+      if (objectUrl) URL.revokeObjectURL(objectUrl);
+    };
+  }, [postId, attachment]);
+
+  const label = `${attachment.name || 'Attachment'} (${Math.ceil(attachment.size / 1024)} KB)`;
+
+  if (failed) {
+    return <div className="text-xs text-muted-foreground">{label} is not available</div>;
+  }
+  if (!url) {
+    return <div className="text-xs text-muted-foreground">Loading {label}...</div>;
+  }
+  if (attachment.mimeType.startsWith('image/')) {
+    return (
+      <img
+        src={url}
+        alt={attachment.name || ''}
+        width={attachment.width ?? undefined}
+        height={attachment.height ?? undefined}
+        className="rounded-md w-full h-auto"
+      />
+    );
+  }
+  return (
+    <a href={url} download={attachment.name || attachment.hash} className="text-sm text-blue-500 hover:underline">
+      {label}
+    </a>
+  );
+};
+
 export default HomePage;
\ No newline at end of file
@@ -4,13 +4,17 @@ import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
 import { useTheme } from '@/components/theme-provider';
 import { toast } from '@/hooks/use-toast';
-import { invoke } from '@tauri-apps/api/core';
+import { approveDeviceLink, exportIdentity, DeviceLinkTicket } from '@/services/auth-service';
 
 const SettingsPage: React.FC = () => {
   const { user, logout } = useAuthStore();
   const { theme, setTheme } = useTheme();
   const [relayUrl, setRelayUrl] = useState('');
   const [isAddingRelay, setIsAddingRelay] = useState(false);
+  const [exportPassphrase, setExportPassphrase] = useState('');
+  const [mnemonic, setMnemonic] = useState<string | null>(null);
+  const [linkTicket, setLinkTicket] = useState('');
+  const [linkApproval, setLinkApproval] = useState<DeviceLinkTicket | null>(null);
   const [relays, setRelays] = useState<string[]>([
     'https://relay.kukuri.network',
     'https://relay.example.com'
@@ -47,10 +51,23 @@ const SettingsPage: React.FC = () => {
     });
   };
 
-  const handleExportData = async () => {
+  const handleExportData = async (includeMnemonic: boolean) => {
+    if (!user) return;
+
     try {
-      // This would be a Tauri command to export user data
-      await invoke('export_user_data', { userId: user?.id });
+      const result = await exportIdentity(user.id, exportPassphrase, includeMnemonic);
+
+      // バックアップをファイルとしてダウンロード
+      const blob = new Blob([result.backup], { type: 'application/json' });
+      const url = URL.createObjectURL(blob);
+      const link = document.createElement('a');
+      link.href = url;
+      link.download = `kukuri-identity-${user.id.slice(0, 8)}.json`;
+      link.click();
+      URL.revokeObjectURL(url);
+
+      setMnemonic(result.mnemonic ?? null);
+      setExportPassphrase('');
       toast({
         title: 'Data exported',
         description: 'Your data has been exported successfully',
@@ -64,6 +81,20 @@ const SettingsPage: React.FC = () => {
     }
   };
 
+  const handleApproveLink = async (e: React.FormEvent) => {
+    e.preventDefault();
+    try {
+      setLinkApproval(await approveDeviceLink(linkTicket));
+      setLinkTicket('');
+    } catch (error) {
+      toast({
+        variant: 'destructive',
+        title: 'Link failed',
+        description: error instanceof Error ? error.message : 'Failed to approve device',
+      });
+    }
+  };
+
   return (
     <div className="container mx-auto max-w-3xl">
       <h1 className="text-2xl font-bold mb-6">Settings</h1>
@@ -152,12 +183,70 @@ const SettingsPage: React.FC = () => {
         <div className="bg-card rounded-lg p-6 shadow-sm">
           <h2 className="text-xl font-semibold mb-4">Data</h2>
           <div className="space-y-4">
-            <Button variant="outline" onClick={handleExportData}>
-              Export Your Data
-            </Button>
+            <Input
+              type="password"
+              placeholder="Current passphrase"
+              value={exportPassphrase}
+              onChange={(e) => setExportPassphrase(e.target.value)}
+            />
+            <div className="flex space-x-2">
+              <Button
+                variant="outline"
+                disabled={!exportPassphrase}
+                onClick={() => handleExportData(false)}
+              >
+                Export Your Data
+              </Button>
+              <Button
+                variant="outline"
+                disabled={!exportPassphrase}
+                onClick={() => handleExportData(true)}
+              >
+                Export with Recovery Phrase
+              </Button>
+            </div>
+            {mnemonic && (
+              <div className="p-3 bg-background rounded">
+                <p className="text-sm font-medium mb-2">
+                  Write down your recovery phrase and keep it somewhere safe:
+                </p>
+                <p className="text-sm font-mono break-words">{mnemonic}</p>
+              </div>
+            )}
           </div>
         </div>
         
+        <div className="bg-card rounded-lg p-6 shadow-sm">
+          <h2 className="text-xl font-semibold mb-4">Devices</h2>
+          <div className="space-y-4">
+            {user?.devices?.map((device) => (
+              <div key={device.deviceKey} className="flex justify-between items-center p-2 bg-background rounded">
+                <span className="text-sm">{device.deviceName}</span>
+              </div>
+            ))}
+            <form onSubmit={handleApproveLink} className="flex space-x-2">
+              <Input
+                placeholder="Link ticket from the new device"
+                value={linkTicket}
+                onChange={(e) => setLinkTicket(e.target.value)}
+                className="flex-1"
+              />
+              <Button type="submit" variant="outline" disabled={!linkTicket}>
+                Approve
+              </Button>
+            </form>
+            {linkApproval && (
+              <div className="p-3 bg-background rounded">
+                <p className="text-sm font-medium mb-2">
+                  Check that the new device shows the code{' '}
+                  <span className="font-mono">{linkApproval.code}</span>, then enter this ticket there:
+                </p>
+                <p className="text-xs font-mono break-all">{linkApproval.ticket}</p>
+              </div>
+            )}
+          </div>
+        </div>
+
         <div className="bg-card rounded-lg p-6 shadow-sm">
           <h2 className="text-xl font-semibold mb-4">Account</h2>
           <div className="space-y-4">
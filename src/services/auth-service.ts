@@ -13,6 +13,16 @@ export interface AuthResult {
   message?: string;
 }
 
+export interface IdentityExport {
+  backup: string;
+  mnemonic?: string | null;
+}
+
+export interface DeviceLinkTicket {
+  ticket: string;
+  code: string;
+}
+
 export interface UserListItem {
   id: string;
   displayName: string;
@@ -31,6 +41,7 @@ export async function createUser(input: CreateUserInput): Promise<AuthResult> {
     const result = await invoke<AuthResult>("create_user", {
       displayName: input.displayName,
       bio: input.bio,
+      passphrase: input.passphrase,
     });
 
     return result;
@@ -42,11 +53,14 @@ export async function createUser(input: CreateUserInput): Promise<AuthResult> {
 
 /**
  * 既存のユーザーでサインインします
+ *
+ * パスフレーズで暗号化された秘密鍵のロックを解除します。
  */
-export async function signIn(userId: string): Promise<AuthResult> {
+export async function signIn(userId: string, passphrase: string): Promise<AuthResult> {
   try {
     const result = await invoke<AuthResult>("sign_in", {
       userId,
+      passphrase,
     });
 
     return result;
@@ -56,6 +70,164 @@ export async function signIn(userId: string): Promise<AuthResult> {
   }
 }
 
+/**
+ * 秘密鍵のパスフレーズを変更します
+ */
+export async function changePassphrase(
+  userId: string,
+  oldPassphrase: string,
+  newPassphrase: string
+): Promise<void> {
+  try {
+    await invoke<void>("change_passphrase", {
+      userId,
+      oldPassphrase,
+      newPassphrase,
+    });
+  } catch (error) {
+    console.error("Error changing passphrase:", error);
+    throw error;
+  }
+}
+
+/**
+ * 秘密鍵・プロフィール・設定の暗号化バックアップを作成します
+ *
+ * `includeMnemonic` を指定するとリカバリーフレーズ（24語）も返します。
+ */
+export async function exportIdentity(
+  userId: string,
+  passphrase: string,
+  includeMnemonic: boolean
+): Promise<IdentityExport> {
+  try {
+    return await invoke<IdentityExport>("export_identity", {
+      userId,
+      passphrase,
+      includeMnemonic,
+    });
+  } catch (error) {
+    console.error("Error exporting identity:", error);
+    throw error;
+  }
+}
+
+/**
+ * 暗号化バックアップからアイデンティティを復元します
+ */
+export async function importIdentity(backup: string, passphrase: string): Promise<AuthResult> {
+  try {
+    return await invoke<AuthResult>("import_identity", {
+      backup,
+      passphrase,
+    });
+  } catch (error) {
+    console.error("Error importing identity:", error);
+    throw error;
+  }
+}
+
+/**
+ * リカバリーフレーズからアイデンティティを復元します
+ *
+ * ローカルにプロフィールが無い場合は `displayName` で新しいプロフィールを作成します。
+ */
+export async function recoverIdentity(
+  mnemonic: string,
+  passphrase: string,
+  displayName?: string
+): Promise<AuthResult> {
+  try {
+    return await invoke<AuthResult>("recover_identity", {
+      mnemonic,
+      passphrase,
+      displayName,
+    });
+  } catch (error) {
+    console.error("Error recovering identity:", error);
+    throw error;
+  }
+}
+
+/**
+ * 秘密鍵をローテーションします
+ *
+ * `revokePrevious` を指定すると、以前の鍵を失効させます（鍵が漏洩した場合など）。
+ */
+export async function rotateKey(passphrase: string, revokePrevious: boolean): Promise<AuthResult> {
+  try {
+    return await invoke<AuthResult>("rotate_key", {
+      passphrase,
+      revokePrevious,
+    });
+  } catch (error) {
+    console.error("Error rotating key:", error);
+    throw error;
+  }
+}
+
+/**
+ * ローテーション済みの以前の鍵を失効させます
+ */
+export async function revokeKey(publicKey: string, reason?: string): Promise<void> {
+  try {
+    await invoke<void>("revoke_key", {
+      publicKey,
+      reason,
+    });
+  } catch (error) {
+    console.error("Error revoking key:", error);
+    throw error;
+  }
+}
+
+/**
+ * 新しい端末でリンク要求チケットを作成します
+ *
+ * 返されたチケットを既存の端末で承認し、両方の端末で確認コードが一致することを確認してください。
+ */
+export async function requestDeviceLink(
+  deviceName: string,
+  passphrase: string
+): Promise<DeviceLinkTicket> {
+  try {
+    return await invoke<DeviceLinkTicket>("request_device_link", {
+      deviceName,
+      passphrase,
+    });
+  } catch (error) {
+    console.error("Error requesting device link:", error);
+    throw error;
+  }
+}
+
+/**
+ * 既存の端末でリンク要求を承認し、承認チケットを作成します
+ */
+export async function approveDeviceLink(ticket: string): Promise<DeviceLinkTicket> {
+  try {
+    return await invoke<DeviceLinkTicket>("approve_device_link", { ticket });
+  } catch (error) {
+    console.error("Error approving device link:", error);
+    throw error;
+  }
+}
+
+/**
+ * 新しい端末で承認チケットを取り込み、リンクを完了します
+ */
+export async function completeDeviceLink(ticket: string, passphrase: string): Promise<AuthResult> {
+  try {
+    return await invoke<AuthResult>("complete_device_link", {
+      ticket,
+      passphrase,
+    });
+  } catch (error) {
+    console.error("Error completing device link:", error);
+    throw error;
+  }
+}
+
 /**
  * ユーザープロフィールを取得します
  */
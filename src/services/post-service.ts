@@ -1,5 +1,17 @@
 import { invoke } from "@tauri-apps/api/core";
-import { CreatePostInput, Post, SearchPostsInput } from "@/models/post";
+import {
+  Attachment,
+  AttachmentPreview,
+  CreatePostInput,
+  Post,
+  PostPage,
+  ReactionKind,
+  ReactionSummary,
+  SearchPostsInput,
+  Thread,
+  TimelinePage,
+  Visibility,
+} from "@/models/post";
 import { validateCreatePost, validateSearchPosts } from "@/lib/validators";
 import { DEFAULT_PAGE_SIZE } from "@/lib/constants";
 
@@ -15,9 +27,9 @@ export interface PostResult {
 }
 
 /**
- * 新しい投稿を作成します
+ * サインイン中のユーザーとして新しい投稿を作成します
  */
-export async function createPost(authorId: string, input: CreatePostInput): Promise<PostResult> {
+export async function createPost(input: CreatePostInput): Promise<PostResult> {
   const validation = validateCreatePost(input);
   if (!validation.success) {
     throw new Error(`Validation error: ${JSON.stringify(validation.error)}`);
@@ -25,9 +37,8 @@ export async function createPost(authorId: string, input: CreatePostInput): Prom
 
   try {
     const result = await invoke<PostResult>("create_post", {
-      authorId,
       content: input.content,
-      attachments: input.attachments || [],
+      visibility: input.visibility,
     });
 
     return result;
@@ -38,16 +49,19 @@ export async function createPost(authorId: string, input: CreatePostInput): Prom
 }
 
 /**
- * すべての投稿を取得します
+ * すべての投稿とリポストを新しい順に1ページ分取得します
+ *
+ * リポストされた投稿は最新のリポストの位置に一度だけ含まれます。
+ * 次のページは前のページの `nextCursor` を指定して取得します。
  */
-export async function getPosts(limit = DEFAULT_PAGE_SIZE, offset = 0): Promise<Post[]> {
+export async function getPosts(limit = DEFAULT_PAGE_SIZE, cursor?: string | null): Promise<TimelinePage> {
   try {
-    const posts = await invoke<Post[]>("get_posts", {
+    const page = await invoke<TimelinePage>("get_posts", {
       limit,
-      offset,
+      cursor,
     });
 
-    return posts;
+    return page;
   } catch (error) {
     console.error("Error fetching posts:", error);
     throw error;
@@ -55,17 +69,38 @@ export async function getPosts(limit = DEFAULT_PAGE_SIZE, offset = 0): Promise<P
 }
 
 /**
- * 特定のユーザーの投稿を取得します
+ * サインイン中のユーザー自身とフォロー中のユーザーの投稿を新しい順に1ページ分取得します
+ */
+export async function getHomeTimeline(limit = DEFAULT_PAGE_SIZE, cursor?: string | null): Promise<TimelinePage> {
+  try {
+    const page = await invoke<TimelinePage>("get_home_timeline", {
+      limit,
+      cursor,
+    });
+
+    return page;
+  } catch (error) {
+    console.error("Error fetching home timeline:", error);
+    throw error;
+  }
+}
+
+/**
+ * 特定のユーザーの投稿を新しい順に1ページ分取得します
  */
-export async function getUserPosts(userId: string, limit = DEFAULT_PAGE_SIZE, offset = 0): Promise<Post[]> {
+export async function getUserPosts(
+  userId: string,
+  limit = DEFAULT_PAGE_SIZE,
+  cursor?: string | null
+): Promise<PostPage> {
   try {
-    const posts = await invoke<Post[]>("get_user_posts", {
+    const page = await invoke<PostPage>("get_user_posts", {
       userId,
       limit,
-      offset,
+      cursor,
     });
 
-    return posts;
+    return page;
   } catch (error) {
     console.error("Error fetching user posts:", error);
     throw error;
@@ -92,4 +127,254 @@ export async function searchPosts(input: SearchPostsInput): Promise<Post[]> {
     console.error("Error searching posts:", error);
     throw error;
   }
-}
\ No newline at end of file
+}
+/**
+ * ハッシュタグが付いた投稿を1ページ分取得します
+ */
+export async function getPostsByHashtag(
+  tag: string,
+  limit = DEFAULT_PAGE_SIZE,
+  cursor?: string | null
+): Promise<PostPage> {
+  try {
+    const page = await invoke<PostPage>("get_posts_by_hashtag", {
+      tag,
+      limit,
+      cursor,
+    });
+
+    return page;
+  } catch (error) {
+    console.error("Error fetching hashtag posts:", error);
+    throw error;
+  }
+}
+
+/**
+ * ユーザーをメンションしている投稿を1ページ分取得します
+ */
+export async function getMentionsForUser(
+  userId: string,
+  limit = DEFAULT_PAGE_SIZE,
+  cursor?: string | null
+): Promise<PostPage> {
+  try {
+    const page = await invoke<PostPage>("get_mentions_for_user", {
+      userId,
+      limit,
+      cursor,
+    });
+
+    return page;
+  } catch (error) {
+    console.error("Error fetching mentions:", error);
+    throw error;
+  }
+}
+
+/**
+ * 添付ファイル付きの投稿を作成します
+ *
+ * ファイルは1件ずつバイト列のままバックエンドに送られ、iroh-blobs に保存されます。
+ * 添付ファイルがある場合、本文は空でも構いません。
+ */
+export async function createPostWithAttachments(
+  content: string,
+  files: File[],
+  visibility?: Visibility
+): Promise<PostResult> {
+  try {
+    const attachments = await Promise.all(
+      files.map(async (file) => ({
+        name: file.name,
+        hash: await invoke<string>("upload_attachment", new Uint8Array(await file.arrayBuffer())),
+      }))
+    );
+
+    return await invoke<PostResult>("create_post_with_attachments", {
+      content,
+      attachments,
+      visibility,
+    });
+  } catch (error) {
+    console.error("Error creating post with attachments:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿の添付ファイル、またはその縮小画像の内容を取得します
+ *
+ * 手元に無い場合はバックエンドがピアから取得します。
+ */
+export async function getAttachment(
+  postId: string,
+  attachment: Pick<Attachment, "hash" | "mimeType">
+): Promise<Blob> {
+  try {
+    const data = await invoke<ArrayBuffer>("get_attachment", { postId, hash: attachment.hash });
+    return new Blob([data], { type: attachment.mimeType });
+  } catch (error) {
+    console.error("Error fetching attachment:", error);
+    throw error;
+  }
+}
+
+/**
+ * 添付画像のプレビュー（縮小画像と blurhash）を取得します
+ *
+ * 縮小画像の内容は getAttachment で取得します。画像以外の添付ファイルや
+ * サイズが上限を超える添付ファイルでは null を返します。
+ */
+export async function getAttachmentPreview(postId: string, hash: string): Promise<AttachmentPreview | null> {
+  try {
+    return await invoke<AttachmentPreview | null>("get_attachment_preview", { postId, hash });
+  } catch (error) {
+    console.error("Error fetching attachment preview:", error);
+    throw error;
+  }
+}
+
+/**
+ * 自分の投稿の本文を編集します
+ */
+export async function editPost(postId: string, content: string): Promise<PostResult> {
+  try {
+    return await invoke<PostResult>("edit_post", { postId, content });
+  } catch (error) {
+    console.error("Error editing post:", error);
+    throw error;
+  }
+}
+
+/**
+ * 自分の投稿を削除します
+ */
+export async function deletePost(postId: string): Promise<PostResult> {
+  try {
+    return await invoke<PostResult>("delete_post", { postId });
+  } catch (error) {
+    console.error("Error deleting post:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿の編集前の版を古い順に取得します
+ */
+export async function getPostHistory(postId: string): Promise<Post[]> {
+  try {
+    return await invoke<Post[]>("get_post_history", { postId });
+  } catch (error) {
+    console.error("Error fetching post history:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿に返信します
+ */
+export async function createReply(parentId: string, content: string): Promise<PostResult> {
+  try {
+    return await invoke<PostResult>("create_reply", { parentId, content });
+  } catch (error) {
+    console.error("Error creating reply:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿が属するスレッドを取得します
+ */
+export async function getThread(postId: string): Promise<Thread> {
+  try {
+    return await invoke<Thread>("get_thread", { postId });
+  } catch (error) {
+    console.error("Error fetching thread:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿それぞれへの返信の数を取得します
+ */
+export async function getReplyCounts(postIds: string[]): Promise<Record<string, number>> {
+  try {
+    return await invoke<Record<string, number>>("get_reply_counts", { postIds });
+  } catch (error) {
+    console.error("Error fetching reply counts:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿を引用した投稿を作成します
+ */
+export async function createQuote(postId: string, content: string): Promise<PostResult> {
+  try {
+    return await invoke<PostResult>("create_quote", { postId, content });
+  } catch (error) {
+    console.error("Error creating quote:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿をリポストします
+ */
+export async function repost(postId: string): Promise<PostResult> {
+  try {
+    return await invoke<PostResult>("repost", { postId });
+  } catch (error) {
+    console.error("Error reposting:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿のリポストを取り消します
+ */
+export async function unrepost(postId: string): Promise<PostResult> {
+  try {
+    return await invoke<PostResult>("unrepost", { postId });
+  } catch (error) {
+    console.error("Error removing repost:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿にリアクションし、更新後の集計を返します
+ */
+export async function addReaction(postId: string, kind: ReactionKind): Promise<ReactionSummary> {
+  try {
+    return await invoke<ReactionSummary>("add_reaction", { postId, kind });
+  } catch (error) {
+    console.error("Error adding reaction:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿へのリアクションを取り消し、更新後の集計を返します
+ */
+export async function removeReaction(postId: string, kind: ReactionKind): Promise<ReactionSummary> {
+  try {
+    return await invoke<ReactionSummary>("remove_reaction", { postId, kind });
+  } catch (error) {
+    console.error("Error removing reaction:", error);
+    throw error;
+  }
+}
+
+/**
+ * 投稿それぞれへのリアクションの集計を取得します
+ */
+export async function getReactions(postIds: string[]): Promise<Record<string, ReactionSummary>> {
+  try {
+    return await invoke<Record<string, ReactionSummary>>("get_reactions", { postIds });
+  } catch (error) {
+    console.error("Error fetching reactions:", error);
+    throw error;
+  }
+}
@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
-import { UpdateProfileInput, User } from "@/models/user";
+import { FollowCounts, UpdateProfileInput, User } from "@/models/user";
 import { validateUpdateProfile } from "@/lib/validators";
 
 /**
@@ -13,12 +13,9 @@ export interface ProfileUpdateResult {
 }
 
 /**
- * プロフィールを更新します
+ * サインイン中のユーザーのプロフィールを更新します
  */
-export async function updateProfile(
-  userId: string,
-  input: UpdateProfileInput
-): Promise<ProfileUpdateResult> {
+export async function updateProfile(input: UpdateProfileInput): Promise<ProfileUpdateResult> {
   const validation = validateUpdateProfile(input);
   if (!validation.success) {
     throw new Error(`Validation error: ${JSON.stringify(validation.error)}`);
@@ -26,10 +23,9 @@ export async function updateProfile(
 
   try {
     const result = await invoke<ProfileUpdateResult>("update_profile", {
-      userId,
       displayName: input.displayName,
       bio: input.bio,
-      avatar: input.avatar,
+      approvalRequired: input.approvalRequired,
     });
 
     return result;
@@ -39,6 +35,56 @@ export async function updateProfile(
   }
 }
 
+/**
+ * サインイン中のユーザーのアバター画像を設定します
+ *
+ * 画像はバイト列のままバックエンドに送られます。
+ */
+export async function setAvatar(file: File): Promise<ProfileUpdateResult> {
+  try {
+    const data = new Uint8Array(await file.arrayBuffer());
+    const result = await invoke<ProfileUpdateResult>("set_avatar", data);
+
+    return result;
+  } catch (error) {
+    console.error("Error setting avatar:", error);
+    throw error;
+  }
+}
+
+/**
+ * サインイン中のユーザーのアバター画像を外します
+ */
+export async function removeAvatar(): Promise<ProfileUpdateResult> {
+  try {
+    const result = await invoke<ProfileUpdateResult>("remove_avatar");
+
+    return result;
+  } catch (error) {
+    console.error("Error removing avatar:", error);
+    throw error;
+  }
+}
+
+/**
+ * ユーザーのアバター画像を取得します
+ *
+ * thumbnail が true の場合は縮小画像を取得します。
+ */
+export async function getAvatar(userId: string, thumbnail = false): Promise<Blob> {
+  try {
+    const data = await invoke<ArrayBuffer>("get_avatar", {
+      userId,
+      thumbnail,
+    });
+
+    return new Blob([data]);
+  } catch (error) {
+    console.error("Error fetching avatar:", error);
+    throw error;
+  }
+}
+
 /**
  * ユーザープロフィールを取得します
  */
@@ -56,15 +102,11 @@ export async function getProfile(userId: string): Promise<User | null> {
 }
 
 /**
- * ユーザーをフォローします
+ * サインイン中のユーザーとしてユーザーをフォローします
  */
-export async function followUser(
-  userId: string,
-  targetUserId: string
-): Promise<ProfileUpdateResult> {
+export async function followUser(targetUserId: string): Promise<ProfileUpdateResult> {
   try {
     const result = await invoke<ProfileUpdateResult>("follow_user", {
-      userId,
       targetUserId,
     });
 
@@ -76,15 +118,11 @@ export async function followUser(
 }
 
 /**
- * ユーザーのフォローを解除します
+ * サインイン中のユーザーとしてユーザーのフォローを解除します
  */
-export async function unfollowUser(
-  userId: string,
-  targetUserId: string
-): Promise<ProfileUpdateResult> {
+export async function unfollowUser(targetUserId: string): Promise<ProfileUpdateResult> {
   try {
     const result = await invoke<ProfileUpdateResult>("unfollow_user", {
-      userId,
       targetUserId,
     });
 
@@ -93,4 +131,147 @@ export async function unfollowUser(
     console.error("Error unfollowing user:", error);
     throw error;
   }
-}
\ No newline at end of file
+}
+
+/**
+ * サインイン中のユーザーへの承認待ちのフォローリクエストを送ったユーザーのIDを取得します
+ */
+export async function getFollowRequests(): Promise<string[]> {
+  try {
+    return await invoke<string[]>("get_follow_requests");
+  } catch (error) {
+    console.error("Error fetching follow requests:", error);
+    throw error;
+  }
+}
+
+/**
+ * ユーザーからのフォローリクエストを承認します
+ */
+export async function approveFollowRequest(followerId: string): Promise<ProfileUpdateResult> {
+  try {
+    return await invoke<ProfileUpdateResult>("approve_follow_request", { followerId });
+  } catch (error) {
+    console.error("Error approving follow request:", error);
+    throw error;
+  }
+}
+
+/**
+ * ユーザーからのフォローリクエストを拒否します
+ */
+export async function rejectFollowRequest(followerId: string): Promise<ProfileUpdateResult> {
+  try {
+    return await invoke<ProfileUpdateResult>("reject_follow_request", { followerId });
+  } catch (error) {
+    console.error("Error rejecting follow request:", error);
+    throw error;
+  }
+}
+/**
+ * ユーザーがフォローしているユーザーのIDを取得します
+ */
+export async function getFollowing(userId: string): Promise<string[]> {
+  try {
+    return await invoke<string[]>("get_following", { userId });
+  } catch (error) {
+    console.error("Error fetching following:", error);
+    throw error;
+  }
+}
+
+/**
+ * ユーザーをフォローしているユーザーのIDを取得します
+ */
+export async function getFollowers(userId: string): Promise<string[]> {
+  try {
+    return await invoke<string[]>("get_followers", { userId });
+  } catch (error) {
+    console.error("Error fetching followers:", error);
+    throw error;
+  }
+}
+
+/**
+ * ユーザーのフォロー数とフォロワー数を取得します
+ */
+export async function getFollowCounts(userId: string): Promise<FollowCounts> {
+  try {
+    return await invoke<FollowCounts>("get_follow_counts", { userId });
+  } catch (error) {
+    console.error("Error fetching follow counts:", error);
+    throw error;
+  }
+}
+
+/**
+ * サインイン中のユーザーとしてユーザーをミュートします
+ */
+export async function muteUser(targetUserId: string): Promise<ProfileUpdateResult> {
+  try {
+    return await invoke<ProfileUpdateResult>("mute_user", { targetUserId });
+  } catch (error) {
+    console.error("Error muting user:", error);
+    throw error;
+  }
+}
+
+/**
+ * サインイン中のユーザーとしてユーザーのミュートを解除します
+ */
+export async function unmuteUser(targetUserId: string): Promise<ProfileUpdateResult> {
+  try {
+    return await invoke<ProfileUpdateResult>("unmute_user", { targetUserId });
+  } catch (error) {
+    console.error("Error unmuting user:", error);
+    throw error;
+  }
+}
+
+/**
+ * サインイン中のユーザーとしてユーザーをブロックします
+ */
+export async function blockUser(targetUserId: string): Promise<ProfileUpdateResult> {
+  try {
+    return await invoke<ProfileUpdateResult>("block_user", { targetUserId });
+  } catch (error) {
+    console.error("Error blocking user:", error);
+    throw error;
+  }
+}
+
+/**
+ * サインイン中のユーザーとしてユーザーのブロックを解除します
+ */
+export async function unblockUser(targetUserId: string): Promise<ProfileUpdateResult> {
+  try {
+    return await invoke<ProfileUpdateResult>("unblock_user", { targetUserId });
+  } catch (error) {
+    console.error("Error unblocking user:", error);
+    throw error;
+  }
+}
+
+/**
+ * サインイン中のユーザーがミュートしているユーザーのIDを取得します
+ */
+export async function getMutedUsers(): Promise<string[]> {
+  try {
+    return await invoke<string[]>("get_muted_users");
+  } catch (error) {
+    console.error("Error fetching muted users:", error);
+    throw error;
+  }
+}
+
+/**
+ * サインイン中のユーザーがブロックしているユーザーのIDを取得します
+ */
+export async function getBlockedUsers(): Promise<string[]> {
+  try {
+    return await invoke<string[]>("get_blocked_users");
+  } catch (error) {
+    console.error("Error fetching blocked users:", error);
+    throw error;
+  }
+}
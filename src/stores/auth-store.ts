@@ -7,6 +7,7 @@ export interface User {
   displayName: string;
   bio: string;
   avatar?: string;
+  avatarThumbnail?: string;
   following: string[];
   followers: string[];
   createdAt: number;
@@ -19,9 +20,10 @@ interface AuthState {
   error: string | null;
   
   // Actions
-  createUser: (displayName: string, bio?: string) => Promise<void>;
-  signIn: (userId: string) => Promise<void>;
+  createUser: (displayName: string, passphrase: string, bio?: string) => Promise<void>;
+  signIn: (userId: string, passphrase: string) => Promise<void>;
   logout: () => void;
+  restoreSession: () => Promise<void>;
 }
 
 export const useAuthStore = create<AuthState>((set) => ({
@@ -30,12 +32,13 @@ export const useAuthStore = create<AuthState>((set) => ({
   isLoading: false,
   error: null,
 
-  createUser: async (displayName: string, bio?: string) => {
+  createUser: async (displayName: string, passphrase: string, bio?: string) => {
     set({ isLoading: true, error: null });
     try {
       const result: { userId: string; success: boolean; message?: string } = await invoke('create_user', {
         displayName,
         bio,
+        passphrase,
       });
 
       if (result.success) {
@@ -68,11 +71,12 @@ export const useAuthStore = create<AuthState>((set) => ({
     }
   },
 
-  signIn: async (userId: string) => {
+  signIn: async (userId: string, passphrase: string) => {
     set({ isLoading: true, error: null });
     try {
       const result: { userId: string; success: boolean; message?: string } = await invoke('sign_in', {
         userId,
+        passphrase,
       });
 
       if (result.success) {
@@ -106,10 +110,26 @@ export const useAuthStore = create<AuthState>((set) => ({
   },
 
   logout: () => {
+    // バックエンドのセッションを終了し、復号済みの秘密鍵を破棄
+    invoke('sign_out').catch((error) => {
+      console.error('Error signing out:', error);
+    });
     set({ user: null, isAuthenticated: false });
     toast({
       title: 'Signed out',
       description: 'You have been signed out successfully',
     });
   },
+
+  restoreSession: async () => {
+    try {
+      // フロントエンドの再読み込み後もバックエンドのセッションは残っている
+      const user: User | null = await invoke('current_user');
+      if (user) {
+        set({ user, isAuthenticated: true });
+      }
+    } catch (error) {
+      console.error('Error restoring session:', error);
+    }
+  },
 }));
\ No newline at end of file
@@ -1,47 +1,135 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/core';
 import { toast } from '@/hooks/use-toast';
+import { createPostWithAttachments } from '@/services/post-service';
+
+// 添付ファイルのメタデータ（内容は iroh-blobs に保存される）
+export interface Attachment {
+  hash: string;
+  mimeType: string;
+  size: number;
+  width?: number | null;
+  height?: number | null;
+  name?: string | null;
+}
+
+// 投稿の編集前の版（内容は iroh-blobs に保存される）
+export interface PostRevision {
+  hash: string;
+  writtenAt: number;
+}
 
 export interface Post {
   id: string;
   authorId: string;
   content: string;
-  attachments: string[];
+  attachments: Attachment[];
   mentions: string[];
   hashtags: string[];
   createdAt: number;
+  editedAt?: number | null;
+  revisions?: PostRevision[];
+  // 返信先の投稿IDとスレッドの最初の投稿のID
+  replyTo?: string | null;
+  rootId?: string | null;
+  // 引用した投稿のID
+  quoteOf?: string | null;
+  // 公開範囲（followers は承認したフォロワーだけに共有）
+  visibility?: 'public' | 'followers';
+  keyRevoked?: boolean;
+}
+
+export type ReactionKind = 'like' | 'heart' | 'laugh' | 'surprised' | 'sad' | 'celebrate';
+
+// 投稿へのリアクションの集計（mine はサインイン中のユーザーのリアクション）
+export interface ReactionSummary {
+  counts: Partial<Record<ReactionKind, number>>;
+  mine: ReactionKind[];
+}
+
+// タイムラインの項目（リポストされた投稿は最新のリポストの位置に一度だけ含まれる）
+export interface TimelineItem {
+  post: Post;
+  quoted?: Post | null;
+  // リポストしたユーザーのID（新しい順）
+  repostedBy: string[];
+  repostedAt?: number | null;
+  reactions: ReactionSummary;
+}
+
+interface PostPage {
+  posts: Post[];
+  nextCursor?: string | null;
+}
+
+interface TimelinePage {
+  items: TimelineItem[];
+  nextCursor?: string | null;
 }
 
 type NetworkStatus = 'connected' | 'disconnected' | 'unknown';
 
+// タイムラインの種類（home: 自分とフォロー中のユーザーの投稿、all: すべての投稿）
+export type Feed = 'home' | 'all';
+
 interface PostState {
-  posts: Post[];
+  feed: Feed;
+  timeline: TimelineItem[];
+  // 次のページのカーソル（null の場合は続きが無い）
+  nextCursor: string | null;
   userPosts: Record<string, Post[]>;
+  userPostsCursor: Record<string, string | null>;
+  // 投稿IDごとの直接の返信の数
+  replyCounts: Record<string, number>;
   isLoading: boolean;
   error: string | null;
   networkStatus: NetworkStatus;
   
   // Actions
-  fetchPosts: (limit?: number, offset?: number) => Promise<void>;
-  fetchUserPosts: (userId: string, limit?: number, offset?: number) => Promise<void>;
-  createPost: (content: string) => Promise<void>;
+  // cursor を指定すると続きのページを既存の投稿に追加する
+  fetchPosts: (limit?: number, cursor?: string | null) => Promise<void>;
+  // タイムラインの種類を切り替えて最初のページを取得する
+  setFeed: (feed: Feed) => Promise<void>;
+  fetchUserPosts: (userId: string, limit?: number, cursor?: string | null) => Promise<void>;
+  // files を指定すると添付ファイル付きで投稿する
+  createPost: (content: string, files?: File[]) => Promise<void>;
+  // 自分の投稿のみ編集・削除できる
+  editPost: (postId: string, content: string) => Promise<boolean>;
+  deletePost: (postId: string) => Promise<boolean>;
+  createReply: (parentId: string, content: string) => Promise<boolean>;
+  createQuote: (postId: string, content: string) => Promise<boolean>;
+  // サインイン中のユーザーとしてリポスト・リポストの取り消しをする
+  repost: (postId: string) => Promise<boolean>;
+  unrepost: (postId: string) => Promise<boolean>;
+  // 同じ種類のリアクションをしていれば取り消し、していなければ追加する
+  toggleReaction: (postId: string, kind: ReactionKind) => Promise<void>;
+  fetchReplyCounts: (postIds: string[]) => Promise<void>;
   searchPosts: (query: string, limit?: number) => Promise<Post[]>;
   refreshPosts: () => Promise<void>;
   setNetworkStatus: (status: NetworkStatus) => void;
 }
 
 export const usePostStore = create<PostState>((set, get) => ({
-  posts: [],
+  feed: 'all',
+  timeline: [],
+  nextCursor: null,
   userPosts: {},
+  userPostsCursor: {},
+  replyCounts: {},
   isLoading: false,
   error: null,
   networkStatus: 'unknown',
 
-  fetchPosts: async (limit = 20, offset = 0) => {
+  fetchPosts: async (limit = 20, cursor = null) => {
     set({ isLoading: true, error: null });
     try {
-      const posts: Post[] = await invoke('get_posts', { limit, offset });
-      set({ posts, isLoading: false });
+      const command = get().feed === 'home' ? 'get_home_timeline' : 'get_posts';
+      const page: TimelinePage = await invoke(command, { limit, cursor });
+      set(state => ({
+        timeline: cursor ? [...state.timeline, ...page.items] : page.items,
+        nextCursor: page.nextCursor ?? null,
+        isLoading: false,
+      }));
     } catch (error) {
       console.error('Error fetching posts:', error);
       set({ 
@@ -56,12 +144,21 @@ export const usePostStore = create<PostState>((set, get) => ({
     }
   },
 
-  fetchUserPosts: async (userId: string, limit = 20, offset = 0) => {
+  setFeed: async (feed: Feed) => {
+    set({ feed, timeline: [], nextCursor: null });
+    await get().fetchPosts();
+  },
+
+  fetchUserPosts: async (userId: string, limit = 20, cursor = null) => {
     set({ isLoading: true, error: null });
     try {
-      const posts: Post[] = await invoke('get_user_posts', { userId, limit, offset });
+      const page: PostPage = await invoke('get_user_posts', { userId, limit, cursor });
       set(state => ({ 
-        userPosts: { ...state.userPosts, [userId]: posts },
+        userPosts: {
+          ...state.userPosts,
+          [userId]: cursor ? [...(state.userPosts[userId] || []), ...page.posts] : page.posts,
+        },
+        userPostsCursor: { ...state.userPostsCursor, [userId]: page.nextCursor ?? null },
         isLoading: false 
       }));
     } catch (error) {
@@ -78,19 +175,13 @@ export const usePostStore = create<PostState>((set, get) => ({
     }
   },
 
-  createPost: async (content: string) => {
+  createPost: async (content: string, files: File[] = []) => {
     set({ isLoading: true, error: null });
     try {
-      // Get the current user ID from auth store
-      const userId = window.__TAURI__?.auth?.userId;
-      if (!userId) {
-        throw new Error('User not authenticated');
-      }
-
-      const result: { post_id: string; success: boolean; message?: string } = await invoke('create_post', {
-        authorId: userId,
-        content,
-      });
+      // 投稿者はバックエンドのセッションユーザー
+      const result: { success: boolean; message?: string } = files.length > 0
+        ? await createPostWithAttachments(content, files)
+        : await invoke('create_post', { content });
 
       if (result.success) {
         // Refresh the posts
@@ -117,6 +208,252 @@ export const usePostStore = create<PostState>((set, get) => ({
     }
   },
 
+  editPost: async (postId: string, content: string): Promise<boolean> => {
+    set({ isLoading: true, error: null });
+    try {
+      const result: { post_id: string; success: boolean; message?: string } = await invoke('edit_post', {
+        postId,
+        content,
+      });
+
+      if (result.success) {
+        // Refresh the posts
+        await get().fetchPosts();
+        set({ isLoading: false });
+        toast({
+          title: 'Post updated',
+          description: 'Your post has been edited successfully',
+        });
+        return true;
+      } else {
+        throw new Error(result.message || 'Failed to edit post');
+      }
+    } catch (error) {
+      console.error('Error editing post:', error);
+      set({ 
+        error: error instanceof Error ? error.message : 'An unknown error occurred', 
+        isLoading: false 
+      });
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: error instanceof Error ? error.message : 'Failed to edit post',
+      });
+      return false;
+    }
+  },
+
+  deletePost: async (postId: string): Promise<boolean> => {
+    set({ isLoading: true, error: null });
+    try {
+      const result: { post_id: string; success: boolean; message?: string } = await invoke('delete_post', {
+        postId,
+      });
+
+      if (result.success) {
+        // 削除した投稿を一覧から除く
+        set(state => ({
+          timeline: state.timeline.filter(item => item.post.id !== postId),
+          userPosts: Object.fromEntries(
+            Object.entries(state.userPosts).map(([userId, posts]) => [
+              userId,
+              posts.filter(post => post.id !== postId),
+            ])
+          ),
+          isLoading: false,
+        }));
+        toast({
+          title: 'Post deleted',
+          description: 'Your post has been deleted',
+        });
+        return true;
+      } else {
+        throw new Error(result.message || 'Failed to delete post');
+      }
+    } catch (error) {
+      console.error('Error deleting post:', error);
+      set({ 
+        error: error instanceof Error ? error.message : 'An unknown error occurred', 
+        isLoading: false 
+      });
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: error instanceof Error ? error.message : 'Failed to delete post',
+      });
+      return false;
+    }
+  },
+
+  createReply: async (parentId: string, content: string): Promise<boolean> => {
+    set({ isLoading: true, error: null });
+    try {
+      const result: { post_id: string; success: boolean; message?: string } = await invoke('create_reply', {
+        parentId,
+        content,
+      });
+
+      if (result.success) {
+        // Refresh the posts and the reply count of the parent
+        await get().fetchPosts();
+        await get().fetchReplyCounts([parentId]);
+        set({ isLoading: false });
+        toast({
+          title: 'Reply posted',
+          description: 'Your reply has been published successfully',
+        });
+        return true;
+      } else {
+        throw new Error(result.message || 'Failed to post reply');
+      }
+    } catch (error) {
+      console.error('Error creating reply:', error);
+      set({ 
+        error: error instanceof Error ? error.message : 'An unknown error occurred', 
+        isLoading: false 
+      });
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: error instanceof Error ? error.message : 'Failed to post reply',
+      });
+      return false;
+    }
+  },
+
+  createQuote: async (postId: string, content: string): Promise<boolean> => {
+    set({ isLoading: true, error: null });
+    try {
+      const result: { post_id: string; success: boolean; message?: string } = await invoke('create_quote', {
+        postId,
+        content,
+      });
+
+      if (result.success) {
+        // Refresh the posts
+        await get().fetchPosts();
+        set({ isLoading: false });
+        toast({
+          title: 'Quote posted',
+          description: 'Your quote has been published successfully',
+        });
+        return true;
+      } else {
+        throw new Error(result.message || 'Failed to post quote');
+      }
+    } catch (error) {
+      console.error('Error creating quote:', error);
+      set({ 
+        error: error instanceof Error ? error.message : 'An unknown error occurred', 
+        isLoading: false 
+      });
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: error instanceof Error ? error.message : 'Failed to post quote',
+      });
+      return false;
+    }
+  },
+
+  repost: async (postId: string): Promise<boolean> => {
+    set({ isLoading: true, error: null });
+    try {
+      const result: { post_id: string; success: boolean; message?: string } = await invoke('repost', {
+        postId,
+      });
+
+      if (result.success) {
+        // Refresh the timeline
+        await get().fetchPosts();
+        set({ isLoading: false });
+        toast({
+          title: 'Reposted',
+          description: 'The post has been shared with your followers',
+        });
+        return true;
+      } else {
+        throw new Error(result.message || 'Failed to repost');
+      }
+    } catch (error) {
+      console.error('Error reposting:', error);
+      set({ 
+        error: error instanceof Error ? error.message : 'An unknown error occurred', 
+        isLoading: false 
+      });
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: error instanceof Error ? error.message : 'Failed to repost',
+      });
+      return false;
+    }
+  },
+
+  unrepost: async (postId: string): Promise<boolean> => {
+    set({ isLoading: true, error: null });
+    try {
+      const result: { post_id: string; success: boolean; message?: string } = await invoke('unrepost', {
+        postId,
+      });
+
+      if (result.success) {
+        // Refresh the timeline
+        await get().fetchPosts();
+        set({ isLoading: false });
+        toast({
+          title: 'Repost removed',
+          description: 'The post is no longer shared',
+        });
+        return true;
+      } else {
+        throw new Error(result.message || 'Failed to remove repost');
+      }
+    } catch (error) {
+      console.error('Error removing repost:', error);
+      set({ 
+        error: error instanceof Error ? error.message : 'An unknown error occurred', 
+        isLoading: false 
+      });
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: error instanceof Error ? error.message : 'Failed to remove repost',
+      });
+      return false;
+    }
+  },
+
+  toggleReaction: async (postId: string, kind: ReactionKind) => {
+    const item = get().timeline.find(item => item.post.id === postId);
+    const command = item?.reactions.mine.includes(kind) ? 'remove_reaction' : 'add_reaction';
+    try {
+      const reactions: ReactionSummary = await invoke(command, { postId, kind });
+      // 投稿全体を再取得せず、集計だけを差し替える
+      set(state => ({
+        timeline: state.timeline.map(item => (item.post.id === postId ? { ...item, reactions } : item)),
+      }));
+    } catch (error) {
+      console.error('Error updating reaction:', error);
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: error instanceof Error ? error.message : 'Failed to update reaction',
+      });
+    }
+  },
+
+  fetchReplyCounts: async (postIds: string[]) => {
+    if (postIds.length === 0) return;
+    try {
+      const counts: Record<string, number> = await invoke('get_reply_counts', { postIds });
+      set(state => ({ replyCounts: { ...state.replyCounts, ...counts } }));
+    } catch (error) {
+      // 返信数は補助的な情報なので、取得に失敗してもエラー表示はしない
+      console.error('Error fetching reply counts:', error);
+    }
+  },
+
   searchPosts: async (query: string, limit = 50): Promise<Post[]> => {
     set({ isLoading: true, error: null });
     try {
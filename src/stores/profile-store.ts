@@ -13,7 +13,8 @@ interface ProfileState {
   
   // Actions
   fetchProfile: (userId: string) => Promise<User | null>;
-  updateProfile: (userId: string, data: { displayName?: string; bio?: string; avatar?: string }) => Promise<boolean>;
+  updateProfile: (userId: string, data: { displayName?: string; bio?: string }) => Promise<boolean>;
+  setAvatar: (userId: string, file: File) => Promise<boolean>;
   followUser: (userId: string, targetUserId: string) => Promise<boolean>;
   unfollowUser: (userId: string, targetUserId: string) => Promise<boolean>;
   refreshUser: (userId: string) => Promise<void>;
@@ -62,11 +63,10 @@ export const useProfileStore = create<ProfileState>((set, get) => ({
     }
   },
 
-  updateProfile: async (userId: string, data: { displayName?: string; bio?: string; avatar?: string }): Promise<boolean> => {
+  updateProfile: async (userId: string, data: { displayName?: string; bio?: string }): Promise<boolean> => {
     set({ isLoading: true, error: null });
     try {
       const result: { success: boolean; message?: string } = await invoke('update_profile', {
-        userId,
         ...data
       });
 
@@ -97,11 +97,43 @@ export const useProfileStore = create<ProfileState>((set, get) => ({
     }
   },
 
+  setAvatar: async (userId: string, file: File): Promise<boolean> => {
+    set({ isLoading: true, error: null });
+    try {
+      const data = new Uint8Array(await file.arrayBuffer());
+      const result: { success: boolean; message?: string } = await invoke('set_avatar', data);
+
+      if (result.success) {
+        // Refresh the profile
+        await get().fetchProfile(userId);
+        set({ isLoading: false });
+        toast({
+          title: 'Avatar updated',
+          description: 'Your avatar has been updated successfully',
+        });
+        return true;
+      } else {
+        throw new Error(result.message || 'Failed to update avatar');
+      }
+    } catch (error) {
+      console.error('Error updating avatar:', error);
+      set({ 
+        error: error instanceof Error ? error.message : 'An unknown error occurred', 
+        isLoading: false 
+      });
+      toast({
+        variant: 'destructive',
+        title: 'Error',
+        description: error instanceof Error ? error.message : 'Failed to update avatar',
+      });
+      return false;
+    }
+  },
+
   followUser: async (userId: string, targetUserId: string): Promise<boolean> => {
     set({ isLoading: true, error: null });
     try {
       const result: { success: boolean; message?: string } = await invoke('follow_user', {
-        userId,
         targetUserId,
       });
 
@@ -137,7 +169,6 @@ export const useProfileStore = create<ProfileState>((set, get) => ({
     set({ isLoading: true, error: null });
     try {
       const result: { success: boolean; message?: string } = await invoke('unfollow_user', {
-        userId,
         targetUserId,
       });
 
@@ -55,7 +55,7 @@ describe('Authentication Flow Integration', () => {
       const store = getState();
       
       // ユーザー作成
-      await store.createUser(testUser.displayName, testUser.bio);
+      await store.createUser(testUser.displayName, 'correct horse battery', testUser.bio);
 
       // 更新された状態を取得
       const updatedStore = getState();
@@ -66,6 +66,7 @@ describe('Authentication Flow Integration', () => {
       expect(invoke).toHaveBeenCalledWith('create_user', {
         displayName: testUser.displayName,
         bio: testUser.bio,
+        passphrase: 'correct horse battery',
       });
     });
 
@@ -84,7 +85,7 @@ describe('Authentication Flow Integration', () => {
       const store = getState();
       
       // サインイン
-      await store.signIn(testUser.id);
+      await store.signIn(testUser.id, 'correct horse battery');
 
       // 更新された状態を取得
       const updatedStore = getState();
@@ -94,6 +95,7 @@ describe('Authentication Flow Integration', () => {
       expect(updatedStore.user).toEqual(testUser);
       expect(invoke).toHaveBeenCalledWith('sign_in', {
         userId: testUser.id,
+        passphrase: 'correct horse battery',
       });
     });
 
@@ -106,7 +108,7 @@ describe('Authentication Flow Integration', () => {
       const store = getState();
       
       // サインイン（エラーが内部で処理される）
-      await store.signIn('invalid-id');
+      await store.signIn('invalid-id', 'wrong passphrase');
 
       // 更新された状態を取得
       const updatedStore = getState();
@@ -132,6 +134,9 @@ describe('Authentication Flow Integration', () => {
       expect(store.isAuthenticated).toBe(true);
       expect(store.user).toEqual(testUser);
 
+      // sign_outのモック
+      (invoke as any).mockResolvedValueOnce(undefined);
+
       // ログアウト
       store.logout();
 
@@ -141,6 +146,7 @@ describe('Authentication Flow Integration', () => {
       // ストアの状態を確認
       expect(store.isAuthenticated).toBe(false);
       expect(store.user).toBe(null);
+      expect(invoke).toHaveBeenCalledWith('sign_out');
     });
   
     describe('Profile Retrieval', () => {
@@ -5,7 +5,7 @@ describe('User Model Validation', () => {
   describe('userSchema', () => {
     it('should validate a valid user', () => {
       const validUser = {
-        id: '123e4567-e89b-12d3-a456-426614174000',
+        id: 'k5oc2dbhqz6ryl3mvcfiuw7rl7hbvygtb5qdwtvqldr5qbwicrha',
         displayName: 'Test User',
         bio: 'This is a test bio',
         following: [],
@@ -33,7 +33,7 @@ describe('User Model Validation', () => {
 
     it('should reject a user with empty display name', () => {
       const invalidUser = {
-        id: '123e4567-e89b-12d3-a456-426614174000',
+        id: 'k5oc2dbhqz6ryl3mvcfiuw7rl7hbvygtb5qdwtvqldr5qbwicrha',
         displayName: '',
         bio: 'This is a test bio',
         following: [],
@@ -47,7 +47,7 @@ describe('User Model Validation', () => {
 
     it('should reject a user with too long bio', () => {
       const invalidUser = {
-        id: '123e4567-e89b-12d3-a456-426614174000',
+        id: 'k5oc2dbhqz6ryl3mvcfiuw7rl7hbvygtb5qdwtvqldr5qbwicrha',
         displayName: 'Test User',
         bio: 'a'.repeat(161), // 161 characters, max is 160
         following: [],
@@ -65,6 +65,7 @@ describe('User Model Validation', () => {
       const validInput = {
         displayName: 'Test User',
         bio: 'This is a test bio',
+        passphrase: 'correct horse battery',
       };
 
       const result = createUserSchema.safeParse(validInput);
@@ -74,6 +75,7 @@ describe('User Model Validation', () => {
     it('should validate input without bio', () => {
       const validInput = {
         displayName: 'Test User',
+        passphrase: 'correct horse battery',
       };
 
       const result = createUserSchema.safeParse(validInput);
@@ -84,6 +86,17 @@ describe('User Model Validation', () => {
       const invalidInput = {
         displayName: '',
         bio: 'This is a test bio',
+        passphrase: 'correct horse battery',
+      };
+
+      const result = createUserSchema.safeParse(invalidInput);
+      expect(result.success).toBe(false);
+    });
+
+    it('should reject a short passphrase', () => {
+      const invalidInput = {
+        displayName: 'Test User',
+        passphrase: 'short',
       };
 
       const result = createUserSchema.safeParse(invalidInput);